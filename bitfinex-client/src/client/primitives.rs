@@ -15,6 +15,11 @@ impl From<String> for ClientId {
         Self(s.parse::<i64>().unwrap())
     }
 }
+impl From<i64> for ClientId {
+    fn from(id: i64) -> Self {
+        Self(id)
+    }
+}
 impl From<ClientId> for i64 {
     fn from(id: ClientId) -> Self {
         id.0
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use url::Url;
 
+use std::time::Duration;
+
 pub const BTC_USD_SWAP: &str = "tBTCUSD";
 
 #[serde_with::serde_as]
@@ -8,14 +10,41 @@ pub const BTC_USD_SWAP: &str = "tBTCUSD";
 pub struct PriceFeedConfig {
     #[serde(default = "default_url")]
     pub url: Url,
+    #[serde_as(as = "serde_with::DurationSeconds<u64>")]
+    #[serde(default = "default_min_reconnect_backoff")]
+    pub min_reconnect_backoff: Duration,
+    #[serde_as(as = "serde_with::DurationSeconds<u64>")]
+    #[serde(default = "default_max_reconnect_backoff")]
+    pub max_reconnect_backoff: Duration,
+    #[serde_as(as = "serde_with::DurationSeconds<u64>")]
+    #[serde(default = "default_unhealthy_msg_interval")]
+    pub unhealthy_msg_interval: Duration,
 }
 
 impl Default for PriceFeedConfig {
     fn default() -> Self {
-        Self { url: default_url() }
+        Self {
+            url: default_url(),
+            min_reconnect_backoff: default_min_reconnect_backoff(),
+            max_reconnect_backoff: default_max_reconnect_backoff(),
+            unhealthy_msg_interval: default_unhealthy_msg_interval(),
+        }
     }
 }
 
 fn default_url() -> Url {
     Url::parse("wss://api-pub.bitfinex.com/ws/2").unwrap()
 }
+
+fn default_min_reconnect_backoff() -> Duration {
+    Duration::from_secs(1)
+}
+
+fn default_max_reconnect_backoff() -> Duration {
+    Duration::from_secs(60)
+}
+
+// Bitfinex sends a heartbeat every 15 seconds on subscribed channels
+fn default_unhealthy_msg_interval() -> Duration {
+    Duration::from_secs(30)
+}
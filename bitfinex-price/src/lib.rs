@@ -7,8 +7,12 @@ pub mod error;
 pub mod price_feed;
 
 use futures::StreamExt;
-use shared::{payload::*, pubsub::*};
-use tokio::join;
+use tokio::sync::RwLock;
+use tracing::warn;
+
+use std::{sync::Arc, time::Duration};
+
+use shared::{health::HealthCheckTrigger, payload::*, pubsub::*, time::TimeStamp};
 
 pub use config::*;
 pub use error::*;
@@ -17,18 +21,65 @@ pub use price_feed::*;
 pub async fn run(
     price_feed_config: PriceFeedConfig,
     price_stream_publisher: memory::Publisher<PriceStreamPayload>,
+    health_check_trigger: HealthCheckTrigger,
 ) -> Result<(), PriceFeedError> {
-    let pf_config = price_feed_config.clone();
-    let mut stream = subscribe_btc_usd_swap_price_tick(pf_config).await?;
+    let last_msg_received = Arc::new(RwLock::new(None));
+    spawn_health_checker(
+        health_check_trigger,
+        Arc::clone(&last_msg_received),
+        price_feed_config.unhealthy_msg_interval,
+    );
 
-    let tick_task = tokio::spawn(async move {
-        while let Some(tick) = stream.next().await {
-            let _res = bitfinex_price_tick_received(&price_stream_publisher, tick).await;
+    let mut backoff = price_feed_config.min_reconnect_backoff;
+    loop {
+        match subscribe_btc_usd_swap_ticker(price_feed_config.clone()).await {
+            Ok(mut stream) => {
+                while let Some(msg) = stream.next().await {
+                    *last_msg_received.write().await = Some(TimeStamp::now());
+                    backoff = price_feed_config.min_reconnect_backoff;
+                    if let TickerMessage::Tick(tick) = msg {
+                        let _res =
+                            bitfinex_price_tick_received(&price_stream_publisher, tick).await;
+                    }
+                }
+                warn!("Bitfinex price feed disconnected, reconnecting in {backoff:?}");
+            }
+            Err(e) => {
+                warn!("Couldn't subscribe to Bitfinex price feed: {e}, retrying in {backoff:?}");
+            }
         }
-    });
-    let _ = join!(tick_task);
+        tokio::time::sleep(backoff).await;
+        backoff = next_backoff(backoff, price_feed_config.max_reconnect_backoff);
+    }
+}
 
-    Ok(())
+fn next_backoff(current: Duration, max: Duration) -> Duration {
+    std::cmp::min(current * 2, max)
+}
+
+fn spawn_health_checker(
+    mut health_check_trigger: HealthCheckTrigger,
+    last_msg_received: Arc<RwLock<Option<TimeStamp>>>,
+    unhealthy_msg_interval: Duration,
+) {
+    tokio::spawn(async move {
+        while let Some(check) = health_check_trigger.next().await {
+            let last_msg_received = *last_msg_received.read().await;
+            let res = match last_msg_received.map(|ts| ts.duration_since()) {
+                Some(time_since)
+                    if time_since.to_std().unwrap_or_default() <= unhealthy_msg_interval =>
+                {
+                    Ok(())
+                }
+                Some(time_since) => Err(format!(
+                    "No bitfinex ticker messages received in the last {} seconds",
+                    time_since.num_seconds()
+                )),
+                None => Err("No bitfinex ticker messages received".to_string()),
+            };
+            let _ = check.send(res);
+        }
+    });
 }
 
 async fn bitfinex_price_tick_received(
@@ -42,3 +93,22 @@ async fn bitfinex_price_tick_received(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_is_capped() {
+        let max = Duration::from_secs(60);
+        let mut backoff = Duration::from_secs(1);
+        for _ in 0..10 {
+            backoff = next_backoff(backoff, max);
+        }
+        assert_eq!(backoff, max);
+        assert_eq!(
+            next_backoff(Duration::from_secs(4), max),
+            Duration::from_secs(8)
+        );
+    }
+}
@@ -9,6 +9,18 @@ pub use tick::*;
 pub async fn subscribe_btc_usd_swap_price_tick(
     config: PriceFeedConfig,
 ) -> Result<std::pin::Pin<Box<dyn Stream<Item = BitfinexPriceTick> + Send>>, PriceFeedError> {
+    let stream = subscribe_btc_usd_swap_ticker(config).await?;
+    Ok(Box::pin(stream.filter_map(|message| async {
+        match message {
+            TickerMessage::Tick(tick) => Some(tick),
+            TickerMessage::Heartbeat(_) => None,
+        }
+    })))
+}
+
+pub async fn subscribe_btc_usd_swap_ticker(
+    config: PriceFeedConfig,
+) -> Result<std::pin::Pin<Box<dyn Stream<Item = TickerMessage> + Send>>, PriceFeedError> {
     let (ws_stream, _) = connect_async(config.url).await?;
     let (mut sender, receiver) = ws_stream.split();
 
@@ -22,14 +34,21 @@ pub async fn subscribe_btc_usd_swap_price_tick(
 
     sender.send(item).await?;
 
-    Ok(Box::pin(receiver.filter_map(|message| async {
-        if let Ok(msg) = message {
-            if let Ok(msg_str) = msg.into_text() {
-                if let Ok(tick) = serde_json::from_str::<BitfinexPriceTick>(&msg_str) {
-                    return Some(tick);
+    Ok(Box::pin(
+        receiver
+            .take_while(|message| futures::future::ready(message.is_ok()))
+            .filter_map(|message| async {
+                if let Ok(msg) = message {
+                    if let Ok(msg_str) = msg.into_text() {
+                        if let Ok(tick) = serde_json::from_str::<BitfinexPriceTick>(&msg_str) {
+                            return Some(TickerMessage::Tick(tick));
+                        }
+                        if let Ok(hb) = serde_json::from_str::<BitfinexHeartbeat>(&msg_str) {
+                            return Some(TickerMessage::Heartbeat(hb));
+                        }
+                    }
                 }
-            }
-        }
-        None
-    })))
+                None
+            }),
+    ))
 }
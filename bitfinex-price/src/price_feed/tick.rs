@@ -21,6 +21,34 @@ pub struct BitfinexPriceTick {
     pub tick: TickerChannelData,
 }
 
+/// `[CHANNEL_ID, "hb"]` sent periodically by Bitfinex on idle channels
+#[derive(Clone, Deserialize, Debug)]
+pub struct BitfinexHeartbeat {
+    pub channel_id: u64,
+    #[serde(deserialize_with = "deserialize_hb")]
+    _hb: (),
+}
+
+fn deserialize_hb<'de, D>(deserializer: D) -> Result<(), D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    if value == "hb" {
+        Ok(())
+    } else {
+        Err(serde::de::Error::custom(format!(
+            "expected 'hb', got '{value}'"
+        )))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum TickerMessage {
+    Tick(BitfinexPriceTick),
+    Heartbeat(BitfinexHeartbeat),
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -31,7 +59,14 @@ mod tests {
         let response_text =
             "[225440,[21099,66.42015405,21101,36.16639035,-3,-0.0001,21101,2780.23882622,21469,20639]]";
         let details = serde_json::from_str::<BitfinexPriceTick>(response_text).unwrap();
-        dbg!(details.clone());
         assert_eq!(details.tick.bid, dec!(21099));
     }
+
+    #[test]
+    fn bitfinex_heartbeat() {
+        let hb = serde_json::from_str::<BitfinexHeartbeat>(r#"[225440,"hb"]"#).unwrap();
+        assert_eq!(hb.channel_id, 225440);
+        assert!(serde_json::from_str::<BitfinexHeartbeat>(r#"[225440,"cs"]"#).is_err());
+        assert!(serde_json::from_str::<BitfinexPriceTick>(r#"[225440,"hb"]"#).is_err());
+    }
 }
@@ -23,7 +23,8 @@ async fn publishes_to_price_stream() -> anyhow::Result<()> {
 
     let _ = tokio::spawn(async move {
         let config = PriceFeedConfig::default();
-        let _ = bitfinex_price::run(config, tick_send).await;
+        let (_, health_check_trigger) = futures::channel::mpsc::unbounded();
+        let _ = bitfinex_price::run(config, tick_send, health_check_trigger).await;
     });
 
     let received_tick = tick_recv.next().await.expect("expected price tick");
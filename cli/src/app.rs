@@ -50,6 +50,15 @@ enum Command {
         /// Bitfinex secret key
         #[clap(env = "BITFINEX_SECRET_KEY", default_value = "")]
         bitfinex_secret_key: String,
+        /// Secret used to verify galoy transaction webhooks
+        #[clap(env = "GALOY_WEBHOOK_SECRET", default_value = "")]
+        galoy_webhook_secret: String,
+        /// Secret used to sign price quotes
+        #[clap(env = "QUOTES_SIGNING_SECRET", default_value = "")]
+        quotes_signing_secret: String,
+        /// Token required by the hedging admin service to change state
+        #[clap(env = "HEDGING_ADMIN_TOKEN", default_value = "")]
+        hedging_admin_token: String,
     },
     /// Gets a quote from the price server
     Price {
@@ -63,6 +72,39 @@ enum Command {
         expiry: Option<u64>,
         amount: Decimal,
     },
+    /// Resolves galoy transactions that couldn't be paired automatically
+    Quarantine {
+        /// Connection string for the stablesats database
+        #[clap(long, env = "PG_CON")]
+        pg_con: String,
+        #[clap(subcommand)]
+        command: QuarantineCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum QuarantineCommand {
+    /// Lists the quarantined galoy transactions
+    List,
+    /// Records a user trade made of two galoy transactions
+    Pair {
+        first_id: String,
+        second_id: String,
+        /// Who is resolving the transactions
+        #[clap(long)]
+        by: String,
+        #[clap(long)]
+        note: Option<String>,
+    },
+    /// Marks a galoy transaction as not being part of a user trade
+    Ignore {
+        id: String,
+        /// Who is resolving the transaction
+        #[clap(long)]
+        by: String,
+        #[clap(long)]
+        note: Option<String>,
+    },
 }
 
 pub async fn run() -> anyhow::Result<()> {
@@ -76,6 +118,9 @@ pub async fn run() -> anyhow::Result<()> {
             okex_passphrase,
             okex_secret_key,
             bitfinex_secret_key,
+            galoy_webhook_secret,
+            quotes_signing_secret,
+            hedging_admin_token,
             pg_con,
         } => {
             let config = Config::from_path(
@@ -87,6 +132,9 @@ pub async fn run() -> anyhow::Result<()> {
                     okex_secret_key,
                     pg_con,
                     bitfinex_secret_key,
+                    galoy_webhook_secret,
+                    quotes_signing_secret,
+                    hedging_admin_token,
                 },
             )?;
             match (run_cmd(config.clone()).await, crash_report_config) {
@@ -105,6 +153,7 @@ pub async fn run() -> anyhow::Result<()> {
             expiry,
             amount,
         } => price_cmd(url, direction, expiry, amount).await?,
+        Command::Quarantine { pg_con, command } => quarantine_cmd(pg_con, command).await?,
     }
     Ok(())
 }
@@ -114,6 +163,7 @@ async fn run_cmd(
         db,
         pubsub,
         price_server,
+        okex_price_feed,
         bitfinex_price_feed,
         user_trades,
         tracing,
@@ -130,6 +180,7 @@ async fn run_cmd(
     let mut handles = Vec::new();
     let mut checkers = HashMap::new();
     let (price_send, price_recv) = memory::channel(price_stream_throttle_period());
+    let okex_order_book = okex_price_feed.config.order_book;
 
     if exchanges
         .okex
@@ -143,7 +194,7 @@ async fn run_cmd(
         let price_send = price_send.clone();
         handles.push(tokio::spawn(async move {
             let _ = okex_send.try_send(
-                okex_price::run(price_send)
+                okex_price::run(okex_price_feed.config, price_send)
                     .await
                     .context("Okex Price Feed error"),
             );
@@ -155,21 +206,29 @@ async fn run_cmd(
 
         let bitfinex_send = send.clone();
         let price_send = price_send.clone();
+        let (snd, recv) = futures::channel::mpsc::unbounded();
+        checkers.insert("bitfinex_price", snd);
         handles.push(tokio::spawn(async move {
             let _ = bitfinex_send.try_send(
-                bitfinex_price::run(bitfinex_price_feed.config, price_send)
+                bitfinex_price::run(bitfinex_price_feed.config, price_send, recv)
                     .await
                     .context("Bitfinex Price Feed error"),
             );
         }));
     }
 
+    let mut pool = None;
+
     if price_server.enabled {
         println!(
             "Starting price server on port {}",
             price_server.server.listen_port
         );
 
+        if price_server.quotes.enabled {
+            pool = Some(crate::db::init_pool(&db).await?);
+        }
+        let quotes_pool = pool.clone();
         let price_send = send.clone();
         let (snd, recv) = futures::channel::mpsc::unbounded();
         checkers.insert("price", snd);
@@ -185,6 +244,10 @@ async fn run_cmd(
                     price,
                     price_server.price_cache,
                     weights,
+                    price_server.mixer,
+                    okex_order_book,
+                    price_server.quotes,
+                    quotes_pool,
                 )
                 .await
                 .context("Price Server error"),
@@ -192,8 +255,6 @@ async fn run_cmd(
         }));
     }
 
-    let mut pool = None;
-
     if hedging.enabled {
         println!("Starting hedging process");
 
@@ -204,23 +265,16 @@ async fn run_cmd(
         let price = price_recv.resubscribe();
         checkers.insert("hedging", snd);
 
-        if let Some(okex_cfg) = exchanges.okex.as_ref() {
-            let okex_config = okex_cfg.config.clone();
-            pool = Some(crate::db::init_pool(&db).await?);
+        if exchanges.okex.is_some() || exchanges.bitfinex.is_some() {
+            if pool.is_none() {
+                pool = Some(crate::db::init_pool(&db).await?);
+            }
             let pool = pool.as_ref().unwrap().clone();
             handles.push(tokio::spawn(async move {
                 let _ = hedging_send.try_send(
-                    hedging::run(
-                        pool,
-                        recv,
-                        hedging.config,
-                        okex_config,
-                        galoy,
-                        pubsub,
-                        price,
-                    )
-                    .await
-                    .context("Hedging error"),
+                    hedging::run(pool, recv, hedging.config, exchanges, galoy, pubsub, price)
+                        .await
+                        .context("Hedging error"),
                 );
             }));
         }
@@ -230,6 +284,8 @@ async fn run_cmd(
         println!("Starting user trades process");
 
         let user_trades_send = send.clone();
+        let (snd, recv) = futures::channel::mpsc::unbounded();
+        checkers.insert("user_trades", snd);
         let pool = if let Some(pool) = pool {
             pool
         } else {
@@ -237,7 +293,7 @@ async fn run_cmd(
         };
         handles.push(tokio::spawn(async move {
             let _ = user_trades_send.try_send(
-                user_trades::run(pool, user_trades.config, galoy)
+                user_trades::run(pool, recv, user_trades.config, galoy)
                     .await
                     .context("User Trades error"),
             );
@@ -267,6 +323,51 @@ async fn price_cmd(
     client.get_price(direction, expiry, amount).await
 }
 
+async fn quarantine_cmd(pg_con: String, command: QuarantineCommand) -> anyhow::Result<()> {
+    let pool = crate::db::init_pool(&crate::db::DbConfig {
+        pg_con,
+        migrate_on_start: false,
+        ..Default::default()
+    })
+    .await?;
+    let quarantine = user_trades::GaloyTransactionsQuarantine::new(pool);
+    match command {
+        QuarantineCommand::List => {
+            for tx in quarantine.list().await? {
+                println!(
+                    "{} {} {} {} {} usd_cents={} created_at={} quarantined_at={} reason={} memo={}",
+                    tx.id,
+                    tx.direction,
+                    tx.settlement_amount,
+                    tx.settlement_currency,
+                    tx.settlement_method,
+                    tx.amount_in_usd_cents,
+                    tx.created_at,
+                    tx.quarantined_at,
+                    tx.reason.unwrap_or_default(),
+                    tx.memo.unwrap_or_default(),
+                );
+            }
+        }
+        QuarantineCommand::Pair {
+            first_id,
+            second_id,
+            by,
+            note,
+        } => {
+            quarantine
+                .pair(first_id.clone(), second_id.clone(), by, note)
+                .await?;
+            println!("Paired {first_id} with {second_id}");
+        }
+        QuarantineCommand::Ignore { id, by, note } => {
+            quarantine.ignore(id.clone(), by, note).await?;
+            println!("Ignored {id}");
+        }
+    }
+    Ok(())
+}
+
 fn price_stream_throttle_period() -> Duration {
     Duration::from_std(std::time::Duration::from_secs(2)).unwrap()
 }
@@ -5,7 +5,8 @@ use std::path::Path;
 use galoy_client::GaloyClientConfig;
 use hedging::{ExchangesConfig, HedgingAppConfig};
 use price_server::{
-    ExchangePriceCacheConfig, FeeCalculatorConfig, PriceServerConfig, PriceServerHealthCheckConfig,
+    ExchangePriceCacheConfig, FeeCalculatorConfig, PriceMixerConfig, PriceServerConfig,
+    PriceServerHealthCheckConfig, QuotesConfig,
 };
 use shared::pubsub::PubSubConfig;
 use user_trades::UserTradesConfig;
@@ -23,6 +24,8 @@ pub struct Config {
     #[serde(default)]
     pub price_server: PriceServerWrapper,
     #[serde(default)]
+    pub okex_price_feed: OkexPriceFeedConfigWrapper,
+    #[serde(default)]
     pub bitfinex_price_feed: BitfinexPriceFeedConfigWrapper,
     #[serde(default)]
     pub user_trades: UserTradesConfigWrapper,
@@ -41,6 +44,9 @@ pub struct EnvOverride {
     pub okex_passphrase: String,
     pub galoy_phone_code: String,
     pub bitfinex_secret_key: String,
+    pub galoy_webhook_secret: String,
+    pub quotes_signing_secret: String,
+    pub hedging_admin_token: String,
 }
 
 impl Config {
@@ -52,7 +58,10 @@ impl Config {
             okex_passphrase,
             okex_secret_key,
             pg_con: stablesats_pg_con,
-            bitfinex_secret_key: _,
+            bitfinex_secret_key,
+            galoy_webhook_secret,
+            quotes_signing_secret,
+            hedging_admin_token,
         }: EnvOverride,
     ) -> anyhow::Result<Self> {
         let config_file = std::fs::read_to_string(path).context("Couldn't read config file")?;
@@ -63,12 +72,19 @@ impl Config {
         }
 
         config.galoy.auth_code = galoy_phone_code;
+        config.user_trades.config.webhook.secret = galoy_webhook_secret;
+        config.price_server.quotes.signing_secret = quotes_signing_secret;
+        config.hedging.config.admin.auth_token = hedging_admin_token;
 
         if let Some(okex) = config.exchanges.okex.as_mut() {
             okex.config.client.secret_key = okex_secret_key;
             okex.config.client.passphrase = okex_passphrase;
         };
 
+        if let Some(bitfinex) = config.exchanges.bitfinex.as_mut() {
+            bitfinex.config.client.secret_key = bitfinex_secret_key;
+        };
+
         config.db.pg_con = stablesats_pg_con;
 
         Ok(config)
@@ -87,6 +103,10 @@ pub struct PriceServerWrapper {
     pub fees: FeeCalculatorConfig,
     #[serde(default)]
     pub price_cache: ExchangePriceCacheConfig,
+    #[serde(default)]
+    pub mixer: PriceMixerConfig,
+    #[serde(default)]
+    pub quotes: QuotesConfig,
 }
 impl Default for PriceServerWrapper {
     fn default() -> Self {
@@ -96,10 +116,18 @@ impl Default for PriceServerWrapper {
             health: PriceServerHealthCheckConfig::default(),
             fees: FeeCalculatorConfig::default(),
             price_cache: ExchangePriceCacheConfig::default(),
+            mixer: PriceMixerConfig::default(),
+            quotes: QuotesConfig::default(),
         }
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct OkexPriceFeedConfigWrapper {
+    #[serde(default)]
+    pub config: okex_price::PriceFeedConfig,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct BitfinexPriceFeedConfigWrapper {
     #[serde(default)]
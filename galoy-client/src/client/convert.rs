@@ -112,6 +112,7 @@ impl TryFrom<stablesats_transactions_list::ResponseData> for GaloyTransactions {
                     created_at: edge.node.created_at.0,
                     amount_in_usd_cents: (edge.node.settlement_amount * cents_per_unit).round(),
                     settlement_amount: edge.node.settlement_amount,
+                    settlement_fee: edge.node.settlement_fee,
                     settlement_currency: edge.node.settlement_currency,
                     settlement_method: edge.node.settlement_via,
                     cents_per_unit,
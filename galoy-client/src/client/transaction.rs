@@ -54,6 +54,17 @@ impl std::fmt::Display for SettlementMethod {
         }
     }
 }
+impl std::str::FromStr for SettlementMethod {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "SettlementViaIntraLedger" => Ok(Self::SettlementViaIntraLedger),
+            "SettlementViaOnChain" => Ok(Self::SettlementViaOnChain),
+            "SettlementViaLn" => Ok(Self::SettlementViaLn),
+            other => Err(format!("Unknown settlement method: {other}")),
+        }
+    }
+}
 
 impl std::fmt::Display for TxDirection {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -64,12 +75,23 @@ impl std::fmt::Display for TxDirection {
         }
     }
 }
+impl std::str::FromStr for TxDirection {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "RECEIVE" => Ok(Self::RECEIVE),
+            "SEND" => Ok(Self::SEND),
+            other => Ok(Self::Other(other.to_string())),
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct GaloyTransaction {
     pub id: String,
     pub cursor: TxCursor,
     pub settlement_amount: Decimal,
+    pub settlement_fee: Decimal,
     pub settlement_currency: SettlementCurrency,
     pub settlement_method: SettlementMethod,
     pub memo: Option<String>,
@@ -1,5 +1,8 @@
-// generated by `sqlx migrate build-script`
-fn main() {
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     // trigger recompilation when a new migration is added
     println!("cargo:rerun-if-changed=migrations");
+
+    std::env::set_var("PROTOC", protobuf_src::protoc());
+    tonic_build::compile_protos("../proto/hedging/admin.proto")?;
+    Ok(())
 }
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+use std::net::{IpAddr, Ipv4Addr};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HedgingAdminConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_listen_address")]
+    pub listen_address: IpAddr,
+    #[serde(default = "default_port")]
+    pub listen_port: u16,
+    /// Bearer token required in the `authorization` metadata of calls that
+    /// change state. Those calls are rejected while it is empty.
+    #[serde(default)]
+    pub auth_token: String,
+}
+impl Default for HedgingAdminConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen_address: default_listen_address(),
+            listen_port: default_port(),
+            auth_token: String::new(),
+        }
+    }
+}
+
+fn default_listen_address() -> IpAddr {
+    IpAddr::V4(Ipv4Addr::LOCALHOST)
+}
+
+fn default_port() -> u16 {
+    3326
+}
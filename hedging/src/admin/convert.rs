@@ -0,0 +1,59 @@
+use rust_decimal::Decimal;
+
+use super::proto;
+use crate::{control::HedgingDecision, error::HedgingError, okex::*};
+
+impl From<HedgingError> for tonic::Status {
+    fn from(err: HedgingError) -> Self {
+        tonic::Status::new(tonic::Code::Unknown, format!("{err}"))
+    }
+}
+
+pub(super) fn to_double(value: Decimal) -> Result<f64, tonic::Status> {
+    f64::try_from(value).map_err(|err| tonic::Status::new(tonic::Code::Internal, format!("{err}")))
+}
+
+impl From<HedgingDecision> for proto::HedgingDecision {
+    fn from(decision: HedgingDecision) -> Self {
+        Self {
+            correlation_id: uuid::Uuid::from(decision.correlation_id).to_string(),
+            action: decision.action,
+            decided_at: decision.decided_at.timestamp(),
+        }
+    }
+}
+
+impl TryFrom<OpenOrder> for proto::PendingOrder {
+    type Error = tonic::Status;
+
+    fn try_from(order: OpenOrder) -> Result<Self, Self::Error> {
+        Ok(Self {
+            client_order_id: order.client_order_id,
+            correlation_id: order.correlation_id.to_string(),
+            action: order.action,
+            size: to_double(order.size.unwrap_or_default())?,
+            unit: order.unit,
+            state: order.state.unwrap_or_default(),
+            lost: order.lost,
+            created_at: order.created_at.timestamp(),
+        })
+    }
+}
+
+impl TryFrom<PendingTransfer> for proto::PendingTransfer {
+    type Error = tonic::Status;
+
+    fn try_from(transfer: PendingTransfer) -> Result<Self, Self::Error> {
+        Ok(Self {
+            client_transfer_id: transfer.client_transfer_id,
+            correlation_id: transfer.correlation_id.to_string(),
+            action: transfer.action,
+            amount_in_btc: to_double(transfer.amount)?,
+            fee_in_btc: to_double(transfer.fee)?,
+            transfer_from: transfer.transfer_from.unwrap_or_default(),
+            transfer_to: transfer.transfer_to.unwrap_or_default(),
+            lost: transfer.lost,
+            created_at: transfer.created_at.timestamp(),
+        })
+    }
+}
@@ -0,0 +1,259 @@
+mod config;
+mod convert;
+
+#[allow(clippy::all)]
+pub mod proto {
+    tonic::include_proto!("services.hedging.admin.v1");
+}
+
+use proto::{hedging_admin_service_server::HedgingAdminService, *};
+use tonic::{transport::Server, Request, Response, Status};
+use tracing::instrument;
+
+use std::sync::Arc;
+
+use ledger::Ledger;
+use shared::pubsub::CorrelationId;
+
+use crate::{
+    control::HedgingControl,
+    error::HedgingError,
+    okex::{job, OkexEngine},
+};
+use convert::to_double;
+
+pub use config::*;
+
+pub struct HedgingAdmin {
+    pool: sqlx::PgPool,
+    ledger: Ledger,
+    control: HedgingControl,
+    okex: Option<Arc<OkexEngine>>,
+    auth_token: String,
+}
+
+impl HedgingAdmin {
+    pub fn new(
+        pool: sqlx::PgPool,
+        ledger: Ledger,
+        control: HedgingControl,
+        okex: Option<Arc<OkexEngine>>,
+        auth_token: String,
+    ) -> Self {
+        Self {
+            pool,
+            ledger,
+            control,
+            okex,
+            auth_token,
+        }
+    }
+
+    /// Checks the bearer token of calls that change the state of hedging
+    fn authorize<T>(&self, request: &Request<T>) -> Result<(), Status> {
+        if self.auth_token.is_empty() {
+            return Err(Status::permission_denied("No admin auth token configured"));
+        }
+        let token = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| Status::unauthenticated("Missing admin auth token"))?;
+        if !constant_time_eq(token.as_bytes(), self.auth_token.as_bytes()) {
+            return Err(Status::unauthenticated("Invalid admin auth token"));
+        }
+        Ok(())
+    }
+
+    fn okex(&self) -> Result<&OkexEngine, Status> {
+        self.okex
+            .as_deref()
+            .ok_or_else(|| Status::failed_precondition("OKX hedging is not configured"))
+    }
+
+    async fn okex_state(&self, okex: &OkexEngine) -> Result<OkexState, Status> {
+        let client = okex.okex_client();
+        let position = client
+            .get_position_in_signed_usd_cents()
+            .await
+            .map_err(HedgingError::from)?;
+        let trading = client
+            .trading_account_balance()
+            .await
+            .map_err(HedgingError::from)?;
+        let funding = client
+            .funding_account_balance()
+            .await
+            .map_err(HedgingError::from)?;
+        let last_decisions = self.control.last_decisions().await;
+        Ok(OkexState {
+            position_in_cents: to_double(position.usd_cents)?,
+            trading_total_balance_in_btc: to_double(trading.total_amt_in_btc)?,
+            trading_used_balance_in_btc: to_double(trading.used_amt_in_btc)?,
+            funding_total_balance_in_btc: to_double(funding.total_amt_in_btc)?,
+            funding_free_balance_in_btc: to_double(funding.free_amt_in_btc)?,
+            last_hedge_decision: last_decisions.okex_hedge.map(HedgingDecision::from),
+            last_funding_decision: last_decisions.okex_funding.map(HedgingDecision::from),
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl HedgingAdminService for HedgingAdmin {
+    #[instrument(name = "hedging.admin.get_hedging_state", skip_all,
+        fields(error, error.level, error.message),
+        err
+    )]
+    async fn get_hedging_state(
+        &self,
+        _request: Request<GetHedgingStateRequest>,
+    ) -> Result<Response<GetHedgingStateResponse>, Status> {
+        shared::tracing::record_error(tracing::Level::ERROR, || async move {
+            let liability = self
+                .ledger
+                .balances()
+                .target_liability_in_cents()
+                .await
+                .map_err(HedgingError::from)?;
+            let paused = self.control.is_paused().await?;
+            let okex = match self.okex.as_deref() {
+                Some(okex) => Some(self.okex_state(okex).await?),
+                None => None,
+            };
+            Ok(Response::new(GetHedgingStateResponse {
+                liability_in_cents: to_double(liability.into())?,
+                paused,
+                okex,
+            }))
+        })
+        .await
+    }
+
+    #[instrument(name = "hedging.admin.list_pending_orders", skip_all,
+        fields(error, error.level, error.message),
+        err
+    )]
+    async fn list_pending_orders(
+        &self,
+        _request: Request<ListPendingOrdersRequest>,
+    ) -> Result<Response<ListPendingOrdersResponse>, Status> {
+        shared::tracing::record_error(tracing::Level::ERROR, || async move {
+            let orders = self
+                .okex()?
+                .orders()
+                .list_open_orders()
+                .await?
+                .into_iter()
+                .map(PendingOrder::try_from)
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Response::new(ListPendingOrdersResponse { orders }))
+        })
+        .await
+    }
+
+    #[instrument(name = "hedging.admin.list_pending_transfers", skip_all,
+        fields(error, error.level, error.message),
+        err
+    )]
+    async fn list_pending_transfers(
+        &self,
+        _request: Request<ListPendingTransfersRequest>,
+    ) -> Result<Response<ListPendingTransfersResponse>, Status> {
+        shared::tracing::record_error(tracing::Level::ERROR, || async move {
+            let transfers = self
+                .okex()?
+                .transfers()
+                .list_pending_transfers()
+                .await?
+                .into_iter()
+                .map(PendingTransfer::try_from)
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Response::new(ListPendingTransfersResponse { transfers }))
+        })
+        .await
+    }
+
+    #[instrument(name = "hedging.admin.pause_hedging", skip_all,
+        fields(reason = request.get_ref().reason, error, error.level, error.message),
+        err
+    )]
+    async fn pause_hedging(
+        &self,
+        request: Request<PauseHedgingRequest>,
+    ) -> Result<Response<PauseHedgingResponse>, Status> {
+        shared::tracing::record_error(tracing::Level::ERROR, || async move {
+            self.authorize(&request)?;
+            let reason = request.into_inner().reason;
+            self.control
+                .pause((!reason.is_empty()).then_some(reason))
+                .await?;
+            Ok(Response::new(PauseHedgingResponse {}))
+        })
+        .await
+    }
+
+    #[instrument(name = "hedging.admin.resume_hedging", skip_all,
+        fields(error, error.level, error.message),
+        err
+    )]
+    async fn resume_hedging(
+        &self,
+        request: Request<ResumeHedgingRequest>,
+    ) -> Result<Response<ResumeHedgingResponse>, Status> {
+        shared::tracing::record_error(tracing::Level::ERROR, || async move {
+            self.authorize(&request)?;
+            self.control.resume().await?;
+            Ok(Response::new(ResumeHedgingResponse {}))
+        })
+        .await
+    }
+
+    #[instrument(name = "hedging.admin.trigger_adjust_hedge", skip_all,
+        fields(correlation_id, error, error.level, error.message),
+        err
+    )]
+    async fn trigger_adjust_hedge(
+        &self,
+        request: Request<TriggerAdjustHedgeRequest>,
+    ) -> Result<Response<TriggerAdjustHedgeResponse>, Status> {
+        shared::tracing::record_error(tracing::Level::ERROR, || async move {
+            self.authorize(&request)?;
+            self.okex()?;
+            let correlation_id = CorrelationId::new();
+            job::spawn_adjust_hedge(&self.pool, correlation_id).await?;
+            Ok(Response::new(TriggerAdjustHedgeResponse {
+                correlation_id: correlation_id.to_string(),
+            }))
+        })
+        .await
+    }
+}
+
+pub(crate) async fn start(
+    config: HedgingAdminConfig,
+    admin: HedgingAdmin,
+) -> Result<(), HedgingError> {
+    Server::builder()
+        .add_service(proto::hedging_admin_service_server::HedgingAdminServiceServer::new(admin))
+        .serve((config.listen_address, config.listen_port).into())
+        .await?;
+    Ok(())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_time_eq_compares_whole_token() {
+        assert!(constant_time_eq(b"token", b"token"));
+        assert!(!constant_time_eq(b"token", b"tokem"));
+        assert!(!constant_time_eq(b"token", b"token2"));
+        assert!(!constant_time_eq(b"", b"token"));
+    }
+}
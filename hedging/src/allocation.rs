@@ -0,0 +1,107 @@
+use rust_decimal::Decimal;
+
+use std::collections::HashMap;
+
+use shared::payload::SyntheticCentLiability;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LiabilityAllocation {
+    pub total_liability: SyntheticCentLiability,
+    pub share: Decimal,
+    pub target_liability: SyntheticCentLiability,
+}
+
+/// Splits the synthetic USD liability across the hedging exchanges according to their
+/// configured weight.
+#[derive(Debug, Clone, Default)]
+pub struct LiabilityAllocator {
+    weights: HashMap<&'static str, Decimal>,
+}
+
+impl LiabilityAllocator {
+    pub fn add_exchange(&mut self, exchange_id: &'static str, weight: Decimal) {
+        if weight > Decimal::ZERO {
+            self.weights.insert(exchange_id, weight);
+        }
+    }
+
+    pub fn share(&self, exchange_id: &str) -> Decimal {
+        let total_weight: Decimal = self.weights.values().sum();
+        if total_weight.is_zero() {
+            return Decimal::ZERO;
+        }
+        self.weights
+            .get(exchange_id)
+            .map(|weight| weight / total_weight)
+            .unwrap_or(Decimal::ZERO)
+    }
+
+    pub fn allocate(
+        &self,
+        exchange_id: &str,
+        total_liability: SyntheticCentLiability,
+    ) -> LiabilityAllocation {
+        let share = self.share(exchange_id);
+        let target_liability =
+            SyntheticCentLiability::try_from((total_liability * share).round_dp(0))
+                .expect("allocated liability has wrong sign");
+        LiabilityAllocation {
+            total_liability,
+            share,
+            target_liability,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    fn liability(amount: Decimal) -> SyntheticCentLiability {
+        SyntheticCentLiability::try_from(amount).unwrap()
+    }
+
+    #[test]
+    fn single_exchange_gets_everything() {
+        let mut allocator = LiabilityAllocator::default();
+        allocator.add_exchange("okex", dec!(0.5));
+        let allocation = allocator.allocate("okex", liability(dec!(100_000)));
+        assert_eq!(allocation.share, dec!(1));
+        assert_eq!(allocation.target_liability, dec!(100_000));
+        assert_eq!(allocation.total_liability, dec!(100_000));
+    }
+
+    #[test]
+    fn split_by_weight() {
+        let mut allocator = LiabilityAllocator::default();
+        allocator.add_exchange("okex", dec!(3));
+        allocator.add_exchange("bitfinex", dec!(1));
+        let total = liability(dec!(100_000));
+        assert_eq!(
+            allocator.allocate("okex", total).target_liability,
+            dec!(75_000)
+        );
+        assert_eq!(
+            allocator.allocate("bitfinex", total).target_liability,
+            dec!(25_000)
+        );
+    }
+
+    #[test]
+    fn unknown_or_zero_weight_exchange_gets_nothing() {
+        let mut allocator = LiabilityAllocator::default();
+        allocator.add_exchange("okex", dec!(1));
+        allocator.add_exchange("bitfinex", dec!(0));
+        let total = liability(dec!(100_000));
+        assert_eq!(
+            allocator.allocate("bitfinex", total).target_liability,
+            dec!(0)
+        );
+        assert_eq!(
+            allocator.allocate("okex", total).target_liability,
+            dec!(100_000)
+        );
+    }
+}
@@ -1,52 +1,131 @@
 use futures::stream::StreamExt;
+use rust_decimal::Decimal;
 use sqlxmq::OwnedHandle;
 
+use std::sync::Arc;
+
 use galoy_client::*;
 use shared::{
     health::HealthCheckTrigger,
-    payload::PriceStreamPayload,
+    payload::{PriceStreamPayload, BITFINEX_EXCHANGE_ID, OKEX_EXCHANGE_ID},
     pubsub::{memory, PubSubConfig, Publisher, Subscriber},
 };
 
-use crate::{config::*, error::*, okex::*};
+use crate::{
+    admin::*, allocation::*, bitfinex::*, config::*, control::*, engine::*, error::*, okex::*,
+    shadow::*, user_trades_sync::*,
+};
 
 pub struct HedgingApp {
     _job_runner_handle: OwnedHandle,
+    admin: Option<(HedgingAdminConfig, HedgingAdmin)>,
+    health_checker: tokio::task::JoinHandle<()>,
 }
 
 impl HedgingApp {
+    /// Serves the admin service if it is enabled, otherwise waits on the health checker.
+    /// Either way the jobs keep running until this returns.
+    pub async fn serve_admin(self) -> Result<(), HedgingError> {
+        let HedgingApp {
+            _job_runner_handle,
+            admin,
+            health_checker,
+        } = self;
+        match admin {
+            Some((config, admin)) => {
+                tracing::info!(
+                    listen_address = %config.listen_address,
+                    listen_port = config.listen_port,
+                    "starting hedging admin server"
+                );
+                start(config, admin).await?;
+            }
+            None => {
+                let _ = health_checker.await;
+            }
+        }
+        Ok(())
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub async fn run(
         pool: sqlx::PgPool,
         health_check_trigger: HealthCheckTrigger,
         HedgingAppConfig {
-            health: health_cfg, ..
+            health: health_cfg,
+            mode,
+            user_trades_sync,
+            admin: admin_config,
         }: HedgingAppConfig,
-        okex_config: OkexConfig,
+        exchanges: ExchangesConfig,
         galoy_client_cfg: GaloyClientConfig,
         pubsub_config: PubSubConfig,
         price_receiver: memory::Subscriber<PriceStreamPayload>,
     ) -> Result<Self, HedgingError> {
-        let (mut jobs, mut channels) = (Vec::new(), Vec::new());
-        OkexEngine::register_jobs(&mut jobs, &mut channels);
+        exchanges.validate()?;
+        let ExchangesConfig { okex, bitfinex } = exchanges;
+        let okex = okex.filter(|exchange| exchange.weight > Decimal::ZERO);
+        let bitfinex = bitfinex.filter(|exchange| exchange.weight > Decimal::ZERO);
 
-        let mut job_registry = sqlxmq::JobRegistry::new(&jobs);
+        let mut liability_allocator = LiabilityAllocator::default();
+        if let Some(exchange) = okex.as_ref() {
+            liability_allocator.add_exchange(OKEX_EXCHANGE_ID, exchange.weight);
+        }
+        if let Some(exchange) = bitfinex.as_ref() {
+            liability_allocator.add_exchange(BITFINEX_EXCHANGE_ID, exchange.weight);
+        }
 
         let ledger = ledger::Ledger::init(&pool).await?;
-        job_registry.set_context(ledger.clone());
-        job_registry.set_context(GaloyClient::connect(galoy_client_cfg).await?);
-        job_registry.set_context(Publisher::new(pubsub_config.clone()).await?);
 
-        let (okex_engine, subscriber) = OkexEngine::run(
-            pool.clone(),
-            okex_config,
-            ledger,
-            pubsub_config,
-            price_receiver.resubscribe(),
-        )
-        .await?;
+        let mut engines: Vec<Arc<dyn HedgingEngine>> = Vec::new();
+        let mut position_subscriber = None;
+        let mut liquidation_monitor = None;
+        let mut okex_engine = None;
+        if let Some(exchange) = okex {
+            let (engine, subscriber) = OkexEngine::run(
+                pool.clone(),
+                exchange.config,
+                ledger.clone(),
+                liability_allocator.clone(),
+                pubsub_config.clone(),
+                price_receiver.resubscribe(),
+            )
+            .await?;
+            position_subscriber = Some(subscriber);
+            liquidation_monitor = Some(engine.liquidation_monitor());
+            okex_engine = Some(Arc::clone(&engine));
+            engines.push(engine);
+        }
+        if let Some(exchange) = bitfinex {
+            let engine = BitfinexEngine::run(
+                pool.clone(),
+                exchange.config,
+                ledger.clone(),
+                liability_allocator.clone(),
+            )
+            .await?;
+            engines.push(engine);
+        }
 
-        okex_engine.add_context_to_job_registry(&mut job_registry);
+        let (mut jobs, mut channels) = (Vec::new(), Vec::new());
+        for engine in engines.iter() {
+            engine.register_jobs(&mut jobs, &mut channels);
+        }
+        let mut job_registry = sqlxmq::JobRegistry::new(&jobs);
+        for engine in engines.iter() {
+            tracing::info!(exchange = engine.exchange_id(), "hedging engine started");
+            engine.add_context_to_job_registry(&mut job_registry);
+        }
+
+        job_registry.set_context(ledger.clone());
+        job_registry.set_context(GaloyClient::connect(galoy_client_cfg).await?);
+        job_registry.set_context(Publisher::new(pubsub_config).await?);
+        job_registry.set_context(liability_allocator);
+        job_registry.set_context(mode);
+        job_registry.set_context(ShadowActions::new(pool.clone()));
+        job_registry.set_context(UserTradesSync::new(pool.clone(), user_trades_sync));
+        let control = HedgingControl::new(pool.clone());
+        job_registry.set_context(control.clone());
 
         let job_runner_handle = job_registry
             .runner(&pool)
@@ -54,36 +133,64 @@ impl HedgingApp {
             .run()
             .await?;
 
-        Self::spawn_health_checker(health_check_trigger, health_cfg, subscriber, price_receiver)
-            .await;
+        let admin = admin_config.enabled.then(|| {
+            let auth_token = admin_config.auth_token.clone();
+            (
+                admin_config,
+                HedgingAdmin::new(pool.clone(), ledger, control, okex_engine, auth_token),
+            )
+        });
+
+        let health_checker = Self::spawn_health_checker(
+            health_check_trigger,
+            health_cfg,
+            position_subscriber,
+            price_receiver,
+            liquidation_monitor,
+        );
         let app = HedgingApp {
             _job_runner_handle: job_runner_handle,
+            admin,
+            health_checker,
         };
         Ok(app)
     }
 
-    async fn spawn_health_checker(
+    fn spawn_health_checker(
         mut health_check_trigger: HealthCheckTrigger,
         health_cfg: HedgingAppHealthConfig,
-        position_sub: Subscriber,
+        position_sub: Option<Subscriber>,
         price_sub: memory::Subscriber<PriceStreamPayload>,
-    ) {
-        while let Some(check) = health_check_trigger.next().await {
-            match (
-                position_sub
-                    .healthy(health_cfg.unhealthy_msg_interval_position)
-                    .await,
-                price_sub
-                    .healthy(health_cfg.unhealthy_msg_interval_price)
-                    .await,
-            ) {
-                (Err(e), _) | (_, Err(e)) => {
-                    let _ = check.send(Err(e));
-                }
-                _ => {
-                    let _ = check.send(Ok(()));
+        liquidation_monitor: Option<LiquidationMonitor>,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            while let Some(check) = health_check_trigger.next().await {
+                let position_health = match position_sub.as_ref() {
+                    Some(sub) => {
+                        sub.healthy(health_cfg.unhealthy_msg_interval_position)
+                            .await
+                    }
+                    None => Ok(()),
+                };
+                let liquidation_health = match liquidation_monitor.as_ref() {
+                    Some(monitor) => monitor.healthy().await,
+                    None => Ok(()),
+                };
+                match (
+                    position_health,
+                    price_sub
+                        .healthy(health_cfg.unhealthy_msg_interval_price)
+                        .await,
+                    liquidation_health,
+                ) {
+                    (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+                        let _ = check.send(Err(e));
+                    }
+                    _ => {
+                        let _ = check.send(Ok(()));
+                    }
                 }
             }
-        }
+        })
     }
 }
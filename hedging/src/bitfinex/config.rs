@@ -0,0 +1,122 @@
+use bitfinex_client::BitfinexConfig as BitfinexClientConfig;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[serde_with::serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BitfinexConfig {
+    #[serde(default)]
+    pub client: BitfinexClientConfig,
+    #[serde_as(as = "serde_with::DurationSeconds<u64>")]
+    #[serde(default = "default_bitfinex_poll_frequency")]
+    pub poll_frequency: Duration,
+    #[serde(default = "default_bitfinex_leverage")]
+    pub leverage: Decimal,
+    #[serde(default)]
+    pub hedging: BitfinexHedgingConfig,
+    #[serde(default)]
+    pub funding: BitfinexFundingConfig,
+}
+impl Default for BitfinexConfig {
+    fn default() -> Self {
+        Self {
+            client: BitfinexClientConfig::default(),
+            poll_frequency: default_bitfinex_poll_frequency(),
+            leverage: default_bitfinex_leverage(),
+            hedging: BitfinexHedgingConfig::default(),
+            funding: BitfinexFundingConfig::default(),
+        }
+    }
+}
+
+fn default_bitfinex_poll_frequency() -> Duration {
+    Duration::from_secs(10)
+}
+
+fn default_bitfinex_leverage() -> Decimal {
+    dec!(3)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BitfinexHedgingConfig {
+    #[serde(default = "default_low_bound_ratio_shorting")]
+    pub low_bound_ratio_shorting: Decimal,
+    #[serde(default = "default_low_safebound_ratio_shorting")]
+    pub low_safebound_ratio_shorting: Decimal,
+    #[serde(default = "default_high_safebound_ratio_shorting")]
+    pub high_safebound_ratio_shorting: Decimal,
+    #[serde(default = "default_high_bound_ratio_shorting")]
+    pub high_bound_ratio_shorting: Decimal,
+
+    #[serde(default = "default_minimum_liability_threshold_cents")]
+    pub minimum_liability_threshold_cents: Decimal,
+    /// Adjustments smaller than this are not worth an order.
+    #[serde(default = "default_minimum_order_size_cents")]
+    pub minimum_order_size_cents: Decimal,
+}
+impl Default for BitfinexHedgingConfig {
+    fn default() -> Self {
+        Self {
+            low_bound_ratio_shorting: default_low_bound_ratio_shorting(),
+            low_safebound_ratio_shorting: default_low_safebound_ratio_shorting(),
+            high_safebound_ratio_shorting: default_high_safebound_ratio_shorting(),
+            high_bound_ratio_shorting: default_high_bound_ratio_shorting(),
+            minimum_liability_threshold_cents: default_minimum_liability_threshold_cents(),
+            minimum_order_size_cents: default_minimum_order_size_cents(),
+        }
+    }
+}
+
+fn default_minimum_liability_threshold_cents() -> Decimal {
+    dec!(5000)
+}
+fn default_minimum_order_size_cents() -> Decimal {
+    dec!(1000)
+}
+fn default_low_bound_ratio_shorting() -> Decimal {
+    dec!(0.95)
+}
+fn default_low_safebound_ratio_shorting() -> Decimal {
+    dec!(0.98)
+}
+fn default_high_safebound_ratio_shorting() -> Decimal {
+    dec!(1.00)
+}
+fn default_high_bound_ratio_shorting() -> Decimal {
+    dec!(1.03)
+}
+
+/// Collateral is held in USDt, in the margin wallet while it backs the position
+/// and in the exchange wallet otherwise.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BitfinexFundingConfig {
+    /// Collateral topped up to this multiple of what the position needs at `leverage`.
+    #[serde(default = "default_low_bound_ratio_collateral")]
+    pub low_bound_ratio_collateral: Decimal,
+    /// Collateral above this multiple is moved back to the exchange wallet.
+    #[serde(default = "default_high_bound_ratio_collateral")]
+    pub high_bound_ratio_collateral: Decimal,
+    #[serde(default = "default_minimum_transfer_amount_usd")]
+    pub minimum_transfer_amount_usd: Decimal,
+}
+impl Default for BitfinexFundingConfig {
+    fn default() -> Self {
+        Self {
+            low_bound_ratio_collateral: default_low_bound_ratio_collateral(),
+            high_bound_ratio_collateral: default_high_bound_ratio_collateral(),
+            minimum_transfer_amount_usd: default_minimum_transfer_amount_usd(),
+        }
+    }
+}
+
+fn default_low_bound_ratio_collateral() -> Decimal {
+    dec!(1.2)
+}
+fn default_high_bound_ratio_collateral() -> Decimal {
+    dec!(1.5)
+}
+fn default_minimum_transfer_amount_usd() -> Decimal {
+    dec!(100)
+}
@@ -0,0 +1,376 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use sqlxmq::NamedJob;
+use tracing::{info_span, Instrument};
+
+use std::sync::Arc;
+
+use bitfinex_client::{
+    BitfinexClient, ClientId, Currency, Instrument as BitfinexInstrument, Wallet,
+};
+use ledger::Ledger;
+use shared::{payload::*, pubsub::CorrelationId};
+
+use super::{config::*, funding_adjustment::*, hedge_adjustment::*, job, orders::*};
+use crate::{allocation::*, engine::*, error::HedgingError};
+
+const BTC_AMOUNT_PRECISION: u32 = 8;
+
+#[derive(Clone)]
+pub struct BitfinexEngine {
+    config: BitfinexConfig,
+    pool: sqlx::PgPool,
+    bitfinex_client: BitfinexClient,
+    ledger: Ledger,
+    liability_allocator: LiabilityAllocator,
+    hedging_adjustment: BitfinexHedgingAdjustment,
+    funding: BitfinexFunding,
+    orders: BitfinexOrders,
+}
+
+impl BitfinexEngine {
+    pub async fn run(
+        pool: sqlx::PgPool,
+        config: BitfinexConfig,
+        ledger: Ledger,
+        liability_allocator: LiabilityAllocator,
+    ) -> Result<Arc<Self>, HedgingError> {
+        let bitfinex_client = BitfinexClient::new(config.client.clone()).await?;
+        let hedging_adjustment = BitfinexHedgingAdjustment::new(config.hedging.clone());
+        let funding = BitfinexFunding::new(config.funding.clone(), config.leverage);
+        let orders = BitfinexOrders::new(pool.clone());
+        let ret = Arc::new(Self {
+            config,
+            pool,
+            bitfinex_client,
+            ledger,
+            liability_allocator,
+            hedging_adjustment,
+            funding,
+            orders,
+        });
+
+        Arc::clone(&ret).spawn_liability_listener().await?;
+
+        Arc::clone(&ret).spawn_non_stop_polling().await?;
+
+        Ok(ret)
+    }
+
+    pub fn hedging_adjustment(&self) -> &BitfinexHedgingAdjustment {
+        &self.hedging_adjustment
+    }
+
+    /// Persists the order and submits it unless this job already did.
+    /// Returns whether an order was submitted.
+    pub async fn execute_adjustment(
+        &self,
+        correlation_id: CorrelationId,
+        action: &BitfinexHedgeAdjustment,
+        allocation: &LiabilityAllocation,
+    ) -> Result<bool, HedgingError> {
+        let order = match *action {
+            BitfinexHedgeAdjustment::DoNothing => return Ok(false),
+            BitfinexHedgeAdjustment::ClosePosition => {
+                if self.position_in_btc().await?.is_zero() {
+                    return Ok(false);
+                }
+                None
+            }
+            BitfinexHedgeAdjustment::Sell(usd_cents) => Some((HedgeOrderSide::Sell, usd_cents)),
+            BitfinexHedgeAdjustment::Buy(usd_cents) => Some((HedgeOrderSide::Buy, usd_cents)),
+        };
+        let instrument = self.instrument().to_string();
+        let ReservedOrder {
+            client_id: id,
+            retry,
+        } = match self
+            .orders
+            .reserve_order_slot(BitfinexOrderReservation {
+                correlation_id,
+                instrument: &instrument,
+                action,
+                target_usd_value: allocation.target_liability * Decimal::NEGATIVE_ONE,
+                total_target_usd_value: allocation.total_liability * Decimal::NEGATIVE_ONE,
+                liability_share: allocation.share,
+            })
+            .await?
+        {
+            Some(reserved) => reserved,
+            None => return Ok(false),
+        };
+        if retry {
+            // The previous attempt may have failed after the exchange accepted the order
+            let id = i64::from(id.clone());
+            if let Some(status) = self
+                .order_history()
+                .await?
+                .into_iter()
+                .find(|status| i64::from(status.client_id.clone()) == id)
+            {
+                self.orders.update_order(status).await?;
+                return Ok(false);
+            }
+        }
+        let client_id = i64::from(id.clone()).to_string();
+        match order {
+            Some((side, usd_cents)) => self.place_order(client_id, side, usd_cents).await?,
+            None => self.close_positions(client_id).await?,
+        }
+        self.orders.mark_as_submitted(id).await?;
+        Ok(true)
+    }
+
+    /// Moves USDt between the exchange and margin wallets so that the margin wallet
+    /// backs the larger of the current and the target position at `leverage`.
+    pub async fn adjust_collateral(
+        &self,
+        target_liability: SyntheticCentLiability,
+        current_position: SyntheticCentExposure,
+    ) -> Result<BitfinexFundingAdjustment, HedgingError> {
+        let abs_position =
+            Decimal::from(target_liability).max(Decimal::from(current_position).abs());
+        let collateral = self.collateral().await?;
+        let action = self.funding.determine_action(abs_position, &collateral);
+        let client_id = i64::from(ClientId::new()).to_string();
+        match action {
+            BitfinexFundingAdjustment::TransferFundingToTrading(amount) => {
+                self.transfer_funding_to_trading(client_id, amount).await?
+            }
+            BitfinexFundingAdjustment::TransferTradingToFunding(amount) => {
+                self.transfer_trading_to_funding(client_id, amount).await?
+            }
+            BitfinexFundingAdjustment::DoNothing => (),
+        }
+        Ok(action)
+    }
+
+    async fn collateral(&self) -> Result<BitfinexCollateral, HedgingError> {
+        let (margin_currency, exchange_currency) = if self.config.client.simulated {
+            (Currency::TESTUSDTF0, Currency::TESTUSDT)
+        } else {
+            (Currency::USTF0, Currency::UST)
+        };
+        let mut collateral = BitfinexCollateral {
+            margin_total: Decimal::ZERO,
+            margin_available: Decimal::ZERO,
+            exchange_available: Decimal::ZERO,
+        };
+        for wallet in self.bitfinex_client.get_wallets().await? {
+            if wallet.wallet_type == Wallet::MARGIN.to_string()
+                && wallet.currency == margin_currency.to_string()
+            {
+                collateral.margin_total += wallet.balance;
+                collateral.margin_available += wallet.balance_available;
+            } else if wallet.wallet_type == Wallet::EXCHANGE.to_string()
+                && wallet.currency == exchange_currency.to_string()
+            {
+                collateral.exchange_available += wallet.balance_available;
+            }
+        }
+        Ok(collateral)
+    }
+
+    /// Updates fills and status of submitted orders that are not complete yet.
+    pub async fn poll_orders(&self) -> Result<(), HedgingError> {
+        let open_orders = self.orders.open_orders().await?;
+        if open_orders.is_empty() {
+            return Ok(());
+        }
+        let mut history = self.order_history().await?;
+        for id in open_orders {
+            let id = i64::from(id);
+            if let Some(idx) = history
+                .iter()
+                .position(|status| i64::from(status.client_id.clone()) == id)
+            {
+                self.orders.update_order(history.swap_remove(idx)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn order_history(&self) -> Result<Vec<BitfinexOrderStatus>, HedgingError> {
+        Ok(self
+            .bitfinex_client
+            .get_orders()
+            .await?
+            .into_iter()
+            .map(|details| {
+                let complete = details.complete
+                    || details
+                        .order_status
+                        .as_deref()
+                        .map(|state| state.starts_with("EXECUTED") || state.starts_with("CANCELED"))
+                        .unwrap_or(false);
+                BitfinexOrderStatus {
+                    client_id: details.client_id,
+                    order_id: i64::try_from(details.id).expect("order id overflows i64"),
+                    state: details.order_status,
+                    btc_amount: details.amount_original,
+                    btc_filled: details.amount_original - details.amount,
+                    avg_price: details.price_avg,
+                    complete,
+                }
+            })
+            .collect())
+    }
+
+    async fn usd_cents_to_btc(&self, usd_cents: Decimal) -> Result<Decimal, HedgingError> {
+        let last_price = self.bitfinex_client.get_last_price_in_usd_cents().await?;
+        Ok((usd_cents.abs() / last_price.usd_cents).round_dp(BTC_AMOUNT_PRECISION))
+    }
+
+    pub async fn liability_allocation(&self) -> Result<LiabilityAllocation, HedgingError> {
+        let total_liability = self.ledger.balances().target_liability_in_cents().await?;
+        Ok(self
+            .liability_allocator
+            .allocate(BITFINEX_EXCHANGE_ID, total_liability))
+    }
+
+    fn instrument(&self) -> BitfinexInstrument {
+        if self.config.client.simulated {
+            BitfinexInstrument::TestBtcUsdSwap
+        } else {
+            BitfinexInstrument::BtcUsdSwap
+        }
+    }
+
+    async fn position_in_btc(&self) -> Result<Decimal, HedgingError> {
+        let instrument = self.instrument().to_string();
+        Ok(self
+            .bitfinex_client
+            .get_positions()
+            .await?
+            .into_iter()
+            .filter(|position| position.symbol == instrument)
+            .map(|position| position.amount)
+            .sum())
+    }
+
+    async fn spawn_liability_listener(self: Arc<Self>) -> Result<(), HedgingError> {
+        job::spawn_adjust_hedge(&self.pool, uuid::Uuid::new_v4()).await?;
+        tokio::spawn(async move {
+            let mut events = self.ledger.usd_liability_balance_events().await;
+            loop {
+                match events.recv().await {
+                    Ok(received) => {
+                        if let ledger::LedgerEventData::BalanceUpdated(data) = received.data {
+                            let correlation_id = data.entry_id;
+                            let span = info_span!(
+                                parent: &received.span,
+                                "hedging.bitfinex.usd_liability_balance_event_received",
+                                correlation_id = %correlation_id,
+                            );
+                            let _ = job::spawn_adjust_hedge(&self.pool, correlation_id)
+                                .instrument(span)
+                                .await;
+                        }
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => (),
+                    _ => {
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+
+    async fn spawn_non_stop_polling(self: Arc<Self>) -> Result<(), HedgingError> {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(self.config.poll_frequency).await;
+                let _ = job::spawn_poll_bitfinex(&self.pool).await;
+                let _ = job::spawn_adjust_hedge(&self.pool, uuid::Uuid::new_v4()).await;
+            }
+        });
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl HedgingEngine for BitfinexEngine {
+    fn exchange_id(&self) -> &'static str {
+        BITFINEX_EXCHANGE_ID
+    }
+
+    fn register_jobs(&self, jobs: &mut Vec<&'static NamedJob>, channels: &mut Vec<&'static str>) {
+        jobs.push(job::adjust_bitfinex_hedge);
+        jobs.push(job::poll_bitfinex);
+        channels.push("hedging.bitfinex");
+    }
+
+    fn add_context_to_job_registry(&self, runner: &mut sqlxmq::JobRegistry) {
+        runner.set_context(self.clone());
+    }
+
+    async fn get_position_in_signed_usd_cents(
+        &self,
+    ) -> Result<SyntheticCentExposure, HedgingError> {
+        let btc_amount = self.position_in_btc().await?;
+        if btc_amount.is_zero() {
+            return Ok(Decimal::ZERO.into());
+        }
+        let last_price = self.bitfinex_client.get_last_price_in_usd_cents().await?;
+        Ok((btc_amount * last_price.usd_cents).into())
+    }
+
+    async fn place_order(
+        &self,
+        client_id: String,
+        side: HedgeOrderSide,
+        usd_cents: Decimal,
+    ) -> Result<(), HedgingError> {
+        let btc_amount = self.usd_cents_to_btc(usd_cents).await?;
+        let signed_btc_amount = match side {
+            HedgeOrderSide::Buy => btc_amount,
+            HedgeOrderSide::Sell => btc_amount * Decimal::NEGATIVE_ONE,
+        };
+        self.bitfinex_client
+            .submit_order(
+                ClientId::from(client_id),
+                signed_btc_amount,
+                self.config.leverage,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn close_positions(&self, client_id: String) -> Result<(), HedgingError> {
+        let btc_amount = self.position_in_btc().await?;
+        if btc_amount.is_zero() {
+            return Ok(());
+        }
+        self.bitfinex_client
+            .submit_order(
+                ClientId::from(client_id),
+                btc_amount * Decimal::NEGATIVE_ONE,
+                self.config.leverage,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn transfer_funding_to_trading(
+        &self,
+        client_id: String,
+        amount: Decimal,
+    ) -> Result<(), HedgingError> {
+        self.bitfinex_client
+            .transfer_funding_to_trading(ClientId::from(client_id), amount)
+            .await?;
+        Ok(())
+    }
+
+    async fn transfer_trading_to_funding(
+        &self,
+        client_id: String,
+        amount: Decimal,
+    ) -> Result<(), HedgingError> {
+        self.bitfinex_client
+            .transfer_trading_to_funding(ClientId::from(client_id), amount)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use super::BitfinexFundingConfig;
+
+const CENTS_PER_USD: Decimal = dec!(100);
+const USD_AMOUNT_PRECISION: u32 = 2;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BitfinexFundingAdjustment {
+    DoNothing,
+    TransferTradingToFunding(Decimal),
+    TransferFundingToTrading(Decimal),
+}
+impl std::fmt::Display for BitfinexFundingAdjustment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BitfinexFundingAdjustment::DoNothing => write!(f, "DoNothing"),
+            BitfinexFundingAdjustment::TransferTradingToFunding(amount_in_usd) => {
+                write!(f, "TransferTradingToFunding({amount_in_usd})")
+            }
+            BitfinexFundingAdjustment::TransferFundingToTrading(amount_in_usd) => {
+                write!(f, "TransferFundingToTrading({amount_in_usd})")
+            }
+        }
+    }
+}
+impl BitfinexFundingAdjustment {
+    pub fn action_type(&self) -> &'static str {
+        match *self {
+            Self::DoNothing => "do-nothing",
+            Self::TransferTradingToFunding(_) => "transfer-trading-to-funding",
+            Self::TransferFundingToTrading(_) => "transfer-funding-to-trading",
+        }
+    }
+
+    pub fn size(&self) -> Option<Decimal> {
+        match *self {
+            Self::TransferTradingToFunding(size) | Self::TransferFundingToTrading(size) => {
+                Some(size)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        "usd"
+    }
+}
+
+/// Balances of the USDt collateral, in usd.
+#[derive(Debug, Clone)]
+pub struct BitfinexCollateral {
+    pub margin_total: Decimal,
+    pub margin_available: Decimal,
+    pub exchange_available: Decimal,
+}
+
+#[derive(Debug, Clone)]
+pub struct BitfinexFunding {
+    config: BitfinexFundingConfig,
+    leverage: Decimal,
+}
+
+impl BitfinexFunding {
+    pub fn new(config: BitfinexFundingConfig, leverage: Decimal) -> Self {
+        Self { config, leverage }
+    }
+
+    /// `abs_position_in_cents` is the largest position the collateral has to back,
+    /// i.e. the current one or the one the next order is heading to.
+    pub fn determine_action(
+        &self,
+        abs_position_in_cents: Decimal,
+        collateral: &BitfinexCollateral,
+    ) -> BitfinexFundingAdjustment {
+        let needed = abs_position_in_cents.abs() / CENTS_PER_USD / self.leverage;
+        let low_bound = needed * self.config.low_bound_ratio_collateral;
+        let high_bound = needed * self.config.high_bound_ratio_collateral;
+        let adjustment = if collateral.margin_total < low_bound {
+            let amount = (low_bound - collateral.margin_total).min(collateral.exchange_available);
+            BitfinexFundingAdjustment::TransferFundingToTrading(amount)
+        } else if collateral.margin_total > high_bound {
+            let amount = (collateral.margin_total - low_bound).min(collateral.margin_available);
+            BitfinexFundingAdjustment::TransferTradingToFunding(amount)
+        } else {
+            BitfinexFundingAdjustment::DoNothing
+        };
+        match adjustment {
+            BitfinexFundingAdjustment::TransferFundingToTrading(amount)
+            | BitfinexFundingAdjustment::TransferTradingToFunding(amount)
+                if amount.round_dp(USD_AMOUNT_PRECISION)
+                    < self.config.minimum_transfer_amount_usd =>
+            {
+                BitfinexFundingAdjustment::DoNothing
+            }
+            BitfinexFundingAdjustment::TransferFundingToTrading(amount) => {
+                BitfinexFundingAdjustment::TransferFundingToTrading(
+                    amount.round_dp(USD_AMOUNT_PRECISION),
+                )
+            }
+            BitfinexFundingAdjustment::TransferTradingToFunding(amount) => {
+                BitfinexFundingAdjustment::TransferTradingToFunding(
+                    amount.round_dp(USD_AMOUNT_PRECISION),
+                )
+            }
+            BitfinexFundingAdjustment::DoNothing => adjustment,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn funding() -> BitfinexFunding {
+        BitfinexFunding::new(BitfinexFundingConfig::default(), dec!(3))
+    }
+
+    fn collateral(margin_total: Decimal, exchange_available: Decimal) -> BitfinexCollateral {
+        BitfinexCollateral {
+            margin_total,
+            margin_available: margin_total,
+            exchange_available,
+        }
+    }
+
+    #[test]
+    fn collateral_within_bounds() {
+        // $3000 position at 3x needs $1000, bounds are $1200 and $1500
+        assert_eq!(
+            funding().determine_action(dec!(300_000), &collateral(dec!(1300), dec!(5000))),
+            BitfinexFundingAdjustment::DoNothing
+        );
+    }
+
+    #[test]
+    fn tops_up_collateral() {
+        assert_eq!(
+            funding().determine_action(dec!(300_000), &collateral(dec!(500), dec!(5000))),
+            BitfinexFundingAdjustment::TransferFundingToTrading(dec!(700))
+        );
+    }
+
+    #[test]
+    fn top_up_is_capped_by_exchange_wallet() {
+        assert_eq!(
+            funding().determine_action(dec!(300_000), &collateral(dec!(500), dec!(400))),
+            BitfinexFundingAdjustment::TransferFundingToTrading(dec!(400))
+        );
+    }
+
+    #[test]
+    fn moves_back_excess_collateral() {
+        assert_eq!(
+            funding().determine_action(dec!(300_000), &collateral(dec!(2000), dec!(0))),
+            BitfinexFundingAdjustment::TransferTradingToFunding(dec!(800))
+        );
+        assert_eq!(
+            funding().determine_action(dec!(0), &collateral(dec!(2000), dec!(0))),
+            BitfinexFundingAdjustment::TransferTradingToFunding(dec!(2000))
+        );
+    }
+
+    #[test]
+    fn ignores_transfers_below_minimum() {
+        assert_eq!(
+            funding().determine_action(dec!(300_000), &collateral(dec!(1150), dec!(5000))),
+            BitfinexFundingAdjustment::DoNothing
+        );
+    }
+}
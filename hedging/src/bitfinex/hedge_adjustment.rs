@@ -0,0 +1,170 @@
+use rust_decimal::Decimal;
+
+use shared::payload::{SyntheticCentExposure, SyntheticCentLiability};
+
+use super::BitfinexHedgingConfig;
+
+/// Bitfinex swaps are traded in btc, so adjustments are sized in usd cents
+/// and converted at the last price when the order is submitted.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BitfinexHedgeAdjustment {
+    DoNothing,
+    ClosePosition,
+    Sell(Decimal),
+    Buy(Decimal),
+}
+impl std::fmt::Display for BitfinexHedgeAdjustment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BitfinexHedgeAdjustment::DoNothing => write!(f, "DoNothing"),
+            BitfinexHedgeAdjustment::ClosePosition => write!(f, "ClosePosition"),
+            BitfinexHedgeAdjustment::Sell(usd_cents) => write!(f, "Sell({usd_cents})"),
+            BitfinexHedgeAdjustment::Buy(usd_cents) => write!(f, "Buy({usd_cents})"),
+        }
+    }
+}
+impl BitfinexHedgeAdjustment {
+    pub fn action_type(&self) -> &'static str {
+        match *self {
+            Self::DoNothing => "do-nothing",
+            Self::ClosePosition => "close-position",
+            Self::Sell(_) => "sell",
+            Self::Buy(_) => "buy",
+        }
+    }
+
+    pub fn size(&self) -> Option<Decimal> {
+        match *self {
+            Self::Sell(usd_cents) | Self::Buy(usd_cents) => Some(usd_cents),
+            _ => None,
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        "usd-cents"
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BitfinexHedgingAdjustment {
+    config: BitfinexHedgingConfig,
+}
+
+impl BitfinexHedgingAdjustment {
+    pub fn new(config: BitfinexHedgingConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn determine_action(
+        &self,
+        abs_liability: SyntheticCentLiability,
+        signed_exposure: SyntheticCentExposure,
+    ) -> BitfinexHedgeAdjustment {
+        if abs_liability >= Decimal::ZERO
+            && abs_liability < self.config.minimum_liability_threshold_cents
+        {
+            return if signed_exposure == Decimal::ZERO {
+                BitfinexHedgeAdjustment::DoNothing
+            } else {
+                BitfinexHedgeAdjustment::ClosePosition
+            };
+        }
+        let signed_liability = abs_liability * Decimal::NEGATIVE_ONE;
+        let abs_exposure = Decimal::from(signed_exposure).abs();
+        let exposure_ratio = signed_exposure / signed_liability;
+        let adjustment = if exposure_ratio.is_sign_negative() {
+            let target_exposure = abs_liability * self.config.low_safebound_ratio_shorting;
+            BitfinexHedgeAdjustment::Sell(target_exposure + abs_exposure)
+        } else if exposure_ratio < self.config.low_bound_ratio_shorting {
+            let target_exposure = abs_liability * self.config.low_safebound_ratio_shorting;
+            BitfinexHedgeAdjustment::Sell(target_exposure - abs_exposure)
+        } else if exposure_ratio > self.config.high_bound_ratio_shorting {
+            let target_exposure = abs_liability * self.config.high_safebound_ratio_shorting;
+            BitfinexHedgeAdjustment::Buy(abs_exposure - target_exposure)
+        } else {
+            BitfinexHedgeAdjustment::DoNothing
+        };
+        match adjustment.size() {
+            Some(usd_cents) if usd_cents.round() < self.config.minimum_order_size_cents => {
+                BitfinexHedgeAdjustment::DoNothing
+            }
+            Some(usd_cents) => match adjustment {
+                BitfinexHedgeAdjustment::Sell(_) => {
+                    BitfinexHedgeAdjustment::Sell(usd_cents.round())
+                }
+                _ => BitfinexHedgeAdjustment::Buy(usd_cents.round()),
+            },
+            None => adjustment,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    fn adjustment() -> BitfinexHedgingAdjustment {
+        BitfinexHedgingAdjustment::new(BitfinexHedgingConfig::default())
+    }
+
+    #[test]
+    fn no_adjustment() {
+        let liability = SyntheticCentLiability::try_from(dec!(10000)).unwrap();
+        let exposure = SyntheticCentExposure::from(dec!(-10000));
+        assert_eq!(
+            adjustment().determine_action(liability, exposure),
+            BitfinexHedgeAdjustment::DoNothing
+        );
+    }
+
+    #[test]
+    fn close_position() {
+        let liability = SyntheticCentLiability::try_from(dec!(0)).unwrap();
+        let exposure = SyntheticCentExposure::from(dec!(-10000));
+        assert_eq!(
+            adjustment().determine_action(liability, exposure),
+            BitfinexHedgeAdjustment::ClosePosition
+        );
+    }
+
+    #[test]
+    fn increase_in_usd_cents() {
+        let liability = SyntheticCentLiability::try_from(dec!(20000)).unwrap();
+        let exposure = SyntheticCentExposure::from(dec!(-10000));
+        assert_eq!(
+            adjustment().determine_action(liability, exposure),
+            BitfinexHedgeAdjustment::Sell(dec!(9600))
+        );
+    }
+
+    #[test]
+    fn decrease_in_usd_cents() {
+        let liability = SyntheticCentLiability::try_from(dec!(100000)).unwrap();
+        let exposure = SyntheticCentExposure::from(dec!(-120000));
+        assert_eq!(
+            adjustment().determine_action(liability, exposure),
+            BitfinexHedgeAdjustment::Buy(dec!(20000))
+        );
+    }
+
+    #[test]
+    fn ignores_adjustments_below_minimum_order_size() {
+        let liability = SyntheticCentLiability::try_from(dec!(100000)).unwrap();
+        let exposure = SyntheticCentExposure::from(dec!(-94000));
+        assert_eq!(
+            adjustment().determine_action(liability, exposure),
+            BitfinexHedgeAdjustment::Sell(dec!(4000))
+        );
+        let exposure = SyntheticCentExposure::from(dec!(-94900));
+        let config = BitfinexHedgingConfig {
+            minimum_order_size_cents: dec!(5000),
+            ..Default::default()
+        };
+        assert_eq!(
+            BitfinexHedgingAdjustment::new(config).determine_action(liability, exposure),
+            BitfinexHedgeAdjustment::DoNothing
+        );
+    }
+}
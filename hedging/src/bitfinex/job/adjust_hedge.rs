@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use tracing::instrument;
+
+use shared::{payload::BITFINEX_EXCHANGE_ID, pubsub::CorrelationId};
+
+use crate::{
+    bitfinex::BitfinexEngine, control::HedgingControl, engine::*, error::*, shadow::*,
+    user_trades_sync::*,
+};
+
+/// Returns false if user trades are not synced yet and the adjustment has to be retried
+#[instrument(name = "hedging.bitfinex.job.adjust_hedge", skip_all, fields(correlation_id = %correlation_id,
+        total_liability, liability_share, target_liability, current_position, action, funding_action, placed_order, user_trades_synced, shadow, paused), err)]
+pub(super) async fn execute(
+    correlation_id: CorrelationId,
+    triggered_at: DateTime<Utc>,
+    user_trades_sync: &UserTradesSync,
+    engine: BitfinexEngine,
+    shadow_actions: Option<&ShadowActions>,
+    control: &HedgingControl,
+) -> Result<bool, HedgingError> {
+    let span = tracing::Span::current();
+    if control.is_paused().await? {
+        span.record("paused", &tracing::field::display(true));
+        return Ok(true);
+    }
+    match user_trades_sync.sync_status(triggered_at).await? {
+        UserTradesSyncStatus::Synced => (),
+        status => {
+            span.record("user_trades_synced", &tracing::field::display(false));
+            return Ok(status != UserTradesSyncStatus::Pending);
+        }
+    }
+    let allocation = engine.liability_allocation().await?;
+    span.record(
+        "total_liability",
+        &tracing::field::display(allocation.total_liability),
+    );
+    span.record(
+        "liability_share",
+        &tracing::field::display(allocation.share),
+    );
+    let target_liability = allocation.target_liability;
+    span.record(
+        "target_liability",
+        &tracing::field::display(target_liability),
+    );
+    let current_position = engine.get_position_in_signed_usd_cents().await?;
+    span.record(
+        "current_position",
+        &tracing::field::display(current_position),
+    );
+
+    let action = engine
+        .hedging_adjustment()
+        .determine_action(target_liability, current_position);
+    span.record("action", &tracing::field::display(&action));
+    if let Some(shadow_actions) = shadow_actions {
+        span.record("shadow", &tracing::field::display(true));
+        shadow_actions
+            .record(NewShadowAction {
+                correlation_id,
+                exchange: BITFINEX_EXCHANGE_ID,
+                job_name: "adjust_bitfinex_hedge",
+                action_type: action.action_type(),
+                action_size: action.size(),
+                action_unit: action.unit(),
+                inputs: serde_json::json!({
+                    "total_liability": allocation.total_liability,
+                    "liability_share": allocation.share,
+                    "target_liability": target_liability,
+                    "current_position": current_position,
+                }),
+            })
+            .await?;
+        return Ok(true);
+    }
+    let funding_action = engine
+        .adjust_collateral(target_liability, current_position)
+        .await?;
+    span.record("funding_action", &tracing::field::display(&funding_action));
+    let placed_order = engine
+        .execute_adjustment(correlation_id, &action, &allocation)
+        .await?;
+    span.record("placed_order", &tracing::field::display(placed_order));
+    Ok(true)
+}
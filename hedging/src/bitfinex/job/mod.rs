@@ -0,0 +1,155 @@
+mod adjust_hedge;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Postgres};
+use sqlxmq::{job, CurrentJob, JobBuilder};
+use tracing::instrument;
+use uuid::{uuid, Uuid};
+
+use std::collections::HashMap;
+
+use shared::{pubsub::CorrelationId, sqlxmq::JobExecutor};
+
+use crate::{
+    bitfinex::BitfinexEngine, config::HedgingMode, control::HedgingControl, error::*,
+    shadow::ShadowActions, user_trades_sync::UserTradesSync,
+};
+
+pub const POLL_BITFINEX_ID: Uuid = uuid!("10000000-0000-0000-0000-000000000005");
+
+#[derive(Serialize, Deserialize)]
+struct AdjustHedgeData {
+    correlation_id: CorrelationId,
+    /// Kept across retries so that the user trades sync can time out
+    #[serde(default = "Utc::now")]
+    triggered_at: DateTime<Utc>,
+    #[serde(flatten)]
+    tracing_data: HashMap<String, String>,
+}
+
+#[instrument(name = "hedging.bitfinex.job.spawn_poll_bitfinex", skip_all, fields(error, error.level, error.message), err)]
+pub async fn spawn_poll_bitfinex(pool: &sqlx::PgPool) -> Result<(), HedgingError> {
+    match JobBuilder::new_with_id(POLL_BITFINEX_ID, "poll_bitfinex")
+        .set_channel_name("hedging.bitfinex")
+        .set_channel_args("poll_bitfinex")
+        .spawn(pool)
+        .await
+    {
+        Err(sqlx::Error::Database(err)) if err.message().contains("duplicate key") => Ok(()),
+        Err(e) => {
+            shared::tracing::insert_error_fields(tracing::Level::ERROR, &e);
+            Err(e.into())
+        }
+        Ok(_) => Ok(()),
+    }
+}
+
+#[instrument(name = "hedging.bitfinex.job.spawn_adjust_hedge", skip_all, fields(error, error.message), err)]
+pub async fn spawn_adjust_hedge<'a>(
+    tx: impl Executor<'a, Database = Postgres>,
+    trigger_id: impl Into<Uuid>,
+) -> Result<(), HedgingError> {
+    let correlation_id = trigger_id.into();
+    match JobBuilder::new("adjust_bitfinex_hedge")
+        .set_ordered(true)
+        .set_channel_name("hedging.bitfinex")
+        .set_channel_args("adjust_hedge")
+        .set_json(&AdjustHedgeData {
+            tracing_data: shared::tracing::extract_tracing_data(),
+            correlation_id: CorrelationId::from(correlation_id),
+            triggered_at: Utc::now(),
+        })
+        .expect("Couldn't set json")
+        .spawn(tx)
+        .await
+    {
+        Err(sqlx::Error::Database(err)) if err.message().contains("duplicate key") => Ok(()),
+        Err(e) => {
+            shared::tracing::insert_error_fields(tracing::Level::ERROR, &e);
+            Err(e.into())
+        }
+        Ok(_) => Ok(()),
+    }
+}
+
+#[instrument(name = "hedging.bitfinex.job.retry_adjust_hedge", skip_all, fields(error, error.message), err)]
+async fn retry_adjust_hedge(
+    pool: &sqlx::PgPool,
+    correlation_id: CorrelationId,
+    triggered_at: DateTime<Utc>,
+    delay: std::time::Duration,
+) -> Result<(), HedgingError> {
+    match JobBuilder::new("adjust_bitfinex_hedge")
+        .set_ordered(true)
+        .set_channel_name("hedging.bitfinex")
+        .set_channel_args("adjust_hedge")
+        .set_delay(delay)
+        .set_json(&AdjustHedgeData {
+            tracing_data: shared::tracing::extract_tracing_data(),
+            correlation_id,
+            triggered_at,
+        })
+        .expect("Couldn't set json")
+        .spawn(pool)
+        .await
+    {
+        Err(e) => {
+            shared::tracing::insert_error_fields(tracing::Level::ERROR, &e);
+            Err(e.into())
+        }
+        Ok(_) => Ok(()),
+    }
+}
+
+#[job(name = "adjust_bitfinex_hedge")]
+pub(super) async fn adjust_bitfinex_hedge(
+    mut current_job: CurrentJob,
+    engine: BitfinexEngine,
+    mode: HedgingMode,
+    shadow_actions: ShadowActions,
+    user_trades_sync: UserTradesSync,
+    control: HedgingControl,
+) -> Result<(), HedgingError> {
+    let pool = current_job.pool().clone();
+    JobExecutor::builder(&mut current_job)
+        .build()
+        .expect("couldn't build JobExecutor")
+        .execute(|data| async move {
+            let data: AdjustHedgeData = data.ok_or(HedgingError::NoJobDataPresent)?;
+            let synced = adjust_hedge::execute(
+                data.correlation_id,
+                data.triggered_at,
+                &user_trades_sync,
+                engine,
+                mode.is_shadow().then_some(&shadow_actions),
+                &control,
+            )
+            .await?;
+            if !synced {
+                retry_adjust_hedge(
+                    &pool,
+                    data.correlation_id,
+                    data.triggered_at,
+                    user_trades_sync.retry_delay(),
+                )
+                .await?;
+            }
+            Ok::<_, HedgingError>(data)
+        })
+        .await?;
+    Ok(())
+}
+
+#[job(name = "poll_bitfinex")]
+pub(super) async fn poll_bitfinex(
+    mut current_job: CurrentJob,
+    engine: BitfinexEngine,
+) -> Result<(), HedgingError> {
+    JobExecutor::builder(&mut current_job)
+        .build()
+        .expect("couldn't build JobExecutor")
+        .execute(|_| async move { engine.poll_orders().await })
+        .await?;
+    Ok(())
+}
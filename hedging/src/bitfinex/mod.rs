@@ -0,0 +1,12 @@
+mod config;
+mod engine;
+mod funding_adjustment;
+mod hedge_adjustment;
+pub mod job;
+mod orders;
+
+pub use config::*;
+pub use engine::*;
+pub use funding_adjustment::*;
+pub use hedge_adjustment::*;
+pub use orders::*;
@@ -0,0 +1,120 @@
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use bitfinex_client::ClientId;
+use shared::pubsub::CorrelationId;
+
+use super::BitfinexHedgeAdjustment;
+use crate::error::HedgingError;
+
+pub struct BitfinexOrderReservation<'a> {
+    pub correlation_id: CorrelationId,
+    pub instrument: &'a str,
+    pub action: &'a BitfinexHedgeAdjustment,
+    pub target_usd_value: Decimal,
+    pub total_target_usd_value: Decimal,
+    pub liability_share: Decimal,
+}
+
+pub struct ReservedOrder {
+    pub client_id: ClientId,
+    /// The slot was reserved by an earlier attempt of the job that never got to submit it.
+    pub retry: bool,
+}
+
+pub struct BitfinexOrderStatus {
+    pub client_id: ClientId,
+    pub order_id: i64,
+    pub state: Option<String>,
+    pub btc_amount: Decimal,
+    pub btc_filled: Decimal,
+    pub avg_price: Decimal,
+    pub complete: bool,
+}
+
+#[derive(Clone)]
+pub struct BitfinexOrders {
+    pool: PgPool,
+}
+
+impl BitfinexOrders {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Persists the order before it is submitted.
+    /// Returns `None` when the job with this `correlation_id` already submitted an order,
+    /// so that a retried job does not place it twice. A slot that was reserved but not
+    /// submitted is handed out again with the action of the retry.
+    pub async fn reserve_order_slot(
+        &self,
+        reservation: BitfinexOrderReservation<'_>,
+    ) -> Result<Option<ReservedOrder>, HedgingError> {
+        let id = i64::from(ClientId::new());
+        let res = sqlx::query!(
+            r#"INSERT INTO bitfinex_orders (
+                 client_id, correlation_id, instrument, action, size_usd_cents,
+                 target_usd_value, total_target_usd_value, liability_share
+               ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+               ON CONFLICT (correlation_id) DO UPDATE
+                 SET instrument = EXCLUDED.instrument, action = EXCLUDED.action, size_usd_cents = EXCLUDED.size_usd_cents,
+                   target_usd_value = EXCLUDED.target_usd_value, total_target_usd_value = EXCLUDED.total_target_usd_value,
+                   liability_share = EXCLUDED.liability_share
+                 WHERE bitfinex_orders.submitted = false
+               RETURNING client_id"#,
+            id,
+            Uuid::from(reservation.correlation_id),
+            reservation.instrument,
+            reservation.action.action_type(),
+            reservation.action.size(),
+            reservation.target_usd_value,
+            reservation.total_target_usd_value,
+            reservation.liability_share,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(res.map(|r| ReservedOrder {
+            client_id: ClientId::from(r.client_id),
+            retry: r.client_id != id,
+        }))
+    }
+
+    pub async fn mark_as_submitted(&self, id: ClientId) -> Result<(), HedgingError> {
+        sqlx::query!(
+            r#"UPDATE bitfinex_orders SET submitted = true WHERE client_id = $1"#,
+            i64::from(id),
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn open_orders(&self) -> Result<Vec<ClientId>, HedgingError> {
+        let res = sqlx::query!(
+            r#"SELECT client_id FROM bitfinex_orders WHERE submitted = true AND complete = false"#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(res
+            .into_iter()
+            .map(|r| ClientId::from(r.client_id))
+            .collect())
+    }
+
+    pub async fn update_order(&self, status: BitfinexOrderStatus) -> Result<(), HedgingError> {
+        sqlx::query!(
+            r#"UPDATE bitfinex_orders SET submitted = true, order_id = $1, state = $2, btc_amount = $3, btc_filled = $4, avg_price = $5, complete = $6 WHERE client_id = $7"#,
+            status.order_id,
+            status.state,
+            status.btc_amount,
+            status.btc_filled,
+            status.avg_price,
+            status.complete,
+            i64::from(status.client_id),
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
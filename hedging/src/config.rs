@@ -2,15 +2,47 @@ use rust_decimal::Decimal;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::time::Duration;
 
-use crate::okex::OkexConfig;
+use crate::{
+    admin::HedgingAdminConfig, bitfinex::BitfinexConfig, error::HedgingError, okex::OkexConfig,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ExchangesConfig {
     pub okex: Option<ExchangeConfig<OkexConfig>>,
+    pub bitfinex: Option<ExchangeConfig<BitfinexConfig>>,
+}
+
+impl ExchangesConfig {
+    /// Rejects negative weights and configurations where no exchange would hedge anything.
+    pub fn validate(&self) -> Result<(), HedgingError> {
+        let weights = [
+            self.okex.as_ref().map(|e| ("okex", e.weight)),
+            self.bitfinex.as_ref().map(|e| ("bitfinex", e.weight)),
+        ];
+        let mut configured = false;
+        let mut hedging = false;
+        for (name, weight) in weights.into_iter().flatten() {
+            if weight < Decimal::ZERO {
+                return Err(HedgingError::InvalidExchangeConfig(format!(
+                    "{name} has a negative weight"
+                )));
+            }
+            configured = true;
+            hedging |= weight > Decimal::ZERO;
+        }
+        if configured && !hedging {
+            return Err(HedgingError::InvalidExchangeConfig(
+                "no exchange has a positive weight".to_string(),
+            ));
+        }
+        Ok(())
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ExchangeConfig<T: DeserializeOwned + Serialize + Default> {
+    /// Share of the liability hedged on this exchange relative to the other weights.
+    /// An exchange with a weight of 0 is not started at all.
     pub weight: Decimal,
     #[serde(bound = "T: DeserializeOwned")]
     #[serde(default)]
@@ -22,6 +54,68 @@ pub struct ExchangeConfig<T: DeserializeOwned + Serialize + Default> {
 pub struct HedgingAppConfig {
     #[serde(default)]
     pub health: HedgingAppHealthConfig,
+    #[serde(default)]
+    pub mode: HedgingMode,
+    #[serde(default)]
+    pub user_trades_sync: UserTradesSyncConfig,
+    #[serde(default)]
+    pub admin: HedgingAdminConfig,
+}
+
+/// In `shadow` mode the engines compute their actions and record them in
+/// `shadow_actions` without placing orders or moving funds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HedgingMode {
+    #[default]
+    Live,
+    Shadow,
+}
+
+impl HedgingMode {
+    pub fn is_shadow(&self) -> bool {
+        matches!(self, Self::Shadow)
+    }
+}
+
+/// Hedging jobs only act once user-trades are synced with the time the job
+/// was triggered, allowing the watermark to trail it by `max_lag`. Jobs that are not
+/// synced yet are retried after `retry_delay`, a job that is still not synced
+/// `timeout` after it was triggered reports an error and is dropped.
+#[serde_with::serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserTradesSyncConfig {
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    #[serde(default = "default_user_trades_max_lag")]
+    pub max_lag: chrono::Duration,
+    #[serde_as(as = "serde_with::DurationSeconds<u64>")]
+    #[serde(default = "default_user_trades_sync_timeout")]
+    pub timeout: Duration,
+    #[serde_as(as = "serde_with::DurationSeconds<u64>")]
+    #[serde(default = "default_user_trades_sync_retry_delay")]
+    pub retry_delay: Duration,
+}
+
+impl Default for UserTradesSyncConfig {
+    fn default() -> Self {
+        Self {
+            max_lag: default_user_trades_max_lag(),
+            timeout: default_user_trades_sync_timeout(),
+            retry_delay: default_user_trades_sync_retry_delay(),
+        }
+    }
+}
+
+fn default_user_trades_max_lag() -> chrono::Duration {
+    chrono::Duration::seconds(10)
+}
+
+fn default_user_trades_sync_timeout() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_user_trades_sync_retry_delay() -> Duration {
+    Duration::from_secs(2)
 }
 
 #[serde_with::serde_as]
@@ -52,3 +146,32 @@ fn default_unhealthy_msg_interval() -> chrono::Duration {
     chrono::Duration::from_std(Duration::from_secs(20))
         .expect("bad default unhealthy_after_msg_delay")
 }
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    fn exchanges(okex: Decimal, bitfinex: Option<Decimal>) -> ExchangesConfig {
+        ExchangesConfig {
+            okex: Some(ExchangeConfig {
+                weight: okex,
+                config: OkexConfig::default(),
+            }),
+            bitfinex: bitfinex.map(|weight| ExchangeConfig {
+                weight,
+                config: BitfinexConfig::default(),
+            }),
+        }
+    }
+
+    #[test]
+    fn validate_exchange_weights() {
+        assert!(exchanges(dec!(1), None).validate().is_ok());
+        assert!(exchanges(dec!(1), Some(dec!(0))).validate().is_ok());
+        assert!(exchanges(dec!(0), None).validate().is_err());
+        assert!(exchanges(dec!(0), Some(dec!(0))).validate().is_err());
+        assert!(exchanges(dec!(1), Some(dec!(-1))).validate().is_err());
+    }
+}
@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+use tracing::instrument;
+
+use std::sync::Arc;
+
+use shared::pubsub::CorrelationId;
+
+use crate::error::HedgingError;
+
+#[derive(Debug, Clone)]
+pub struct HedgingDecision {
+    pub correlation_id: CorrelationId,
+    pub action: String,
+    pub decided_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LastDecisions {
+    pub okex_hedge: Option<HedgingDecision>,
+    pub okex_funding: Option<HedgingDecision>,
+}
+
+/// Operator controls shared by the jobs and the admin service.
+/// The pause flag lives in the database so it applies to every job runner,
+/// the last decisions are those taken by the jobs of this process.
+#[derive(Clone)]
+pub struct HedgingControl {
+    pool: sqlx::PgPool,
+    last_decisions: Arc<RwLock<LastDecisions>>,
+}
+
+impl HedgingControl {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self {
+            pool,
+            last_decisions: Arc::new(RwLock::new(LastDecisions::default())),
+        }
+    }
+
+    pub async fn is_paused(&self) -> Result<bool, HedgingError> {
+        let row = sqlx::query!("SELECT paused FROM hedging_control")
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|row| row.paused).unwrap_or(false))
+    }
+
+    #[instrument(name = "hedging.control.pause", skip(self))]
+    pub async fn pause(&self, reason: Option<String>) -> Result<(), HedgingError> {
+        self.set_paused(true, reason).await
+    }
+
+    #[instrument(name = "hedging.control.resume", skip(self))]
+    pub async fn resume(&self) -> Result<(), HedgingError> {
+        self.set_paused(false, None).await
+    }
+
+    pub async fn record_okex_hedge_decision(
+        &self,
+        correlation_id: CorrelationId,
+        action: &impl std::fmt::Display,
+    ) {
+        self.last_decisions.write().await.okex_hedge = Some(HedgingDecision {
+            correlation_id,
+            action: action.to_string(),
+            decided_at: Utc::now(),
+        });
+    }
+
+    pub async fn record_okex_funding_decision(
+        &self,
+        correlation_id: CorrelationId,
+        action: &impl std::fmt::Display,
+    ) {
+        self.last_decisions.write().await.okex_funding = Some(HedgingDecision {
+            correlation_id,
+            action: action.to_string(),
+            decided_at: Utc::now(),
+        });
+    }
+
+    pub async fn last_decisions(&self) -> LastDecisions {
+        self.last_decisions.read().await.clone()
+    }
+
+    async fn set_paused(&self, paused: bool, reason: Option<String>) -> Result<(), HedgingError> {
+        sqlx::query!(
+            r#"INSERT INTO hedging_control (id, paused, reason) VALUES (TRUE, $1, $2)
+               ON CONFLICT (id) DO UPDATE SET paused = $1, reason = $2, updated_at = NOW()"#,
+            paused,
+            reason
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use sqlxmq::{JobRegistry, NamedJob};
+
+use shared::payload::SyntheticCentExposure;
+
+use crate::error::HedgingError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HedgeOrderSide {
+    Buy,
+    Sell,
+}
+
+impl std::fmt::Display for HedgeOrderSide {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HedgeOrderSide::Buy => write!(f, "buy"),
+            HedgeOrderSide::Sell => write!(f, "sell"),
+        }
+    }
+}
+
+/// Operations an exchange has to support to hold (part of) the stablesats hedge.
+/// Orders and transfers are reserved by the engine's own jobs before they are placed,
+/// `client_id` is the id they were persisted under so that a retry reuses it.
+#[async_trait]
+pub trait HedgingEngine: Send + Sync + 'static {
+    fn exchange_id(&self) -> &'static str;
+
+    fn register_jobs(&self, jobs: &mut Vec<&'static NamedJob>, channels: &mut Vec<&'static str>);
+
+    fn add_context_to_job_registry(&self, registry: &mut JobRegistry);
+
+    async fn get_position_in_signed_usd_cents(&self)
+        -> Result<SyntheticCentExposure, HedgingError>;
+
+    async fn place_order(
+        &self,
+        client_id: String,
+        side: HedgeOrderSide,
+        usd_cents: Decimal,
+    ) -> Result<(), HedgingError>;
+
+    async fn close_positions(&self, client_id: String) -> Result<(), HedgingError>;
+
+    /// `amount` is denominated in the currency the exchange holds as collateral.
+    async fn transfer_funding_to_trading(
+        &self,
+        client_id: String,
+        amount: Decimal,
+    ) -> Result<(), HedgingError>;
+
+    /// `amount` is denominated in the currency the exchange holds as collateral.
+    async fn transfer_trading_to_funding(
+        &self,
+        client_id: String,
+        amount: Decimal,
+    ) -> Result<(), HedgingError>;
+}
@@ -24,8 +24,14 @@ pub enum HedgingError {
     GaloyClient(#[from] galoy_client::GaloyClientError),
     #[error("HedgingError - BitfinexClient: {0}")]
     BitfinextClient(#[from] bitfinex_client::BitfinexClientError),
+    #[error("HedgingError - TonicTransport: {0}")]
+    TonicTransport(#[from] tonic::transport::Error),
+    #[error("HedgingError - InvalidExchangeConfig: {0}")]
+    InvalidExchangeConfig(String),
     #[error("HedgingError - NoJobDataPresent")]
     NoJobDataPresent,
+    #[error("HedgingError - TransferNotInitiated: {0}")]
+    TransferNotInitiated(String),
     #[error("UserTradesError - Leger: {0}")]
     Ledger(#[from] ledger::LedgerError),
 }
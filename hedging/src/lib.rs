@@ -1,26 +1,41 @@
 #![cfg_attr(feature = "fail-on-warnings", deny(warnings))]
 #![cfg_attr(feature = "fail-on-warnings", deny(clippy::all))]
 
+mod admin;
+mod allocation;
 mod app;
+mod bitfinex;
 mod config;
+mod control;
+mod engine;
 mod error;
-pub(crate) mod hack_user_trades_lag;
 mod okex;
+mod shadow;
+mod user_trades_sync;
 
 use galoy_client::GaloyClientConfig;
 use shared::{health::HealthCheckTrigger, payload::*, pubsub::*};
 
+pub use admin::{proto as admin_proto, HedgingAdmin, HedgingAdminConfig};
+pub use allocation::*;
 pub use app::*;
+pub use bitfinex::{
+    BitfinexConfig, BitfinexHedgeAdjustment, BitfinexOrderReservation, BitfinexOrderStatus,
+    BitfinexOrders,
+};
 pub use config::*;
+pub use control::*;
+pub use engine::*;
 pub use error::*;
-pub use okex::OkexConfig;
+pub use okex::{OkexConfig, OkexTransfers, UnaccountedTransfer};
+pub use shadow::*;
 
 #[allow(clippy::too_many_arguments)]
 pub async fn run(
     pool: sqlx::PgPool,
     health_check_trigger: HealthCheckTrigger,
     config: HedgingAppConfig,
-    okex_config: OkexConfig,
+    exchanges: ExchangesConfig,
     galoy_config: GaloyClientConfig,
     pubsub_cfg: PubSubConfig,
     tick_receiver: memory::Subscriber<PriceStreamPayload>,
@@ -29,11 +44,12 @@ pub async fn run(
         pool,
         health_check_trigger,
         config,
-        okex_config,
+        exchanges,
         galoy_config,
         pubsub_cfg,
         tick_receiver,
     )
-    .await?;
-    Ok(())
+    .await?
+    .serve_admin()
+    .await
 }
@@ -16,12 +16,137 @@ pub struct OkexConfig {
     pub funding: OkexFundingConfig,
     #[serde(default)]
     pub hedging: OkexHedgingConfig,
+    #[serde(default)]
+    pub passive_order: OkexPassiveOrderConfig,
+    #[serde(default)]
+    pub twap: OkexTwapConfig,
+    #[serde(default)]
+    pub funding_fees: OkexFundingFeesConfig,
+    #[serde(default)]
+    pub liquidation: OkexLiquidationConfig,
 }
 
 fn default_okex_poll_frequency() -> Duration {
     Duration::from_secs(10)
 }
 
+#[serde_with::serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OkexPassiveOrderConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde_as(as = "serde_with::DurationSeconds<u64>")]
+    #[serde(default = "default_passive_order_timeout")]
+    pub timeout: Duration,
+    #[serde_as(as = "serde_with::DurationMilliSeconds<u64>")]
+    #[serde(default = "default_passive_order_poll_interval")]
+    pub poll_interval: Duration,
+}
+impl Default for OkexPassiveOrderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout: default_passive_order_timeout(),
+            poll_interval: default_passive_order_poll_interval(),
+        }
+    }
+}
+
+fn default_passive_order_timeout() -> Duration {
+    Duration::from_secs(10)
+}
+fn default_passive_order_poll_interval() -> Duration {
+    Duration::from_millis(1000)
+}
+
+#[serde_with::serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OkexTwapConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_max_child_order_contracts")]
+    pub max_child_order_contracts: u32,
+    #[serde_as(as = "serde_with::DurationSeconds<u64>")]
+    #[serde(default = "default_child_order_interval")]
+    pub child_order_interval: Duration,
+    /// Regular adjustments are skipped while a chain is running, unless its
+    /// last child order is older than this (eg. because the chain died).
+    #[serde_as(as = "serde_with::DurationSeconds<u64>")]
+    #[serde(default = "default_chain_timeout")]
+    pub chain_timeout: Duration,
+}
+impl Default for OkexTwapConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_child_order_contracts: default_max_child_order_contracts(),
+            child_order_interval: default_child_order_interval(),
+            chain_timeout: default_chain_timeout(),
+        }
+    }
+}
+
+fn default_max_child_order_contracts() -> u32 {
+    50
+}
+fn default_child_order_interval() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn default_chain_timeout() -> Duration {
+    Duration::from_secs(600)
+}
+
+#[serde_with::serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OkexFundingFeesConfig {
+    #[serde_as(as = "serde_with::DurationSeconds<u64>")]
+    #[serde(default = "default_funding_fees_poll_interval")]
+    pub poll_interval: Duration,
+    #[serde(default = "default_negative_funding_alert_threshold_btc")]
+    pub negative_funding_alert_threshold_btc: Decimal,
+}
+impl Default for OkexFundingFeesConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: default_funding_fees_poll_interval(),
+            negative_funding_alert_threshold_btc: default_negative_funding_alert_threshold_btc(),
+        }
+    }
+}
+
+fn default_funding_fees_poll_interval() -> Duration {
+    Duration::from_secs(3600)
+}
+fn default_negative_funding_alert_threshold_btc() -> Decimal {
+    dec!(0.01)
+}
+
+/// Distances are relative to the mark price, ie 0.2 means the estimated
+/// liquidation price is 20% away from the mark price.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OkexLiquidationConfig {
+    #[serde(default = "default_unhealthy_liquidation_distance")]
+    pub unhealthy_distance: Decimal,
+    #[serde(default = "default_emergency_liquidation_distance")]
+    pub emergency_distance: Decimal,
+}
+impl Default for OkexLiquidationConfig {
+    fn default() -> Self {
+        Self {
+            unhealthy_distance: default_unhealthy_liquidation_distance(),
+            emergency_distance: default_emergency_liquidation_distance(),
+        }
+    }
+}
+
+fn default_unhealthy_liquidation_distance() -> Decimal {
+    dec!(0.2)
+}
+fn default_emergency_liquidation_distance() -> Decimal {
+    dec!(0.15)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OkexHedgingConfig {
     #[serde(default = "default_low_bound_ratio_shorting")]
@@ -35,6 +160,9 @@ pub struct OkexHedgingConfig {
 
     #[serde(default = "default_minimum_liability_threshold_cents")]
     pub minimum_liability_threshold_cents: Decimal,
+
+    #[serde(default)]
+    pub funding_rate_adjustment: FundingRateAdjustmentConfig,
 }
 impl Default for OkexHedgingConfig {
     fn default() -> Self {
@@ -44,10 +172,40 @@ impl Default for OkexHedgingConfig {
             high_safebound_ratio_shorting: default_high_safebound_ratio_shorting(),
             high_bound_ratio_shorting: default_high_bound_ratio_shorting(),
             minimum_liability_threshold_cents: default_minimum_liability_threshold_cents(),
+            funding_rate_adjustment: FundingRateAdjustmentConfig::default(),
+        }
+    }
+}
+
+/// Lowers the shorting ratio bounds while shorts pay a lot of funding.
+/// The reduction grows linearly from 0 at `negative_funding_rate_threshold`
+/// to `max_ratio_reduction` at twice that rate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FundingRateAdjustmentConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_negative_funding_rate_threshold")]
+    pub negative_funding_rate_threshold: Decimal,
+    #[serde(default = "default_max_ratio_reduction")]
+    pub max_ratio_reduction: Decimal,
+}
+impl Default for FundingRateAdjustmentConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            negative_funding_rate_threshold: default_negative_funding_rate_threshold(),
+            max_ratio_reduction: default_max_ratio_reduction(),
         }
     }
 }
 
+fn default_negative_funding_rate_threshold() -> Decimal {
+    dec!(-0.0003)
+}
+fn default_max_ratio_reduction() -> Decimal {
+    dec!(0.02)
+}
+
 fn default_minimum_liability_threshold_cents() -> Decimal {
     dec!(5000)
 }
@@ -1,28 +1,38 @@
+use async_trait::async_trait;
 use futures::stream::StreamExt;
+use rust_decimal::Decimal;
 use sqlxmq::NamedJob;
 use tracing::{info_span, instrument, Instrument};
 
 use std::sync::Arc;
 
 use ledger::Ledger;
-use okex_client::OkexClient;
+use okex_client::{
+    BtcUsdSwapContracts, ClientOrderId, ClientTransferId, OkexClient, OkexOrderSide, OkexOrderType,
+};
 use shared::{
     payload::*,
     pubsub::{memory, PubSubConfig, Subscriber},
 };
 
-use super::{config::*, funding_adjustment::*, hedge_adjustment::*, job, orders::*, transfers::*};
-use crate::error::HedgingError;
+use super::{
+    config::*, funding_adjustment::*, hedge_adjustment::*, job, liquidation::*, orders::*,
+    transfers::*,
+};
+use crate::{allocation::*, engine::*, error::HedgingError};
 
 pub struct OkexEngine {
     config: OkexConfig,
     pool: sqlx::PgPool,
     orders: OkexOrders,
     transfers: OkexTransfers,
+    funding_fees: OkexFundingFees,
     okex_client: OkexClient,
     ledger: Ledger,
+    liability_allocator: LiabilityAllocator,
     funding_adjustment: FundingAdjustment,
     hedging_adjustment: HedgingAdjustment,
+    liquidation_monitor: LiquidationMonitor,
 }
 
 impl OkexEngine {
@@ -30,27 +40,33 @@ impl OkexEngine {
         pool: sqlx::PgPool,
         config: OkexConfig,
         ledger: Ledger,
+        liability_allocator: LiabilityAllocator,
         pubsub_config: PubSubConfig,
         price_receiver: memory::Subscriber<PriceStreamPayload>,
     ) -> Result<(Arc<Self>, Subscriber), HedgingError> {
         let okex_client = OkexClient::new(config.client.clone()).await?;
         let orders = OkexOrders::new(pool.clone()).await?;
         let transfers = OkexTransfers::new(pool.clone()).await?;
+        let funding_fees = OkexFundingFees::new(pool.clone());
         okex_client
             .check_leverage(config.funding.high_bound_ratio_leverage)
             .await?;
         let funding_adjustment =
             FundingAdjustment::new(config.funding.clone(), config.hedging.clone());
         let hedging_adjustment = HedgingAdjustment::new(config.hedging.clone());
+        let liquidation_monitor = LiquidationMonitor::new(config.liquidation.clone());
         let ret = Arc::new(Self {
             config,
             pool,
             okex_client,
             orders,
             transfers,
+            funding_fees,
             ledger,
+            liability_allocator,
             funding_adjustment,
             hedging_adjustment,
+            liquidation_monitor,
         });
 
         Arc::clone(&ret)
@@ -68,21 +84,20 @@ impl OkexEngine {
         Ok((ret, subscriber))
     }
 
-    pub fn add_context_to_job_registry(&self, runner: &mut sqlxmq::JobRegistry) {
-        runner.set_context(self.okex_client.clone());
-        runner.set_context(self.orders.clone());
-        runner.set_context(self.transfers.clone());
-        runner.set_context(job::OkexPollDelay(self.config.poll_frequency));
-        runner.set_context(self.funding_adjustment.clone());
-        runner.set_context(self.hedging_adjustment.clone());
-        runner.set_context(self.config.funding.clone());
+    pub fn liquidation_monitor(&self) -> LiquidationMonitor {
+        self.liquidation_monitor.clone()
     }
 
-    pub fn register_jobs(jobs: &mut Vec<&'static NamedJob>, channels: &mut Vec<&str>) {
-        jobs.push(job::adjust_hedge);
-        jobs.push(job::poll_okex);
-        jobs.push(job::adjust_funding);
-        channels.push("hedging.okex");
+    pub(crate) fn okex_client(&self) -> &OkexClient {
+        &self.okex_client
+    }
+
+    pub(crate) fn orders(&self) -> &OkexOrders {
+        &self.orders
+    }
+
+    pub(crate) fn transfers(&self) -> &OkexTransfers {
+        &self.transfers
     }
 
     async fn spawn_okex_price_listener(
@@ -229,7 +244,7 @@ impl OkexEngine {
         correlation_id: impl Into<uuid::Uuid> + std::fmt::Debug,
         signed_usd_exposure: SyntheticCentExposure,
     ) -> Result<(), HedgingError> {
-        let amount = self.ledger.balances().target_liability_in_cents().await?;
+        let amount = self.target_liability_in_cents().await?;
         let action = self
             .hedging_adjustment
             .determine_action(amount, signed_usd_exposure);
@@ -246,7 +261,7 @@ impl OkexEngine {
         correlation_id: impl Into<uuid::Uuid> + std::fmt::Debug,
         signed_usd_exposure: SyntheticCentExposure,
     ) -> Result<(), HedgingError> {
-        let target_liability_in_cents = self.ledger.balances().target_liability_in_cents().await?;
+        let target_liability_in_cents = self.target_liability_in_cents().await?;
         let last_price_in_usd_cents = self
             .okex_client
             .get_last_price_in_usd_cents()
@@ -269,13 +284,118 @@ impl OkexEngine {
         Ok(())
     }
 
+    async fn target_liability_in_cents(&self) -> Result<SyntheticCentLiability, HedgingError> {
+        let total_liability = self.ledger.balances().target_liability_in_cents().await?;
+        Ok(self
+            .liability_allocator
+            .allocate(OKEX_EXCHANGE_ID, total_liability)
+            .target_liability)
+    }
+
     async fn spawn_non_stop_polling(self: Arc<Self>) -> Result<(), HedgingError> {
+        job::spawn_poll_funding_fees(&self.pool, std::time::Duration::from_secs(1)).await?;
         tokio::spawn(async move {
             loop {
                 let _ = job::spawn_poll_okex(&self.pool, std::time::Duration::from_secs(1)).await;
+                let _ = job::spawn_poll_funding_fees(
+                    &self.pool,
+                    self.config.funding_fees.poll_interval,
+                )
+                .await;
                 tokio::time::sleep(self.config.poll_frequency).await;
             }
         });
         Ok(())
     }
 }
+
+#[async_trait]
+impl HedgingEngine for OkexEngine {
+    fn exchange_id(&self) -> &'static str {
+        OKEX_EXCHANGE_ID
+    }
+
+    fn register_jobs(&self, jobs: &mut Vec<&'static NamedJob>, channels: &mut Vec<&'static str>) {
+        jobs.push(job::adjust_hedge);
+        jobs.push(job::poll_okex);
+        jobs.push(job::adjust_funding);
+        jobs.push(job::poll_funding_fees);
+        channels.push("hedging.okex");
+    }
+
+    fn add_context_to_job_registry(&self, runner: &mut sqlxmq::JobRegistry) {
+        runner.set_context(self.okex_client.clone());
+        runner.set_context(self.orders.clone());
+        runner.set_context(self.transfers.clone());
+        runner.set_context(job::OkexPollDelay(self.config.poll_frequency));
+        runner.set_context(self.funding_adjustment.clone());
+        runner.set_context(self.hedging_adjustment.clone());
+        runner.set_context(self.config.funding.clone());
+        runner.set_context(self.config.passive_order.clone());
+        runner.set_context(self.config.twap.clone());
+        runner.set_context(self.config.funding_fees.clone());
+        runner.set_context(self.funding_fees.clone());
+        runner.set_context(self.liquidation_monitor.clone());
+    }
+
+    async fn get_position_in_signed_usd_cents(
+        &self,
+    ) -> Result<SyntheticCentExposure, HedgingError> {
+        let position = self.okex_client.get_position_in_signed_usd_cents().await?;
+        Ok(position.usd_cents.into())
+    }
+
+    async fn place_order(
+        &self,
+        client_id: String,
+        side: HedgeOrderSide,
+        usd_cents: Decimal,
+    ) -> Result<(), HedgingError> {
+        let contracts = (usd_cents / CONTRACT_SIZE_CENTS).round().abs();
+        let contracts =
+            BtcUsdSwapContracts::from(u32::try_from(contracts).expect("decimal to u32"));
+        let side = match side {
+            HedgeOrderSide::Buy => OkexOrderSide::Buy,
+            HedgeOrderSide::Sell => OkexOrderSide::Sell,
+        };
+        self.okex_client
+            .place_order(
+                ClientOrderId::from(client_id),
+                side,
+                &contracts,
+                OkexOrderType::Market,
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn close_positions(&self, client_id: String) -> Result<(), HedgingError> {
+        self.okex_client
+            .close_positions(ClientOrderId::from(client_id))
+            .await?;
+        Ok(())
+    }
+
+    async fn transfer_funding_to_trading(
+        &self,
+        client_id: String,
+        amount: Decimal,
+    ) -> Result<(), HedgingError> {
+        self.okex_client
+            .transfer_funding_to_trading(ClientTransferId::from(client_id), amount)
+            .await?;
+        Ok(())
+    }
+
+    async fn transfer_trading_to_funding(
+        &self,
+        client_id: String,
+        amount: Decimal,
+    ) -> Result<(), HedgingError> {
+        self.okex_client
+            .transfer_trading_to_funding(ClientTransferId::from(client_id), amount)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::instrument;
+use uuid::Uuid;
+
+use okex_client::FundingFeeBill;
+
+use crate::error::HedgingError;
+
+pub struct UnaccountedFundingFee {
+    pub bill_id: String,
+    pub amount: Decimal,
+    pub paid_at: DateTime<Utc>,
+    pub ledger_tx_id: ledger::LedgerTxId,
+}
+
+#[derive(Clone)]
+pub struct OkexFundingFees {
+    pool: PgPool,
+}
+
+impl OkexFundingFees {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Returns false if the bill had already been persisted
+    #[instrument(name = "okex_funding_fees.persist", skip(self))]
+    pub async fn persist(&self, bill: &FundingFeeBill) -> Result<bool, HedgingError> {
+        let res = sqlx::query!(
+            r#"INSERT INTO okex_funding_fees (bill_id, instrument, amount, paid_at)
+               VALUES ($1, $2, $3, $4) ON CONFLICT (bill_id) DO NOTHING"#,
+            bill.bill_id,
+            bill.instrument_id,
+            bill.amount_in_btc,
+            bill.timestamp,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    #[instrument(name = "okex_funding_fees.find_unaccounted", skip_all)]
+    pub async fn find_unaccounted(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<Option<UnaccountedFundingFee>, HedgingError> {
+        let tx_id = Uuid::new_v4();
+        let fee = sqlx::query!(
+            r#"UPDATE okex_funding_fees
+               SET ledger_tx_id = $1
+               WHERE bill_id = (
+                 SELECT bill_id FROM okex_funding_fees WHERE ledger_tx_id IS NULL AND amount <> 0 ORDER BY paid_at LIMIT 1
+               ) RETURNING bill_id, amount, paid_at"#,
+            tx_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        Ok(fee.map(|fee| UnaccountedFundingFee {
+            bill_id: fee.bill_id,
+            amount: fee.amount,
+            paid_at: fee.paid_at,
+            ledger_tx_id: ledger::LedgerTxId::from(tx_id),
+        }))
+    }
+
+    /// Timestamp of the newest bill that has been persisted
+    pub async fn last_paid_at(&self) -> Result<Option<DateTime<Utc>>, HedgingError> {
+        let res = sqlx::query!(r#"SELECT MAX(paid_at) as "paid_at" FROM okex_funding_fees"#)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(res.paid_at)
+    }
+
+    /// Net funding received (positive) or paid (negative) since the given time
+    pub async fn total_since(&self, since: DateTime<Utc>) -> Result<Decimal, HedgingError> {
+        let res = sqlx::query!(
+            r#"SELECT COALESCE(SUM(amount), 0) as "total!" FROM okex_funding_fees WHERE paid_at >= $1"#,
+            since
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(res.total)
+    }
+}
@@ -54,6 +54,22 @@ impl OkexHedgeAdjustment {
         self.size()
             .map(|size| Decimal::ONE_HUNDRED * Decimal::from(size))
     }
+
+    /// Caps a Sell / Buy at `max_contracts`.
+    /// Returns the capped adjustment and whether some of the original size is left over.
+    pub fn slice(self, max_contracts: u32) -> (Self, bool) {
+        match self.size() {
+            Some(size) if size > max_contracts => {
+                let contracts = BtcUsdSwapContracts::from(max_contracts);
+                let sliced = match self {
+                    Self::Sell(_) => Self::Sell(contracts),
+                    _ => Self::Buy(contracts),
+                };
+                (sliced, true)
+            }
+            _ => (self, false),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -71,6 +87,37 @@ impl HedgingAdjustment {
         abs_liability: SyntheticCentLiability,
         signed_exposure: SyntheticCentExposure,
     ) -> OkexHedgeAdjustment {
+        self.determine_action_with_funding_rate(abs_liability, signed_exposure, None)
+    }
+
+    pub fn uses_funding_rate(&self) -> bool {
+        self.config.funding_rate_adjustment.enabled
+    }
+
+    /// How much the shorting ratio bounds are lowered for the given (predicted) funding rate.
+    pub fn ratio_reduction(&self, funding_rate: Option<Decimal>) -> Decimal {
+        let config = &self.config.funding_rate_adjustment;
+        let threshold = config.negative_funding_rate_threshold;
+        match funding_rate {
+            Some(rate) if config.enabled && rate < threshold && !threshold.is_zero() => {
+                let scale = ((threshold - rate) / threshold.abs()).min(Decimal::ONE);
+                config.max_ratio_reduction * scale
+            }
+            _ => Decimal::ZERO,
+        }
+    }
+
+    pub fn determine_action_with_funding_rate(
+        &self,
+        abs_liability: SyntheticCentLiability,
+        signed_exposure: SyntheticCentExposure,
+        funding_rate: Option<Decimal>,
+    ) -> OkexHedgeAdjustment {
+        let reduction = self.ratio_reduction(funding_rate);
+        let low_bound_ratio_shorting = self.config.low_bound_ratio_shorting - reduction;
+        let low_safebound_ratio_shorting = self.config.low_safebound_ratio_shorting - reduction;
+        let high_safebound_ratio_shorting = self.config.high_safebound_ratio_shorting - reduction;
+        let high_bound_ratio_shorting = self.config.high_bound_ratio_shorting - reduction;
         if abs_liability >= Decimal::ZERO
             && abs_liability < self.config.minimum_liability_threshold_cents
         {
@@ -84,7 +131,7 @@ impl HedgingAdjustment {
             let abs_exposure = Decimal::from(signed_exposure).abs();
             let exposure_ratio = signed_exposure / signed_liability;
             if exposure_ratio.is_sign_negative() {
-                let target_exposure = abs_liability * self.config.low_safebound_ratio_shorting;
+                let target_exposure = abs_liability * low_safebound_ratio_shorting;
                 let contracts = ((target_exposure + abs_exposure) / CONTRACT_SIZE_CENTS)
                     .round()
                     .abs();
@@ -95,8 +142,8 @@ impl HedgingAdjustment {
                         u32::try_from(contracts).expect("decimal to u32"),
                     ))
                 }
-            } else if exposure_ratio < self.config.low_bound_ratio_shorting {
-                let target_exposure = abs_liability * self.config.low_safebound_ratio_shorting;
+            } else if exposure_ratio < low_bound_ratio_shorting {
+                let target_exposure = abs_liability * low_safebound_ratio_shorting;
                 let contracts = ((target_exposure - abs_exposure) / CONTRACT_SIZE_CENTS)
                     .round()
                     .abs();
@@ -107,8 +154,8 @@ impl HedgingAdjustment {
                         u32::try_from(contracts).expect("decimal to u32"),
                     ))
                 }
-            } else if exposure_ratio > self.config.high_bound_ratio_shorting {
-                let target_exposure = abs_liability * self.config.high_safebound_ratio_shorting;
+            } else if exposure_ratio > high_bound_ratio_shorting {
+                let target_exposure = abs_liability * high_safebound_ratio_shorting;
                 let contracts = ((abs_exposure - target_exposure) / CONTRACT_SIZE_CENTS)
                     .round()
                     .abs();
@@ -332,4 +379,60 @@ mod tests {
             ))
         );
     }
+
+    #[test]
+    fn slice_caps_large_adjustments() {
+        let (action, remaining) =
+            OkexHedgeAdjustment::Sell(BtcUsdSwapContracts::from(50)).slice(20);
+        assert_eq!(
+            action,
+            OkexHedgeAdjustment::Sell(BtcUsdSwapContracts::from(20))
+        );
+        assert!(remaining);
+
+        let (action, remaining) = OkexHedgeAdjustment::Buy(BtcUsdSwapContracts::from(20)).slice(20);
+        assert_eq!(
+            action,
+            OkexHedgeAdjustment::Buy(BtcUsdSwapContracts::from(20))
+        );
+        assert!(!remaining);
+
+        let (action, remaining) = OkexHedgeAdjustment::ClosePosition.slice(20);
+        assert_eq!(action, OkexHedgeAdjustment::ClosePosition);
+        assert!(!remaining);
+    }
+
+    #[test]
+    fn funding_rate_lowers_the_target_ratio() {
+        let mut config = OkexHedgingConfig::default();
+        config.funding_rate_adjustment.enabled = true;
+        let hedging_adjustment = HedgingAdjustment { config };
+        assert_eq!(
+            hedging_adjustment.ratio_reduction(Some(dec!(0.0001))),
+            dec!(0)
+        );
+        assert_eq!(
+            hedging_adjustment.ratio_reduction(Some(dec!(-0.00045))),
+            dec!(0.01)
+        );
+        assert_eq!(
+            hedging_adjustment.ratio_reduction(Some(dec!(-0.01))),
+            dec!(0.02)
+        );
+
+        let liability = SyntheticCentLiability::try_from(dec!(1000000)).unwrap();
+        let exposure = SyntheticCentExposure::from(dec!(-940000));
+        assert_eq!(
+            hedging_adjustment.determine_action_with_funding_rate(liability, exposure, None),
+            OkexHedgeAdjustment::Sell(BtcUsdSwapContracts::from(4))
+        );
+        assert_eq!(
+            hedging_adjustment.determine_action_with_funding_rate(
+                liability,
+                exposure,
+                Some(dec!(-0.01))
+            ),
+            OkexHedgeAdjustment::DoNothing
+        );
+    }
 }
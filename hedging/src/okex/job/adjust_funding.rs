@@ -1,41 +1,74 @@
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 use tracing::instrument;
 
 use galoy_client::*;
 use okex_client::*;
-use shared::pubsub::CorrelationId;
+use shared::{payload::OKEX_EXCHANGE_ID, pubsub::CorrelationId};
 
-use crate::{error::*, okex::*};
+use crate::{
+    allocation::LiabilityAllocator, control::HedgingControl, error::*, okex::*, shadow::*,
+    user_trades_sync::*,
+};
 
 const SATS_PER_BTC: Decimal = dec!(100_000_000);
 
+/// Returns false if user trades are not synced yet and the adjustment has to be retried
 #[instrument(name = "hedging.okex.job.adjust_funding", skip_all, fields(correlation_id = %correlation_id,
         target_liability, current_position, last_price_in_usd_cents, funding_available_balance,
         trading_available_balance, onchain_fees, action, client_transfer_id,
-        transferred_funding, lag_ok), err)]
+        transferred_funding, user_trades_synced, liquidation_distance, emergency_transfer, shadow, paused), err)]
+#[allow(clippy::too_many_arguments)]
 pub(super) async fn execute(
     correlation_id: CorrelationId,
-    pool: &sqlx::PgPool,
+    triggered_at: DateTime<Utc>,
+    user_trades_sync: &UserTradesSync,
     ledger: ledger::Ledger,
+    liability_allocator: LiabilityAllocator,
     okex: OkexClient,
     okex_transfers: OkexTransfers,
     galoy: GaloyClient,
     funding_adjustment: FundingAdjustment,
-) -> Result<(), HedgingError> {
+    liquidation_monitor: LiquidationMonitor,
+    shadow_actions: Option<&ShadowActions>,
+    control: &HedgingControl,
+) -> Result<bool, HedgingError> {
     let span = tracing::Span::current();
-    if !crate::hack_user_trades_lag::lag_ok(pool).await? {
-        span.record("lag_ok", &tracing::field::display(false));
-        return Ok(());
+    let current_position = okex.get_position_in_signed_usd_cents().await?;
+    let margin = &current_position.margin;
+    if let Some(distance) = margin.liquidation_distance() {
+        span.record("liquidation_distance", &tracing::field::display(distance));
+    }
+    let emergency = liquidation_monitor.update(margin).await;
+    span.record("emergency_transfer", &tracing::field::display(emergency));
+
+    if !emergency && control.is_paused().await? {
+        span.record("paused", &tracing::field::display(true));
+        return Ok(true);
+    }
+
+    if !emergency {
+        match user_trades_sync.sync_status(triggered_at).await? {
+            UserTradesSyncStatus::Synced => (),
+            status => {
+                span.record("user_trades_synced", &tracing::field::display(false));
+                return Ok(status != UserTradesSyncStatus::Pending);
+            }
+        }
     }
 
-    let target_liability_in_cents = ledger.balances().target_liability_in_cents().await?;
+    let target_liability_in_cents = liability_allocator
+        .allocate(
+            OKEX_EXCHANGE_ID,
+            ledger.balances().target_liability_in_cents().await?,
+        )
+        .target_liability;
     span.record(
         "target_liability",
         &tracing::field::display(target_liability_in_cents),
     );
 
-    let current_position = okex.get_position_in_signed_usd_cents().await?;
     span.record(
         "current_position",
         &tracing::field::display(current_position.usd_cents),
@@ -62,17 +95,51 @@ pub(super) async fn execute(
         "trading_available_balance",
         &tracing::field::display(&trading_available_balance),
     );
-    let action = funding_adjustment.determine_action(
-        target_liability_in_cents,
-        current_position.usd_cents.into(),
-        trading_available_balance.total_amt_in_btc,
-        last_price_in_usd_cents,
-        funding_available_balance.total_amt_in_btc,
-    );
+    let action = if emergency && funding_available_balance.free_amt_in_btc > Decimal::ZERO {
+        // Too close to liquidation: move everything we can, minimum transfer size or not
+        OkexFundingAdjustment::TransferFundingToTrading(funding_available_balance.free_amt_in_btc)
+    } else {
+        funding_adjustment.determine_action(
+            target_liability_in_cents,
+            current_position.usd_cents.into(),
+            trading_available_balance.total_amt_in_btc,
+            last_price_in_usd_cents,
+            funding_available_balance.total_amt_in_btc,
+        )
+    };
     span.record("action", &tracing::field::display(&action));
+    control
+        .record_okex_funding_decision(correlation_id, &action)
+        .await;
+
+    if let Some(shadow_actions) = shadow_actions {
+        span.record("shadow", &tracing::field::display(true));
+        shadow_actions
+            .record(NewShadowAction {
+                correlation_id,
+                exchange: OKEX_EXCHANGE_ID,
+                job_name: "adjust_funding",
+                action_type: action.action_type(),
+                action_size: action.size(),
+                action_unit: action.unit(),
+                inputs: serde_json::json!({
+                    "target_liability": target_liability_in_cents,
+                    "current_position": current_position.usd_cents,
+                    "last_price_in_usd_cents": last_price_in_usd_cents,
+                    "funding_btc_free_balance": funding_available_balance.free_amt_in_btc,
+                    "funding_btc_total_balance": funding_available_balance.total_amt_in_btc,
+                    "trading_btc_used_balance": trading_available_balance.used_amt_in_btc,
+                    "trading_btc_total_balance": trading_available_balance.total_amt_in_btc,
+                    "liquidation_distance": margin.liquidation_distance(),
+                    "emergency_transfer": emergency,
+                }),
+            })
+            .await?;
+        return Ok(true);
+    }
 
     if okex.is_simulated() {
-        return Ok(());
+        return Ok(true);
     }
 
     let fees = okex.get_onchain_fees().await?;
@@ -146,11 +213,11 @@ pub(super) async fn execute(
                     {
                         span.record(
                             "client_transfer_id",
-                            &tracing::field::display(String::from(client_id)),
+                            &tracing::field::display(String::from(client_id.clone())),
                         );
 
                         let amount_in_sats = amount * SATS_PER_BTC;
-                        let memo: String = format!("deposit of {amount_in_sats} sats to OKX");
+                        let memo = deposit_memo(&client_id, amount_in_sats);
                         let _ = galoy
                             .send_onchain_payment(deposit_address, amount_in_sats, Some(memo), 1)
                             .await?;
@@ -182,5 +249,5 @@ pub(super) async fn execute(
             span.record("transferred_funding", &tracing::field::display(true));
         }
     };
-    Ok(())
+    Ok(true)
 }
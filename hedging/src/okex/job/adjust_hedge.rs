@@ -1,27 +1,133 @@
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use tracing::instrument;
+use uuid::Uuid;
 
 use okex_client::*;
-use shared::pubsub::CorrelationId;
+use shared::{payload::OKEX_EXCHANGE_ID, pubsub::CorrelationId};
 
-use crate::{error::*, okex::*};
+use crate::{
+    allocation::LiabilityAllocator, control::HedgingControl, error::*, okex::*, shadow::*,
+    user_trades_sync::*,
+};
+
+/// Job to schedule once an adjustment is done
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum AdjustHedgeFollowUp {
+    /// Place the next child order of the TWAP chain
+    TwapChild(Uuid),
+    /// User trades were not synced yet, run the adjustment again
+    Retry,
+}
 
 #[instrument(name = "hedging.okex.job.adjust_hedge", skip_all, fields(correlation_id = %correlation_id,
-        target_liability, current_position, action, placed_order, client_order_id, lag_ok), err)]
+        total_liability, liability_share, target_liability, current_position, funding_rate, action, placed_order, client_order_id, passive_fill, parent_order_id, active_twap_parent, user_trades_synced, shadow, paused), err)]
+#[allow(clippy::too_many_arguments)]
 pub(super) async fn execute(
     correlation_id: CorrelationId,
-    pool: &sqlx::PgPool,
+    parent_order_id: Option<Uuid>,
+    triggered_at: DateTime<Utc>,
+    user_trades_sync: &UserTradesSync,
     ledger: ledger::Ledger,
+    liability_allocator: LiabilityAllocator,
     okex: OkexClient,
     okex_orders: OkexOrders,
     hedging_adjustment: HedgingAdjustment,
-) -> Result<(), HedgingError> {
+    passive_order: OkexPassiveOrderConfig,
+    twap: &OkexTwapConfig,
+    shadow_actions: Option<&ShadowActions>,
+    control: &HedgingControl,
+) -> Result<Option<AdjustHedgeFollowUp>, HedgingError> {
     let span = tracing::Span::current();
-    if !crate::hack_user_trades_lag::lag_ok(pool).await? {
-        span.record("lag_ok", &tracing::field::display(false));
-        return Ok(());
+    if control.is_paused().await? {
+        span.record("paused", &tracing::field::display(true));
+        if let Some(parent_order_id) = parent_order_id {
+            okex_orders.finish_twap_chain(parent_order_id).await?;
+        }
+        return Ok(None);
+    }
+    let active_twap_parent = if twap.enabled && parent_order_id.is_none() {
+        okex_orders.active_twap_parent(twap.chain_timeout).await?
+    } else {
+        None
+    };
+    if let Some(active_twap_parent) = yields_to_twap_chain(parent_order_id, active_twap_parent) {
+        // The children of the chain re-evaluate the target, so this adjustment is folded into it
+        span.record(
+            "active_twap_parent",
+            &tracing::field::display(active_twap_parent),
+        );
+        return Ok(None);
+    }
+    match user_trades_sync.sync_status(triggered_at).await? {
+        UserTradesSyncStatus::Synced => (),
+        UserTradesSyncStatus::Pending => {
+            span.record("user_trades_synced", &tracing::field::display(false));
+            return Ok(Some(AdjustHedgeFollowUp::Retry));
+        }
+        UserTradesSyncStatus::TimedOut => {
+            span.record("user_trades_synced", &tracing::field::display(false));
+            if let Some(parent_order_id) = parent_order_id {
+                okex_orders.finish_twap_chain(parent_order_id).await?;
+            }
+            return Ok(None);
+        }
+    }
+    let mut slicer = TwapSlicer::new(twap, parent_order_id);
+    adjust(
+        correlation_id,
+        &mut slicer,
+        ledger,
+        liability_allocator,
+        okex,
+        okex_orders.clone(),
+        hedging_adjustment,
+        passive_order,
+        shadow_actions,
+        control,
+    )
+    .await?;
+    let next_parent_order_id = slicer.next_parent_order_id();
+    match (parent_order_id, next_parent_order_id) {
+        (_, Some(next_parent_order_id)) => {
+            span.record(
+                "parent_order_id",
+                &tracing::field::display(next_parent_order_id),
+            );
+        }
+        (Some(parent_order_id), None) => okex_orders.finish_twap_chain(parent_order_id).await?,
+        (None, None) => (),
     }
-    let target_liability = ledger.balances().target_liability_in_cents().await?;
+    Ok(next_parent_order_id.map(AdjustHedgeFollowUp::TwapChild))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn adjust(
+    correlation_id: CorrelationId,
+    slicer: &mut TwapSlicer,
+    ledger: ledger::Ledger,
+    liability_allocator: LiabilityAllocator,
+    okex: OkexClient,
+    okex_orders: OkexOrders,
+    hedging_adjustment: HedgingAdjustment,
+    passive_order: OkexPassiveOrderConfig,
+    shadow_actions: Option<&ShadowActions>,
+    control: &HedgingControl,
+) -> Result<(), HedgingError> {
+    let span = tracing::Span::current();
+    let allocation = liability_allocator.allocate(
+        OKEX_EXCHANGE_ID,
+        ledger.balances().target_liability_in_cents().await?,
+    );
+    span.record(
+        "total_liability",
+        &tracing::field::display(allocation.total_liability),
+    );
+    span.record(
+        "liability_share",
+        &tracing::field::display(allocation.share),
+    );
+    let target_liability = allocation.target_liability;
     span.record(
         "target_liability",
         &tracing::field::display(target_liability),
@@ -32,41 +138,285 @@ pub(super) async fn execute(
         &tracing::field::display(current_position),
     );
 
-    let action = hedging_adjustment.determine_action(target_liability, current_position.into());
+    let funding_rate = if hedging_adjustment.uses_funding_rate() {
+        let rate = okex.get_funding_rate().await?;
+        let rate = rate.next_funding_rate.unwrap_or(rate.funding_rate);
+        span.record("funding_rate", &tracing::field::display(rate));
+        Some(rate)
+    } else {
+        None
+    };
+
+    let action = hedging_adjustment.determine_action_with_funding_rate(
+        target_liability,
+        current_position.into(),
+        funding_rate,
+    );
+    control
+        .record_okex_hedge_decision(correlation_id, &action)
+        .await;
+    if let Some(shadow_actions) = shadow_actions {
+        span.record("action", &tracing::field::display(&action));
+        span.record("shadow", &tracing::field::display(true));
+        shadow_actions
+            .record(NewShadowAction {
+                correlation_id,
+                exchange: OKEX_EXCHANGE_ID,
+                job_name: "adjust_hedge",
+                action_type: action.action_type(),
+                action_size: action.size().map(Decimal::from),
+                action_unit: action.unit(),
+                inputs: serde_json::json!({
+                    "total_liability": allocation.total_liability,
+                    "liability_share": allocation.share,
+                    "target_liability": target_liability,
+                    "current_position": current_position,
+                    "funding_rate": funding_rate,
+                }),
+            })
+            .await?;
+        return Ok(());
+    }
+    let action = slicer.slice(action);
     span.record("action", &tracing::field::display(&action));
+    let reservation = OrderReservation {
+        correlation_id,
+        action: &action,
+        target_usd_value: target_liability * Decimal::NEGATIVE_ONE,
+        total_target_usd_value: allocation.total_liability * Decimal::NEGATIVE_ONE,
+        liability_share: allocation.share,
+        usd_value_before_order: current_position,
+        parent_order_id: slicer.parent_order_id,
+        twap_remaining: slicer.next_parent_order_id().is_some(),
+    };
+    let order_id = match action {
+        OkexHedgeAdjustment::DoNothing => return Ok(()),
+        _ => okex_orders.reserve_order_slot(reservation).await?,
+    };
+    let order_id = match order_id {
+        Some(order_id) => order_id,
+        None => {
+            span.record("placed_order", &tracing::field::display(false));
+            return Ok(());
+        }
+    };
+    span.record(
+        "client_order_id",
+        &tracing::field::display(String::from(order_id.clone())),
+    );
     match action {
-        OkexHedgeAdjustment::DoNothing => {}
-        _ => {
-            let reservation = OrderReservation {
-                correlation_id,
-                action: &action,
-                target_usd_value: target_liability * Decimal::NEGATIVE_ONE,
-                usd_value_before_order: current_position,
+        OkexHedgeAdjustment::ClosePosition => {
+            okex.close_positions(order_id).await?;
+        }
+        OkexHedgeAdjustment::Sell(ref contracts) | OkexHedgeAdjustment::Buy(ref contracts) => {
+            let side = match action {
+                OkexHedgeAdjustment::Sell(_) => OkexOrderSide::Sell,
+                _ => OkexOrderSide::Buy,
             };
-            if let Some(order_id) = okex_orders.reserve_order_slot(reservation).await? {
-                span.record(
-                    "client_order_id",
-                    &tracing::field::display(String::from(order_id.clone())),
-                );
-                match action {
-                    OkexHedgeAdjustment::ClosePosition => {
-                        okex.close_positions(order_id).await?;
+            if !passive_order.enabled {
+                okex.place_order(order_id, side, contracts, OkexOrderType::Market, None)
+                    .await?;
+            } else if !place_passive_order(
+                &okex,
+                &okex_orders,
+                &passive_order,
+                order_id,
+                side,
+                contracts,
+            )
+            .await?
+            {
+                span.record("passive_fill", &tracing::field::display(false));
+                let current_position = okex.get_position_in_signed_usd_cents().await?.usd_cents;
+                let action = slicer.slice(hedging_adjustment.determine_action_with_funding_rate(
+                    target_liability,
+                    current_position.into(),
+                    funding_rate,
+                ));
+                let fallback_side = match action {
+                    OkexHedgeAdjustment::Sell(_) => OkexOrderSide::Sell,
+                    OkexHedgeAdjustment::Buy(_) => OkexOrderSide::Buy,
+                    _ => {
+                        span.record("placed_order", &tracing::field::display(false));
+                        return Ok(());
                     }
-                    OkexHedgeAdjustment::Sell(ref contracts) => {
-                        okex.place_order(order_id, OkexOrderSide::Sell, contracts)
-                            .await?;
+                };
+                let reservation = OrderReservation {
+                    correlation_id,
+                    action: &action,
+                    target_usd_value: target_liability * Decimal::NEGATIVE_ONE,
+                    total_target_usd_value: allocation.total_liability * Decimal::NEGATIVE_ONE,
+                    liability_share: allocation.share,
+                    usd_value_before_order: current_position,
+                    parent_order_id: slicer.parent_order_id,
+                    twap_remaining: slicer.next_parent_order_id().is_some(),
+                };
+                match (okex_orders.reserve_order_slot(reservation).await?, &action) {
+                    (Some(order_id), OkexHedgeAdjustment::Sell(contracts))
+                    | (Some(order_id), OkexHedgeAdjustment::Buy(contracts)) => {
+                        span.record(
+                            "client_order_id",
+                            &tracing::field::display(String::from(order_id.clone())),
+                        );
+                        okex.place_order(
+                            order_id,
+                            fallback_side,
+                            contracts,
+                            OkexOrderType::Market,
+                            None,
+                        )
+                        .await?;
                     }
-                    OkexHedgeAdjustment::Buy(ref contracts) => {
-                        okex.place_order(order_id, OkexOrderSide::Buy, contracts)
-                            .await?;
+                    _ => {
+                        span.record("placed_order", &tracing::field::display(false));
+                        return Ok(());
                     }
-                    _ => unreachable!(),
                 }
-                span.record("placed_order", &tracing::field::display(true));
             } else {
-                span.record("placed_order", &tracing::field::display(false));
+                span.record("passive_fill", &tracing::field::display(true));
             }
         }
-    };
+        OkexHedgeAdjustment::DoNothing => return Ok(()),
+    }
+    span.record("placed_order", &tracing::field::display(true));
     Ok(())
 }
+
+/// Regular adjustments (without a parent) are skipped while a TWAP chain is placing
+/// child orders. Returns the parent of the chain to yield to.
+fn yields_to_twap_chain(
+    parent_order_id: Option<Uuid>,
+    active_twap_parent: Option<Uuid>,
+) -> Option<Uuid> {
+    match parent_order_id {
+        None => active_twap_parent,
+        Some(_) => None,
+    }
+}
+
+/// Splits adjustments above the configured size into child orders sharing a parent id.
+/// Each child re-evaluates the target so the remaining size follows liability changes.
+struct TwapSlicer {
+    max_child_order_contracts: Option<u32>,
+    parent_order_id: Option<Uuid>,
+    remaining: bool,
+}
+
+impl TwapSlicer {
+    fn new(config: &OkexTwapConfig, parent_order_id: Option<Uuid>) -> Self {
+        Self {
+            max_child_order_contracts: config.enabled.then_some(config.max_child_order_contracts),
+            parent_order_id,
+            remaining: parent_order_id.is_some(),
+        }
+    }
+
+    fn slice(&mut self, action: OkexHedgeAdjustment) -> OkexHedgeAdjustment {
+        let (action, remaining) = match self.max_child_order_contracts {
+            Some(max_contracts) => action.slice(max_contracts),
+            None => (action, false),
+        };
+        self.remaining = remaining;
+        if remaining && self.parent_order_id.is_none() {
+            self.parent_order_id = Some(Uuid::new_v4());
+        }
+        action
+    }
+
+    fn next_parent_order_id(&self) -> Option<Uuid> {
+        if self.remaining {
+            self.parent_order_id
+        } else {
+            None
+        }
+    }
+}
+
+/// Places a post-only order at the near touch and waits for it to fill.
+/// Returns false if the order was canceled without filling completely.
+async fn place_passive_order(
+    okex: &OkexClient,
+    okex_orders: &OkexOrders,
+    config: &OkexPassiveOrderConfig,
+    order_id: ClientOrderId,
+    side: OkexOrderSide,
+    contracts: &BtcUsdSwapContracts,
+) -> Result<bool, HedgingError> {
+    let price = okex.get_last_price_in_usd_cents().await?;
+    let limit_price = match side {
+        OkexOrderSide::Buy => price.best_bid_in_usd,
+        OkexOrderSide::Sell => price.best_ask_in_usd,
+    };
+    okex.place_order(
+        order_id.clone(),
+        side,
+        contracts,
+        OkexOrderType::PostOnly,
+        Some(limit_price),
+    )
+    .await?;
+
+    let deadline = tokio::time::Instant::now() + config.timeout;
+    let mut details = okex.order_details(order_id.clone()).await?;
+    while !details.complete && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(config.poll_interval).await;
+        details = okex.order_details(order_id.clone()).await?;
+    }
+    if !details.complete {
+        // The order may fill before the cancel reaches the exchange
+        let cancelled = okex.cancel_order(order_id.clone()).await;
+        details = okex.order_details(order_id).await?;
+        if !details.complete {
+            cancelled?;
+        }
+    }
+    let filled = details.state == "filled";
+    okex_orders.update_order(details).await?;
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn twap() -> OkexTwapConfig {
+        OkexTwapConfig {
+            enabled: true,
+            max_child_order_contracts: 50,
+            ..OkexTwapConfig::default()
+        }
+    }
+
+    fn sell(contracts: u32) -> OkexHedgeAdjustment {
+        OkexHedgeAdjustment::Sell(BtcUsdSwapContracts::from(contracts))
+    }
+
+    #[test]
+    fn regular_jobs_yield_to_running_twap_chain() {
+        // A regular job starts a chain for 120 contracts
+        assert_eq!(yields_to_twap_chain(None, None), None);
+        let mut slicer = TwapSlicer::new(&twap(), None);
+        assert_eq!(slicer.slice(sell(120)), sell(50));
+        let parent = slicer.next_parent_order_id().expect("chain started");
+        // twap_remaining of the reserved order keeps the chain active
+        let mut active_twap_parent = Some(parent);
+
+        // Regular jobs triggered in between are folded into the chain
+        assert_eq!(yields_to_twap_chain(None, active_twap_parent), Some(parent));
+
+        // Children keep running and re-evaluate the remaining size
+        assert_eq!(yields_to_twap_chain(Some(parent), active_twap_parent), None);
+        let mut slicer = TwapSlicer::new(&twap(), Some(parent));
+        assert_eq!(slicer.slice(sell(70)), sell(50));
+        assert_eq!(slicer.next_parent_order_id(), Some(parent));
+        assert_eq!(yields_to_twap_chain(None, active_twap_parent), Some(parent));
+
+        let mut slicer = TwapSlicer::new(&twap(), Some(parent));
+        assert_eq!(slicer.slice(sell(20)), sell(20));
+        assert_eq!(slicer.next_parent_order_id(), None);
+        active_twap_parent = None;
+
+        // Once the chain is done regular jobs adjust again
+        assert_eq!(yields_to_twap_chain(None, active_twap_parent), None);
+    }
+}
@@ -1,7 +1,9 @@
 mod adjust_funding;
 mod adjust_hedge;
+mod poll_funding_fees;
 mod poll_okex;
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{Executor, Postgres};
 use sqlxmq::{job, CurrentJob, JobBuilder};
@@ -17,10 +19,15 @@ use shared::{
     sqlxmq::JobExecutor,
 };
 
-use crate::{error::*, okex::*};
+use crate::{
+    allocation::LiabilityAllocator, config::HedgingMode, control::HedgingControl, error::*,
+    okex::*, shadow::*, user_trades_sync::UserTradesSync,
+};
 
 // retired: uuid!("10000000-0000-0000-0000-000000000001");
 pub const POLL_OKEX_ID: Uuid = uuid!("10000000-0000-0000-0000-000000000002");
+pub const POLL_FUNDING_FEES_ID: Uuid = uuid!("10000000-0000-0000-0000-000000000003");
+pub const EMERGENCY_ADJUST_FUNDING_ID: Uuid = uuid!("10000000-0000-0000-0000-000000000004");
 
 #[derive(Debug, Clone)]
 pub(super) struct OkexPollDelay(pub(super) std::time::Duration);
@@ -46,9 +53,35 @@ pub async fn spawn_poll_okex(
     }
 }
 
+#[instrument(name = "hedging.okex.job.spawn_poll_funding_fees", skip_all, fields(error, error.level, error.message), err)]
+pub async fn spawn_poll_funding_fees(
+    pool: &sqlx::PgPool,
+    duration: std::time::Duration,
+) -> Result<(), HedgingError> {
+    match JobBuilder::new_with_id(POLL_FUNDING_FEES_ID, "poll_funding_fees")
+        .set_channel_name("hedging.okex")
+        .set_channel_args("poll_funding_fees")
+        .set_delay(duration)
+        .spawn(pool)
+        .await
+    {
+        Err(sqlx::Error::Database(err)) if err.message().contains("duplicate key") => Ok(()),
+        Err(e) => {
+            shared::tracing::insert_error_fields(tracing::Level::ERROR, &e);
+            Err(e.into())
+        }
+        Ok(_) => Ok(()),
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 struct AdjustHedgeData {
     correlation_id: CorrelationId,
+    #[serde(default)]
+    parent_order_id: Option<Uuid>,
+    /// Kept across retries so that the user trades sync can time out
+    #[serde(default = "Utc::now")]
+    triggered_at: DateTime<Utc>,
     #[serde(flatten)]
     tracing_data: HashMap<String, String>,
 }
@@ -66,6 +99,8 @@ pub async fn spawn_adjust_hedge<'a>(
         .set_json(&AdjustHedgeData {
             tracing_data: shared::tracing::extract_tracing_data(),
             correlation_id: CorrelationId::from(correlation_id),
+            parent_order_id: None,
+            triggered_at: Utc::now(),
         })
         .expect("Couldn't set json")
         .spawn(tx)
@@ -80,34 +115,111 @@ pub async fn spawn_adjust_hedge<'a>(
     }
 }
 
+/// Spawns a TWAP child order or a retry of an adjustment that was not synced yet
+#[instrument(name = "hedging.okex.job.spawn_delayed_adjust_hedge", skip_all, fields(error, error.message), err)]
+async fn spawn_delayed_adjust_hedge(
+    pool: &sqlx::PgPool,
+    correlation_id: CorrelationId,
+    parent_order_id: Option<Uuid>,
+    triggered_at: DateTime<Utc>,
+    delay: std::time::Duration,
+) -> Result<(), HedgingError> {
+    match JobBuilder::new("adjust_hedge")
+        .set_ordered(true)
+        .set_channel_name("hedging.okex")
+        .set_channel_args("adjust_hedge")
+        .set_delay(delay)
+        .set_json(&AdjustHedgeData {
+            tracing_data: shared::tracing::extract_tracing_data(),
+            correlation_id,
+            parent_order_id,
+            triggered_at,
+        })
+        .expect("Couldn't set json")
+        .spawn(pool)
+        .await
+    {
+        Err(e) => {
+            shared::tracing::insert_error_fields(tracing::Level::ERROR, &e);
+            Err(e.into())
+        }
+        Ok(_) => Ok(()),
+    }
+}
+
 #[job(name = "poll_okex")]
+#[allow(clippy::too_many_arguments)]
 pub(super) async fn poll_okex(
     mut current_job: CurrentJob,
     OkexPollDelay(delay): OkexPollDelay,
+    ledger: ledger::Ledger,
     okex: OkexClient,
     okex_orders: OkexOrders,
     okex_transfers: OkexTransfers,
+    galoy: GaloyClient,
     publisher: Publisher,
     funding_config: OkexFundingConfig,
+    liquidation_monitor: LiquidationMonitor,
 ) -> Result<(), HedgingError> {
+    let pool = current_job.pool().clone();
     JobExecutor::builder(&mut current_job)
         .build()
         .expect("couldn't build JobExecutor")
         .execute(|_| async move {
-            poll_okex::execute(okex_orders, okex_transfers, okex, publisher, funding_config).await
+            poll_okex::execute(
+                &pool,
+                &ledger,
+                okex_orders,
+                okex_transfers,
+                okex,
+                galoy,
+                publisher,
+                funding_config,
+                liquidation_monitor,
+            )
+            .await
         })
         .await?;
     spawn_poll_okex(current_job.pool(), delay).await?;
     Ok(())
 }
 
+#[job(name = "poll_funding_fees")]
+pub(super) async fn poll_funding_fees(
+    mut current_job: CurrentJob,
+    ledger: ledger::Ledger,
+    okex: OkexClient,
+    funding_fees: OkexFundingFees,
+    config: OkexFundingFeesConfig,
+) -> Result<(), HedgingError> {
+    let pool = current_job.pool().clone();
+    let poll_interval = config.poll_interval;
+    JobExecutor::builder(&mut current_job)
+        .build()
+        .expect("couldn't build JobExecutor")
+        .execute(|_| async move {
+            poll_funding_fees::execute(&pool, &ledger, okex, funding_fees, &config).await
+        })
+        .await?;
+    spawn_poll_funding_fees(current_job.pool(), poll_interval).await?;
+    Ok(())
+}
+
 #[job(name = "adjust_hedge")]
+#[allow(clippy::too_many_arguments)]
 pub(super) async fn adjust_hedge(
     mut current_job: CurrentJob,
     ledger: ledger::Ledger,
+    liability_allocator: LiabilityAllocator,
     okex: OkexClient,
     okex_orders: OkexOrders,
     hedging_adjustment: HedgingAdjustment,
+    passive_order: OkexPassiveOrderConfig,
+    twap: OkexTwapConfig,
+    mode: HedgingMode,
+    shadow_actions: ShadowActions,
+    user_trades_sync: UserTradesSync,
+    control: HedgingControl,
 ) -> Result<(), HedgingError> {
     let pool = current_job.pool().clone();
     JobExecutor::builder(&mut current_job)
@@ -115,15 +227,45 @@ pub(super) async fn adjust_hedge(
         .expect("couldn't build JobExecutor")
         .execute(|data| async move {
             let data: AdjustHedgeData = data.ok_or(HedgingError::NoJobDataPresent)?;
-            adjust_hedge::execute(
+            let follow_up = adjust_hedge::execute(
                 data.correlation_id,
-                &pool,
+                data.parent_order_id,
+                data.triggered_at,
+                &user_trades_sync,
                 ledger,
+                liability_allocator,
                 okex,
                 okex_orders,
                 hedging_adjustment,
+                passive_order,
+                &twap,
+                mode.is_shadow().then_some(&shadow_actions),
+                &control,
             )
             .await?;
+            match follow_up {
+                Some(adjust_hedge::AdjustHedgeFollowUp::TwapChild(parent_order_id)) => {
+                    spawn_delayed_adjust_hedge(
+                        &pool,
+                        data.correlation_id,
+                        Some(parent_order_id),
+                        Utc::now(),
+                        twap.child_order_interval,
+                    )
+                    .await?
+                }
+                Some(adjust_hedge::AdjustHedgeFollowUp::Retry) => {
+                    spawn_delayed_adjust_hedge(
+                        &pool,
+                        data.correlation_id,
+                        data.parent_order_id,
+                        data.triggered_at,
+                        user_trades_sync.retry_delay(),
+                    )
+                    .await?
+                }
+                None => (),
+            }
             Ok::<_, HedgingError>(data)
         })
         .await?;
@@ -133,6 +275,9 @@ pub(super) async fn adjust_hedge(
 #[derive(Serialize, Deserialize)]
 struct AdjustFundingData {
     correlation_id: CorrelationId,
+    /// Kept across retries so that the user trades sync can time out
+    #[serde(default = "Utc::now")]
+    triggered_at: DateTime<Utc>,
     #[serde(flatten)]
     tracing_data: HashMap<String, String>,
 }
@@ -150,6 +295,7 @@ pub async fn spawn_adjust_funding<'a>(
         .set_json(&AdjustFundingData {
             tracing_data: shared::tracing::extract_tracing_data(),
             correlation_id: CorrelationId::from(correlation_id),
+            triggered_at: Utc::now(),
         })
         .expect("Couldn't set json")
         .spawn(tx)
@@ -164,14 +310,50 @@ pub async fn spawn_adjust_funding<'a>(
     }
 }
 
+#[instrument(name = "hedging.okex.job.retry_adjust_funding", skip_all, fields(error, error.message), err)]
+async fn retry_adjust_funding(
+    pool: &sqlx::PgPool,
+    correlation_id: CorrelationId,
+    triggered_at: DateTime<Utc>,
+    delay: std::time::Duration,
+) -> Result<(), HedgingError> {
+    match JobBuilder::new("adjust_funding")
+        .set_ordered(true)
+        .set_channel_name("hedging.okex")
+        .set_channel_args("adjust_funding")
+        .set_delay(delay)
+        .set_json(&AdjustFundingData {
+            tracing_data: shared::tracing::extract_tracing_data(),
+            correlation_id,
+            triggered_at,
+        })
+        .expect("Couldn't set json")
+        .spawn(pool)
+        .await
+    {
+        Err(e) => {
+            shared::tracing::insert_error_fields(tracing::Level::ERROR, &e);
+            Err(e.into())
+        }
+        Ok(_) => Ok(()),
+    }
+}
+
 #[job(name = "adjust_funding")]
+#[allow(clippy::too_many_arguments)]
 pub(super) async fn adjust_funding(
     mut current_job: CurrentJob,
     ledger: ledger::Ledger,
+    liability_allocator: LiabilityAllocator,
     okex: OkexClient,
     okex_transfers: OkexTransfers,
     galoy: GaloyClient,
     funding_adjustment: FundingAdjustment,
+    liquidation_monitor: LiquidationMonitor,
+    mode: HedgingMode,
+    shadow_actions: ShadowActions,
+    user_trades_sync: UserTradesSync,
+    control: HedgingControl,
 ) -> Result<(), HedgingError> {
     let pool = current_job.pool().clone();
     JobExecutor::builder(&mut current_job)
@@ -179,16 +361,30 @@ pub(super) async fn adjust_funding(
         .expect("couldn't build JobExecutor")
         .execute(|data| async move {
             let data: AdjustFundingData = data.ok_or(HedgingError::NoJobDataPresent)?;
-            adjust_funding::execute(
+            let synced = adjust_funding::execute(
                 data.correlation_id,
-                &pool,
+                data.triggered_at,
+                &user_trades_sync,
                 ledger,
+                liability_allocator,
                 okex,
                 okex_transfers,
                 galoy,
                 funding_adjustment,
+                liquidation_monitor,
+                mode.is_shadow().then_some(&shadow_actions),
+                &control,
             )
             .await?;
+            if !synced {
+                retry_adjust_funding(
+                    &pool,
+                    data.correlation_id,
+                    data.triggered_at,
+                    user_trades_sync.retry_delay(),
+                )
+                .await?;
+            }
             Ok::<_, HedgingError>(data)
         })
         .await?;
@@ -0,0 +1,72 @@
+use okex_client::OkexClient;
+use tracing::instrument;
+
+use crate::{error::HedgingError, okex::*};
+
+#[instrument(name = "hedging.okex.job.poll_funding_fees", skip_all, fields(n_new_bills, funding_last_24h, error, error.level, error.message), err)]
+pub async fn execute(
+    pool: &sqlx::PgPool,
+    ledger: &ledger::Ledger,
+    okex: OkexClient,
+    funding_fees: OkexFundingFees,
+    config: &OkexFundingFeesConfig,
+) -> Result<(), HedgingError> {
+    let span = tracing::Span::current();
+    // Bills are not necessarily persisted in order (eg. a page failed half way),
+    // so keep paging until we are past the newest bill we already know about.
+    let last_paid_at = funding_fees.last_paid_at().await?;
+    let mut after = None;
+    let mut n_new_bills = 0;
+    loop {
+        let bills = okex.funding_fee_bills(after).await?;
+        for bill in bills.iter() {
+            if funding_fees.persist(bill).await? {
+                n_new_bills += 1;
+            }
+        }
+        after = match bills.last() {
+            Some(bill) if last_paid_at.map_or(true, |last| bill.timestamp >= last) => {
+                Some(bill.bill_id.clone())
+            }
+            _ => break,
+        };
+    }
+    span.record("n_new_bills", &tracing::field::display(n_new_bills));
+
+    loop {
+        let mut tx = pool.begin().await?;
+        if let Some(fee) = funding_fees.find_unaccounted(&mut tx).await? {
+            ledger
+                .okex_funding_payment(
+                    tx,
+                    fee.ledger_tx_id,
+                    ledger::OkexFundingPaymentParams {
+                        btc_amount: fee.amount,
+                        meta: ledger::OkexFundingPaymentMeta {
+                            timestamp: fee.paid_at,
+                            bill_id: fee.bill_id,
+                        },
+                    },
+                )
+                .await?;
+        } else {
+            break;
+        }
+    }
+
+    let funding_last_24h = funding_fees
+        .total_since(chrono::Utc::now() - chrono::Duration::hours(24))
+        .await?;
+    span.record(
+        "funding_last_24h",
+        &tracing::field::display(funding_last_24h),
+    );
+    if funding_last_24h < -config.negative_funding_alert_threshold_btc {
+        shared::tracing::insert_error_fields(
+            tracing::Level::WARN,
+            format!("Paid {} BTC of funding in the last 24h", -funding_last_24h),
+        );
+    }
+
+    Ok(())
+}
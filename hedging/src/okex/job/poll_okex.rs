@@ -1,3 +1,7 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use galoy_client::{GaloyClient, GaloyTransactions, TxDirection};
 use okex_client::{OkexClient, OkexClientError, PositionSize};
 use shared::{
     payload::{
@@ -9,16 +13,24 @@ use shared::{
 
 use crate::{error::HedgingError, okex::*};
 
+const SATS_PER_BTC: Decimal = dec!(100_000_000);
+
+#[allow(clippy::too_many_arguments)]
 pub async fn execute(
+    pool: &sqlx::PgPool,
+    ledger: &ledger::Ledger,
     okex_orders: OkexOrders,
     okex_transfers: OkexTransfers,
     okex: OkexClient,
+    galoy: GaloyClient,
     publisher: Publisher,
     funding_config: OkexFundingConfig,
+    liquidation_monitor: LiquidationMonitor,
 ) -> Result<(), HedgingError> {
     let PositionSize {
         usd_cents,
         instrument_id,
+        margin,
         ..
     } = okex.get_position_in_signed_usd_cents().await?;
     publisher
@@ -29,6 +41,11 @@ pub async fn execute(
         })
         .await?;
 
+    if liquidation_monitor.update(&margin).await {
+        // Fixed id so that at most one emergency transfer is queued at a time
+        super::spawn_adjust_funding(pool, super::EMERGENCY_ADJUST_FUNDING_ID).await?;
+    }
+
     let mut execute_sweep = false;
     for id in okex_orders.open_orders().await? {
         match okex.order_details(id.clone()).await {
@@ -48,6 +65,8 @@ pub async fn execute(
         okex_orders.sweep_lost_records().await?;
     }
 
+    update_ledger(pool, &okex_orders, ledger).await?;
+
     let mut execute_transfer_sweep = false;
     for id in okex_transfers.get_pending_transfers().await? {
         match okex.transfer_state_by_client_id(id.clone()).await {
@@ -99,5 +118,195 @@ pub async fn execute(
         okex_transfers.sweep_lost_records().await?;
     }
 
+    capture_deposit_send_fees(&okex_transfers, &galoy, &funding_config).await?;
+    update_transfers_ledger(pool, &okex_transfers, ledger).await?;
+
+    Ok(())
+}
+
+async fn update_ledger(
+    pool: &sqlx::PgPool,
+    okex_orders: &OkexOrders,
+    ledger: &ledger::Ledger,
+) -> Result<(), HedgingError> {
+    loop {
+        let mut tx = pool.begin().await?;
+        if let Some(fee) = okex_orders.find_unaccounted_fee(&mut tx).await? {
+            ledger
+                .okex_trading_fee(
+                    tx,
+                    fee.ledger_tx_id,
+                    ledger::OkexTradingFeeParams {
+                        btc_amount: fee.btc_amount,
+                        meta: order_meta(&fee),
+                    },
+                )
+                .await?;
+        } else {
+            break;
+        }
+    }
+    loop {
+        let mut tx = pool.begin().await?;
+        if let Some(pnl) = okex_orders.find_unaccounted_pnl(&mut tx).await? {
+            ledger
+                .okex_realized_pnl(
+                    tx,
+                    pnl.ledger_tx_id,
+                    ledger::OkexRealizedPnlParams {
+                        btc_amount: pnl.btc_amount,
+                        meta: order_meta(&pnl),
+                    },
+                )
+                .await?;
+        } else {
+            break;
+        }
+    }
     Ok(())
 }
+
+/// Deposits are only posted to the ledger once the fee galoy charged for sending them is known.
+/// The payment result doesn't include the fee so it is looked up in the wallet history,
+/// paging back until the oldest deposit still missing its fee.
+async fn capture_deposit_send_fees(
+    okex_transfers: &OkexTransfers,
+    galoy: &GaloyClient,
+    funding_config: &OkexFundingConfig,
+) -> Result<(), HedgingError> {
+    let mut deposits = okex_transfers.get_deposits_without_send_fee().await?;
+    let oldest = match deposits.iter().map(|(_, created_at)| *created_at).min() {
+        Some(oldest) => oldest,
+        None => return Ok(()),
+    };
+    let mut cursor = None;
+    loop {
+        let GaloyTransactions {
+            cursor: next,
+            list,
+            has_more,
+        } = galoy.transactions_list(cursor).await?;
+        for tx in list
+            .iter()
+            .filter(|tx| matches!(tx.direction, TxDirection::SEND))
+        {
+            let memo = match tx.memo.as_deref() {
+                Some(memo) => memo,
+                None => continue,
+            };
+            if let Some(idx) = deposits
+                .iter()
+                .position(|(id, _)| is_deposit_memo(memo, id))
+            {
+                let (id, _) = deposits.swap_remove(idx);
+                okex_transfers
+                    .record_send_fee(id, tx.settlement_fee.abs() / SATS_PER_BTC)
+                    .await?;
+            }
+        }
+        let reached_oldest = list.iter().any(|tx| tx.created_at < oldest);
+        if deposits.is_empty() || reached_oldest || !has_more || next.is_none() {
+            break;
+        }
+        cursor = next;
+    }
+
+    for (id, created_at) in deposits {
+        if chrono::Utc::now() - created_at > funding_config.deposit_lost_timeout_seconds {
+            shared::tracing::insert_error_fields(
+                tracing::Level::ERROR,
+                format!(
+                    "No galoy send found for deposit {}, booking it without a send fee",
+                    String::from(id.clone())
+                ),
+            );
+            okex_transfers.record_send_fee(id, Decimal::ZERO).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn update_transfers_ledger(
+    pool: &sqlx::PgPool,
+    okex_transfers: &OkexTransfers,
+    ledger: &ledger::Ledger,
+) -> Result<(), HedgingError> {
+    loop {
+        let mut tx = pool.begin().await?;
+        if let Some(transfer) = okex_transfers.find_unaccounted_initiation(&mut tx).await? {
+            ledger
+                .okex_transfer_initiated(
+                    tx,
+                    transfer.ledger_tx_id,
+                    transfer_params(&transfer, transfer.created_at),
+                )
+                .await?;
+        } else {
+            break;
+        }
+    }
+    loop {
+        let mut tx = pool.begin().await?;
+        if let Some(transfer) = okex_transfers.find_unaccounted_settlement(&mut tx).await? {
+            ledger
+                .okex_transfer_settled(
+                    tx,
+                    transfer.ledger_tx_id,
+                    transfer_params(
+                        &transfer,
+                        transfer.completed_at.unwrap_or(transfer.created_at),
+                    ),
+                )
+                .await?;
+        } else {
+            break;
+        }
+    }
+    loop {
+        let mut tx = pool.begin().await?;
+        if let Some(transfer) = okex_transfers.find_unaccounted_revert(&mut tx).await? {
+            let initiated_ledger_tx_id = transfer.initiated_ledger_tx_id.ok_or_else(|| {
+                HedgingError::TransferNotInitiated(String::from(
+                    transfer.client_transfer_id.clone(),
+                ))
+            })?;
+            ledger
+                .revert_okex_transfer_initiated(
+                    tx,
+                    transfer.ledger_tx_id,
+                    ledger::RevertOkexTransferInitiatedParams {
+                        initiated_ledger_tx_id,
+                        transfer: transfer_params(&transfer, chrono::Utc::now()),
+                    },
+                )
+                .await?;
+        } else {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn transfer_params(
+    transfer: &UnaccountedTransfer,
+    timestamp: chrono::DateTime<chrono::Utc>,
+) -> ledger::OkexTransferParams {
+    ledger::OkexTransferParams {
+        btc_amount: transfer.amount,
+        btc_fee: transfer.fee,
+        meta: ledger::OkexTransferMeta {
+            timestamp,
+            direction: transfer.direction,
+            client_transfer_id: String::from(transfer.client_transfer_id.clone()),
+            transfer_id: transfer.transfer_id.clone().unwrap_or_default(),
+        },
+    }
+}
+
+fn order_meta(amount: &UnaccountedOrderAmount) -> ledger::OkexOrderMeta {
+    ledger::OkexOrderMeta {
+        timestamp: amount.created_at,
+        client_order_id: String::from(amount.client_order_id.clone()),
+        order_id: amount.order_id.clone().unwrap_or_default(),
+    }
+}
@@ -0,0 +1,82 @@
+use rust_decimal::Decimal;
+use tokio::sync::RwLock;
+
+use std::sync::Arc;
+
+use okex_client::PositionMargin;
+
+use super::config::OkexLiquidationConfig;
+
+/// Keeps the latest margin data of the OKX position so that the health check
+/// and the funding job can tell how close the short is to liquidation.
+///
+/// The state is kept in memory per process: it starts out empty (healthy) after a
+/// restart until the next `poll_okex` or `adjust_funding` job has updated it.
+#[derive(Debug, Clone)]
+pub struct LiquidationMonitor {
+    config: OkexLiquidationConfig,
+    latest_distance: Arc<RwLock<Option<Decimal>>>,
+}
+
+impl LiquidationMonitor {
+    pub fn new(config: OkexLiquidationConfig) -> Self {
+        Self {
+            config,
+            latest_distance: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Records the margin data and returns whether an emergency transfer is needed
+    pub async fn update(&self, margin: &PositionMargin) -> bool {
+        let distance = margin.liquidation_distance();
+        *self.latest_distance.write().await = distance;
+        self.is_emergency(distance)
+    }
+
+    pub async fn healthy(&self) -> Result<(), String> {
+        self.check(*self.latest_distance.read().await)
+    }
+
+    fn is_emergency(&self, distance: Option<Decimal>) -> bool {
+        matches!(distance, Some(distance) if distance < self.config.emergency_distance)
+    }
+
+    fn check(&self, distance: Option<Decimal>) -> Result<(), String> {
+        match distance {
+            Some(distance) if distance < self.config.unhealthy_distance => Err(format!(
+                "OKX position is within {distance} of its liquidation price"
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    fn monitor() -> LiquidationMonitor {
+        LiquidationMonitor::new(OkexLiquidationConfig {
+            unhealthy_distance: dec!(0.2),
+            emergency_distance: dec!(0.1),
+        })
+    }
+
+    #[test]
+    fn unhealthy_when_close_to_liquidation() {
+        let monitor = monitor();
+        assert!(monitor.check(None).is_ok());
+        assert!(monitor.check(Some(dec!(0.3))).is_ok());
+        assert!(monitor.check(Some(dec!(0.15))).is_err());
+    }
+
+    #[test]
+    fn emergency_when_closer_than_emergency_distance() {
+        let monitor = monitor();
+        assert!(!monitor.is_emergency(None));
+        assert!(!monitor.is_emergency(Some(dec!(0.15))));
+        assert!(monitor.is_emergency(Some(dec!(0.05))));
+    }
+}
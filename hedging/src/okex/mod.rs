@@ -1,14 +1,18 @@
 mod config;
 mod engine;
 mod funding_adjustment;
+mod funding_fees;
 mod hedge_adjustment;
 pub mod job;
+mod liquidation;
 mod orders;
 mod transfers;
 
 pub use config::*;
 pub use engine::*;
 pub use funding_adjustment::*;
+pub use funding_fees::*;
 pub use hedge_adjustment::*;
+pub use liquidation::*;
 pub use orders::*;
 pub use transfers::*;
@@ -1,5 +1,7 @@
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
-use sqlx::{Executor, PgPool};
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use tracing::instrument;
 use uuid::Uuid;
 
 use okex_client::{ClientOrderId, OrderDetails};
@@ -12,7 +14,32 @@ pub struct OrderReservation<'a> {
     pub correlation_id: CorrelationId,
     pub action: &'a OkexHedgeAdjustment,
     pub target_usd_value: Decimal,
+    pub total_target_usd_value: Decimal,
+    pub liability_share: Decimal,
     pub usd_value_before_order: Decimal,
+    pub parent_order_id: Option<Uuid>,
+    /// Whether the TWAP chain of `parent_order_id` has more child orders to place.
+    pub twap_remaining: bool,
+}
+
+/// Fee or realized pnl of a completed order that has not been posted to the ledger yet
+pub struct UnaccountedOrderAmount {
+    pub client_order_id: ClientOrderId,
+    pub order_id: Option<String>,
+    pub btc_amount: Decimal,
+    pub created_at: DateTime<Utc>,
+    pub ledger_tx_id: ledger::LedgerTxId,
+}
+
+pub struct OpenOrder {
+    pub client_order_id: String,
+    pub correlation_id: Uuid,
+    pub action: String,
+    pub size: Option<Decimal>,
+    pub unit: String,
+    pub state: Option<String>,
+    pub lost: bool,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Clone)]
@@ -46,8 +73,9 @@ impl OkexOrders {
             r#"INSERT INTO okex_orders (
               client_order_id, correlation_id, instrument,
               action, size, unit, size_usd_value, target_usd_value,
-              position_usd_value_before_order
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)"#,
+              total_target_usd_value, liability_share,
+              position_usd_value_before_order, parent_order_id, twap_remaining
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)"#,
             String::from(id.clone()),
             Uuid::from(reservation.correlation_id),
             "BTC-USD-SWAP",
@@ -56,7 +84,11 @@ impl OkexOrders {
             reservation.action.unit(),
             reservation.action.size_in_usd(),
             reservation.target_usd_value,
+            reservation.total_target_usd_value,
+            reservation.liability_share,
             reservation.usd_value_before_order,
+            reservation.parent_order_id,
+            reservation.twap_remaining,
         )
         .execute(&mut tx)
         .await?;
@@ -64,6 +96,40 @@ impl OkexOrders {
         Ok(Some(id))
     }
 
+    /// Parent id of the TWAP chain that is still placing child orders, if any.
+    /// A chain whose last child order is older than `timeout` is considered dead.
+    pub async fn active_twap_parent(
+        &self,
+        timeout: std::time::Duration,
+    ) -> Result<Option<Uuid>, HedgingError> {
+        let since = Utc::now()
+            - chrono::Duration::from_std(timeout).unwrap_or_else(|_| chrono::Duration::zero());
+        let row = sqlx::query!(
+            r#"SELECT parent_order_id, twap_remaining, created_at
+               FROM okex_orders WHERE lost = false ORDER BY created_at DESC LIMIT 1"#
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.and_then(|row| {
+            if row.twap_remaining && row.created_at > since {
+                row.parent_order_id
+            } else {
+                None
+            }
+        }))
+    }
+
+    /// Marks the TWAP chain as done when its last child did not place an order.
+    pub async fn finish_twap_chain(&self, parent_order_id: Uuid) -> Result<(), HedgingError> {
+        sqlx::query!(
+            r#"UPDATE okex_orders SET twap_remaining = false WHERE parent_order_id = $1 AND twap_remaining = true"#,
+            parent_order_id,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
     pub async fn open_orders(&self) -> Result<Vec<ClientOrderId>, HedgingError> {
         let res = sqlx::query!(r#"SELECT client_order_id FROM okex_orders WHERE complete = false"#)
             .fetch_all(&self.pool)
@@ -74,12 +140,35 @@ impl OkexOrders {
             .collect())
     }
 
+    pub async fn list_open_orders(&self) -> Result<Vec<OpenOrder>, HedgingError> {
+        let res = sqlx::query!(
+            r#"SELECT client_order_id, correlation_id, action, size, unit, state, lost, created_at
+               FROM okex_orders WHERE complete = false ORDER BY created_at"#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(res
+            .into_iter()
+            .map(|r| OpenOrder {
+                client_order_id: r.client_order_id,
+                correlation_id: r.correlation_id,
+                action: r.action,
+                size: r.size,
+                unit: r.unit,
+                state: r.state,
+                lost: r.lost,
+                created_at: r.created_at,
+            })
+            .collect())
+    }
+
     pub async fn update_order(&self, details: OrderDetails) -> Result<(), HedgingError> {
         sqlx::query!(
-            r#"UPDATE okex_orders SET lost = false, order_id = $1, avg_price = $2, fee = $3, state = $4, complete = $5 WHERE client_order_id = $6"#,
+            r#"UPDATE okex_orders SET lost = false, order_id = $1, avg_price = $2, fee = $3, pnl = $4, state = $5, complete = $6 WHERE client_order_id = $7"#,
             details.ord_id,
             details.avg_px,
             details.fee,
+            details.pnl,
             details.state,
             details.complete,
             String::from(details.cl_ord_id),
@@ -107,4 +196,58 @@ impl OkexOrders {
         .await?;
         Ok(())
     }
+
+    #[instrument(name = "okex_orders.find_unaccounted_fee", skip_all)]
+    pub async fn find_unaccounted_fee(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<Option<UnaccountedOrderAmount>, HedgingError> {
+        let tx_id = Uuid::new_v4();
+        let order = sqlx::query!(
+            r#"UPDATE okex_orders
+               SET fee_ledger_tx_id = $1
+               WHERE client_order_id = (
+                 SELECT client_order_id FROM okex_orders
+                 WHERE complete = true AND fee_ledger_tx_id IS NULL AND fee IS NOT NULL AND fee <> 0
+                 ORDER BY created_at LIMIT 1
+               ) RETURNING client_order_id, order_id, fee as "fee!", created_at"#,
+            tx_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        Ok(order.map(|order| UnaccountedOrderAmount {
+            client_order_id: ClientOrderId::from(order.client_order_id),
+            order_id: order.order_id,
+            btc_amount: order.fee,
+            created_at: order.created_at,
+            ledger_tx_id: ledger::LedgerTxId::from(tx_id),
+        }))
+    }
+
+    #[instrument(name = "okex_orders.find_unaccounted_pnl", skip_all)]
+    pub async fn find_unaccounted_pnl(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<Option<UnaccountedOrderAmount>, HedgingError> {
+        let tx_id = Uuid::new_v4();
+        let order = sqlx::query!(
+            r#"UPDATE okex_orders
+               SET pnl_ledger_tx_id = $1
+               WHERE client_order_id = (
+                 SELECT client_order_id FROM okex_orders
+                 WHERE complete = true AND pnl_ledger_tx_id IS NULL AND pnl IS NOT NULL AND pnl <> 0
+                 ORDER BY created_at LIMIT 1
+               ) RETURNING client_order_id, order_id, pnl as "pnl!", created_at"#,
+            tx_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        Ok(order.map(|order| UnaccountedOrderAmount {
+            client_order_id: ClientOrderId::from(order.client_order_id),
+            order_id: order.order_id,
+            btc_amount: order.pnl,
+            created_at: order.created_at,
+            ledger_tx_id: ledger::LedgerTxId::from(tx_id),
+        }))
+    }
 }
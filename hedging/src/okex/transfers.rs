@@ -1,5 +1,7 @@
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
-use sqlx::{Executor, PgPool};
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use tracing::instrument;
 use uuid::Uuid;
 
 use okex_client::{ClientTransferId, TransferState, WithdrawalStatus};
@@ -19,6 +21,19 @@ pub struct TransferReservationSharedData {
     pub funding_btc_total_balance: Decimal,
 }
 
+/// Memo of the galoy payment funding a deposit. It is tagged with the client transfer id
+/// so that the fee galoy charged can be looked up in the wallet transactions afterwards.
+pub fn deposit_memo(client_id: &ClientTransferId, amount_in_sats: Decimal) -> String {
+    format!(
+        "deposit of {amount_in_sats} sats to OKX [{}]",
+        String::from(client_id.clone())
+    )
+}
+
+pub fn is_deposit_memo(memo: &str, client_id: &ClientTransferId) -> bool {
+    memo.ends_with(&format!("[{}]", String::from(client_id.clone())))
+}
+
 pub struct TransferReservation<'a> {
     pub action_size: Option<Decimal>,
     pub fee: Decimal,
@@ -27,6 +42,31 @@ pub struct TransferReservation<'a> {
     pub shared: &'a TransferReservationSharedData,
 }
 
+pub struct PendingTransfer {
+    pub client_transfer_id: String,
+    pub correlation_id: Uuid,
+    pub action: String,
+    pub amount: Decimal,
+    pub fee: Decimal,
+    pub transfer_from: Option<String>,
+    pub transfer_to: Option<String>,
+    pub lost: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Transfer whose latest state has not been posted to the ledger yet
+pub struct UnaccountedTransfer {
+    pub client_transfer_id: ClientTransferId,
+    pub transfer_id: Option<String>,
+    pub direction: ledger::OkexTransferDirection,
+    pub amount: Decimal,
+    pub fee: Decimal,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub ledger_tx_id: ledger::LedgerTxId,
+    pub initiated_ledger_tx_id: Option<ledger::LedgerTxId>,
+}
+
 #[derive(Clone)]
 pub struct OkexTransfers {
     pool: PgPool,
@@ -70,8 +110,9 @@ impl OkexTransfers {
                 trading_btc_total_balance,
                 current_usd_btc_price,
                 funding_btc_total_balance,
-                state
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)"#,
+                state,
+                send_fee_pending
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $3 = 'deposit')"#,
             String::from(id.clone()),
             Uuid::from(reservation.shared.correlation_id),
             reservation.shared.action_type,
@@ -129,7 +170,9 @@ impl OkexTransfers {
         transfer_id: String,
     ) -> Result<(), HedgingError> {
         sqlx::query!(
-            r#"UPDATE okex_transfers SET lost = false, transfer_id = $1, state = $2 WHERE client_transfer_id = $3"#,
+            r#"UPDATE okex_transfers SET lost = false, transfer_id = $1, state = $2,
+                 completed_at = CASE WHEN $2 = 'success' THEN COALESCE(completed_at, NOW()) ELSE completed_at END
+               WHERE client_transfer_id = $3"#,
             transfer_id,
             state,
             String::from(client_id),
@@ -139,6 +182,60 @@ impl OkexTransfers {
         Ok(())
     }
 
+    /// Deposits paid from the galoy wallet whose on-chain fee has not been recorded yet
+    pub async fn get_deposits_without_send_fee(
+        &self,
+    ) -> Result<Vec<(ClientTransferId, DateTime<Utc>)>, HedgingError> {
+        let res = sqlx::query!(
+            r#"SELECT client_transfer_id, created_at FROM okex_transfers
+               WHERE action = 'deposit' AND send_fee_pending AND state IN ('pending', 'success')"#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(res
+            .into_iter()
+            .map(|r| (ClientTransferId::from(r.client_transfer_id), r.created_at))
+            .collect())
+    }
+
+    pub async fn record_send_fee(
+        &self,
+        client_id: ClientTransferId,
+        fee: Decimal,
+    ) -> Result<(), HedgingError> {
+        sqlx::query!(
+            r#"UPDATE okex_transfers SET fee = $1, send_fee_pending = false WHERE client_transfer_id = $2"#,
+            fee,
+            String::from(client_id),
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_pending_transfers(&self) -> Result<Vec<PendingTransfer>, HedgingError> {
+        let res = sqlx::query!(
+            r#"SELECT client_transfer_id, correlation_id, action, amount, fee, transfer_from, transfer_to, lost, created_at
+               FROM okex_transfers WHERE state = 'pending' ORDER BY created_at"#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(res
+            .into_iter()
+            .map(|r| PendingTransfer {
+                client_transfer_id: r.client_transfer_id,
+                correlation_id: r.correlation_id,
+                action: r.action,
+                amount: r.amount,
+                fee: r.fee,
+                transfer_from: r.transfer_from,
+                transfer_to: r.transfer_to,
+                lost: r.lost,
+                created_at: r.created_at,
+            })
+            .collect())
+    }
+
     pub async fn get_pending_transfers(&self) -> Result<Vec<ClientTransferId>, HedgingError> {
         let res =
             sqlx::query!(r#"SELECT client_transfer_id FROM okex_transfers WHERE action IN ('transfer-trading-to-funding', 'transfer-funding-to-trading') AND state = 'pending'"#)
@@ -152,7 +249,9 @@ impl OkexTransfers {
 
     pub async fn update_transfer(&self, details: TransferState) -> Result<(), HedgingError> {
         sqlx::query!(
-            r#"UPDATE okex_transfers SET lost = false, transfer_id = $1, state = $2 WHERE client_transfer_id = $3"#,
+            r#"UPDATE okex_transfers SET lost = false, transfer_id = $1, state = $2,
+                 completed_at = CASE WHEN $2 = 'success' THEN COALESCE(completed_at, NOW()) ELSE completed_at END
+               WHERE client_transfer_id = $3"#,
             details.transfer_id,
             details.state,
             String::from(details.client_id),
@@ -175,7 +274,9 @@ impl OkexTransfers {
 
     pub async fn update_withdrawal(&self, details: WithdrawalStatus) -> Result<(), HedgingError> {
         sqlx::query!(
-            r#"UPDATE okex_transfers SET lost = false, transfer_id = $1, state = $2 WHERE client_transfer_id = $3"#,
+            r#"UPDATE okex_transfers SET lost = false, transfer_id = $1, state = $2,
+                 completed_at = CASE WHEN $2 = 'success' THEN COALESCE(completed_at, NOW()) ELSE completed_at END
+               WHERE client_transfer_id = $3"#,
             details.transaction_id,
             details.state,
             String::from(details.client_id),
@@ -203,4 +304,111 @@ impl OkexTransfers {
         .await?;
         Ok(())
     }
+
+    #[instrument(name = "okex_transfers.find_unaccounted_initiation", skip_all)]
+    pub async fn find_unaccounted_initiation(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<Option<UnaccountedTransfer>, HedgingError> {
+        let tx_id = Uuid::new_v4();
+        let transfer = sqlx::query!(
+            r#"UPDATE okex_transfers
+               SET initiated_ledger_tx_id = $1
+               WHERE client_transfer_id = (
+                 SELECT client_transfer_id FROM okex_transfers
+                 WHERE state IN ('pending', 'success') AND lost = false AND send_fee_pending = false
+                   AND skip_ledger = false AND initiated_ledger_tx_id IS NULL
+                 ORDER BY created_at LIMIT 1
+                 FOR UPDATE SKIP LOCKED
+               ) RETURNING client_transfer_id, transfer_id, action, amount, fee, created_at"#,
+            tx_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        Ok(transfer.map(|t| UnaccountedTransfer {
+            client_transfer_id: ClientTransferId::from(t.client_transfer_id),
+            transfer_id: t.transfer_id,
+            direction: transfer_direction(&t.action),
+            amount: t.amount,
+            fee: t.fee,
+            created_at: t.created_at,
+            completed_at: None,
+            ledger_tx_id: ledger::LedgerTxId::from(tx_id),
+            initiated_ledger_tx_id: None,
+        }))
+    }
+
+    #[instrument(name = "okex_transfers.find_unaccounted_settlement", skip_all)]
+    pub async fn find_unaccounted_settlement(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<Option<UnaccountedTransfer>, HedgingError> {
+        let tx_id = Uuid::new_v4();
+        let transfer = sqlx::query!(
+            r#"UPDATE okex_transfers
+               SET settled_ledger_tx_id = $1
+               WHERE client_transfer_id = (
+                 SELECT client_transfer_id FROM okex_transfers
+                 WHERE state = 'success' AND initiated_ledger_tx_id IS NOT NULL AND settled_ledger_tx_id IS NULL
+                 ORDER BY created_at LIMIT 1
+                 FOR UPDATE SKIP LOCKED
+               ) RETURNING client_transfer_id, transfer_id, action, amount, fee, created_at, completed_at, initiated_ledger_tx_id"#,
+            tx_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        Ok(transfer.map(|t| UnaccountedTransfer {
+            client_transfer_id: ClientTransferId::from(t.client_transfer_id),
+            transfer_id: t.transfer_id,
+            direction: transfer_direction(&t.action),
+            amount: t.amount,
+            fee: t.fee,
+            created_at: t.created_at,
+            completed_at: t.completed_at,
+            ledger_tx_id: ledger::LedgerTxId::from(tx_id),
+            initiated_ledger_tx_id: t.initiated_ledger_tx_id.map(ledger::LedgerTxId::from),
+        }))
+    }
+
+    #[instrument(name = "okex_transfers.find_unaccounted_revert", skip_all)]
+    pub async fn find_unaccounted_revert(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<Option<UnaccountedTransfer>, HedgingError> {
+        let tx_id = Uuid::new_v4();
+        let transfer = sqlx::query!(
+            r#"UPDATE okex_transfers
+               SET revert_ledger_tx_id = $1
+               WHERE client_transfer_id = (
+                 SELECT client_transfer_id FROM okex_transfers
+                 WHERE state IN ('failed', 'deleted') AND initiated_ledger_tx_id IS NOT NULL AND revert_ledger_tx_id IS NULL
+                 ORDER BY created_at LIMIT 1
+                 FOR UPDATE SKIP LOCKED
+               ) RETURNING client_transfer_id, transfer_id, action, amount, fee, created_at, initiated_ledger_tx_id"#,
+            tx_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        Ok(transfer.map(|t| UnaccountedTransfer {
+            client_transfer_id: ClientTransferId::from(t.client_transfer_id),
+            transfer_id: t.transfer_id,
+            direction: transfer_direction(&t.action),
+            amount: t.amount,
+            fee: t.fee,
+            created_at: t.created_at,
+            completed_at: None,
+            ledger_tx_id: ledger::LedgerTxId::from(tx_id),
+            initiated_ledger_tx_id: t.initiated_ledger_tx_id.map(ledger::LedgerTxId::from),
+        }))
+    }
+}
+
+fn transfer_direction(action: &str) -> ledger::OkexTransferDirection {
+    match action {
+        "deposit" => ledger::OkexTransferDirection::GaloyToOkexFunding,
+        "withdraw" => ledger::OkexTransferDirection::OkexFundingToGaloy,
+        "transfer-trading-to-funding" => ledger::OkexTransferDirection::OkexTradingToFunding,
+        "transfer-funding-to-trading" => ledger::OkexTransferDirection::OkexFundingToTrading,
+        _ => unreachable!("okex_transfers.action is constrained by the schema"),
+    }
 }
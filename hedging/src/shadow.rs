@@ -0,0 +1,50 @@
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+use shared::pubsub::CorrelationId;
+
+use crate::error::HedgingError;
+
+/// An action the engine would have taken if it was running live.
+#[derive(Debug)]
+pub struct NewShadowAction {
+    pub correlation_id: CorrelationId,
+    pub exchange: &'static str,
+    pub job_name: &'static str,
+    pub action_type: &'static str,
+    pub action_size: Option<Decimal>,
+    pub action_unit: &'static str,
+    pub inputs: serde_json::Value,
+}
+
+#[derive(Clone)]
+pub struct ShadowActions {
+    pool: PgPool,
+}
+
+impl ShadowActions {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    #[instrument(name = "shadow_actions.record", skip(self))]
+    pub async fn record(&self, action: NewShadowAction) -> Result<(), HedgingError> {
+        sqlx::query!(
+            r#"INSERT INTO shadow_actions (id, correlation_id, exchange, job_name, action_type, action_size, action_unit, inputs)
+               VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"#,
+            Uuid::new_v4(),
+            Uuid::from(action.correlation_id),
+            action.exchange,
+            action.job_name,
+            action.action_type,
+            action.action_size,
+            action.action_unit,
+            action.inputs,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+use chrono::{DateTime, Utc};
+use tracing::instrument;
+
+use crate::{config::UserTradesSyncConfig, error::*};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserTradesSyncStatus {
+    Synced,
+    /// The job should be retried after `retry_delay`
+    Pending,
+    /// The watermark did not catch up within the timeout, the job is dropped
+    TimedOut,
+}
+
+/// Checks the watermark published by user-trades, so that hedging only
+/// acts on ledger balances that include every user trade up to a given point.
+/// Jobs that are not synced yet reschedule themselves after `retry_delay`
+/// instead of blocking their channel.
+#[derive(Clone)]
+pub struct UserTradesSync {
+    pool: sqlx::PgPool,
+    config: UserTradesSyncConfig,
+}
+
+impl UserTradesSync {
+    pub fn new(pool: sqlx::PgPool, config: UserTradesSyncConfig) -> Self {
+        Self { pool, config }
+    }
+
+    pub fn retry_delay(&self) -> std::time::Duration {
+        self.config.retry_delay
+    }
+
+    /// Checks whether the ledger has caught up with `triggered_at`, the time the job
+    /// was first spawned. Once the job has waited longer than the configured timeout
+    /// an error is flagged on the span and the job is expected to give up.
+    #[instrument(
+        name = "hedging.user_trades_sync.sync_status",
+        skip(self),
+        fields(synced_at, error, error.level, error.message),
+        err
+    )]
+    pub async fn sync_status(
+        &self,
+        triggered_at: DateTime<Utc>,
+    ) -> Result<UserTradesSyncStatus, HedgingError> {
+        let synced_at = self.synced_at().await?;
+        if let Some(synced_at) = synced_at {
+            tracing::Span::current().record("synced_at", &tracing::field::display(synced_at));
+        }
+        let status = status(&self.config, synced_at, triggered_at, Utc::now());
+        if status != UserTradesSyncStatus::TimedOut {
+            return Ok(status);
+        }
+        let synced = synced_at
+            .map(|synced_at| format!("only synced up to {synced_at}"))
+            .unwrap_or_else(|| "never synced".to_string());
+        shared::tracing::insert_error_fields(
+            tracing::Level::ERROR,
+            format!(
+                "User trades are {synced} after waiting {}s for {triggered_at}, giving up",
+                self.config.timeout.as_secs()
+            ),
+        );
+        Ok(UserTradesSyncStatus::TimedOut)
+    }
+
+    async fn synced_at(&self) -> Result<Option<DateTime<Utc>>, HedgingError> {
+        let row = sqlx::query!("SELECT synced_at FROM user_trades_watermark")
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|row| row.synced_at))
+    }
+}
+
+fn status(
+    config: &UserTradesSyncConfig,
+    synced_at: Option<DateTime<Utc>>,
+    triggered_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> UserTradesSyncStatus {
+    if matches!(synced_at, Some(synced_at) if synced_at >= triggered_at - config.max_lag) {
+        return UserTradesSyncStatus::Synced;
+    }
+    let timeout =
+        chrono::Duration::from_std(config.timeout).unwrap_or_else(|_| chrono::Duration::zero());
+    if now - triggered_at <= timeout {
+        UserTradesSyncStatus::Pending
+    } else {
+        UserTradesSyncStatus::TimedOut
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_are_measured_from_the_trigger() {
+        let config = UserTradesSyncConfig::default();
+        let triggered_at = Utc::now();
+        let synced_at = Some(triggered_at - chrono::Duration::seconds(20));
+        assert_eq!(
+            status(&config, synced_at, triggered_at, triggered_at),
+            UserTradesSyncStatus::Pending
+        );
+        assert_eq!(
+            status(
+                &config,
+                synced_at,
+                triggered_at,
+                triggered_at + chrono::Duration::seconds(30)
+            ),
+            UserTradesSyncStatus::Pending
+        );
+        assert_eq!(
+            status(
+                &config,
+                synced_at,
+                triggered_at,
+                triggered_at + chrono::Duration::seconds(31)
+            ),
+            UserTradesSyncStatus::TimedOut
+        );
+        assert_eq!(
+            status(
+                &config,
+                None,
+                triggered_at,
+                triggered_at + chrono::Duration::seconds(31)
+            ),
+            UserTradesSyncStatus::TimedOut
+        );
+    }
+
+    #[test]
+    fn synced_within_max_lag() {
+        let config = UserTradesSyncConfig::default();
+        let triggered_at = Utc::now();
+        assert_eq!(
+            status(
+                &config,
+                Some(triggered_at - chrono::Duration::seconds(10)),
+                triggered_at,
+                triggered_at + chrono::Duration::seconds(60)
+            ),
+            UserTradesSyncStatus::Synced
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use galoy_client::GaloyClientConfig;
+use serial_test::serial;
+use tonic::{Code, Request};
+
+use shared::pubsub::{memory, PubSubConfig};
+
+use hedging::{
+    admin_proto::{
+        hedging_admin_service_client::HedgingAdminServiceClient,
+        hedging_admin_service_server::HedgingAdminService, *,
+    },
+    *,
+};
+
+const TOKEN: &str = "admin-token";
+
+async fn init_pool() -> anyhow::Result<sqlx::PgPool> {
+    let pg_host = std::env::var("PG_HOST").unwrap_or("localhost".to_string());
+    let pg_con = format!("postgres://user:password@{pg_host}:5432/pg");
+    Ok(sqlx::PgPool::connect(&pg_con).await?)
+}
+
+async fn admin(pool: &sqlx::PgPool, auth_token: &str) -> anyhow::Result<HedgingAdmin> {
+    Ok(HedgingAdmin::new(
+        pool.clone(),
+        ledger::Ledger::init(pool).await?,
+        HedgingControl::new(pool.clone()),
+        None,
+        auth_token.to_string(),
+    ))
+}
+
+fn galoy_client_config() -> GaloyClientConfig {
+    GaloyClientConfig {
+        api: std::env::var("GALOY_GRAPHQL_URI").expect("GALOY_GRAPHQL_URI not set"),
+        phone_number: std::env::var("PHONE_NUMBER").expect("PHONE_NUMBER not set"),
+        auth_code: std::env::var("AUTH_CODE").expect("AUTH_CODE not set"),
+    }
+}
+
+fn authorized<T>(message: T, token: &str) -> Request<T> {
+    let mut request = Request::new(message);
+    request.metadata_mut().insert(
+        "authorization",
+        format!("Bearer {token}").parse().expect("valid metadata"),
+    );
+    request
+}
+
+#[tokio::test]
+#[serial]
+async fn control_pause_and_resume() -> anyhow::Result<()> {
+    let pool = init_pool().await?;
+    let control = HedgingControl::new(pool.clone());
+
+    control.pause(Some("maintenance".to_string())).await?;
+    assert!(control.is_paused().await?);
+    assert!(HedgingControl::new(pool).is_paused().await?);
+
+    control.resume().await?;
+    assert!(!control.is_paused().await?);
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn admin_mutations_require_token() -> anyhow::Result<()> {
+    let pool = init_pool().await?;
+    let control = HedgingControl::new(pool.clone());
+    control.resume().await?;
+    let admin = admin(&pool, TOKEN).await?;
+
+    let err = admin
+        .pause_hedging(Request::new(PauseHedgingRequest {
+            reason: "no token".to_string(),
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::Unauthenticated);
+    let err = admin
+        .pause_hedging(authorized(
+            PauseHedgingRequest {
+                reason: "wrong token".to_string(),
+            },
+            "not-the-token",
+        ))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::Unauthenticated);
+    let err = admin
+        .trigger_adjust_hedge(Request::new(TriggerAdjustHedgeRequest {}))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::Unauthenticated);
+    assert!(!control.is_paused().await?);
+
+    let err = admin(&pool, "")
+        .await?
+        .resume_hedging(authorized(ResumeHedgingRequest {}, ""))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::PermissionDenied);
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn admin_pause_and_resume() -> anyhow::Result<()> {
+    let pool = init_pool().await?;
+    let control = HedgingControl::new(pool.clone());
+    let admin = admin(&pool, TOKEN).await?;
+
+    admin
+        .pause_hedging(authorized(
+            PauseHedgingRequest {
+                reason: "maintenance".to_string(),
+            },
+            TOKEN,
+        ))
+        .await?;
+    assert!(control.is_paused().await?);
+    let state = admin
+        .get_hedging_state(Request::new(GetHedgingStateRequest {}))
+        .await?
+        .into_inner();
+    assert!(state.paused);
+    assert!(state.okex.is_none());
+
+    admin
+        .resume_hedging(authorized(ResumeHedgingRequest {}, TOKEN))
+        .await?;
+    assert!(!control.is_paused().await?);
+    let state = admin
+        .get_hedging_state(Request::new(GetHedgingStateRequest {}))
+        .await?
+        .into_inner();
+    assert!(!state.paused);
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn admin_okex_calls_need_okex_engine() -> anyhow::Result<()> {
+    let pool = init_pool().await?;
+    let admin = admin(&pool, TOKEN).await?;
+
+    let err = admin
+        .list_pending_orders(Request::new(ListPendingOrdersRequest {}))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::FailedPrecondition);
+    let err = admin
+        .list_pending_transfers(Request::new(ListPendingTransfersRequest {}))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::FailedPrecondition);
+    let err = admin
+        .trigger_adjust_hedge(authorized(TriggerAdjustHedgeRequest {}, TOKEN))
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::FailedPrecondition);
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn run_serves_admin() -> anyhow::Result<()> {
+    let pool = init_pool().await?;
+    HedgingControl::new(pool.clone()).resume().await?;
+    let (_, tick_recv) = memory::channel(chrono::Duration::seconds(1));
+    let redis_host = std::env::var("REDIS_HOST").unwrap_or("localhost".to_string());
+    let pubsub_config = PubSubConfig {
+        host: Some(redis_host),
+        ..PubSubConfig::default()
+    };
+    let listen_port = 3336;
+    // Keeps the health checker, which runs next to the admin server, alive
+    let (_health_check_send, health_check_recv) = futures::channel::mpsc::unbounded();
+    tokio::spawn(hedging::run(
+        pool,
+        health_check_recv,
+        HedgingAppConfig {
+            admin: HedgingAdminConfig {
+                enabled: true,
+                listen_port,
+                auth_token: TOKEN.to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        ExchangesConfig {
+            okex: None,
+            bitfinex: None,
+        },
+        galoy_client_config(),
+        pubsub_config,
+        tick_recv,
+    ));
+
+    let mut client = None;
+    for _ in 0..50 {
+        match HedgingAdminServiceClient::connect(format!("http://127.0.0.1:{listen_port}")).await {
+            Ok(connected) => {
+                client = Some(connected);
+                break;
+            }
+            Err(_) => tokio::time::sleep(std::time::Duration::from_millis(200)).await,
+        }
+    }
+    let state = client
+        .expect("admin server never started")
+        .get_hedging_state(GetHedgingStateRequest {})
+        .await?
+        .into_inner();
+    assert!(!state.paused);
+    assert!(state.okex.is_none());
+    Ok(())
+}
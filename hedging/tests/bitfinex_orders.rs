@@ -0,0 +1,101 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serial_test::serial;
+
+use shared::pubsub::CorrelationId;
+
+use hedging::*;
+
+async fn init_pool() -> anyhow::Result<sqlx::PgPool> {
+    let pg_host = std::env::var("PG_HOST").unwrap_or("localhost".to_string());
+    let pg_con = format!("postgres://user:password@{pg_host}:5432/pg");
+    Ok(sqlx::PgPool::connect(&pg_con).await?)
+}
+
+fn reservation(
+    correlation_id: CorrelationId,
+    action: &BitfinexHedgeAdjustment,
+) -> BitfinexOrderReservation<'_> {
+    BitfinexOrderReservation {
+        correlation_id,
+        instrument: "tBTCF0:USTF0",
+        action,
+        target_usd_value: dec!(-4000),
+        total_target_usd_value: dec!(-10000),
+        liability_share: dec!(0.4),
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn reserve_order_slot() -> anyhow::Result<()> {
+    let pool = init_pool().await?;
+    let orders = BitfinexOrders::new(pool.clone());
+    let correlation_id = CorrelationId::new();
+
+    let reserved = orders
+        .reserve_order_slot(reservation(
+            correlation_id,
+            &BitfinexHedgeAdjustment::Sell(dec!(10000)),
+        ))
+        .await?
+        .expect("slot not reserved");
+    assert!(!reserved.retry);
+    let (target, total_target, share): (Decimal, Decimal, Decimal) = sqlx::query_as(
+        "SELECT target_usd_value, total_target_usd_value, liability_share FROM bitfinex_orders WHERE client_id = $1",
+    )
+    .bind(i64::from(reserved.client_id.clone()))
+    .fetch_one(&pool)
+    .await?;
+    assert_eq!(
+        (target, total_target, share),
+        (dec!(-4000), dec!(-10000), dec!(0.4))
+    );
+
+    // A retry that failed to submit gets the same slot back
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    let retried = orders
+        .reserve_order_slot(reservation(
+            correlation_id,
+            &BitfinexHedgeAdjustment::Sell(dec!(12000)),
+        ))
+        .await?
+        .expect("slot not handed out again");
+    assert!(retried.retry);
+    assert_eq!(
+        i64::from(retried.client_id.clone()),
+        i64::from(reserved.client_id.clone())
+    );
+
+    orders.mark_as_submitted(retried.client_id).await?;
+    assert!(orders
+        .reserve_order_slot(reservation(
+            correlation_id,
+            &BitfinexHedgeAdjustment::Sell(dec!(12000)),
+        ))
+        .await?
+        .is_none());
+    assert!(orders
+        .open_orders()
+        .await?
+        .into_iter()
+        .any(|id| i64::from(id) == i64::from(reserved.client_id.clone())));
+
+    orders
+        .update_order(BitfinexOrderStatus {
+            client_id: reserved.client_id.clone(),
+            order_id: 1,
+            state: Some("EXECUTED @ 30000.0(-0.004)".to_string()),
+            btc_amount: dec!(-0.004),
+            btc_filled: dec!(-0.004),
+            avg_price: dec!(30000),
+            complete: true,
+        })
+        .await?;
+    assert!(!orders
+        .open_orders()
+        .await?
+        .into_iter()
+        .any(|id| i64::from(id) == i64::from(reserved.client_id.clone())));
+    Ok(())
+}
@@ -8,7 +8,7 @@ use serial_test::serial;
 
 use std::{env, fs};
 
-use okex_client::*;
+use okex_client::{mock_server::*, *};
 use shared::{payload::*, pubsub::*};
 
 use hedging::*;
@@ -23,17 +23,9 @@ fn load_fixture(path: &str) -> anyhow::Result<Fixture> {
     Ok(serde_json::from_str(&contents)?)
 }
 
-fn okex_config() -> OkexConfig {
-    let api_key = env::var("OKEX_API_KEY").expect("OKEX_API_KEY not set");
-    let passphrase = env::var("OKEX_PASSPHRASE").expect("OKEX_PASS_PHRASE not set");
-    let secret_key = env::var("OKEX_SECRET_KEY").expect("OKEX_SECRET_KEY not set");
+fn okex_config(okex: &MockOkexServer) -> OkexConfig {
     OkexConfig {
-        client: OkexClientConfig {
-            api_key,
-            passphrase,
-            secret_key,
-            simulated: true,
-        },
+        client: okex.client_config(),
         ..Default::default()
     }
 }
@@ -99,8 +91,9 @@ async fn expect_exposure_equal(
 
 #[tokio::test]
 #[serial]
-#[ignore = "okex is very unstable"]
 async fn hedging() -> anyhow::Result<()> {
+    let okex_server = MockOkexServer::start(MockOkexState::default()).await;
+    let okex_cfg = okex_config(&okex_server);
     let (_, tick_recv) = memory::channel(chrono::Duration::from_std(
         std::time::Duration::from_secs(1),
     )?);
@@ -112,6 +105,13 @@ async fn hedging() -> anyhow::Result<()> {
     let pg_host = std::env::var("PG_HOST").unwrap_or("localhost".to_string());
     let pg_con = format!("postgres://user:password@{pg_host}:5432/pg",);
     let pool = sqlx::PgPool::connect(&pg_con).await?;
+    // user-trades is not running here, so mark it as synced for the duration of the test
+    sqlx::query(
+        "INSERT INTO user_trades_watermark (synced_at) VALUES (NOW() + INTERVAL '1 hour')
+         ON CONFLICT (id) DO UPDATE SET synced_at = EXCLUDED.synced_at",
+    )
+    .execute(&pool)
+    .await?;
 
     let publisher = Publisher::new(pubsub_config.clone()).await?;
     let mut subscriber = Subscriber::new(pubsub_config.clone()).await?;
@@ -127,7 +127,13 @@ async fn hedging() -> anyhow::Result<()> {
             HedgingAppConfig {
                 ..Default::default()
             },
-            okex_config(),
+            ExchangesConfig {
+                okex: Some(ExchangeConfig {
+                    weight: dec!(1),
+                    config: okex_cfg.clone(),
+                }),
+                bitfinex: None,
+            },
             galoy_client_config(),
             pubsub_config.clone(),
             tick_recv.resubscribe(),
@@ -143,7 +149,7 @@ async fn hedging() -> anyhow::Result<()> {
         .into_iter();
     publisher.publish(payloads.next().unwrap()).await?;
 
-    let okex = OkexClient::new(okex_config().client).await?;
+    let okex = OkexClient::new(okex_cfg.client).await?;
     expect_exposure_equal(&mut stream, dec!(0)).await;
 
     publisher.publish(payloads.next().unwrap()).await?;
@@ -156,6 +162,8 @@ async fn hedging() -> anyhow::Result<()> {
                 ClientOrderId::new(),
                 OkexOrderSide::Sell,
                 &BtcUsdSwapContracts::from(5),
+                OkexOrderType::Market,
+                None,
             )
             .await?;
             expect_exposure_below(&mut stream, dec!(-50000)).await;
@@ -0,0 +1,154 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serial_test::serial;
+
+use okex_client::{ClientTransferId, TransferState};
+
+use hedging::*;
+
+async fn init_pool() -> anyhow::Result<sqlx::PgPool> {
+    let pg_host = std::env::var("PG_HOST").unwrap_or("localhost".to_string());
+    let pg_con = format!("postgres://user:password@{pg_host}:5432/pg");
+    Ok(sqlx::PgPool::connect(&pg_con).await?)
+}
+
+async fn insert_transfer(
+    pool: &sqlx::PgPool,
+    action: &str,
+    amount: Decimal,
+    send_fee_pending: bool,
+) -> anyhow::Result<ClientTransferId> {
+    let id = ClientTransferId::new();
+    sqlx::query(
+        "INSERT INTO okex_transfers (client_transfer_id, correlation_id, action, currency, amount, fee,
+           target_usd_exposure, current_usd_exposure, trading_btc_used_balance, trading_btc_total_balance,
+           current_usd_btc_price, funding_btc_total_balance, state, send_fee_pending)
+         VALUES ($1, $2, $3, 'btc', $4, 0, 0, 0, 0, 0, 0, 0, 'pending', $5)",
+    )
+    .bind(String::from(id.clone()))
+    .bind(uuid::Uuid::new_v4())
+    .bind(action)
+    .bind(amount)
+    .bind(send_fee_pending)
+    .execute(pool)
+    .await?;
+    Ok(id)
+}
+
+#[derive(Clone, Copy)]
+enum Unaccounted {
+    Initiation,
+    Settlement,
+    Revert,
+}
+
+// Claims every unaccounted transfer of the kind, including leftovers of earlier runs
+async fn claim_all(
+    pool: &sqlx::PgPool,
+    transfers: &OkexTransfers,
+    kind: Unaccounted,
+) -> anyhow::Result<Vec<UnaccountedTransfer>> {
+    let mut claimed = Vec::new();
+    loop {
+        let mut tx = pool.begin().await?;
+        let transfer = match kind {
+            Unaccounted::Initiation => transfers.find_unaccounted_initiation(&mut tx).await?,
+            Unaccounted::Settlement => transfers.find_unaccounted_settlement(&mut tx).await?,
+            Unaccounted::Revert => transfers.find_unaccounted_revert(&mut tx).await?,
+        };
+        tx.commit().await?;
+        match transfer {
+            Some(transfer) => claimed.push(transfer),
+            None => return Ok(claimed),
+        }
+    }
+}
+
+fn find<'a>(
+    claimed: &'a [UnaccountedTransfer],
+    id: &ClientTransferId,
+) -> Option<&'a UnaccountedTransfer> {
+    claimed.iter().find(|transfer| {
+        String::from(transfer.client_transfer_id.clone()) == String::from(id.clone())
+    })
+}
+
+#[tokio::test]
+#[serial]
+async fn finds_unaccounted_transfers() -> anyhow::Result<()> {
+    let pool = init_pool().await?;
+    let transfers = OkexTransfers::new(pool.clone()).await?;
+    let deposit = insert_transfer(&pool, "deposit", dec!(0.1), true).await?;
+    let transfer = insert_transfer(&pool, "transfer-trading-to-funding", dec!(0.2), false).await?;
+
+    // A deposit is only initiated once the fee galoy charged for it is known
+    let claimed = claim_all(&pool, &transfers, Unaccounted::Initiation).await?;
+    assert!(find(&claimed, &deposit).is_none());
+    let initiated_transfer = find(&claimed, &transfer).expect("transfer not initiated");
+    assert_eq!(initiated_transfer.amount, dec!(0.2));
+    assert!(initiated_transfer.initiated_ledger_tx_id.is_none());
+
+    transfers
+        .record_send_fee(deposit.clone(), dec!(0.00001))
+        .await?;
+    let claimed = claim_all(&pool, &transfers, Unaccounted::Initiation).await?;
+    let initiated_deposit = find(&claimed, &deposit).expect("deposit not initiated");
+    assert_eq!(initiated_deposit.fee, dec!(0.00001));
+    assert!(claim_all(&pool, &transfers, Unaccounted::Initiation)
+        .await?
+        .is_empty());
+
+    // Only completed transfers are settled, at the time they completed
+    assert!(find(
+        &claim_all(&pool, &transfers, Unaccounted::Settlement).await?,
+        &transfer
+    )
+    .is_none());
+    transfers
+        .update_transfer(TransferState {
+            state: "success".to_string(),
+            transfer_id: "transfer_id".to_string(),
+            client_id: String::from(transfer.clone()),
+        })
+        .await?;
+    let claimed = claim_all(&pool, &transfers, Unaccounted::Settlement).await?;
+    let settled = find(&claimed, &transfer).expect("transfer not settled");
+    assert_eq!(
+        settled.initiated_ledger_tx_id,
+        Some(initiated_transfer.ledger_tx_id)
+    );
+    assert!(settled.completed_at.is_some());
+    assert!(find(&claimed, &deposit).is_none());
+
+    // Failed transfers revert their initiation once
+    transfers
+        .update_deposit(deposit.clone(), "failed".to_string(), "tx_id".to_string())
+        .await?;
+    let claimed = claim_all(&pool, &transfers, Unaccounted::Revert).await?;
+    let reverted = find(&claimed, &deposit).expect("deposit not reverted");
+    assert_eq!(
+        reverted.initiated_ledger_tx_id,
+        Some(initiated_deposit.ledger_tx_id)
+    );
+    assert!(find(&claimed, &transfer).is_none());
+    assert!(claim_all(&pool, &transfers, Unaccounted::Revert)
+        .await?
+        .is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn skips_transfers_made_before_the_ledger() -> anyhow::Result<()> {
+    let pool = init_pool().await?;
+    let transfers = OkexTransfers::new(pool.clone()).await?;
+    let transfer = insert_transfer(&pool, "transfer-funding-to-trading", dec!(0.3), false).await?;
+    sqlx::query("UPDATE okex_transfers SET skip_ledger = true WHERE client_transfer_id = $1")
+        .bind(String::from(transfer.clone()))
+        .execute(&pool)
+        .await?;
+
+    let claimed = claim_all(&pool, &transfers, Unaccounted::Initiation).await?;
+    assert!(find(&claimed, &transfer).is_none());
+    Ok(())
+}
@@ -1,11 +1,13 @@
 use rust_decimal::Decimal;
+use sqlx::PgPool;
 use sqlx_ledger::{balance::AccountBalance, AccountId as LedgerAccountId, Currency, SqlxLedger};
 use tracing::instrument;
 
-use crate::{constants::*, LedgerError};
+use crate::{constants::*, history, BalanceAsOf, LedgerError};
 use shared::payload::SyntheticCentLiability;
 
 pub struct Balances<'a> {
+    pub(super) pool: &'a PgPool,
     pub(super) inner: &'a SqlxLedger,
     pub(super) usd: Currency,
     pub(super) btc: Currency,
@@ -33,11 +35,60 @@ impl<'a> Balances<'a> {
         Ok(res)
     }
 
+    pub async fn stablesats_liability_as_of(
+        &self,
+        as_of: BalanceAsOf,
+    ) -> Result<Decimal, LedgerError> {
+        history::settled_balance_as_of(self.pool, STABLESATS_LIABILITY_ID, self.usd, as_of).await
+    }
+
     pub async fn stablesats_btc_wallet(&self) -> Result<Option<AccountBalance>, LedgerError> {
         self.get_ledger_account_balance(STABLESATS_BTC_WALLET_ID, self.btc)
             .await
     }
 
+    pub async fn stablesats_btc_wallet_as_of(
+        &self,
+        as_of: BalanceAsOf,
+    ) -> Result<Decimal, LedgerError> {
+        history::settled_balance_as_of(self.pool, STABLESATS_BTC_WALLET_ID, self.btc, as_of).await
+    }
+
+    pub async fn okex_trading(&self) -> Result<Option<AccountBalance>, LedgerError> {
+        self.get_ledger_account_balance(OKEX_TRADING_ID, self.btc)
+            .await
+    }
+
+    pub async fn okex_funding(&self) -> Result<Option<AccountBalance>, LedgerError> {
+        self.get_ledger_account_balance(OKEX_FUNDING_ID, self.btc)
+            .await
+    }
+
+    pub async fn okex_transfers_in_transit(&self) -> Result<Option<AccountBalance>, LedgerError> {
+        self.get_ledger_account_balance(OKEX_TRANSFERS_IN_TRANSIT_ID, self.btc)
+            .await
+    }
+
+    pub async fn onchain_fees(&self) -> Result<Option<AccountBalance>, LedgerError> {
+        self.get_ledger_account_balance(ONCHAIN_FEES_ID, self.btc)
+            .await
+    }
+
+    pub async fn okex_trading_fees(&self) -> Result<Option<AccountBalance>, LedgerError> {
+        self.get_ledger_account_balance(OKEX_TRADING_FEES_ID, self.btc)
+            .await
+    }
+
+    pub async fn okex_funding_payments(&self) -> Result<Option<AccountBalance>, LedgerError> {
+        self.get_ledger_account_balance(OKEX_FUNDING_PAYMENTS_ID, self.btc)
+            .await
+    }
+
+    pub async fn okex_realized_pnl(&self) -> Result<Option<AccountBalance>, LedgerError> {
+        self.get_ledger_account_balance(OKEX_REALIZED_PNL_ID, self.btc)
+            .await
+    }
+
     #[instrument(name = "ledger.get_ledger_account_balance", skip(self))]
     pub async fn get_ledger_account_balance(
         &self,
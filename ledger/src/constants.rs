@@ -11,6 +11,32 @@ pub(super) const USER_SELLS_USD_CODE: &str = "USER_SELLS_USD";
 pub(super) const USER_SELLS_USD_ID: Uuid = uuid!("00000000-0000-0000-0000-000000000002");
 pub(super) const REVERT_USER_SELLS_USD_CODE: &str = "REVERT_USER_SELLS_USD";
 pub(super) const REVERT_USER_SELLS_USD_ID: Uuid = uuid!("00000000-0000-0000-0000-100000000002");
+pub(super) const OKEX_TRADING_FEE_PAID_CODE: &str = "OKEX_TRADING_FEE_PAID";
+pub(super) const OKEX_TRADING_FEE_PAID_ID: Uuid = uuid!("00000000-0000-0000-0000-000000000003");
+pub(super) const OKEX_TRADING_FEE_REBATE_CODE: &str = "OKEX_TRADING_FEE_REBATE";
+pub(super) const OKEX_TRADING_FEE_REBATE_ID: Uuid = uuid!("00000000-0000-0000-0000-000000000004");
+pub(super) const OKEX_FUNDING_RECEIVED_CODE: &str = "OKEX_FUNDING_RECEIVED";
+pub(super) const OKEX_FUNDING_RECEIVED_ID: Uuid = uuid!("00000000-0000-0000-0000-000000000005");
+pub(super) const OKEX_FUNDING_PAID_CODE: &str = "OKEX_FUNDING_PAID";
+pub(super) const OKEX_FUNDING_PAID_ID: Uuid = uuid!("00000000-0000-0000-0000-000000000006");
+pub(super) const OKEX_REALIZED_PNL_GAIN_CODE: &str = "OKEX_REALIZED_PNL_GAIN";
+pub(super) const OKEX_REALIZED_PNL_GAIN_ID: Uuid = uuid!("00000000-0000-0000-0000-000000000007");
+pub(super) const OKEX_REALIZED_PNL_LOSS_CODE: &str = "OKEX_REALIZED_PNL_LOSS";
+pub(super) const OKEX_REALIZED_PNL_LOSS_ID: Uuid = uuid!("00000000-0000-0000-0000-000000000008");
+pub(super) const OKEX_TRANSFER_INITIATED_CODE: &str = "OKEX_TRANSFER_INITIATED";
+pub(super) const OKEX_TRANSFER_INITIATED_ID: Uuid = uuid!("00000000-0000-0000-0000-000000000009");
+pub(super) const REVERT_OKEX_TRANSFER_INITIATED_CODE: &str = "REVERT_OKEX_TRANSFER_INITIATED";
+pub(super) const REVERT_OKEX_TRANSFER_INITIATED_ID: Uuid =
+    uuid!("00000000-0000-0000-0000-100000000009");
+pub(super) const OKEX_TRANSFER_SETTLED_CODE: &str = "OKEX_TRANSFER_SETTLED";
+pub(super) const OKEX_TRANSFER_SETTLED_ID: Uuid = uuid!("00000000-0000-0000-0000-000000000010");
+pub(super) const OKEX_DEPOSIT_INITIATED_CODE: &str = "OKEX_DEPOSIT_INITIATED";
+pub(super) const OKEX_DEPOSIT_INITIATED_ID: Uuid = uuid!("00000000-0000-0000-0000-000000000011");
+pub(super) const REVERT_OKEX_DEPOSIT_INITIATED_CODE: &str = "REVERT_OKEX_DEPOSIT_INITIATED";
+pub(super) const REVERT_OKEX_DEPOSIT_INITIATED_ID: Uuid =
+    uuid!("00000000-0000-0000-0000-100000000011");
+pub(super) const OKEX_WITHDRAWAL_SETTLED_CODE: &str = "OKEX_WITHDRAWAL_SETTLED";
+pub(super) const OKEX_WITHDRAWAL_SETTLED_ID: Uuid = uuid!("00000000-0000-0000-0000-000000000012");
 
 // Journal
 pub(super) const STABLESATS_JOURNAL_NAME: &str = "Stablesats";
@@ -29,5 +55,26 @@ pub(super) const STABLESATS_OMNIBUS_ID: Uuid = uuid!("20000000-1000-0000-0000-00
 pub(super) const STABLESATS_LIABILITY: &str = "STABLESATS_LIABILITY";
 pub(super) const STABLESATS_LIABILITY_ID: Uuid = uuid!("20000000-2100-0000-0000-000000000000");
 
+pub(super) const OKEX_TRADING: &str = "OKEX_TRADING";
+pub(super) const OKEX_TRADING_ID: Uuid = uuid!("20000000-3000-0000-0000-000000000000");
+
+pub(super) const OKEX_FUNDING: &str = "OKEX_FUNDING";
+pub(super) const OKEX_FUNDING_ID: Uuid = uuid!("20000000-3100-0000-0000-000000000000");
+
+pub(super) const OKEX_TRANSFERS_IN_TRANSIT: &str = "OKEX_TRANSFERS_IN_TRANSIT";
+pub(super) const OKEX_TRANSFERS_IN_TRANSIT_ID: Uuid = uuid!("20000000-4000-0000-0000-000000000000");
+
+pub(super) const OKEX_TRADING_FEES: &str = "OKEX_TRADING_FEES";
+pub(super) const OKEX_TRADING_FEES_ID: Uuid = uuid!("30000000-1000-0000-0000-000000000000");
+
+pub(super) const OKEX_FUNDING_PAYMENTS: &str = "OKEX_FUNDING_PAYMENTS";
+pub(super) const OKEX_FUNDING_PAYMENTS_ID: Uuid = uuid!("30000000-2000-0000-0000-000000000000");
+
+pub(super) const OKEX_REALIZED_PNL: &str = "OKEX_REALIZED_PNL";
+pub(super) const OKEX_REALIZED_PNL_ID: Uuid = uuid!("30000000-3000-0000-0000-000000000000");
+
+pub(super) const ONCHAIN_FEES: &str = "ONCHAIN_FEES";
+pub(super) const ONCHAIN_FEES_ID: Uuid = uuid!("30000000-4000-0000-0000-000000000000");
+
 pub const SATS_PER_BTC: Decimal = dec!(100_000_000);
 pub const CENTS_PER_USD: Decimal = dec!(100);
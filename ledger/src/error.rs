@@ -5,4 +5,8 @@ use thiserror::Error;
 pub enum LedgerError {
     #[error("LedgerError - SqlxLedger: {0}")]
     SqlxLedger(#[from] sqlx_ledger::SqlxLedgerError),
+    #[error("LedgerError - Sqlx: {0}")]
+    Sqlx(#[from] sqlx::Error),
+    #[error("LedgerError - EntryNotFound: {0}")]
+    EntryNotFound(uuid::Uuid),
 }
@@ -0,0 +1,199 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use sqlx_ledger::{AccountId as LedgerAccountId, Currency};
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{constants::*, LedgerError, LedgerTxId};
+
+#[derive(Debug, Clone, Copy)]
+pub enum BalanceAsOf {
+    /// Balance after every entry that was posted up to the timestamp
+    Timestamp(DateTime<Utc>),
+    /// Balance of every transaction effective on or before the date, whenever it was posted
+    Effective(NaiveDate),
+    /// Balance right after the transaction of the entry was posted to the account
+    Entry(Uuid),
+}
+
+#[derive(Debug, Clone)]
+pub struct LedgerTransaction {
+    pub id: LedgerTxId,
+    pub template_code: String,
+    pub correlation_id: Uuid,
+    pub external_id: String,
+    pub effective: NaiveDate,
+    pub meta: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LedgerTransactionsCursor {
+    pub created_at: DateTime<Utc>,
+    pub id: LedgerTxId,
+}
+
+impl From<&LedgerTransaction> for LedgerTransactionsCursor {
+    fn from(tx: &LedgerTransaction) -> Self {
+        Self {
+            created_at: tx.created_at,
+            id: tx.id,
+        }
+    }
+}
+
+// The raw SQL below reads the tables of sqlx-ledger 0.5.5 directly: every posted
+// transaction adds one version per (journal, account, currency) to sqlx_ledger_balances,
+// pointing at the last entry it posted to the account, and entries / transactions
+// are never updated. Re-check these queries when upgrading sqlx-ledger.
+#[instrument(name = "ledger.history.settled_balance_as_of", skip(pool), err)]
+pub(super) async fn settled_balance_as_of(
+    pool: &PgPool,
+    account_id: impl Into<LedgerAccountId> + std::fmt::Debug,
+    currency: Currency,
+    as_of: BalanceAsOf,
+) -> Result<Decimal, LedgerError> {
+    let account_id = Uuid::from(account_id.into());
+    let (timestamp, version) = match as_of {
+        BalanceAsOf::Timestamp(timestamp) => (Some(timestamp), None),
+        BalanceAsOf::Effective(effective) => {
+            return settled_balance_effective(pool, account_id, currency, effective).await
+        }
+        BalanceAsOf::Entry(entry_id) => {
+            let row = sqlx::query!(
+                r#"SELECT b.version FROM sqlx_ledger_balances b
+                    JOIN sqlx_ledger_entries e ON e.id = b.entry_id
+                    WHERE b.journal_id = $1 AND b.account_id = $2 AND b.currency = $3
+                      AND e.transaction_id = (SELECT transaction_id FROM sqlx_ledger_entries WHERE id = $4 LIMIT 1)
+                    ORDER BY b.version DESC LIMIT 1"#,
+                STABLESATS_JOURNAL_ID,
+                account_id,
+                currency.code(),
+                entry_id
+            )
+            .fetch_optional(pool)
+            .await?;
+            (
+                None,
+                Some(row.ok_or(LedgerError::EntryNotFound(entry_id))?.version),
+            )
+        }
+    };
+    let row = sqlx::query!(
+        r#"SELECT b.settled_dr_balance, b.settled_cr_balance, a.normal_balance_type = 'debit' AS "debit_normal!"
+            FROM sqlx_ledger_balances b
+            JOIN LATERAL (
+              SELECT normal_balance_type FROM sqlx_ledger_accounts
+              WHERE id = b.account_id ORDER BY version DESC LIMIT 1
+            ) a ON TRUE
+            WHERE b.journal_id = $1 AND b.account_id = $2 AND b.currency = $3
+              AND ($4::TIMESTAMPTZ IS NULL OR b.created_at <= $4)
+              AND ($5::INT IS NULL OR b.version <= $5)
+            ORDER BY b.version DESC LIMIT 1"#,
+        STABLESATS_JOURNAL_ID,
+        account_id,
+        currency.code(),
+        timestamp,
+        version
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row
+        .map(|row| {
+            settled(
+                row.debit_normal,
+                row.settled_dr_balance,
+                row.settled_cr_balance,
+            )
+        })
+        .unwrap_or(Decimal::ZERO))
+}
+
+async fn settled_balance_effective(
+    pool: &PgPool,
+    account_id: Uuid,
+    currency: Currency,
+    effective: NaiveDate,
+) -> Result<Decimal, LedgerError> {
+    let row = sqlx::query!(
+        r#"SELECT s.settled_dr_balance AS "settled_dr_balance!", s.settled_cr_balance AS "settled_cr_balance!",
+                  a.normal_balance_type = 'debit' AS "debit_normal!"
+            FROM (
+              SELECT COALESCE(SUM(e.units) FILTER (WHERE e.direction = 'debit'), 0) AS settled_dr_balance,
+                     COALESCE(SUM(e.units) FILTER (WHERE e.direction = 'credit'), 0) AS settled_cr_balance
+              FROM sqlx_ledger_entries e
+              JOIN sqlx_ledger_transactions t ON t.id = e.transaction_id
+              WHERE e.journal_id = $1 AND e.account_id = $2 AND e.currency = $3
+                AND e.layer = 'settled' AND t.effective <= $4
+            ) s
+            JOIN LATERAL (
+              SELECT normal_balance_type FROM sqlx_ledger_accounts
+              WHERE id = $2 ORDER BY version DESC LIMIT 1
+            ) a ON TRUE"#,
+        STABLESATS_JOURNAL_ID,
+        account_id,
+        currency.code(),
+        effective
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row
+        .map(|row| {
+            settled(
+                row.debit_normal,
+                row.settled_dr_balance,
+                row.settled_cr_balance,
+            )
+        })
+        .unwrap_or(Decimal::ZERO))
+}
+
+fn settled(
+    debit_normal: bool,
+    settled_dr_balance: Decimal,
+    settled_cr_balance: Decimal,
+) -> Decimal {
+    if debit_normal {
+        settled_dr_balance - settled_cr_balance
+    } else {
+        settled_cr_balance - settled_dr_balance
+    }
+}
+
+#[instrument(name = "ledger.history.list_transactions_by_template", skip(pool), err)]
+pub(super) async fn list_transactions_by_template(
+    pool: &PgPool,
+    template_code: &str,
+    after: Option<LedgerTransactionsCursor>,
+    limit: usize,
+) -> Result<Vec<LedgerTransaction>, LedgerError> {
+    let rows = sqlx::query!(
+        r#"SELECT t.id, t.correlation_id, t.external_id, t.effective, t.metadata, t.created_at
+            FROM sqlx_ledger_transactions t
+            JOIN sqlx_ledger_tx_templates tt ON tt.id = t.tx_template_id
+            WHERE t.journal_id = $1 AND tt.code = $2
+              AND ($3::TIMESTAMPTZ IS NULL OR (t.created_at, t.id) > ($3, $4))
+            ORDER BY t.created_at, t.id
+            LIMIT $5"#,
+        STABLESATS_JOURNAL_ID,
+        template_code,
+        after.map(|c| c.created_at),
+        after.map(|c| Uuid::from(c.id)),
+        limit as i64
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| LedgerTransaction {
+            id: LedgerTxId::from(row.id),
+            template_code: template_code.to_string(),
+            correlation_id: row.correlation_id,
+            external_id: row.external_id,
+            effective: row.effective,
+            meta: row.metadata,
+            created_at: row.created_at,
+        })
+        .collect())
+}
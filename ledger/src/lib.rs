@@ -8,10 +8,12 @@ use tracing::instrument;
 mod balances;
 mod constants;
 mod error;
+mod history;
 mod templates;
 
 use constants::*;
 pub use error::*;
+pub use history::*;
 pub use templates::*;
 
 use sqlx_ledger::{
@@ -29,6 +31,7 @@ const DEFAULT_BUFFER_SIZE: usize = 100;
 
 #[derive(Debug, Clone)]
 pub struct Ledger {
+    pool: PgPool,
     inner: SqlxLedger,
     events: EventSubscriber,
     usd: Currency,
@@ -45,14 +48,19 @@ impl Ledger {
         Self::stablesats_btc_wallet_account(&inner).await?;
         Self::stablesats_omnibus_account(&inner).await?;
         Self::stablesats_liability_account(&inner).await?;
+        Self::okex_trading_accounts(&inner).await?;
+        Self::okex_funding_accounts(&inner).await?;
 
         templates::UserBuysUsd::init(&inner).await?;
         templates::UserSellsUsd::init(&inner).await?;
         templates::RevertUserBuysUsd::init(&inner).await?;
         templates::RevertUserSellsUsd::init(&inner).await?;
+        templates::OkexTrading::init(&inner).await?;
+        templates::OkexTransfer::init(&inner).await?;
 
         Ok(Self {
             events: inner.events(DEFAULT_BUFFER_SIZE).await?,
+            pool: pool.clone(),
             inner,
             usd: "USD".parse().unwrap(),
             btc: "BTC".parse().unwrap(),
@@ -61,6 +69,7 @@ impl Ledger {
 
     pub fn balances(&'_ self) -> balances::Balances<'_> {
         balances::Balances {
+            pool: &self.pool,
             inner: &self.inner,
             usd: self.usd,
             btc: self.btc,
@@ -119,6 +128,111 @@ impl Ledger {
         Ok(())
     }
 
+    #[instrument(name = "ledger.okex_trading_fee", skip(self, tx))]
+    pub async fn okex_trading_fee(
+        &self,
+        tx: Transaction<'_, Postgres>,
+        id: LedgerTxId,
+        params: OkexTradingFeeParams,
+    ) -> Result<(), LedgerError> {
+        let code = if params.btc_amount.is_sign_negative() {
+            OKEX_TRADING_FEE_PAID_CODE
+        } else {
+            OKEX_TRADING_FEE_REBATE_CODE
+        };
+        self.inner
+            .post_transaction_in_tx(tx, id, code, Some(params.tx_params()))
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(name = "ledger.okex_funding_payment", skip(self, tx))]
+    pub async fn okex_funding_payment(
+        &self,
+        tx: Transaction<'_, Postgres>,
+        id: LedgerTxId,
+        params: OkexFundingPaymentParams,
+    ) -> Result<(), LedgerError> {
+        let code = if params.btc_amount.is_sign_negative() {
+            OKEX_FUNDING_PAID_CODE
+        } else {
+            OKEX_FUNDING_RECEIVED_CODE
+        };
+        self.inner
+            .post_transaction_in_tx(tx, id, code, Some(params.tx_params()))
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(name = "ledger.okex_realized_pnl", skip(self, tx))]
+    pub async fn okex_realized_pnl(
+        &self,
+        tx: Transaction<'_, Postgres>,
+        id: LedgerTxId,
+        params: OkexRealizedPnlParams,
+    ) -> Result<(), LedgerError> {
+        let code = if params.btc_amount.is_sign_negative() {
+            OKEX_REALIZED_PNL_LOSS_CODE
+        } else {
+            OKEX_REALIZED_PNL_GAIN_CODE
+        };
+        self.inner
+            .post_transaction_in_tx(tx, id, code, Some(params.tx_params()))
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(name = "ledger.okex_transfer_initiated", skip(self, tx))]
+    pub async fn okex_transfer_initiated(
+        &self,
+        tx: Transaction<'_, Postgres>,
+        id: LedgerTxId,
+        params: OkexTransferParams,
+    ) -> Result<(), LedgerError> {
+        let code = params.meta.direction.initiated_code();
+        self.inner
+            .post_transaction_in_tx(tx, id, code, Some(params))
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(name = "ledger.revert_okex_transfer_initiated", skip(self, tx))]
+    pub async fn revert_okex_transfer_initiated(
+        &self,
+        tx: Transaction<'_, Postgres>,
+        id: LedgerTxId,
+        params: RevertOkexTransferInitiatedParams,
+    ) -> Result<(), LedgerError> {
+        let code = params.transfer.meta.direction.revert_initiated_code();
+        self.inner
+            .post_transaction_in_tx(tx, id, code, Some(params))
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(name = "ledger.okex_transfer_settled", skip(self, tx))]
+    pub async fn okex_transfer_settled(
+        &self,
+        tx: Transaction<'_, Postgres>,
+        id: LedgerTxId,
+        params: OkexTransferParams,
+    ) -> Result<(), LedgerError> {
+        let code = params.meta.direction.settled_code();
+        self.inner
+            .post_transaction_in_tx(tx, id, code, Some(params))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn list_transactions_by_template(
+        &self,
+        template_code: &str,
+        after: Option<LedgerTransactionsCursor>,
+        limit: usize,
+    ) -> Result<Vec<LedgerTransaction>, LedgerError> {
+        history::list_transactions_by_template(&self.pool, template_code, after, limit).await
+    }
+
     pub async fn usd_liability_balance_events(&self) -> broadcast::Receiver<SqlxLedgerEvent> {
         self.events
             .account_balance(STABLESATS_JOURNAL_ID.into(), STABLESATS_LIABILITY_ID.into())
@@ -200,4 +314,88 @@ impl Ledger {
             Err(e) => Err(e.into()),
         }
     }
+
+    #[instrument(name = "ledger.okex_trading_accounts", skip_all)]
+    async fn okex_trading_accounts(ledger: &SqlxLedger) -> Result<(), LedgerError> {
+        let accounts = [
+            (
+                OKEX_TRADING,
+                OKEX_TRADING_ID,
+                DebitOrCredit::Debit,
+                "Account for btc collateral in the OKX trading account",
+            ),
+            (
+                OKEX_TRADING_FEES,
+                OKEX_TRADING_FEES_ID,
+                DebitOrCredit::Credit,
+                "Account for fees paid and rebates received on OKX orders",
+            ),
+            (
+                OKEX_FUNDING_PAYMENTS,
+                OKEX_FUNDING_PAYMENTS_ID,
+                DebitOrCredit::Credit,
+                "Account for funding paid and received on the OKX swap",
+            ),
+            (
+                OKEX_REALIZED_PNL,
+                OKEX_REALIZED_PNL_ID,
+                DebitOrCredit::Credit,
+                "Account for realized profit and loss on the OKX swap",
+            ),
+        ];
+        for (code, id, normal_balance_type, description) in accounts {
+            let new_account = NewAccount::builder()
+                .code(code)
+                .id(id)
+                .name(code)
+                .normal_balance_type(normal_balance_type)
+                .description(description.to_string())
+                .build()
+                .expect("Couldn't create okex trading account");
+            match ledger.accounts().create(new_account).await {
+                Ok(_) | Err(SqlxLedgerError::DuplicateKey(_)) => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    #[instrument(name = "ledger.okex_funding_accounts", skip_all)]
+    async fn okex_funding_accounts(ledger: &SqlxLedger) -> Result<(), LedgerError> {
+        let accounts = [
+            (
+                OKEX_FUNDING,
+                OKEX_FUNDING_ID,
+                DebitOrCredit::Debit,
+                "Account for btc held in the OKX funding account",
+            ),
+            (
+                OKEX_TRANSFERS_IN_TRANSIT,
+                OKEX_TRANSFERS_IN_TRANSIT_ID,
+                DebitOrCredit::Debit,
+                "Account for btc that left its source but has not arrived yet",
+            ),
+            (
+                ONCHAIN_FEES,
+                ONCHAIN_FEES_ID,
+                DebitOrCredit::Debit,
+                "Account for on-chain fees paid moving btc to and from OKX",
+            ),
+        ];
+        for (code, id, normal_balance_type, description) in accounts {
+            let new_account = NewAccount::builder()
+                .code(code)
+                .id(id)
+                .name(code)
+                .normal_balance_type(normal_balance_type)
+                .description(description.to_string())
+                .build()
+                .expect("Couldn't create okex funding account");
+            match ledger.accounts().create(new_account).await {
+                Ok(_) | Err(SqlxLedgerError::DuplicateKey(_)) => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
 }
@@ -1,8 +1,12 @@
+mod okex_trading;
+mod okex_transfers;
 mod revert_user_buys_usd;
 mod revert_user_sells_usd;
 mod user_buys_usd;
 mod user_sells_usd;
 
+pub use okex_trading::*;
+pub use okex_transfers::*;
 pub use revert_user_buys_usd::*;
 pub use revert_user_sells_usd::*;
 pub use user_buys_usd::*;
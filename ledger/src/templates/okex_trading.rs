@@ -0,0 +1,233 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx_ledger::{tx_template::*, SqlxLedger, SqlxLedgerError};
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{constants::*, error::*};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OkexOrderMeta {
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub timestamp: DateTime<Utc>,
+    pub client_order_id: String,
+    pub order_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OkexFundingPaymentMeta {
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub timestamp: DateTime<Utc>,
+    pub bill_id: String,
+}
+
+/// Fee as reported by OKX: negative when charged, positive for maker rebates.
+#[derive(Debug, Clone)]
+pub struct OkexTradingFeeParams {
+    pub btc_amount: Decimal,
+    pub meta: OkexOrderMeta,
+}
+
+/// Positive when stablesats receives funding, negative when it pays.
+#[derive(Debug, Clone)]
+pub struct OkexFundingPaymentParams {
+    pub btc_amount: Decimal,
+    pub meta: OkexFundingPaymentMeta,
+}
+
+/// Realized profit (positive) or loss (negative) of a position reducing order.
+#[derive(Debug, Clone)]
+pub struct OkexRealizedPnlParams {
+    pub btc_amount: Decimal,
+    pub meta: OkexOrderMeta,
+}
+
+pub(crate) trait SignedBtcAmount {
+    fn btc_amount(&self) -> Decimal;
+    fn timestamp(&self) -> DateTime<Utc>;
+    fn meta(&self) -> serde_json::Value;
+
+    fn tx_params(&self) -> TxParams {
+        let mut params = TxParams::default();
+        params.insert("btc_amount", self.btc_amount().abs());
+        params.insert("meta", self.meta());
+        params.insert("effective", self.timestamp().naive_utc().date());
+        params
+    }
+}
+
+impl SignedBtcAmount for OkexTradingFeeParams {
+    fn btc_amount(&self) -> Decimal {
+        self.btc_amount
+    }
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.meta.timestamp
+    }
+    fn meta(&self) -> serde_json::Value {
+        serde_json::to_value(&self.meta).expect("Couldn't serialize meta")
+    }
+}
+
+impl SignedBtcAmount for OkexFundingPaymentParams {
+    fn btc_amount(&self) -> Decimal {
+        self.btc_amount
+    }
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.meta.timestamp
+    }
+    fn meta(&self) -> serde_json::Value {
+        serde_json::to_value(&self.meta).expect("Couldn't serialize meta")
+    }
+}
+
+impl SignedBtcAmount for OkexRealizedPnlParams {
+    fn btc_amount(&self) -> Decimal {
+        self.btc_amount
+    }
+    fn timestamp(&self) -> DateTime<Utc> {
+        self.meta.timestamp
+    }
+    fn meta(&self) -> serde_json::Value {
+        serde_json::to_value(&self.meta).expect("Couldn't serialize meta")
+    }
+}
+
+fn defs() -> Vec<ParamDefinition> {
+    vec![
+        ParamDefinition::builder()
+            .name("btc_amount")
+            .r#type(ParamDataType::DECIMAL)
+            .build()
+            .unwrap(),
+        ParamDefinition::builder()
+            .name("meta")
+            .r#type(ParamDataType::JSON)
+            .build()
+            .unwrap(),
+        ParamDefinition::builder()
+            .name("effective")
+            .r#type(ParamDataType::DATE)
+            .build()
+            .unwrap(),
+    ]
+}
+
+/// Moves btc between the OKX trading account and one of the PnL accounts.
+/// `income` templates credit the PnL account, the others debit it.
+struct OkexPnlTemplate {
+    id: Uuid,
+    code: &'static str,
+    description: &'static str,
+    account_id: Uuid,
+    income: bool,
+}
+
+const OKEX_PNL_TEMPLATES: [OkexPnlTemplate; 6] = [
+    OkexPnlTemplate {
+        id: OKEX_TRADING_FEE_PAID_ID,
+        code: OKEX_TRADING_FEE_PAID_CODE,
+        description: "OKX trading fee paid",
+        account_id: OKEX_TRADING_FEES_ID,
+        income: false,
+    },
+    OkexPnlTemplate {
+        id: OKEX_TRADING_FEE_REBATE_ID,
+        code: OKEX_TRADING_FEE_REBATE_CODE,
+        description: "OKX trading fee rebate",
+        account_id: OKEX_TRADING_FEES_ID,
+        income: true,
+    },
+    OkexPnlTemplate {
+        id: OKEX_FUNDING_RECEIVED_ID,
+        code: OKEX_FUNDING_RECEIVED_CODE,
+        description: "OKX funding received",
+        account_id: OKEX_FUNDING_PAYMENTS_ID,
+        income: true,
+    },
+    OkexPnlTemplate {
+        id: OKEX_FUNDING_PAID_ID,
+        code: OKEX_FUNDING_PAID_CODE,
+        description: "OKX funding paid",
+        account_id: OKEX_FUNDING_PAYMENTS_ID,
+        income: false,
+    },
+    OkexPnlTemplate {
+        id: OKEX_REALIZED_PNL_GAIN_ID,
+        code: OKEX_REALIZED_PNL_GAIN_CODE,
+        description: "OKX realized gain",
+        account_id: OKEX_REALIZED_PNL_ID,
+        income: true,
+    },
+    OkexPnlTemplate {
+        id: OKEX_REALIZED_PNL_LOSS_ID,
+        code: OKEX_REALIZED_PNL_LOSS_CODE,
+        description: "OKX realized loss",
+        account_id: OKEX_REALIZED_PNL_ID,
+        income: false,
+    },
+];
+
+pub struct OkexTrading {}
+
+impl OkexTrading {
+    #[instrument(name = "ledger.okex_trading.init", skip_all)]
+    pub async fn init(ledger: &SqlxLedger) -> Result<(), LedgerError> {
+        for template in OKEX_PNL_TEMPLATES.iter() {
+            Self::init_template(ledger, template).await?;
+        }
+        Ok(())
+    }
+
+    async fn init_template(
+        ledger: &SqlxLedger,
+        template: &OkexPnlTemplate,
+    ) -> Result<(), LedgerError> {
+        let tx_input = TxInput::builder()
+            .journal_id(format!("uuid('{STABLESATS_JOURNAL_ID}')"))
+            .effective("params.effective")
+            .metadata("params.meta")
+            .description(format!("'{}'", template.description))
+            .build()
+            .expect("Couldn't build TxInput");
+        let ((pnl_direction, pnl_suffix), (trading_direction, trading_suffix)) = if template.income
+        {
+            (("CREDIT", "CR"), ("DEBIT", "DR"))
+        } else {
+            (("DEBIT", "DR"), ("CREDIT", "CR"))
+        };
+        let entries = vec![
+            EntryInput::builder()
+                .entry_type(format!("'{}_BTC_{pnl_suffix}'", template.code))
+                .currency("'BTC'")
+                .account_id(format!("uuid('{}')", template.account_id))
+                .direction(pnl_direction)
+                .layer("SETTLED")
+                .units("params.btc_amount")
+                .build()
+                .expect("Couldn't build okex pnl entry"),
+            EntryInput::builder()
+                .entry_type(format!("'{}_BTC_{trading_suffix}'", template.code))
+                .currency("'BTC'")
+                .account_id(format!("uuid('{OKEX_TRADING_ID}')"))
+                .direction(trading_direction)
+                .layer("SETTLED")
+                .units("params.btc_amount")
+                .build()
+                .expect("Couldn't build okex trading entry"),
+        ];
+
+        let new_template = NewTxTemplate::builder()
+            .id(template.id)
+            .code(template.code)
+            .tx_input(tx_input)
+            .entries(entries)
+            .params(defs())
+            .build()
+            .expect("Couldn't build okex pnl template");
+        match ledger.tx_templates().create(new_template).await {
+            Ok(_) | Err(SqlxLedgerError::DuplicateKey(_)) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
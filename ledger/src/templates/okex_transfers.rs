@@ -0,0 +1,392 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx_ledger::{tx_template::*, SqlxLedger, SqlxLedgerError, TransactionId as LedgerTxId};
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{constants::*, error::*};
+
+/// Where the btc of an `okex_transfers` record is moved from and to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OkexTransferDirection {
+    GaloyToOkexFunding,
+    OkexFundingToGaloy,
+    OkexTradingToFunding,
+    OkexFundingToTrading,
+}
+
+impl OkexTransferDirection {
+    fn accounts(&self) -> (Uuid, Uuid) {
+        match self {
+            Self::GaloyToOkexFunding => (STABLESATS_BTC_WALLET_ID, OKEX_FUNDING_ID),
+            Self::OkexFundingToGaloy => (OKEX_FUNDING_ID, STABLESATS_BTC_WALLET_ID),
+            Self::OkexTradingToFunding => (OKEX_TRADING_ID, OKEX_FUNDING_ID),
+            Self::OkexFundingToTrading => (OKEX_FUNDING_ID, OKEX_TRADING_ID),
+        }
+    }
+
+    // The galoy wallet is credit-normal while the OKX accounts are debit-normal,
+    // so btc crossing between them goes through dedicated templates.
+    pub(crate) fn initiated_code(&self) -> &'static str {
+        match self {
+            Self::GaloyToOkexFunding => OKEX_DEPOSIT_INITIATED_CODE,
+            _ => OKEX_TRANSFER_INITIATED_CODE,
+        }
+    }
+
+    pub(crate) fn revert_initiated_code(&self) -> &'static str {
+        match self {
+            Self::GaloyToOkexFunding => REVERT_OKEX_DEPOSIT_INITIATED_CODE,
+            _ => REVERT_OKEX_TRANSFER_INITIATED_CODE,
+        }
+    }
+
+    pub(crate) fn settled_code(&self) -> &'static str {
+        match self {
+            Self::OkexFundingToGaloy => OKEX_WITHDRAWAL_SETTLED_CODE,
+            _ => OKEX_TRANSFER_SETTLED_CODE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OkexTransferMeta {
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub timestamp: DateTime<Utc>,
+    pub direction: OkexTransferDirection,
+    pub client_transfer_id: String,
+    pub transfer_id: String,
+}
+
+/// `btc_fee` is paid on top of `btc_amount` by the source account
+/// (on-chain withdrawal fee charged by OKX).
+#[derive(Debug, Clone)]
+pub struct OkexTransferParams {
+    pub btc_amount: Decimal,
+    pub btc_fee: Decimal,
+    pub meta: OkexTransferMeta,
+}
+
+#[derive(Debug, Clone)]
+pub struct RevertOkexTransferInitiatedParams {
+    pub initiated_ledger_tx_id: LedgerTxId,
+    pub transfer: OkexTransferParams,
+}
+
+impl OkexTransferParams {
+    fn defs() -> Vec<ParamDefinition> {
+        vec![
+            ParamDefinition::builder()
+                .name("btc_amount")
+                .r#type(ParamDataType::DECIMAL)
+                .build()
+                .unwrap(),
+            ParamDefinition::builder()
+                .name("btc_fee")
+                .r#type(ParamDataType::DECIMAL)
+                .build()
+                .unwrap(),
+            ParamDefinition::builder()
+                .name("btc_total")
+                .r#type(ParamDataType::DECIMAL)
+                .build()
+                .unwrap(),
+            ParamDefinition::builder()
+                .name("source_account_id")
+                .r#type(ParamDataType::UUID)
+                .build()
+                .unwrap(),
+            ParamDefinition::builder()
+                .name("destination_account_id")
+                .r#type(ParamDataType::UUID)
+                .build()
+                .unwrap(),
+            ParamDefinition::builder()
+                .name("meta")
+                .r#type(ParamDataType::JSON)
+                .build()
+                .unwrap(),
+            ParamDefinition::builder()
+                .name("effective")
+                .r#type(ParamDataType::DATE)
+                .build()
+                .unwrap(),
+        ]
+    }
+}
+
+impl From<OkexTransferParams> for TxParams {
+    fn from(
+        OkexTransferParams {
+            btc_amount,
+            btc_fee,
+            meta,
+        }: OkexTransferParams,
+    ) -> Self {
+        let (source, destination) = meta.direction.accounts();
+        let effective = meta.timestamp.naive_utc().date();
+        let meta = serde_json::to_value(meta).expect("Couldn't serialize meta");
+        let mut params = Self::default();
+        params.insert("btc_amount", btc_amount);
+        params.insert("btc_fee", btc_fee);
+        params.insert("btc_total", btc_amount + btc_fee);
+        params.insert("source_account_id", source);
+        params.insert("destination_account_id", destination);
+        params.insert("meta", meta);
+        params.insert("effective", effective);
+        params
+    }
+}
+
+impl From<RevertOkexTransferInitiatedParams> for TxParams {
+    fn from(
+        RevertOkexTransferInitiatedParams {
+            initiated_ledger_tx_id,
+            transfer,
+        }: RevertOkexTransferInitiatedParams,
+    ) -> Self {
+        let mut params = Self::from(transfer);
+        params.insert("correlation_id", Uuid::from(initiated_ledger_tx_id));
+        params
+    }
+}
+
+pub struct OkexTransfer {}
+
+impl OkexTransfer {
+    #[instrument(name = "ledger.okex_transfer.init", skip_all)]
+    pub async fn init(ledger: &SqlxLedger) -> Result<(), LedgerError> {
+        Self::init_initiated(ledger, false).await?;
+        Self::init_initiated(ledger, true).await?;
+        Self::init_deposit_initiated(ledger, false).await?;
+        Self::init_deposit_initiated(ledger, true).await?;
+        Self::init_settled(ledger).await?;
+        Self::init_withdrawal_settled(ledger).await?;
+        Ok(())
+    }
+
+    /// Moves the amount out of an OKX source account into transit and books the fee.
+    /// The revert template posts the same entries with negated units.
+    async fn init_initiated(ledger: &SqlxLedger, revert: bool) -> Result<(), LedgerError> {
+        let (id, code, description, sign) = if revert {
+            (
+                REVERT_OKEX_TRANSFER_INITIATED_ID,
+                REVERT_OKEX_TRANSFER_INITIATED_CODE,
+                "'REVERT: OKX transfer initiated'",
+                " * -1",
+            )
+        } else {
+            (
+                OKEX_TRANSFER_INITIATED_ID,
+                OKEX_TRANSFER_INITIATED_CODE,
+                "'OKX transfer initiated'",
+                "",
+            )
+        };
+        let entries = vec![
+            entry(
+                format!("'{code}_BTC_CR'"),
+                "params.source_account_id".to_string(),
+                "CREDIT",
+                format!("params.btc_total{sign}"),
+            ),
+            entry(
+                format!("'{code}_BTC_DR'"),
+                format!("uuid('{OKEX_TRANSFERS_IN_TRANSIT_ID}')"),
+                "DEBIT",
+                format!("params.btc_amount{sign}"),
+            ),
+            entry(
+                format!("'{code}_FEE_BTC_DR'"),
+                format!("uuid('{ONCHAIN_FEES_ID}')"),
+                "DEBIT",
+                format!("params.btc_fee{sign}"),
+            ),
+        ];
+        create_template(ledger, id, code, description, revert, entries).await
+    }
+
+    /// Moves the amount plus the send fee out of the galoy wallet into transit.
+    /// The wallet leg is balanced against EXTERNAL_OMNIBUS as in the user trade
+    /// templates, the OKX leg against STABLESATS_OMNIBUS.
+    async fn init_deposit_initiated(ledger: &SqlxLedger, revert: bool) -> Result<(), LedgerError> {
+        let (id, code, description, sign) = if revert {
+            (
+                REVERT_OKEX_DEPOSIT_INITIATED_ID,
+                REVERT_OKEX_DEPOSIT_INITIATED_CODE,
+                "'REVERT: OKX deposit initiated'",
+                " * -1",
+            )
+        } else {
+            (
+                OKEX_DEPOSIT_INITIATED_ID,
+                OKEX_DEPOSIT_INITIATED_CODE,
+                "'OKX deposit initiated'",
+                "",
+            )
+        };
+        let entries = vec![
+            entry(
+                format!("'{code}_WALLET_BTC_DR'"),
+                format!("uuid('{STABLESATS_BTC_WALLET_ID}')"),
+                "DEBIT",
+                format!("params.btc_total{sign}"),
+            ),
+            entry(
+                format!("'{code}_WALLET_BTC_CR'"),
+                format!("uuid('{EXTERNAL_OMNIBUS_ID}')"),
+                "CREDIT",
+                format!("params.btc_total{sign}"),
+            ),
+            entry(
+                format!("'{code}_BTC_CR'"),
+                format!("uuid('{STABLESATS_OMNIBUS_ID}')"),
+                "CREDIT",
+                format!("params.btc_total{sign}"),
+            ),
+            entry(
+                format!("'{code}_BTC_DR'"),
+                format!("uuid('{OKEX_TRANSFERS_IN_TRANSIT_ID}')"),
+                "DEBIT",
+                format!("params.btc_amount{sign}"),
+            ),
+            entry(
+                format!("'{code}_FEE_BTC_DR'"),
+                format!("uuid('{ONCHAIN_FEES_ID}')"),
+                "DEBIT",
+                format!("params.btc_fee{sign}"),
+            ),
+        ];
+        create_template(ledger, id, code, description, revert, entries).await
+    }
+
+    async fn init_settled(ledger: &SqlxLedger) -> Result<(), LedgerError> {
+        let entries = vec![
+            entry(
+                "'OKEX_TRANSFER_SETTLED_BTC_CR'".to_string(),
+                format!("uuid('{OKEX_TRANSFERS_IN_TRANSIT_ID}')"),
+                "CREDIT",
+                "params.btc_amount".to_string(),
+            ),
+            entry(
+                "'OKEX_TRANSFER_SETTLED_BTC_DR'".to_string(),
+                "params.destination_account_id".to_string(),
+                "DEBIT",
+                "params.btc_amount".to_string(),
+            ),
+        ];
+        create_template(
+            ledger,
+            OKEX_TRANSFER_SETTLED_ID,
+            OKEX_TRANSFER_SETTLED_CODE,
+            "'OKX transfer settled'",
+            false,
+            entries,
+        )
+        .await
+    }
+
+    /// Moves the amount out of transit into the galoy wallet, balanced the same
+    /// way as the deposit template.
+    async fn init_withdrawal_settled(ledger: &SqlxLedger) -> Result<(), LedgerError> {
+        let entries = vec![
+            entry(
+                "'OKEX_WITHDRAWAL_SETTLED_BTC_CR'".to_string(),
+                format!("uuid('{OKEX_TRANSFERS_IN_TRANSIT_ID}')"),
+                "CREDIT",
+                "params.btc_amount".to_string(),
+            ),
+            entry(
+                "'OKEX_WITHDRAWAL_SETTLED_BTC_DR'".to_string(),
+                format!("uuid('{STABLESATS_OMNIBUS_ID}')"),
+                "DEBIT",
+                "params.btc_amount".to_string(),
+            ),
+            entry(
+                "'OKEX_WITHDRAWAL_SETTLED_WALLET_BTC_CR'".to_string(),
+                format!("uuid('{STABLESATS_BTC_WALLET_ID}')"),
+                "CREDIT",
+                "params.btc_amount".to_string(),
+            ),
+            entry(
+                "'OKEX_WITHDRAWAL_SETTLED_WALLET_BTC_DR'".to_string(),
+                format!("uuid('{EXTERNAL_OMNIBUS_ID}')"),
+                "DEBIT",
+                "params.btc_amount".to_string(),
+            ),
+        ];
+        create_template(
+            ledger,
+            OKEX_WITHDRAWAL_SETTLED_ID,
+            OKEX_WITHDRAWAL_SETTLED_CODE,
+            "'OKX withdrawal settled'",
+            false,
+            entries,
+        )
+        .await
+    }
+}
+
+fn entry(entry_type: String, account_id: String, direction: &str, units: String) -> EntryInput {
+    EntryInput::builder()
+        .entry_type(entry_type)
+        .currency("'BTC'")
+        .account_id(account_id)
+        .direction(direction)
+        .layer("SETTLED")
+        .units(units)
+        .build()
+        .expect("Couldn't build okex transfer entry")
+}
+
+async fn create_template(
+    ledger: &SqlxLedger,
+    id: Uuid,
+    code: &str,
+    description: &str,
+    revert: bool,
+    entries: Vec<EntryInput>,
+) -> Result<(), LedgerError> {
+    let tx_input = if revert {
+        TxInput::builder()
+            .journal_id(format!("uuid('{STABLESATS_JOURNAL_ID}')"))
+            .effective("params.effective")
+            .correlation_id("params.correlation_id")
+            .metadata("params.meta")
+            .description(description)
+            .build()
+    } else {
+        TxInput::builder()
+            .journal_id(format!("uuid('{STABLESATS_JOURNAL_ID}')"))
+            .effective("params.effective")
+            .metadata("params.meta")
+            .description(description)
+            .build()
+    }
+    .expect("Couldn't build TxInput");
+
+    let mut params = OkexTransferParams::defs();
+    if revert {
+        params.push(
+            ParamDefinition::builder()
+                .name("correlation_id")
+                .r#type(ParamDataType::UUID)
+                .build()
+                .unwrap(),
+        );
+    }
+    let template = NewTxTemplate::builder()
+        .id(id)
+        .code(code)
+        .tx_input(tx_input)
+        .entries(entries)
+        .params(params)
+        .build()
+        .expect("Couldn't build okex transfer template");
+    match ledger.tx_templates().create(template).await {
+        Ok(_) | Err(SqlxLedgerError::DuplicateKey(_)) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -1,5 +1,6 @@
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
+use serial_test::serial;
 
 use stablesats_ledger::*;
 
@@ -11,6 +12,7 @@ pub async fn init_pool() -> anyhow::Result<sqlx::PgPool> {
 }
 
 #[tokio::test]
+#[serial]
 async fn user_buys_and_sells_usd() -> anyhow::Result<()> {
     let pool = init_pool().await?;
 
@@ -81,3 +83,257 @@ async fn user_buys_and_sells_usd() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+#[serial]
+async fn okex_fees_funding_and_pnl() -> anyhow::Result<()> {
+    let pool = init_pool().await?;
+
+    let ledger = Ledger::init(&pool).await?;
+
+    let balance = |b: Option<sqlx_ledger::balance::AccountBalance>| {
+        b.map(|b| b.settled()).unwrap_or(Decimal::ZERO)
+    };
+    let before_trading = balance(ledger.balances().okex_trading().await?);
+    let before_fees = balance(ledger.balances().okex_trading_fees().await?);
+    let before_funding = balance(ledger.balances().okex_funding_payments().await?);
+    let before_pnl = balance(ledger.balances().okex_realized_pnl().await?);
+
+    let order_meta = OkexOrderMeta {
+        timestamp: chrono::Utc::now(),
+        client_order_id: "client_order_id".to_string(),
+        order_id: "order_id".to_string(),
+    };
+    ledger
+        .okex_trading_fee(
+            pool.begin().await?,
+            LedgerTxId::new(),
+            OkexTradingFeeParams {
+                btc_amount: dec!(-0.0001),
+                meta: order_meta.clone(),
+            },
+        )
+        .await?;
+    ledger
+        .okex_realized_pnl(
+            pool.begin().await?,
+            LedgerTxId::new(),
+            OkexRealizedPnlParams {
+                btc_amount: dec!(0.002),
+                meta: order_meta,
+            },
+        )
+        .await?;
+    ledger
+        .okex_funding_payment(
+            pool.begin().await?,
+            LedgerTxId::new(),
+            OkexFundingPaymentParams {
+                btc_amount: dec!(-0.0003),
+                meta: OkexFundingPaymentMeta {
+                    timestamp: chrono::Utc::now(),
+                    bill_id: "bill_id".to_string(),
+                },
+            },
+        )
+        .await?;
+
+    let after_fees = balance(ledger.balances().okex_trading_fees().await?);
+    let after_funding = balance(ledger.balances().okex_funding_payments().await?);
+    let after_pnl = balance(ledger.balances().okex_realized_pnl().await?);
+    let after_trading = balance(ledger.balances().okex_trading().await?);
+    assert_eq!(after_fees - before_fees, dec!(-0.0001));
+    assert_eq!(after_funding - before_funding, dec!(-0.0003));
+    assert_eq!(after_pnl - before_pnl, dec!(0.002));
+    assert_eq!(after_trading - before_trading, dec!(0.0016));
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn balances_as_of_and_transactions_by_template() -> anyhow::Result<()> {
+    let pool = init_pool().await?;
+
+    let ledger = Ledger::init(&pool).await?;
+
+    let id = LedgerTxId::new();
+    ledger
+        .user_buys_usd(
+            pool.begin().await?,
+            id,
+            UserBuysUsdParams {
+                satoshi_amount: dec!(1000000),
+                usd_cents_amount: dec!(500),
+                meta: UserBuysUsdMeta {
+                    timestamp: chrono::Utc::now(),
+                    btc_tx_id: "btc_tx_id".to_string(),
+                    usd_tx_id: "usd_tx_id".to_string(),
+                },
+            },
+        )
+        .await?;
+
+    let mut after = None;
+    let tx = loop {
+        let page = ledger
+            .list_transactions_by_template("USER_BUYS_USD", after, 100)
+            .await?;
+        assert!(!page.is_empty());
+        if let Some(tx) = page.iter().find(|tx| tx.id == id) {
+            break tx.clone();
+        }
+        after = page.last().map(LedgerTransactionsCursor::from);
+    };
+    assert_eq!(tx.template_code, "USER_BUYS_USD");
+    assert_eq!(tx.meta.unwrap()["btc_tx_id"], "btc_tx_id");
+
+    let posted = BalanceAsOf::Timestamp(tx.created_at);
+    let before = BalanceAsOf::Timestamp(tx.created_at - chrono::Duration::microseconds(1));
+    let balances = ledger.balances();
+    assert_eq!(
+        balances.stablesats_liability_as_of(posted).await?
+            - balances.stablesats_liability_as_of(before).await?,
+        dec!(5)
+    );
+    assert_eq!(
+        balances.stablesats_btc_wallet_as_of(posted).await?
+            - balances.stablesats_btc_wallet_as_of(before).await?,
+        dec!(0.01)
+    );
+
+    let (entry_id,): (uuid::Uuid,) =
+        sqlx::query_as("SELECT id FROM sqlx_ledger_entries WHERE transaction_id = $1 LIMIT 1")
+            .bind(uuid::Uuid::from(id))
+            .fetch_one(&pool)
+            .await?;
+    let entry = BalanceAsOf::Entry(entry_id);
+    assert_eq!(
+        balances.stablesats_liability_as_of(entry).await?
+            - balances.stablesats_liability_as_of(before).await?,
+        dec!(5)
+    );
+    assert!(balances
+        .stablesats_liability_as_of(BalanceAsOf::Entry(uuid::Uuid::new_v4()))
+        .await
+        .is_err());
+
+    // Back-dated to a day no other run posts to, so only this transaction is effective on it
+    let effective = chrono::NaiveDate::from_ymd_opt(1900, 1, 1).unwrap()
+        + chrono::Duration::days(chrono::Utc::now().timestamp_nanos().rem_euclid(40_000));
+    ledger
+        .user_buys_usd(
+            pool.begin().await?,
+            LedgerTxId::new(),
+            UserBuysUsdParams {
+                satoshi_amount: dec!(1000000),
+                usd_cents_amount: dec!(500),
+                meta: UserBuysUsdMeta {
+                    timestamp: chrono::DateTime::from_utc(
+                        effective.and_hms_opt(12, 0, 0).unwrap(),
+                        chrono::Utc,
+                    ),
+                    btc_tx_id: "btc_tx_id".to_string(),
+                    usd_tx_id: "usd_tx_id".to_string(),
+                },
+            },
+        )
+        .await?;
+    assert_eq!(
+        balances
+            .stablesats_liability_as_of(BalanceAsOf::Effective(effective))
+            .await?
+            - balances
+                .stablesats_liability_as_of(BalanceAsOf::Effective(
+                    effective - chrono::Duration::days(1)
+                ))
+                .await?,
+        dec!(5)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn okex_transfers() -> anyhow::Result<()> {
+    let pool = init_pool().await?;
+
+    let ledger = Ledger::init(&pool).await?;
+
+    let balance = |b: Option<sqlx_ledger::balance::AccountBalance>| {
+        b.map(|b| b.settled()).unwrap_or(Decimal::ZERO)
+    };
+    let before_funding = balance(ledger.balances().okex_funding().await?);
+    let before_in_transit = balance(ledger.balances().okex_transfers_in_transit().await?);
+    let before_fees = balance(ledger.balances().onchain_fees().await?);
+    let before_wallet = balance(ledger.balances().stablesats_btc_wallet().await?);
+
+    let transfer = |direction, btc_amount, btc_fee| OkexTransferParams {
+        btc_amount,
+        btc_fee,
+        meta: OkexTransferMeta {
+            timestamp: chrono::Utc::now(),
+            direction,
+            client_transfer_id: "client_transfer_id".to_string(),
+            transfer_id: "transfer_id".to_string(),
+        },
+    };
+
+    let deposit = transfer(
+        OkexTransferDirection::GaloyToOkexFunding,
+        dec!(0.01),
+        Decimal::ZERO,
+    );
+    ledger
+        .okex_transfer_initiated(pool.begin().await?, LedgerTxId::new(), deposit.clone())
+        .await?;
+    let in_transit = balance(ledger.balances().okex_transfers_in_transit().await?);
+    assert_eq!(in_transit - before_in_transit, dec!(0.01));
+    let wallet = balance(ledger.balances().stablesats_btc_wallet().await?);
+    assert_eq!(wallet - before_wallet, dec!(-0.01));
+    ledger
+        .okex_transfer_settled(pool.begin().await?, LedgerTxId::new(), deposit)
+        .await?;
+
+    let withdrawal = transfer(
+        OkexTransferDirection::OkexFundingToGaloy,
+        dec!(0.004),
+        dec!(0.0001),
+    );
+    let initiated_ledger_tx_id = LedgerTxId::new();
+    ledger
+        .okex_transfer_initiated(
+            pool.begin().await?,
+            initiated_ledger_tx_id,
+            withdrawal.clone(),
+        )
+        .await?;
+    ledger
+        .revert_okex_transfer_initiated(
+            pool.begin().await?,
+            LedgerTxId::new(),
+            RevertOkexTransferInitiatedParams {
+                initiated_ledger_tx_id,
+                transfer: withdrawal.clone(),
+            },
+        )
+        .await?;
+    ledger
+        .okex_transfer_initiated(pool.begin().await?, LedgerTxId::new(), withdrawal.clone())
+        .await?;
+    ledger
+        .okex_transfer_settled(pool.begin().await?, LedgerTxId::new(), withdrawal)
+        .await?;
+    let after_wallet = balance(ledger.balances().stablesats_btc_wallet().await?);
+    assert_eq!(after_wallet - wallet, dec!(0.004));
+
+    let after_funding = balance(ledger.balances().okex_funding().await?);
+    let after_in_transit = balance(ledger.balances().okex_transfers_in_transit().await?);
+    let after_fees = balance(ledger.balances().onchain_fees().await?);
+    assert_eq!(after_funding - before_funding, dec!(0.0059));
+    assert_eq!(after_in_transit, before_in_transit);
+    assert_eq!(after_fees - before_fees, dec!(0.0001));
+
+    Ok(())
+}
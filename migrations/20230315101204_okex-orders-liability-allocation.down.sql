@@ -0,0 +1,2 @@
+ALTER TABLE okex_orders DROP COLUMN liability_share;
+ALTER TABLE okex_orders DROP COLUMN total_target_usd_value;
@@ -0,0 +1,2 @@
+ALTER TABLE okex_orders ADD COLUMN total_target_usd_value NUMERIC;
+ALTER TABLE okex_orders ADD COLUMN liability_share NUMERIC;
@@ -0,0 +1,11 @@
+CREATE TABLE quotes (
+  id UUID PRIMARY KEY,
+  direction VARCHAR NOT NULL,
+  amount_in_satoshis NUMERIC NOT NULL,
+  amount_in_cents NUMERIC NOT NULL,
+  fee_rate NUMERIC NOT NULL,
+  fee_amount NUMERIC NOT NULL,
+  expires_at TIMESTAMPTZ NOT NULL,
+  accepted_at TIMESTAMPTZ,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,2 @@
+DROP INDEX okex_orders_parent_order_id_idx;
+ALTER TABLE okex_orders DROP COLUMN parent_order_id;
@@ -0,0 +1,2 @@
+ALTER TABLE okex_orders ADD COLUMN parent_order_id UUID;
+CREATE INDEX okex_orders_parent_order_id_idx ON okex_orders (parent_order_id);
@@ -0,0 +1,3 @@
+ALTER TABLE okex_orders DROP COLUMN pnl_ledger_tx_id;
+ALTER TABLE okex_orders DROP COLUMN fee_ledger_tx_id;
+ALTER TABLE okex_orders DROP COLUMN pnl;
@@ -0,0 +1,3 @@
+ALTER TABLE okex_orders ADD COLUMN pnl NUMERIC;
+ALTER TABLE okex_orders ADD COLUMN fee_ledger_tx_id UUID DEFAULT NULL;
+ALTER TABLE okex_orders ADD COLUMN pnl_ledger_tx_id UUID DEFAULT NULL;
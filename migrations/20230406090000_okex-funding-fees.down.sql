@@ -0,0 +1 @@
+DROP TABLE okex_funding_fees;
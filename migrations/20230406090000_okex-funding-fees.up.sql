@@ -0,0 +1,9 @@
+CREATE TABLE okex_funding_fees (
+  bill_id VARCHAR PRIMARY KEY,
+  instrument VARCHAR(32) NOT NULL,
+  amount NUMERIC NOT NULL,
+  paid_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  ledger_tx_id UUID DEFAULT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+CREATE INDEX okex_funding_fees_paid_at_idx ON okex_funding_fees (paid_at);
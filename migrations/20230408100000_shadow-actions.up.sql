@@ -0,0 +1,12 @@
+CREATE TABLE shadow_actions (
+  id UUID PRIMARY KEY,
+  correlation_id UUID NOT NULL,
+  exchange VARCHAR(32) NOT NULL,
+  job_name VARCHAR NOT NULL,
+  action_type VARCHAR NOT NULL,
+  action_size NUMERIC,
+  action_unit VARCHAR NOT NULL,
+  inputs JSONB NOT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+CREATE INDEX shadow_actions_created_at_idx ON shadow_actions (created_at);
@@ -0,0 +1 @@
+DROP TABLE user_trades_watermark;
@@ -0,0 +1,6 @@
+CREATE TABLE user_trades_watermark (
+  id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+  synced_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  galoy_cursor VARCHAR,
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
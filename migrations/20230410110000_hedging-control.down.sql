@@ -0,0 +1 @@
+DROP TABLE hedging_control;
@@ -0,0 +1,6 @@
+CREATE TABLE hedging_control (
+  id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+  paused BOOLEAN NOT NULL,
+  reason VARCHAR,
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
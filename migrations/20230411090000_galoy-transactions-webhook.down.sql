@@ -0,0 +1 @@
+ALTER TABLE galoy_transactions DROP COLUMN from_webhook;
@@ -0,0 +1 @@
+ALTER TABLE galoy_transactions ADD COLUMN from_webhook BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,5 @@
+DROP TABLE galoy_transaction_resolutions;
+
+ALTER TABLE galoy_transactions DROP COLUMN ignored_at;
+ALTER TABLE galoy_transactions DROP COLUMN quarantine_reason;
+ALTER TABLE galoy_transactions DROP COLUMN quarantined_at;
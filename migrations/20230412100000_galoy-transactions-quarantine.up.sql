@@ -0,0 +1,12 @@
+ALTER TABLE galoy_transactions ADD COLUMN quarantined_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE galoy_transactions ADD COLUMN quarantine_reason VARCHAR;
+ALTER TABLE galoy_transactions ADD COLUMN ignored_at TIMESTAMP WITH TIME ZONE;
+
+CREATE TABLE galoy_transaction_resolutions (
+  id UUID PRIMARY KEY,
+  action VARCHAR NOT NULL,
+  galoy_transaction_ids VARCHAR[] NOT NULL,
+  resolved_by VARCHAR NOT NULL,
+  note VARCHAR,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,11 @@
+CREATE TABLE reconciliation_reports (
+  id UUID PRIMARY KEY,
+  ledger_usd_liability_in_cents NUMERIC NOT NULL,
+  galoy_usd_balance_in_cents NUMERIC NOT NULL,
+  usd_difference_in_cents NUMERIC NOT NULL,
+  ledger_btc_balance_in_sats NUMERIC NOT NULL,
+  galoy_btc_balance_in_sats NUMERIC NOT NULL,
+  btc_difference_in_sats NUMERIC NOT NULL,
+  within_tolerance BOOLEAN NOT NULL,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
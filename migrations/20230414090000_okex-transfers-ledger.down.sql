@@ -0,0 +1,3 @@
+ALTER TABLE okex_transfers DROP COLUMN revert_ledger_tx_id;
+ALTER TABLE okex_transfers DROP COLUMN settled_ledger_tx_id;
+ALTER TABLE okex_transfers DROP COLUMN initiated_ledger_tx_id;
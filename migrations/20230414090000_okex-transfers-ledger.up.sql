@@ -0,0 +1,3 @@
+ALTER TABLE okex_transfers ADD COLUMN initiated_ledger_tx_id UUID DEFAULT NULL;
+ALTER TABLE okex_transfers ADD COLUMN settled_ledger_tx_id UUID DEFAULT NULL;
+ALTER TABLE okex_transfers ADD COLUMN revert_ledger_tx_id UUID DEFAULT NULL;
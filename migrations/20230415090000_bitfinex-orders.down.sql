@@ -0,0 +1 @@
+DROP TABLE bitfinex_orders;
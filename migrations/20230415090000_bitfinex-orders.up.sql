@@ -0,0 +1,9 @@
+CREATE TABLE bitfinex_orders (
+  client_id BIGINT PRIMARY KEY,
+  correlation_id UUID UNIQUE NOT NULL,
+  instrument VARCHAR(32) NOT NULL,
+  action VARCHAR(20) NOT NULL,
+  size_usd_cents NUMERIC,
+  submitted BOOLEAN NOT NULL DEFAULT FALSE,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
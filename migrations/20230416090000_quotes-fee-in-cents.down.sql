@@ -0,0 +1,2 @@
+DROP INDEX idx_quotes_unaccepted_expires_at;
+ALTER TABLE quotes RENAME COLUMN fee_amount_in_cents TO fee_amount;
@@ -0,0 +1,2 @@
+ALTER TABLE quotes RENAME COLUMN fee_amount TO fee_amount_in_cents;
+CREATE INDEX idx_quotes_unaccepted_expires_at ON quotes (expires_at) WHERE accepted_at IS NULL;
@@ -0,0 +1 @@
+ALTER TABLE okex_orders DROP COLUMN twap_remaining;
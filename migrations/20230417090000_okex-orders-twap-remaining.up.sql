@@ -0,0 +1 @@
+ALTER TABLE okex_orders ADD COLUMN twap_remaining BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,6 @@
+DROP INDEX reconciliation_reports_baseline_idx;
+ALTER TABLE reconciliation_reports
+  DROP COLUMN is_baseline,
+  DROP COLUMN usd_drift_in_cents,
+  DROP COLUMN btc_drift_in_sats,
+  DROP COLUMN btc_checked;
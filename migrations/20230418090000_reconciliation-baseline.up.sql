@@ -0,0 +1,7 @@
+ALTER TABLE reconciliation_reports
+  ADD COLUMN is_baseline BOOLEAN NOT NULL DEFAULT false,
+  ADD COLUMN usd_drift_in_cents NUMERIC NOT NULL DEFAULT 0,
+  ADD COLUMN btc_drift_in_sats NUMERIC NOT NULL DEFAULT 0,
+  ADD COLUMN btc_checked BOOLEAN NOT NULL DEFAULT false;
+
+CREATE UNIQUE INDEX reconciliation_reports_baseline_idx ON reconciliation_reports (is_baseline) WHERE is_baseline;
@@ -0,0 +1,3 @@
+ALTER TABLE okex_transfers DROP COLUMN skip_ledger;
+ALTER TABLE okex_transfers DROP COLUMN send_fee_pending;
+ALTER TABLE okex_transfers DROP COLUMN completed_at;
@@ -0,0 +1,7 @@
+ALTER TABLE okex_transfers ADD COLUMN completed_at TIMESTAMP WITH TIME ZONE DEFAULT NULL;
+ALTER TABLE okex_transfers ADD COLUMN send_fee_pending BOOLEAN NOT NULL DEFAULT false;
+
+-- Transfers made before they were booked in the ledger are covered by the
+-- reconciliation baseline and must not be posted retroactively.
+ALTER TABLE okex_transfers ADD COLUMN skip_ledger BOOLEAN NOT NULL DEFAULT false;
+UPDATE okex_transfers SET skip_ledger = true WHERE initiated_ledger_tx_id IS NULL;
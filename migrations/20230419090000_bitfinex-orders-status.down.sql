@@ -0,0 +1,6 @@
+ALTER TABLE bitfinex_orders DROP COLUMN complete;
+ALTER TABLE bitfinex_orders DROP COLUMN avg_price;
+ALTER TABLE bitfinex_orders DROP COLUMN btc_filled;
+ALTER TABLE bitfinex_orders DROP COLUMN btc_amount;
+ALTER TABLE bitfinex_orders DROP COLUMN state;
+ALTER TABLE bitfinex_orders DROP COLUMN order_id;
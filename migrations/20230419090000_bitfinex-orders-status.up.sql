@@ -0,0 +1,6 @@
+ALTER TABLE bitfinex_orders ADD COLUMN order_id BIGINT;
+ALTER TABLE bitfinex_orders ADD COLUMN state VARCHAR(128);
+ALTER TABLE bitfinex_orders ADD COLUMN btc_amount NUMERIC;
+ALTER TABLE bitfinex_orders ADD COLUMN btc_filled NUMERIC;
+ALTER TABLE bitfinex_orders ADD COLUMN avg_price NUMERIC;
+ALTER TABLE bitfinex_orders ADD COLUMN complete BOOLEAN NOT NULL DEFAULT FALSE;
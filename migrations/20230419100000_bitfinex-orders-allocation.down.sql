@@ -0,0 +1,3 @@
+ALTER TABLE bitfinex_orders DROP COLUMN liability_share;
+ALTER TABLE bitfinex_orders DROP COLUMN total_target_usd_value;
+ALTER TABLE bitfinex_orders DROP COLUMN target_usd_value;
@@ -0,0 +1,3 @@
+ALTER TABLE bitfinex_orders ADD COLUMN target_usd_value NUMERIC;
+ALTER TABLE bitfinex_orders ADD COLUMN total_target_usd_value NUMERIC;
+ALTER TABLE bitfinex_orders ADD COLUMN liability_share NUMERIC;
@@ -30,6 +30,8 @@ pub enum OkexClientError {
     DecimalConversion(#[from] rust_decimal::Error),
     #[error("OkexClientError - MisconfiguredAccount: {0}")]
     MisconfiguredAccount(String),
+    #[error("OkexClientError - MissingOrderPrice: '{0}' orders need a price")]
+    MissingOrderPrice(String),
 }
 
 impl From<(String, String)> for OkexClientError {
@@ -2,7 +2,7 @@ mod error;
 mod okex_response;
 mod primitives;
 
-use chrono::{SecondsFormat, Utc};
+use chrono::{SecondsFormat, TimeZone, Utc};
 use data_encoding::BASE64;
 use reqwest::{
     header::{HeaderMap, HeaderValue, CONTENT_TYPE},
@@ -37,6 +37,7 @@ pub const OKEX_MINIMUM_WITHDRAWAL_FEE: Decimal = dec!(0.0002);
 pub const OKEX_MAXIMUM_WITHDRAWAL_FEE: Decimal = dec!(0.0004);
 pub const OKEX_MINIMUM_WITHDRAWAL_AMOUNT: Decimal = dec!(0.001);
 pub const OKEX_MAXIMUM_WITHDRAWAL_AMOUNT: Decimal = dec!(500);
+const FUNDING_FEE_BILL_TYPE: &str = "8";
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct OkexClientConfig {
@@ -48,6 +49,9 @@ pub struct OkexClientConfig {
     pub secret_key: String,
     #[serde(default)]
     pub simulated: bool,
+    /// Overrides the OKX REST endpoint, eg. to point the client at a mock server
+    #[serde(default)]
+    pub base_url: Option<String>,
 }
 
 #[derive(Clone)]
@@ -63,7 +67,7 @@ impl OkexClient {
             config,
         };
         let path = "/api/v5/account/config";
-        let config_url = Self::url_for_path(path);
+        let config_url = client.url_for_path(path);
         let headers = client.get_request_headers(path)?;
 
         let response = client
@@ -110,7 +114,7 @@ impl OkexClient {
 
     pub async fn leverage_info(&self) -> Result<OkexLeverageInfoData, OkexClientError> {
         let path = "/api/v5/account/leverage-info?instId=BTC-USD-SWAP&mgnMode=cross";
-        let config_url = Self::url_for_path(path);
+        let config_url = self.url_for_path(path);
         let headers = self.get_request_headers(path)?;
 
         let response = self
@@ -146,7 +150,7 @@ impl OkexClient {
         let response = self
             .rate_limit_client(request_path)
             .await
-            .get(Self::url_for_path(request_path))
+            .get(self.url_for_path(request_path))
             .headers(headers)
             .send()
             .await?;
@@ -166,7 +170,7 @@ impl OkexClient {
         let response = self
             .rate_limit_client(request_path)
             .await
-            .get(Self::url_for_path(request_path))
+            .get(self.url_for_path(request_path))
             .headers(headers)
             .send()
             .await?;
@@ -203,7 +207,7 @@ impl OkexClient {
         let response = self
             .rate_limit_client(request_path)
             .await
-            .post(Self::url_for_path(request_path))
+            .post(self.url_for_path(request_path))
             .headers(headers)
             .body(request_body)
             .send()
@@ -236,7 +240,7 @@ impl OkexClient {
         let response = self
             .rate_limit_client(request_path)
             .await
-            .post(Self::url_for_path(request_path))
+            .post(self.url_for_path(request_path))
             .headers(headers)
             .body(request_body)
             .send()
@@ -257,7 +261,7 @@ impl OkexClient {
         let response = self
             .rate_limit_client(request_path)
             .await
-            .get(Self::url_for_path(request_path))
+            .get(self.url_for_path(request_path))
             .headers(headers)
             .send()
             .await?;
@@ -280,7 +284,7 @@ impl OkexClient {
         let response = self
             .rate_limit_client(request_path)
             .await
-            .get(Self::url_for_path(request_path))
+            .get(self.url_for_path(request_path))
             .headers(headers)
             .send()
             .await?;
@@ -317,7 +321,7 @@ impl OkexClient {
         let response = self
             .rate_limit_client(static_request_path)
             .await
-            .get(Self::url_for_path(&request_path))
+            .get(self.url_for_path(&request_path))
             .headers(headers)
             .send()
             .await?;
@@ -343,7 +347,7 @@ impl OkexClient {
         let response = self
             .rate_limit_client(static_request_path)
             .await
-            .get(Self::url_for_path(&request_path))
+            .get(self.url_for_path(&request_path))
             .headers(headers)
             .send()
             .await?;
@@ -381,7 +385,7 @@ impl OkexClient {
         let response = self
             .rate_limit_client(request_path)
             .await
-            .post(Self::url_for_path(request_path))
+            .post(self.url_for_path(request_path))
             .headers(headers)
             .body(request_body)
             .send()
@@ -406,7 +410,7 @@ impl OkexClient {
         let response = self
             .rate_limit_client(request_path)
             .await
-            .get(Self::url_for_path(request_path))
+            .get(self.url_for_path(request_path))
             .headers(headers)
             .send()
             .await?;
@@ -450,7 +454,7 @@ impl OkexClient {
         let response = self
             .rate_limit_client(static_request_path)
             .await
-            .get(Self::url_for_path(&request_path))
+            .get(self.url_for_path(&request_path))
             .headers(headers)
             .send()
             .await?;
@@ -482,7 +486,12 @@ impl OkexClient {
         id: ClientOrderId,
         side: OkexOrderSide,
         contracts: &BtcUsdSwapContracts,
+        order_type: OkexOrderType,
+        price_in_usd: Option<Decimal>,
     ) -> Result<OrderId, OkexClientError> {
+        if order_type.requires_price() && price_in_usd.is_none() {
+            return Err(OkexClientError::MissingOrderPrice(order_type.to_string()));
+        }
         let mut body: HashMap<String, String> = HashMap::new();
         body.insert("ccy".to_string(), TradeCurrency::BTC.to_string());
         body.insert("clOrdId".to_string(), id.0);
@@ -492,9 +501,12 @@ impl OkexClient {
         );
         body.insert("tdMode".to_string(), OkexMarginMode::Cross.to_string());
         body.insert("side".to_string(), side.to_string());
-        body.insert("ordType".to_string(), OkexOrderType::Market.to_string());
+        body.insert("ordType".to_string(), order_type.to_string());
         body.insert("posSide".to_string(), OkexPositionSide::Net.to_string());
         body.insert("sz".to_string(), contracts.0.to_string());
+        if let Some(price) = price_in_usd {
+            body.insert("px".to_string(), price.to_string());
+        }
         let request_body = serde_json::to_string(&body)?;
 
         let request_path = "/api/v5/trade/order";
@@ -503,7 +515,7 @@ impl OkexClient {
         let response = self
             .rate_limit_client(request_path)
             .await
-            .post(Self::url_for_path(request_path))
+            .post(self.url_for_path(request_path))
             .headers(headers)
             .body(request_body)
             .send()
@@ -530,7 +542,7 @@ impl OkexClient {
         let response = self
             .rate_limit_client(static_request_path)
             .await
-            .get(Self::url_for_path(&request_path))
+            .get(self.url_for_path(&request_path))
             .headers(headers)
             .send()
             .await?;
@@ -542,6 +554,38 @@ impl OkexClient {
         Ok(details)
     }
 
+    #[instrument(name = "okex_client.cancel_order", skip(self), err)]
+    pub async fn cancel_order(&self, id: ClientOrderId) -> Result<(), OkexClientError> {
+        let mut body: HashMap<String, String> = HashMap::new();
+        body.insert(
+            "instId".to_string(),
+            OkexInstrumentId::BtcUsdSwap.to_string(),
+        );
+        body.insert("clOrdId".to_string(), id.0);
+        let request_body = serde_json::to_string(&body)?;
+
+        let request_path = "/api/v5/trade/cancel-order";
+        let headers = self.post_request_headers(request_path, &request_body)?;
+
+        let response = self
+            .rate_limit_client(request_path)
+            .await
+            .post(self.url_for_path(request_path))
+            .headers(headers)
+            .body(request_body)
+            .send()
+            .await?;
+
+        let order_data = Self::extract_response_data::<CancelOrderData>(response).await?;
+        if order_data.s_code != "0" {
+            return Err(OkexClientError::UnexpectedResponse {
+                msg: order_data.s_msg,
+                code: order_data.s_code,
+            });
+        }
+        Ok(())
+    }
+
     pub async fn get_last_price_in_usd_cents(&self) -> Result<LastPrice, OkexClientError> {
         let request_path = "/api/v5/market/ticker?instId=BTC-USD-SWAP";
         let headers = self.get_request_headers(request_path)?;
@@ -549,26 +593,107 @@ impl OkexClient {
         let response = self
             .rate_limit_client(request_path)
             .await
-            .get(Self::url_for_path(request_path))
+            .get(self.url_for_path(request_path))
             .headers(headers)
             .send()
             .await?;
 
-        if let Some(LastPriceData { last, .. }) =
-            Self::extract_optional_response_data::<LastPriceData>(response).await?
+        if let Some(LastPriceData {
+            last,
+            bid_px,
+            ask_px,
+            ..
+        }) = Self::extract_optional_response_data::<LastPriceData>(response).await?
         {
             Ok(LastPrice {
                 usd_cents: last * Decimal::ONE_HUNDRED,
+                best_bid_in_usd: bid_px,
+                best_ask_in_usd: ask_px,
             })
         } else {
             Err(OkexClientError::NoLastPriceAvailable)
         }
     }
 
+    #[instrument(name = "okex_client.get_funding_rate", skip(self), err)]
+    pub async fn get_funding_rate(&self) -> Result<FundingRate, OkexClientError> {
+        let request_path = "/api/v5/public/funding-rate?instId=BTC-USD-SWAP";
+        let response = self
+            .rate_limit_client(request_path)
+            .await
+            .get(self.url_for_path(request_path))
+            .send()
+            .await?;
+
+        let FundingRateData {
+            funding_rate,
+            next_funding_rate,
+            ..
+        } = Self::extract_response_data::<FundingRateData>(response).await?;
+        Ok(FundingRate {
+            funding_rate,
+            next_funding_rate: next_funding_rate.parse().ok(),
+        })
+    }
+
+    /// Funding fee bills of the last 7 days, newest first.
+    /// Pass the `bill_id` of the last bill of a page as `after` to fetch the next (older) page.
+    #[instrument(name = "okex_client.funding_fee_bills", skip(self), err)]
+    pub async fn funding_fee_bills(
+        &self,
+        after: Option<String>,
+    ) -> Result<Vec<FundingFeeBill>, OkexClientError> {
+        let static_request_path = "/api/v5/account/bills";
+        let mut request_path = format!(
+            "{static_request_path}?instType=SWAP&ccy=BTC&type={FUNDING_FEE_BILL_TYPE}&limit=100"
+        );
+        if let Some(after) = after {
+            request_path = format!("{request_path}&after={after}");
+        }
+        let headers = self.get_request_headers(&request_path)?;
+        let response = self
+            .rate_limit_client(static_request_path)
+            .await
+            .get(self.url_for_path(&request_path))
+            .headers(headers)
+            .send()
+            .await?;
+
+        let bills = Self::extract_response_data_array::<BillData>(response).await?;
+        bills
+            .into_iter()
+            .filter(|bill| bill.bill_type == FUNDING_FEE_BILL_TYPE)
+            .map(|bill| {
+                let timestamp = bill
+                    .ts
+                    .parse::<i64>()
+                    .ok()
+                    .and_then(|ts| Utc.timestamp_millis_opt(ts).single())
+                    .ok_or_else(|| OkexClientError::UnexpectedResponse {
+                        msg: format!("Invalid bill timestamp: {}", bill.ts),
+                        code: "0".to_string(),
+                    })?;
+                Ok(FundingFeeBill {
+                    bill_id: bill.bill_id,
+                    instrument_id: bill.inst_id,
+                    amount_in_btc: bill.bal_chg,
+                    timestamp,
+                })
+            })
+            .collect()
+    }
+
     #[instrument(
         name = "okex_client.get_position_in_signed_usd_cents",
         skip_all,
-        fields(notional_usd, position_in_ct, last_price),
+        fields(
+            notional_usd,
+            position_in_ct,
+            last_price,
+            margin_ratio,
+            liquidation_price,
+            mark_price
+        ),
         err
     )]
     pub async fn get_position_in_signed_usd_cents(&self) -> Result<PositionSize, OkexClientError> {
@@ -578,7 +703,7 @@ impl OkexClient {
         let response = self
             .rate_limit_client(request_path)
             .await
-            .get(Self::url_for_path(request_path))
+            .get(self.url_for_path(request_path))
             .headers(headers)
             .send()
             .await?;
@@ -587,6 +712,9 @@ impl OkexClient {
             notional_usd,
             pos,
             last,
+            mgn_ratio,
+            liq_px,
+            mark_px,
             ..
         }) = Self::extract_optional_response_data::<PositionData>(response).await?
         {
@@ -594,23 +722,34 @@ impl OkexClient {
             span.record("notional_usd", &tracing::field::display(&notional_usd));
             span.record("position_in_ct", &tracing::field::display(&pos));
             span.record("last_price", &tracing::field::display(&last));
+            span.record("margin_ratio", &tracing::field::display(&mgn_ratio));
+            span.record("liquidation_price", &tracing::field::display(&liq_px));
+            span.record("mark_price", &tracing::field::display(&mark_px));
 
             let d_result = pos.parse::<Decimal>();
             let n_result = notional_usd.parse::<Decimal>();
             let l_result = last.parse::<Decimal>();
-
-            match (d_result, n_result, l_result) {
-                (Ok(direction), Ok(notional_usd), Ok(last)) => Ok(PositionSize {
-                    instrument_id: OkexInstrumentId::BtcUsdSwap,
-                    usd_cents: notional_usd
-                        * Decimal::ONE_HUNDRED
-                        * if direction > Decimal::ZERO {
-                            Decimal::ONE
-                        } else {
-                            Decimal::NEGATIVE_ONE
+            let m_result = mark_px.parse::<Decimal>();
+
+            match (d_result, n_result, l_result, m_result) {
+                (Ok(direction), Ok(notional_usd), Ok(last), Ok(mark_price_in_usd)) => {
+                    Ok(PositionSize {
+                        instrument_id: OkexInstrumentId::BtcUsdSwap,
+                        usd_cents: notional_usd
+                            * Decimal::ONE_HUNDRED
+                            * if direction > Decimal::ZERO {
+                                Decimal::ONE
+                            } else {
+                                Decimal::NEGATIVE_ONE
+                            },
+                        last_price_in_usd_cents: last * Decimal::ONE_HUNDRED,
+                        margin: PositionMargin {
+                            margin_ratio: mgn_ratio.parse::<Decimal>().ok(),
+                            liquidation_price_in_usd: liq_px.parse::<Decimal>().ok(),
+                            mark_price_in_usd,
                         },
-                    last_price_in_usd_cents: last * Decimal::ONE_HUNDRED,
-                }),
+                    })
+                }
                 _ => Err(OkexClientError::NonParsablePositionData),
             }
         } else {
@@ -638,7 +777,7 @@ impl OkexClient {
         let response = self
             .rate_limit_client(request_path)
             .await
-            .post(Self::url_for_path(request_path))
+            .post(self.url_for_path(request_path))
             .headers(headers)
             .body(request_body)
             .send()
@@ -705,8 +844,9 @@ impl OkexClient {
         BASE64.encode(signature.as_ref())
     }
 
-    fn url_for_path(path: &str) -> String {
-        format!("{OKEX_API_URL}{path}")
+    fn url_for_path(&self, path: &str) -> String {
+        let base_url = self.config.base_url.as_deref().unwrap_or(OKEX_API_URL);
+        format!("{base_url}{path}")
     }
 
     fn post_request_headers(
@@ -1,5 +1,5 @@
 use rust_decimal::Decimal;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 
 use super::primitives::ClientOrderId;
 
@@ -159,19 +159,57 @@ pub struct OrderData {
     pub s_msg: String,
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelOrderData {
+    pub cl_ord_id: String,
+    pub ord_id: String,
+    pub s_code: String,
+    pub s_msg: String,
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct OrderDetails {
     pub cl_ord_id: ClientOrderId,
     pub ord_id: String,
+    // Empty until the order has (partially) filled
+    #[serde(deserialize_with = "empty_string_as_zero")]
     pub avg_px: Decimal,
+    #[serde(deserialize_with = "empty_string_as_zero")]
     pub fee: Decimal,
     pub sz: Decimal,
+    #[serde(default, deserialize_with = "empty_string_as_zero")]
+    pub acc_fill_sz: Decimal,
+    /// Realized profit and loss, only set on position reducing orders
+    #[serde(default, deserialize_with = "empty_string_as_zero")]
+    pub pnl: Decimal,
     pub state: String,
     #[serde(skip)]
     pub complete: bool,
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FundingRateData {
+    pub inst_id: String,
+    pub funding_rate: Decimal,
+    pub next_funding_rate: String,
+    pub funding_time: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BillData {
+    pub bill_id: String,
+    pub inst_id: String,
+    pub ccy: String,
+    pub bal_chg: Decimal,
+    #[serde(rename = "type")]
+    pub bill_type: String,
+    pub ts: String,
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct LastPriceData {
@@ -257,3 +295,14 @@ pub struct OkexLeverageInfoData {
     pub pos_side: String,
     pub lever: Decimal,
 }
+
+fn empty_string_as_zero<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    if value.is_empty() {
+        return Ok(Decimal::ZERO);
+    }
+    value.parse().map_err(serde::de::Error::custom)
+}
@@ -149,6 +149,25 @@ pub struct OrderId {
 #[derive(Debug)]
 pub struct LastPrice {
     pub usd_cents: Decimal,
+    pub best_bid_in_usd: Decimal,
+    pub best_ask_in_usd: Decimal,
+}
+
+#[derive(Debug, Clone)]
+pub struct FundingRate {
+    /// Rate applied at the next funding time, positive when longs pay shorts
+    pub funding_rate: Decimal,
+    /// Predicted rate for the funding time after that, if OKX publishes one
+    pub next_funding_rate: Option<Decimal>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FundingFeeBill {
+    pub bill_id: String,
+    pub instrument_id: String,
+    /// Positive when funding was received, negative when it was paid
+    pub amount_in_btc: Decimal,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
 #[derive(Debug)]
@@ -156,6 +175,26 @@ pub struct PositionSize {
     pub instrument_id: OkexInstrumentId,
     pub usd_cents: Decimal,
     pub last_price_in_usd_cents: Decimal,
+    pub margin: PositionMargin,
+}
+
+#[derive(Debug, Clone)]
+pub struct PositionMargin {
+    pub margin_ratio: Option<Decimal>,
+    pub liquidation_price_in_usd: Option<Decimal>,
+    pub mark_price_in_usd: Decimal,
+}
+
+impl PositionMargin {
+    /// Relative distance between the mark price and the estimated
+    /// liquidation price, `None` when OKX does not report one
+    pub fn liquidation_distance(&self) -> Option<Decimal> {
+        let liquidation_price = self.liquidation_price_in_usd?;
+        if self.mark_price_in_usd <= Decimal::ZERO {
+            return None;
+        }
+        Some((liquidation_price - self.mark_price_in_usd).abs() / self.mark_price_in_usd)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -229,6 +268,7 @@ impl Display for OkexOrderSide {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OkexOrderType {
     Market,
     Limit,
@@ -238,6 +278,12 @@ pub enum OkexOrderType {
     OptimalLimitIoc,
 }
 
+impl OkexOrderType {
+    pub fn requires_price(&self) -> bool {
+        !matches!(self, OkexOrderType::Market | OkexOrderType::OptimalLimitIoc)
+    }
+}
+
 impl Display for OkexOrderType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match *self {
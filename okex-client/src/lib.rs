@@ -2,5 +2,7 @@
 #![cfg_attr(feature = "fail-on-warnings", deny(clippy::all))]
 
 mod client;
+#[cfg(feature = "test-support")]
+pub mod mock_server;
 
 pub use client::*;
@@ -0,0 +1,575 @@
+//! In-process emulation of the subset of the OKX REST API used by [`OkexClient`](crate::OkexClient).
+//! Requests are not authenticated. Market orders and orders crossing the spread fill immediately,
+//! resting limit orders stay live until [`MockOkexState::fill_live_order`] or a cancel.
+mod state;
+
+use axum::{
+    extract::{Query, State},
+    routing::{get, post},
+    Json, Router,
+};
+use rust_decimal::Decimal;
+use serde_json::{json, Map, Value};
+use tokio::{sync::Mutex, task::JoinHandle};
+
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+
+use crate::OkexClientConfig;
+pub use state::*;
+
+type SharedState = Arc<Mutex<MockOkexState>>;
+type Params = HashMap<String, String>;
+
+pub struct MockOkexServer {
+    addr: SocketAddr,
+    state: SharedState,
+    handle: JoinHandle<()>,
+}
+
+impl MockOkexServer {
+    pub async fn start(state: MockOkexState) -> Self {
+        let state = Arc::new(Mutex::new(state));
+        let listener =
+            std::net::TcpListener::bind("127.0.0.1:0").expect("couldn't bind mock okex server");
+        let addr = listener
+            .local_addr()
+            .expect("mock okex server has no address");
+        let server = axum::Server::from_tcp(listener)
+            .expect("couldn't start mock okex server")
+            .serve(router(Arc::clone(&state)).into_make_service());
+        let handle = tokio::spawn(async move {
+            let _ = server.await;
+        });
+        Self {
+            addr,
+            state,
+            handle,
+        }
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn client_config(&self) -> OkexClientConfig {
+        OkexClientConfig {
+            api_key: "mock-api-key".to_string(),
+            passphrase: "mock-passphrase".to_string(),
+            secret_key: "mock-secret-key".to_string(),
+            simulated: true,
+            base_url: Some(self.base_url()),
+        }
+    }
+
+    pub async fn state(&self) -> MockOkexState {
+        self.state.lock().await.clone()
+    }
+
+    pub async fn update_state(&self, f: impl FnOnce(&mut MockOkexState)) {
+        f(&mut *self.state.lock().await)
+    }
+}
+
+impl Drop for MockOkexServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+fn router(state: SharedState) -> Router {
+    Router::new()
+        .route("/api/v5/account/config", get(account_config))
+        .route("/api/v5/account/leverage-info", get(leverage_info))
+        .route("/api/v5/account/positions", get(positions))
+        .route("/api/v5/account/balance", get(trading_balance))
+        .route("/api/v5/account/bills", get(bills))
+        .route("/api/v5/market/ticker", get(ticker))
+        .route("/api/v5/public/funding-rate", get(funding_rate))
+        .route("/api/v5/trade/order", post(place_order).get(order_details))
+        .route("/api/v5/trade/cancel-order", post(cancel_order))
+        .route("/api/v5/trade/close-position", post(close_position))
+        .route("/api/v5/asset/balances", get(funding_balance))
+        .route("/api/v5/asset/transfer", post(transfer))
+        .route("/api/v5/asset/transfer-state", get(transfer_state))
+        .route("/api/v5/asset/deposit-address", get(deposit_address))
+        .route("/api/v5/asset/deposit-history", get(deposit_history))
+        .route("/api/v5/asset/currencies", get(currencies))
+        .route("/api/v5/asset/withdrawal", post(withdraw))
+        .route("/api/v5/asset/withdrawal-history", get(withdrawal_history))
+        .with_state(state)
+}
+
+fn respond(state: &MockOkexState, data: Vec<Value>) -> Json<Value> {
+    if let Some((code, msg)) = &state.error {
+        return Json(json!({ "code": code, "msg": msg, "data": null }));
+    }
+    Json(json!({ "code": "0", "msg": "", "data": data }))
+}
+
+fn error(code: &str, msg: &str) -> Json<Value> {
+    Json(json!({ "code": code, "msg": msg, "data": null }))
+}
+
+/// Fills in every field the client deserializes with an empty string.
+fn with_defaults(fields: &[&str], overrides: Value) -> Value {
+    let mut map: Map<String, Value> = fields
+        .iter()
+        .map(|field| (field.to_string(), Value::String(String::new())))
+        .collect();
+    if let Value::Object(overrides) = overrides {
+        map.extend(overrides);
+    }
+    Value::Object(map)
+}
+
+fn decimal_param(body: &Params, key: &str) -> Decimal {
+    body.get(key)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_default()
+}
+
+async fn account_config(State(state): State<SharedState>) -> Json<Value> {
+    let state = state.lock().await;
+    let data = json!({
+        "acctLv": state.acct_lv,
+        "autoLoan": false,
+        "ctIsoMode": "automatic",
+        "greeksType": "PA",
+        "level": "Lv1",
+        "levelTmp": "",
+        "mgnIsoMode": "automatic",
+        "posMode": state.pos_mode,
+        "uid": "mock",
+    });
+    respond(&state, vec![data])
+}
+
+async fn leverage_info(State(state): State<SharedState>) -> Json<Value> {
+    let state = state.lock().await;
+    let data = json!({
+        "instId": "BTC-USD-SWAP",
+        "mgnMode": "cross",
+        "posSide": "net",
+        "lever": state.leverage.to_string(),
+    });
+    respond(&state, vec![data])
+}
+
+async fn positions(State(state): State<SharedState>) -> Json<Value> {
+    let state = state.lock().await;
+    let data = with_defaults(
+        &[
+            "adl",
+            "availPos",
+            "avgPx",
+            "cTime",
+            "ccy",
+            "deltaBS",
+            "deltaPA",
+            "gammaBS",
+            "gammaPA",
+            "imr",
+            "instId",
+            "instType",
+            "interest",
+            "usdPx",
+            "last",
+            "lever",
+            "liab",
+            "liabCcy",
+            "liqPx",
+            "markPx",
+            "margin",
+            "mgnMode",
+            "mgnRatio",
+            "mmr",
+            "notionalUsd",
+            "optVal",
+            "pos",
+            "posCcy",
+            "posId",
+            "posSide",
+            "thetaBS",
+            "thetaPA",
+            "tradeId",
+            "uTime",
+            "upl",
+            "uplRatio",
+            "vegaBS",
+            "vegaPA",
+        ],
+        json!({
+            "ccy": "BTC",
+            "instId": "BTC-USD-SWAP",
+            "instType": "SWAP",
+            "last": state.last_price_in_usd.to_string(),
+            "lever": state.leverage.to_string(),
+            "liqPx": state
+                .liquidation_price_in_usd
+                .map(|px| px.to_string())
+                .unwrap_or_default(),
+            "markPx": state.last_price_in_usd.to_string(),
+            "mgnMode": "cross",
+            "mgnRatio": state
+                .margin_ratio
+                .map(|ratio| ratio.to_string())
+                .unwrap_or_default(),
+            "notionalUsd": state.notional_usd().to_string(),
+            "pos": state.position_in_contracts.to_string(),
+            "posSide": "net",
+        }),
+    );
+    respond(&state, vec![data])
+}
+
+async fn trading_balance(State(state): State<SharedState>) -> Json<Value> {
+    let state = state.lock().await;
+    let details = with_defaults(
+        &[
+            "availBal",
+            "availEq",
+            "cashBal",
+            "ccy",
+            "crossLiab",
+            "disEq",
+            "eq",
+            "eqUsd",
+            "frozenBal",
+            "interest",
+            "isoEq",
+            "isoLiab",
+            "isoUpl",
+            "liab",
+            "maxLoan",
+            "mgnRatio",
+            "notionalLever",
+            "ordFrozen",
+            "twap",
+            "uTime",
+            "upl",
+            "uplLiab",
+            "stgyEq",
+            "spotInUseAmt",
+        ],
+        json!({
+            "availEq": state.trading_balance_in_btc.to_string(),
+            "ccy": "BTC",
+            "eq": state.trading_balance_in_btc.to_string(),
+            "frozenBal": "0",
+        }),
+    );
+    let data = with_defaults(
+        &[
+            "adjEq",
+            "imr",
+            "isoEq",
+            "mgnRatio",
+            "mmr",
+            "notionalUsd",
+            "ordFroz",
+            "totalEq",
+            "uTime",
+        ],
+        json!({ "details": [details] }),
+    );
+    respond(&state, vec![data])
+}
+
+async fn bills(State(state): State<SharedState>, Query(params): Query<Params>) -> Json<Value> {
+    let state = state.lock().await;
+    let limit = params
+        .get("limit")
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(100);
+    let data = state
+        .funding_fee_bills
+        .iter()
+        .skip_while(|bill| {
+            params
+                .get("after")
+                .map(|after| &bill.bill_id >= after)
+                .unwrap_or(false)
+        })
+        .take(limit)
+        .map(|bill| {
+            json!({
+                "billId": bill.bill_id,
+                "instId": "BTC-USD-SWAP",
+                "ccy": "BTC",
+                "balChg": bill.amount_in_btc.to_string(),
+                "type": "8",
+                "ts": bill.timestamp_millis.to_string(),
+            })
+        })
+        .collect();
+    respond(&state, data)
+}
+
+async fn funding_rate(State(state): State<SharedState>) -> Json<Value> {
+    let state = state.lock().await;
+    let data = json!({
+        "instId": "BTC-USD-SWAP",
+        "fundingRate": state.funding_rate.to_string(),
+        "nextFundingRate": state
+            .next_funding_rate
+            .map(|rate| rate.to_string())
+            .unwrap_or_default(),
+        "fundingTime": "0",
+    });
+    respond(&state, vec![data])
+}
+
+async fn ticker(State(state): State<SharedState>) -> Json<Value> {
+    let state = state.lock().await;
+    let data = json!({
+        "instType": "SWAP",
+        "instId": "BTC-USD-SWAP",
+        "last": state.last_price_in_usd.to_string(),
+        "lastSz": "1",
+        "askPx": state.best_ask_in_usd().to_string(),
+        "askSz": "1",
+        "bidPx": state.best_bid_in_usd().to_string(),
+        "bidSz": "1",
+    });
+    respond(&state, vec![data])
+}
+
+async fn place_order(State(state): State<SharedState>, Json(body): Json<Params>) -> Json<Value> {
+    let mut state = state.lock().await;
+    let cl_ord_id = body.get("clOrdId").cloned().unwrap_or_default();
+    if state.orders.contains_key(&cl_ord_id) {
+        return error("51016", "Duplicated clOrdId");
+    }
+    let side = body.get("side").cloned().unwrap_or_default();
+    let ord_type = body.get("ordType").cloned().unwrap_or_default();
+    let px = body.get("px").and_then(|px| px.parse().ok());
+    let ord_id = state.place_order(
+        cl_ord_id.clone(),
+        &side,
+        decimal_param(&body, "sz"),
+        &ord_type,
+        px,
+    );
+    let data = json!({
+        "clOrdId": cl_ord_id,
+        "ordId": ord_id,
+        "tag": "",
+        "sCode": "0",
+        "sMsg": "",
+    });
+    respond(&state, vec![data])
+}
+
+async fn order_details(
+    State(state): State<SharedState>,
+    Query(params): Query<Params>,
+) -> Json<Value> {
+    let state = state.lock().await;
+    let order = params
+        .get("clOrdId")
+        .and_then(|cl_ord_id| state.orders.get(cl_ord_id));
+    match order {
+        Some(order) => {
+            let data = json!({
+                "clOrdId": order.cl_ord_id,
+                "ordId": order.ord_id,
+                "avgPx": if order.acc_fill_sz.is_zero() { String::new() } else { order.avg_px.to_string() },
+                "fee": order.fee.to_string(),
+                "sz": order.sz.to_string(),
+                "accFillSz": order.acc_fill_sz.to_string(),
+                "pnl": "0",
+                "state": order.state,
+            });
+            respond(&state, vec![data])
+        }
+        None => error("51603", "Order does not exist"),
+    }
+}
+
+async fn cancel_order(State(state): State<SharedState>, Json(body): Json<Params>) -> Json<Value> {
+    let mut state = state.lock().await;
+    let cl_ord_id = body.get("clOrdId").cloned().unwrap_or_default();
+    let ord_id = match state.orders.get(&cl_ord_id) {
+        Some(order) => order.ord_id.clone(),
+        None => return error("51603", "Order does not exist"),
+    };
+    let (s_code, s_msg) = if state.cancel(&cl_ord_id) {
+        ("0", "")
+    } else {
+        (
+            "51400",
+            "Order cancellation failed as the order has been filled, canceled or does not exist",
+        )
+    };
+    let data = json!({
+        "clOrdId": cl_ord_id,
+        "ordId": ord_id,
+        "sCode": s_code,
+        "sMsg": s_msg,
+    });
+    respond(&state, vec![data])
+}
+
+async fn close_position(State(state): State<SharedState>) -> Json<Value> {
+    let mut state = state.lock().await;
+    if state.position_in_contracts.is_zero() {
+        return error("51023", "Position does not exist");
+    }
+    state.position_in_contracts = Decimal::ZERO;
+    let data = json!({ "instId": "BTC-USD-SWAP", "posSide": "net" });
+    respond(&state, vec![data])
+}
+
+async fn funding_balance(State(state): State<SharedState>) -> Json<Value> {
+    let state = state.lock().await;
+    let balance = state.funding_balance_in_btc.to_string();
+    let data = json!({
+        "availBal": balance,
+        "bal": balance,
+        "ccy": "BTC",
+        "frozenBal": "0",
+    });
+    respond(&state, vec![data])
+}
+
+async fn transfer(State(state): State<SharedState>, Json(body): Json<Params>) -> Json<Value> {
+    let mut state = state.lock().await;
+    let transfer = state.transfer(
+        body.get("clientId").cloned().unwrap_or_default(),
+        body.get("from").cloned().unwrap_or_default(),
+        body.get("to").cloned().unwrap_or_default(),
+        decimal_param(&body, "amt"),
+    );
+    let data = json!({
+        "transId": transfer.trans_id,
+        "ccy": "BTC",
+        "clientId": transfer.client_id,
+        "from": transfer.from,
+        "amt": transfer.amt.to_string(),
+        "to": transfer.to,
+    });
+    respond(&state, vec![data])
+}
+
+async fn transfer_state(
+    State(state): State<SharedState>,
+    Query(params): Query<Params>,
+) -> Json<Value> {
+    let state = state.lock().await;
+    let transfer = state.transfers.iter().find(|transfer| {
+        params.get("transId") == Some(&transfer.trans_id)
+            || params.get("clientId") == Some(&transfer.client_id)
+    });
+    match transfer {
+        Some(transfer) => {
+            let data = json!({
+                "amt": transfer.amt.to_string(),
+                "ccy": "BTC",
+                "clientId": transfer.client_id,
+                "from": transfer.from,
+                "state": transfer.state,
+                "subAcct": "",
+                "to": transfer.to,
+                "transId": transfer.trans_id,
+            });
+            respond(&state, vec![data])
+        }
+        None => error("58129", "Transfer does not exist"),
+    }
+}
+
+async fn deposit_address(State(state): State<SharedState>) -> Json<Value> {
+    let state = state.lock().await;
+    let data = json!({
+        "chain": "BTC-Bitcoin",
+        "ctAddr": "",
+        "ccy": "BTC",
+        "to": "6",
+        "addr": state.deposit_address,
+        "selected": true,
+    });
+    respond(&state, vec![data])
+}
+
+async fn deposit_history(State(state): State<SharedState>) -> Json<Value> {
+    let state = state.lock().await;
+    let data = state
+        .deposits
+        .iter()
+        .enumerate()
+        .map(|(idx, deposit)| {
+            json!({
+                "actualDepBlkConfirm": "1",
+                "amt": deposit.amt.to_string(),
+                "ccy": "BTC",
+                "chain": "BTC-Bitcoin",
+                "depId": format!("{}", idx + 1),
+                "from": "",
+                "state": deposit.state,
+                "to": deposit.to,
+                "ts": "0",
+                "txId": deposit.tx_id,
+            })
+        })
+        .collect();
+    respond(&state, data)
+}
+
+async fn currencies(State(state): State<SharedState>) -> Json<Value> {
+    let state = state.lock().await;
+    let data = json!({
+        "ccy": "BTC",
+        "chain": "BTC-Bitcoin",
+        "minFee": "0.0002",
+        "maxFee": "0.0004",
+        "minWd": "0.001",
+        "maxWd": "500",
+    });
+    respond(&state, vec![data])
+}
+
+async fn withdraw(State(state): State<SharedState>, Json(body): Json<Params>) -> Json<Value> {
+    let mut state = state.lock().await;
+    let withdrawal = state.withdraw(
+        body.get("clientId").cloned().unwrap_or_default(),
+        body.get("toAddr").cloned().unwrap_or_default(),
+        decimal_param(&body, "amt"),
+    );
+    let data = json!({
+        "amt": withdrawal.amt.to_string(),
+        "wdId": withdrawal.wd_id,
+        "ccy": "BTC",
+        "clientId": withdrawal.client_id,
+        "chain": "BTC-Bitcoin",
+    });
+    respond(&state, vec![data])
+}
+
+async fn withdrawal_history(
+    State(state): State<SharedState>,
+    Query(params): Query<Params>,
+) -> Json<Value> {
+    let state = state.lock().await;
+    let withdrawal = state
+        .withdrawals
+        .iter()
+        .find(|withdrawal| params.get("clientId") == Some(&withdrawal.client_id));
+    match withdrawal {
+        Some(withdrawal) => {
+            let data = json!({
+                "ccy": "BTC",
+                "chain": "BTC-Bitcoin",
+                "amt": withdrawal.amt.to_string(),
+                "ts": "0",
+                "from": "",
+                "to": withdrawal.to,
+                "txId": withdrawal.tx_id,
+                "state": withdrawal.state,
+                "wdId": withdrawal.wd_id,
+                "clientId": withdrawal.client_id,
+            });
+            respond(&state, vec![data])
+        }
+        None => respond(&state, Vec::new()),
+    }
+}
@@ -0,0 +1,258 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use std::collections::HashMap;
+
+pub const CONTRACT_SIZE_IN_USD: Decimal = dec!(100);
+
+/// Scriptable account state served by the [`MockOkexServer`](super::MockOkexServer).
+#[derive(Debug, Clone)]
+pub struct MockOkexState {
+    pub pos_mode: String,
+    pub acct_lv: String,
+    pub leverage: Decimal,
+    pub last_price_in_usd: Decimal,
+    /// Distance of the best bid and best ask from the last price
+    pub half_spread_in_usd: Decimal,
+    /// Signed number of BTC-USD-SWAP contracts
+    pub position_in_contracts: Decimal,
+    pub funding_balance_in_btc: Decimal,
+    pub trading_balance_in_btc: Decimal,
+    pub fee_rate: Decimal,
+    pub funding_rate: Decimal,
+    pub next_funding_rate: Option<Decimal>,
+    /// Estimated liquidation price of the position, empty when not set
+    pub liquidation_price_in_usd: Option<Decimal>,
+    pub margin_ratio: Option<Decimal>,
+    pub deposit_address: String,
+    pub orders: HashMap<String, MockOrder>,
+    pub transfers: Vec<MockTransfer>,
+    pub deposits: Vec<MockDeposit>,
+    pub withdrawals: Vec<MockWithdrawal>,
+    /// Funding fee bills, newest first
+    pub funding_fee_bills: Vec<MockFundingFeeBill>,
+    /// State reported for new withdrawals, see the OKX withdrawal-history docs
+    pub withdrawal_state: String,
+    /// When set every request is answered with this error code and message
+    pub error: Option<(String, String)>,
+}
+
+impl Default for MockOkexState {
+    fn default() -> Self {
+        Self {
+            pos_mode: "net_mode".to_string(),
+            acct_lv: "2".to_string(),
+            leverage: dec!(4),
+            last_price_in_usd: dec!(20_000),
+            half_spread_in_usd: Decimal::ZERO,
+            position_in_contracts: Decimal::ZERO,
+            funding_balance_in_btc: Decimal::ZERO,
+            trading_balance_in_btc: Decimal::ZERO,
+            fee_rate: dec!(0.0005),
+            funding_rate: dec!(0.0001),
+            next_funding_rate: None,
+            liquidation_price_in_usd: None,
+            margin_ratio: None,
+            deposit_address: "bc1qmockokexdepositaddress".to_string(),
+            orders: HashMap::new(),
+            transfers: Vec::new(),
+            deposits: Vec::new(),
+            withdrawals: Vec::new(),
+            funding_fee_bills: Vec::new(),
+            withdrawal_state: "2".to_string(),
+            error: None,
+        }
+    }
+}
+
+impl MockOkexState {
+    pub fn notional_usd(&self) -> Decimal {
+        self.position_in_contracts.abs() * CONTRACT_SIZE_IN_USD
+    }
+
+    pub fn best_bid_in_usd(&self) -> Decimal {
+        self.last_price_in_usd - self.half_spread_in_usd
+    }
+
+    pub fn best_ask_in_usd(&self) -> Decimal {
+        self.last_price_in_usd + self.half_spread_in_usd
+    }
+
+    /// Fills a resting order at its limit price, as if the market had traded through it.
+    pub fn fill_live_order(&mut self, cl_ord_id: &str) {
+        if let Some(order) = self.orders.get(cl_ord_id).cloned() {
+            if order.state == "live" {
+                let price = order.px.unwrap_or(self.last_price_in_usd);
+                self.fill(&order.cl_ord_id, price);
+            }
+        }
+    }
+
+    pub(super) fn place_order(
+        &mut self,
+        cl_ord_id: String,
+        side: &str,
+        contracts: Decimal,
+        ord_type: &str,
+        px: Option<Decimal>,
+    ) -> String {
+        let ord_id = format!("{}", self.orders.len() + 1);
+        self.orders.insert(
+            cl_ord_id.clone(),
+            MockOrder {
+                cl_ord_id: cl_ord_id.clone(),
+                ord_id: ord_id.clone(),
+                side: side.to_string(),
+                ord_type: ord_type.to_string(),
+                sz: contracts,
+                px,
+                acc_fill_sz: Decimal::ZERO,
+                avg_px: Decimal::ZERO,
+                fee: Decimal::ZERO,
+                state: "live".to_string(),
+            },
+        );
+        let touch = if side == "buy" {
+            self.best_ask_in_usd()
+        } else {
+            self.best_bid_in_usd()
+        };
+        let crosses = match px {
+            Some(px) if side == "buy" => px >= touch,
+            Some(px) => px <= touch,
+            None => true,
+        };
+        match (ord_type, crosses) {
+            ("post_only", true) | ("ioc", false) | ("fok", false) => self.cancel(&cl_ord_id),
+            ("limit", false) | ("post_only", false) => (),
+            _ => self.fill(&cl_ord_id, touch),
+        }
+        ord_id
+    }
+
+    pub(super) fn cancel(&mut self, cl_ord_id: &str) -> bool {
+        match self.orders.get_mut(cl_ord_id) {
+            Some(order) if order.state == "live" => {
+                order.state = "canceled".to_string();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn fill(&mut self, cl_ord_id: &str, price: Decimal) {
+        let fee_rate = self.fee_rate;
+        let order = self.orders.get_mut(cl_ord_id).expect("order not found");
+        let contracts = order.sz;
+        let signed_contracts = if order.side == "buy" {
+            contracts
+        } else {
+            -contracts
+        };
+        let fee = -(contracts * CONTRACT_SIZE_IN_USD / price * fee_rate);
+        order.avg_px = price;
+        order.acc_fill_sz = contracts;
+        order.fee = fee;
+        order.state = "filled".to_string();
+        self.position_in_contracts += signed_contracts;
+        self.trading_balance_in_btc += fee;
+    }
+
+    pub(super) fn transfer(
+        &mut self,
+        client_id: String,
+        from: String,
+        to: String,
+        amt: Decimal,
+    ) -> MockTransfer {
+        match (from.as_str(), to.as_str()) {
+            ("6", "18") => {
+                self.funding_balance_in_btc -= amt;
+                self.trading_balance_in_btc += amt;
+            }
+            ("18", "6") => {
+                self.trading_balance_in_btc -= amt;
+                self.funding_balance_in_btc += amt;
+            }
+            _ => (),
+        }
+        let transfer = MockTransfer {
+            trans_id: format!("{}", self.transfers.len() + 1),
+            client_id,
+            from,
+            to,
+            amt,
+            state: "success".to_string(),
+        };
+        self.transfers.push(transfer.clone());
+        transfer
+    }
+
+    pub(super) fn withdraw(
+        &mut self,
+        client_id: String,
+        to: String,
+        amt: Decimal,
+    ) -> MockWithdrawal {
+        self.funding_balance_in_btc -= amt;
+        let withdrawal = MockWithdrawal {
+            wd_id: format!("{}", self.withdrawals.len() + 1),
+            client_id,
+            to,
+            amt,
+            tx_id: format!("mock-withdrawal-tx-{}", self.withdrawals.len() + 1),
+            state: self.withdrawal_state.clone(),
+        };
+        self.withdrawals.push(withdrawal.clone());
+        withdrawal
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockOrder {
+    pub cl_ord_id: String,
+    pub ord_id: String,
+    pub side: String,
+    pub ord_type: String,
+    pub sz: Decimal,
+    pub px: Option<Decimal>,
+    pub acc_fill_sz: Decimal,
+    pub avg_px: Decimal,
+    pub fee: Decimal,
+    pub state: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockTransfer {
+    pub trans_id: String,
+    pub client_id: String,
+    pub from: String,
+    pub to: String,
+    pub amt: Decimal,
+    pub state: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockDeposit {
+    pub to: String,
+    pub amt: Decimal,
+    pub tx_id: String,
+    pub state: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockWithdrawal {
+    pub wd_id: String,
+    pub client_id: String,
+    pub to: String,
+    pub amt: Decimal,
+    pub tx_id: String,
+    pub state: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockFundingFeeBill {
+    pub bill_id: String,
+    pub amount_in_btc: Decimal,
+    pub timestamp_millis: i64,
+}
@@ -16,6 +16,7 @@ async fn configured_okex_client() -> anyhow::Result<OkexClient> {
         passphrase,
         secret_key,
         simulated: true,
+        base_url: None,
     })
     .await?;
 
@@ -58,6 +59,7 @@ async fn client_is_missing_header() -> anyhow::Result<()> {
         passphrase: "".to_string(),
         secret_key: "".to_string(),
         simulated: true,
+        base_url: None,
     })
     .await;
 
@@ -213,6 +215,8 @@ async fn open_close_position() -> anyhow::Result<()> {
             ClientOrderId::new(),
             OkexOrderSide::Sell,
             &BtcUsdSwapContracts::from(1),
+            OkexOrderType::Market,
+            None,
         )
         .await?;
 
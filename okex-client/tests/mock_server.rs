@@ -0,0 +1,225 @@
+use rust_decimal_macros::dec;
+
+use okex_client::{mock_server::*, *};
+
+#[tokio::test]
+async fn orders_update_the_position() -> anyhow::Result<()> {
+    let server = MockOkexServer::start(MockOkexState::default()).await;
+    let client = OkexClient::new(server.client_config()).await?;
+    client.check_leverage(dec!(4)).await?;
+
+    let id = ClientOrderId::new();
+    client
+        .place_order(
+            id.clone(),
+            OkexOrderSide::Sell,
+            &BtcUsdSwapContracts::from(5),
+            OkexOrderType::Market,
+            None,
+        )
+        .await?;
+    let details = client.order_details(id).await?;
+    assert!(details.complete);
+    assert_eq!(details.sz, dec!(5));
+
+    let position = client.get_position_in_signed_usd_cents().await?;
+    assert_eq!(position.usd_cents, dec!(-50000));
+
+    client.close_positions(ClientOrderId::new()).await?;
+    assert_eq!(server.state().await.position_in_contracts, dec!(0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn transfers_move_balances() -> anyhow::Result<()> {
+    let server = MockOkexServer::start(MockOkexState {
+        funding_balance_in_btc: dec!(1),
+        ..Default::default()
+    })
+    .await;
+    let client = OkexClient::new(server.client_config()).await?;
+
+    let transfer_id = client
+        .transfer_funding_to_trading(ClientTransferId::new(), dec!(0.25))
+        .await?;
+    let state = client.transfer_state(transfer_id).await?;
+    assert_eq!(state.state, "success");
+
+    let trading = client.trading_account_balance().await?;
+    assert_eq!(trading.total_amt_in_btc, dec!(0.25));
+    let funding = client.funding_account_balance().await?;
+    assert_eq!(funding.total_amt_in_btc, dec!(0.75));
+    Ok(())
+}
+
+#[tokio::test]
+async fn deposits_and_withdrawals_are_scriptable() -> anyhow::Result<()> {
+    let server = MockOkexServer::start(MockOkexState {
+        funding_balance_in_btc: dec!(1),
+        ..Default::default()
+    })
+    .await;
+    let client = OkexClient::new(server.client_config()).await?;
+
+    server
+        .update_state(|state| {
+            state.deposits.push(MockDeposit {
+                to: "bc1qdeposit".to_string(),
+                amt: dec!(0.1),
+                tx_id: "deposit-tx".to_string(),
+                state: "2".to_string(),
+            })
+        })
+        .await;
+    let deposit = client
+        .fetch_deposit("bc1qdeposit".to_string(), dec!(0.1))
+        .await?;
+    assert_eq!(deposit.state, "success");
+
+    let client_id = ClientTransferId::new();
+    client
+        .withdraw_btc_onchain(
+            client_id.clone(),
+            dec!(0.5),
+            dec!(0.0002),
+            "bc1qwithdrawal".to_string(),
+        )
+        .await?;
+    let withdrawal = client.fetch_withdrawal_by_client_id(client_id).await?;
+    assert_eq!(withdrawal.state, "success");
+    assert_eq!(server.state().await.funding_balance_in_btc, dec!(0.5));
+    Ok(())
+}
+
+#[tokio::test]
+async fn scripted_errors_are_returned() -> anyhow::Result<()> {
+    let server = MockOkexServer::start(MockOkexState::default()).await;
+    let client = OkexClient::new(server.client_config()).await?;
+    server
+        .update_state(|state| state.error = Some(("50011".to_string(), "Rate limit".to_string())))
+        .await;
+    assert!(client.get_last_price_in_usd_cents().await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn post_only_orders_rest_until_filled_or_canceled() -> anyhow::Result<()> {
+    let server = MockOkexServer::start(MockOkexState {
+        half_spread_in_usd: dec!(5),
+        ..Default::default()
+    })
+    .await;
+    let client = OkexClient::new(server.client_config()).await?;
+    let price = client.get_last_price_in_usd_cents().await?;
+    assert_eq!(price.best_ask_in_usd, dec!(20005));
+
+    let id = ClientOrderId::new();
+    client
+        .place_order(
+            id.clone(),
+            OkexOrderSide::Sell,
+            &BtcUsdSwapContracts::from(2),
+            OkexOrderType::PostOnly,
+            Some(price.best_ask_in_usd),
+        )
+        .await?;
+    let details = client.order_details(id.clone()).await?;
+    assert!(!details.complete);
+    assert_eq!(details.acc_fill_sz, dec!(0));
+
+    server
+        .update_state(|state| state.fill_live_order(&String::from(id.clone())))
+        .await;
+    let details = client.order_details(id.clone()).await?;
+    assert!(details.complete);
+    assert_eq!(details.avg_px, dec!(20005));
+    assert!(client.cancel_order(id).await.is_err());
+
+    let id = ClientOrderId::new();
+    client
+        .place_order(
+            id.clone(),
+            OkexOrderSide::Buy,
+            &BtcUsdSwapContracts::from(2),
+            OkexOrderType::PostOnly,
+            Some(price.best_bid_in_usd),
+        )
+        .await?;
+    client.cancel_order(id.clone()).await?;
+    let details = client.order_details(id).await?;
+    assert!(details.complete);
+    assert_eq!(details.acc_fill_sz, dec!(0));
+
+    assert_eq!(server.state().await.position_in_contracts, dec!(-2));
+    Ok(())
+}
+
+#[tokio::test]
+async fn priced_orders_need_a_price() -> anyhow::Result<()> {
+    let server = MockOkexServer::start(MockOkexState::default()).await;
+    let client = OkexClient::new(server.client_config()).await?;
+    let result = client
+        .place_order(
+            ClientOrderId::new(),
+            OkexOrderSide::Buy,
+            &BtcUsdSwapContracts::from(1),
+            OkexOrderType::Limit,
+            None,
+        )
+        .await;
+    assert!(matches!(result, Err(OkexClientError::MissingOrderPrice(_))));
+    Ok(())
+}
+
+#[tokio::test]
+async fn funding_fee_bills_are_paged() -> anyhow::Result<()> {
+    let funding_fee_bills = (1..=3)
+        .rev()
+        .map(|id| MockFundingFeeBill {
+            bill_id: id.to_string(),
+            amount_in_btc: dec!(-0.0001),
+            timestamp_millis: 1_680_000_000_000 + id,
+        })
+        .collect();
+    let server = MockOkexServer::start(MockOkexState {
+        funding_fee_bills,
+        ..Default::default()
+    })
+    .await;
+    let client = OkexClient::new(server.client_config()).await?;
+
+    let bills = client.funding_fee_bills(None).await?;
+    assert_eq!(bills.len(), 3);
+    assert_eq!(bills[0].bill_id, "3");
+    assert_eq!(bills[0].amount_in_btc, dec!(-0.0001));
+
+    let older = client.funding_fee_bills(Some("2".to_string())).await?;
+    assert_eq!(older.len(), 1);
+    assert_eq!(older[0].bill_id, "1");
+    Ok(())
+}
+
+#[tokio::test]
+async fn position_margin_reports_liquidation_distance() -> anyhow::Result<()> {
+    let server = MockOkexServer::start(MockOkexState {
+        position_in_contracts: dec!(-10),
+        last_price_in_usd: dec!(20_000),
+        liquidation_price_in_usd: Some(dec!(25_000)),
+        margin_ratio: Some(dec!(12.5)),
+        ..Default::default()
+    })
+    .await;
+    let client = OkexClient::new(server.client_config()).await?;
+
+    let margin = client.get_position_in_signed_usd_cents().await?.margin;
+    assert_eq!(margin.margin_ratio, Some(dec!(12.5)));
+    assert_eq!(margin.liquidation_price_in_usd, Some(dec!(25_000)));
+    assert_eq!(margin.liquidation_distance(), Some(dec!(0.25)));
+
+    server
+        .update_state(|state| state.liquidation_price_in_usd = None)
+        .await;
+    let margin = client.get_position_in_signed_usd_cents().await?.margin;
+    assert_eq!(margin.liquidation_distance(), None);
+    Ok(())
+}
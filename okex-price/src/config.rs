@@ -0,0 +1,7 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct PriceFeedConfig {
+    #[serde(default)]
+    pub order_book: bool,
+}
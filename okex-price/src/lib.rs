@@ -1,6 +1,7 @@
 #![cfg_attr(feature = "fail-on-warnings", deny(warnings))]
 #![cfg_attr(feature = "fail-on-warnings", deny(clippy::all))]
 
+pub mod config;
 mod convert;
 pub mod error;
 pub mod okex_shared;
@@ -11,14 +12,26 @@ use futures::StreamExt;
 use shared::{payload::*, pubsub::*};
 use tokio::join;
 
+pub use config::*;
 pub use error::*;
 pub use okex_shared::*;
 pub use order_book::*;
 pub use price_feed::*;
 
 pub async fn run(
+    price_feed_config: PriceFeedConfig,
     price_stream_publisher: memory::Publisher<PriceStreamPayload>,
 ) -> Result<(), PriceFeedError> {
+    if price_feed_config.order_book {
+        let books_publisher = price_stream_publisher.clone();
+        tokio::spawn(async move {
+            loop {
+                let _res = order_book_subscription(books_publisher.clone()).await;
+                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+            }
+        });
+    }
+
     let _ = tokio::spawn(async move {
         loop {
             let publisher = price_stream_publisher.clone();
@@ -28,15 +41,6 @@ pub async fn run(
                         let _res = okex_price_tick_received(&publisher, tick).await;
                     }
                 });
-
-                // let order_book_task = tokio::spawn(async move {
-                //     loop {
-                //         let _res = order_book_subscription(books_publisher.clone(), &price_feed_config).await;
-                //         tokio::time::sleep(Duration::from_secs(5_u64)).await;
-                //     }
-                // });
-
-                // let _ = join!(tick_task, order_book_task);
                 let _ = join!(tick_task);
             }
         }
@@ -46,20 +50,19 @@ pub async fn run(
     Ok(())
 }
 
-#[allow(dead_code)]
 async fn order_book_subscription(
-    publisher: memory::Publisher<OkexBtcUsdSwapOrderBookPayload>,
+    publisher: memory::Publisher<PriceStreamPayload>,
 ) -> Result<(), PriceFeedError> {
     let mut stream = subscribe_btc_usd_swap_order_book().await?;
     let full_load = stream.next().await.ok_or(PriceFeedError::InitialFullLoad)?;
     let order_book = CompleteOrderBook::try_from(OrderBookIncrement::try_from(full_load)?)?;
-    let cache = OrderBookCache::new(order_book);
+    let mut cache = OrderBookCache::new(order_book);
 
     let (send, recv) = tokio::sync::oneshot::channel();
 
     tokio::spawn(async move {
         while let Some(book) = stream.next().await {
-            if let Err(e) = okex_order_book_received(&publisher, book, cache.clone()).await {
+            if let Err(e) = okex_order_book_received(&publisher, book, &mut cache).await {
                 let _ = send.send(e);
                 break;
             }
@@ -81,21 +84,19 @@ async fn okex_price_tick_received(
     Ok(())
 }
 
-#[allow(dead_code)]
 async fn okex_order_book_received(
-    publisher: &memory::Publisher<OkexBtcUsdSwapOrderBookPayload>,
+    publisher: &memory::Publisher<PriceStreamPayload>,
     book: OkexOrderBook,
-    mut cache: OrderBookCache,
+    cache: &mut OrderBookCache,
 ) -> Result<(), PriceFeedError> {
     if let Ok(increment) = OrderBookIncrement::try_from(book) {
         cache.update_order_book(increment)?;
-        if let Ok(complete_order_book) =
-            OkexBtcUsdSwapOrderBookPayload::try_from(cache.latest().clone())
-        {
-            publisher
-                .throttle_publish("OKEX_ORDER_BOOK", complete_order_book)
-                .await?;
-        }
+        let payload = PriceStreamPayload::OkexBtcUsdSwapOrderBookPayload(OrderBookPayload::from(
+            cache.latest().clone(),
+        ));
+        publisher
+            .throttle_publish("OKEX_ORDER_BOOK", payload)
+            .await?;
     }
 
     Ok(())
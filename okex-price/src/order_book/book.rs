@@ -7,6 +7,7 @@ use std::collections::BTreeMap;
 use crate::{ChannelArgs, PriceFeedError};
 
 const CHECKSUM_DEPTH_LIMIT: usize = 25;
+const BTC_USD_SWAP_CONTRACT_SIZE_CENTS: u32 = 10_000;
 
 #[derive(Debug, Deserialize, PartialEq, Eq, Clone, Serialize)]
 #[serde(rename_all = "lowercase")]
@@ -168,29 +169,32 @@ impl CompleteOrderBook {
     }
 }
 
+/// Normalizes the book to prices in cents per sat and quantities in sats
+/// (okex quotes the BTC-USD-SWAP book in USD per BTC and number of contracts).
 impl From<CompleteOrderBook> for OrderBookPayload {
     fn from(book: CompleteOrderBook) -> Self {
-        let mut asks_map = BTreeMap::new();
-        for (ask_price, ask_qty) in book.asks {
-            let price = PriceRatioRaw::from_one_btc_in_usd_price(ask_price.0).numerator_amount();
-            let _ = asks_map.insert(PriceRaw::from(price), QuantityRaw::from(ask_qty));
-        }
-
-        let mut bids_map = BTreeMap::new();
-        for (bid_price, bid_qty) in book.bids {
-            let price = PriceRatioRaw::from_one_btc_in_usd_price(bid_price.0).numerator_amount();
-            let _ = bids_map.insert(PriceRaw::from(price), QuantityRaw::from(bid_qty));
-        }
-
         Self {
-            asks: asks_map,
-            bids: bids_map,
+            asks: normalize_side(book.asks),
+            bids: normalize_side(book.bids),
             timestamp: book.timestamp,
             exchange: ExchangeIdRaw::from(OKEX_EXCHANGE_ID),
         }
     }
 }
 
+fn normalize_side(side: BTreeMap<OrderPrice, Decimal>) -> BTreeMap<PriceRaw, QuantityRaw> {
+    side.into_iter()
+        .filter_map(|(price, contracts)| {
+            let price = PriceRatioRaw::from_one_btc_in_usd_price(price.0).numerator_amount();
+            if price.is_zero() {
+                return None;
+            }
+            let sats = contracts * Decimal::from(BTC_USD_SWAP_CONTRACT_SIZE_CENTS) / price;
+            Some((PriceRaw::from(price), QuantityRaw::from(sats)))
+        })
+        .collect()
+}
+
 #[derive(Clone)]
 pub struct OrderBookCache {
     current: CompleteOrderBook,
@@ -249,4 +253,21 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn payload_is_normalized_to_sats() {
+        let mut asks = BTreeMap::new();
+        asks.insert(OrderPrice::from(Decimal::from(20_000)), Decimal::from(2));
+        let book = CompleteOrderBook {
+            asks,
+            bids: BTreeMap::new(),
+            timestamp: TimeStamp::now(),
+            checksum: 0,
+        };
+
+        let payload = OrderBookPayload::from(book);
+        let (price, quantity) = payload.asks.into_iter().next().unwrap();
+        assert_eq!(Decimal::from(price), Decimal::new(2, 2));
+        assert_eq!(Decimal::from(quantity), Decimal::from(1_000_000));
+    }
 }
@@ -1,9 +1,13 @@
 mod config;
 
-use futures::stream::StreamExt;
+use chrono::Utc;
+use futures::stream::{Stream, StreamExt};
 use rust_decimal::Decimal;
+use tokio::sync::broadcast;
 use tracing::{info_span, instrument, Instrument};
 
+use std::sync::Arc;
+
 use shared::{
     health::HealthCheckTrigger,
     payload::{PriceStreamPayload, BITFINEX_EXCHANGE_ID, OKEX_EXCHANGE_ID},
@@ -12,14 +16,27 @@ use shared::{
 
 use crate::{
     cache_config::ExchangePriceCacheConfig, exchange_tick_cache::ExchangeTickCache,
-    price_mixer::PriceMixer,
+    fallback_price_provider::FallbackPriceProvider, mixer_config::PriceMixerConfig,
+    order_book_cache::OrderBookCache, price_mixer::PriceMixer,
 };
-pub use crate::{currency::*, error::*, fee_calculator::*};
+pub use crate::{currency::*, error::*, fee_calculator::*, price_mixer::PriceSource, quotes::*};
 pub use config::*;
 
+/// Mixed prices published to streaming subscribers whenever a provider is updated.
+#[derive(Debug, Clone)]
+pub struct PriceUpdate {
+    pub mid_price_of_one_sat: UsdCents,
+    pub bid_price_of_one_sat: UsdCents,
+    pub ask_price_of_one_sat: UsdCents,
+    pub timestamp: chrono::DateTime<Utc>,
+}
+
 pub struct PriceApp {
-    price_mixer: PriceMixer,
+    price_mixer: Arc<PriceMixer>,
+    price_updates: broadcast::Sender<()>,
     fee_calculator: FeeCalculator,
+    quotes: Option<Quotes>,
+    quotes_config: QuotesConfig,
 }
 
 impl PriceApp {
@@ -30,6 +47,10 @@ impl PriceApp {
         subscriber: memory::Subscriber<PriceStreamPayload>,
         price_cache_config: ExchangePriceCacheConfig,
         exchange_weights: ExchangeWeights,
+        mixer_config: PriceMixerConfig,
+        okex_order_book: bool,
+        quotes_config: QuotesConfig,
+        pool: Option<sqlx::PgPool>,
     ) -> Result<Self, PriceAppError> {
         let health_subscriber = subscriber.resubscribe();
         tokio::spawn(async move {
@@ -42,37 +63,89 @@ impl PriceApp {
             }
         });
 
-        let mut price_mixer = PriceMixer::new();
+        let mut price_mixer = PriceMixer::new(mixer_config);
+        let (price_updates, _) = broadcast::channel(1);
 
         if let Some(weight) = exchange_weights.okex {
             if weight > Decimal::ZERO {
                 let okex_price_cache = ExchangeTickCache::new(price_cache_config.clone());
-                Self::subscribe_okex(subscriber.resubscribe(), okex_price_cache.clone()).await?;
-                price_mixer.add_provider(OKEX_EXCHANGE_ID, okex_price_cache, weight);
+                Self::subscribe_okex(
+                    subscriber.resubscribe(),
+                    okex_price_cache.clone(),
+                    price_updates.clone(),
+                )
+                .await?;
+                if okex_order_book {
+                    let okex_order_book_cache = OrderBookCache::new(price_cache_config.clone());
+                    Self::subscribe_okex_order_book(
+                        subscriber.resubscribe(),
+                        okex_order_book_cache.clone(),
+                        price_updates.clone(),
+                    )
+                    .await?;
+                    // Ticks keep okex priced while the book is stale or not deep enough
+                    price_mixer.add_provider(
+                        OKEX_EXCHANGE_ID,
+                        FallbackPriceProvider::new(okex_order_book_cache, okex_price_cache),
+                        weight,
+                    );
+                } else {
+                    price_mixer.add_provider(OKEX_EXCHANGE_ID, okex_price_cache, weight);
+                }
             }
         }
 
         if let Some(weight) = exchange_weights.bitfinex {
             if weight > Decimal::ZERO {
                 let bitfinex_price_cache = ExchangeTickCache::new(price_cache_config.clone());
-                Self::subscribe_bitfinex(subscriber.resubscribe(), bitfinex_price_cache.clone())
-                    .await?;
+                Self::subscribe_bitfinex(
+                    subscriber.resubscribe(),
+                    bitfinex_price_cache.clone(),
+                    price_updates.clone(),
+                )
+                .await?;
                 price_mixer.add_provider(BITFINEX_EXCHANGE_ID, bitfinex_price_cache, weight);
             }
         }
 
+        let quotes = match pool {
+            Some(pool) if quotes_config.enabled => {
+                let quotes = Quotes::new(pool, &quotes_config)?;
+                Self::spawn_quotes_cleanup(quotes.clone(), quotes_config.clone());
+                Some(quotes)
+            }
+            _ => None,
+        };
+
         let fee_calculator = FeeCalculator::new(fee_calc_cfg);
         let app = Self {
-            price_mixer,
+            price_mixer: Arc::new(price_mixer),
+            price_updates,
             fee_calculator,
+            quotes,
+            quotes_config,
         };
 
         Ok(app)
     }
 
+    fn spawn_quotes_cleanup(quotes: Quotes, config: QuotesConfig) {
+        let interval = config
+            .cleanup_interval
+            .to_std()
+            .unwrap_or(std::time::Duration::from_secs(600));
+        tokio::spawn(async move {
+            loop {
+                let _ = quotes.delete_expired(config.expired_retention).await;
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
     async fn subscribe_okex(
         mut subscriber: memory::Subscriber<PriceStreamPayload>,
         price_cache: ExchangeTickCache,
+        price_updates: broadcast::Sender<()>,
     ) -> Result<(), PriceAppError> {
         tokio::spawn(async move {
             while let Some(msg) = subscriber.next().await {
@@ -87,6 +160,34 @@ impl PriceApp {
                         price_cache
                             .apply_update(price_msg, msg.meta.correlation_id)
                             .await;
+                        let _ = price_updates.send(());
+                    }
+                    .instrument(span)
+                    .await;
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn subscribe_okex_order_book(
+        mut subscriber: memory::Subscriber<PriceStreamPayload>,
+        order_book_cache: OrderBookCache,
+        price_updates: broadcast::Sender<()>,
+    ) -> Result<(), PriceAppError> {
+        tokio::spawn(async move {
+            while let Some(msg) = subscriber.next().await {
+                if let PriceStreamPayload::OkexBtcUsdSwapOrderBookPayload(book) = msg.payload {
+                    let span = info_span!(
+                        "price_server.okex_order_book_received",
+                        message_type = %msg.payload_type,
+                        correlation_id = %msg.meta.correlation_id
+                    );
+                    shared::tracing::inject_tracing_data(&span, &msg.meta.tracing_data);
+                    async {
+                        order_book_cache.apply_update(book).await;
+                        let _ = price_updates.send(());
                     }
                     .instrument(span)
                     .await;
@@ -100,6 +201,7 @@ impl PriceApp {
     async fn subscribe_bitfinex(
         mut subscriber: memory::Subscriber<PriceStreamPayload>,
         price_cache: ExchangeTickCache,
+        price_updates: broadcast::Sender<()>,
     ) -> Result<(), PriceAppError> {
         tokio::spawn(async move {
             while let Some(msg) = subscriber.next().await {
@@ -114,6 +216,7 @@ impl PriceApp {
                         price_cache
                             .apply_update(price_msg, msg.meta.correlation_id)
                             .await;
+                        let _ = price_updates.send(());
                     }
                     .instrument(span)
                     .await;
@@ -124,6 +227,13 @@ impl PriceApp {
         Ok(())
     }
 
+    async fn current_fee_calculator(&self) -> FeeCalculator {
+        match self.price_mixer.realized_volatility().await {
+            Some(volatility) => self.fee_calculator.with_volatility(volatility),
+            None => self.fee_calculator.clone(),
+        }
+    }
+
     #[instrument(name = "price_server.get_cents_from_sats_for_immediate_buy", skip_all, fields(correlation_id, amount = %sats.amount()), ret, err)]
     pub async fn get_cents_from_sats_for_immediate_buy(
         &self,
@@ -131,11 +241,15 @@ impl PriceApp {
     ) -> Result<UsdCents, PriceAppError> {
         let cents = UsdCents::from_decimal(
             self.price_mixer
-                .apply(|p| *p.buy_usd().cents_from_sats(sats.clone()).amount())
+                .apply(|p| Ok(*p.buy_usd().cents_from_sats(sats.clone())?.amount()))
                 .await?,
         );
 
-        Ok(self.fee_calculator.decrease_by_immediate_fee(cents).floor())
+        Ok(self
+            .current_fee_calculator()
+            .await
+            .decrease_by_immediate_fee(cents)
+            .floor())
     }
 
     #[instrument(name = "price_server.get_cents_from_sats_for_immediate_sell", skip_all, fields(correlation_id, amount = %sats.amount()), ret, err)]
@@ -145,36 +259,50 @@ impl PriceApp {
     ) -> Result<UsdCents, PriceAppError> {
         let cents = UsdCents::from_decimal(
             self.price_mixer
-                .apply(|p| *p.sell_usd().cents_from_sats(sats.clone()).amount())
+                .apply(|p| Ok(*p.sell_usd().cents_from_sats(sats.clone())?.amount()))
                 .await?,
         );
-        Ok(self.fee_calculator.increase_by_immediate_fee(cents).ceil())
+        Ok(self
+            .current_fee_calculator()
+            .await
+            .increase_by_immediate_fee(cents)
+            .ceil())
     }
 
-    #[instrument(name = "price_server.get_cents_from_sats_for_future_buy", skip_all, fields(correlation_id, amount = %sats.amount()), ret, err)]
+    #[instrument(name = "price_server.get_cents_from_sats_for_future_buy", skip_all, fields(correlation_id, amount = %sats.amount(), time_in_seconds = %time_in_seconds), ret, err)]
     pub async fn get_cents_from_sats_for_future_buy(
         &self,
         sats: Sats,
+        time_in_seconds: u64,
     ) -> Result<UsdCents, PriceAppError> {
         let cents = UsdCents::from_decimal(
             self.price_mixer
-                .apply(|p| *p.buy_usd().cents_from_sats(sats.clone()).amount())
+                .apply(|p| Ok(*p.buy_usd().cents_from_sats(sats.clone())?.amount()))
                 .await?,
         );
-        Ok(self.fee_calculator.decrease_by_delayed_fee(cents).floor())
+        Ok(self
+            .current_fee_calculator()
+            .await
+            .decrease_by_delayed_fee(time_in_seconds, cents)
+            .floor())
     }
 
-    #[instrument(name = "price_server.get_cents_from_sats_for_future_sell", skip_all, fields(correlation_id, amount = %sats.amount()), ret, err)]
+    #[instrument(name = "price_server.get_cents_from_sats_for_future_sell", skip_all, fields(correlation_id, amount = %sats.amount(), time_in_seconds = %time_in_seconds), ret, err)]
     pub async fn get_cents_from_sats_for_future_sell(
         &self,
         sats: Sats,
+        time_in_seconds: u64,
     ) -> Result<UsdCents, PriceAppError> {
         let cents = UsdCents::from_decimal(
             self.price_mixer
-                .apply(|p| *p.sell_usd().cents_from_sats(sats.clone()).amount())
+                .apply(|p| Ok(*p.sell_usd().cents_from_sats(sats.clone())?.amount()))
                 .await?,
         );
-        Ok(self.fee_calculator.increase_by_delayed_fee(cents).ceil())
+        Ok(self
+            .current_fee_calculator()
+            .await
+            .increase_by_delayed_fee(time_in_seconds, cents)
+            .ceil())
     }
 
     #[instrument(name = "price_server.get_sats_from_cents_for_immediate_buy", skip_all, fields(correlation_id, amount = %cents.amount()), ret, err)]
@@ -184,10 +312,14 @@ impl PriceApp {
     ) -> Result<Sats, PriceAppError> {
         let sats = Sats::from_decimal(
             self.price_mixer
-                .apply(|p| *p.buy_usd().sats_from_cents(cents.clone()).amount())
+                .apply(|p| Ok(*p.buy_usd().sats_from_cents(cents.clone())?.amount()))
                 .await?,
         );
-        Ok(self.fee_calculator.increase_by_immediate_fee(sats).ceil())
+        Ok(self
+            .current_fee_calculator()
+            .await
+            .increase_by_immediate_fee(sats)
+            .ceil())
     }
 
     #[instrument(name = "price_server.get_sats_from_cents_for_immediate_sell", skip_all, fields(correlation_id, amount = %cents.amount()), ret, err)]
@@ -197,38 +329,203 @@ impl PriceApp {
     ) -> Result<Sats, PriceAppError> {
         let sats = Sats::from_decimal(
             self.price_mixer
-                .apply(|p| *p.sell_usd().sats_from_cents(cents.clone()).amount())
+                .apply(|p| Ok(*p.sell_usd().sats_from_cents(cents.clone())?.amount()))
                 .await?,
         );
 
-        Ok(self.fee_calculator.decrease_by_immediate_fee(sats).floor())
+        Ok(self
+            .current_fee_calculator()
+            .await
+            .decrease_by_immediate_fee(sats)
+            .floor())
     }
 
-    #[instrument(name = "price_server.get_sats_from_cents_for_future_buy", skip_all, fields(correlation_id, amount = %cents.amount()), ret, err)]
+    #[instrument(name = "price_server.get_sats_from_cents_for_future_buy", skip_all, fields(correlation_id, amount = %cents.amount(), time_in_seconds = %time_in_seconds), ret, err)]
     pub async fn get_sats_from_cents_for_future_buy(
         &self,
         cents: UsdCents,
+        time_in_seconds: u64,
     ) -> Result<Sats, PriceAppError> {
         let sats = Sats::from_decimal(
             self.price_mixer
-                .apply(|p| *p.buy_usd().sats_from_cents(cents.clone()).amount())
+                .apply(|p| Ok(*p.buy_usd().sats_from_cents(cents.clone())?.amount()))
                 .await?,
         );
 
-        Ok(self.fee_calculator.increase_by_delayed_fee(sats).ceil())
+        Ok(self
+            .current_fee_calculator()
+            .await
+            .increase_by_delayed_fee(time_in_seconds, sats)
+            .ceil())
     }
 
-    #[instrument(name = "price_server.get_sats_from_cents_for_future_sell", skip_all, fields(correlation_id, amount = %cents.amount()), ret, err)]
+    #[instrument(name = "price_server.get_sats_from_cents_for_future_sell", skip_all, fields(correlation_id, amount = %cents.amount(), time_in_seconds = %time_in_seconds), ret, err)]
     pub async fn get_sats_from_cents_for_future_sell(
         &self,
         cents: UsdCents,
+        time_in_seconds: u64,
     ) -> Result<Sats, PriceAppError> {
         let sats = Sats::from_decimal(
             self.price_mixer
-                .apply(|p| *p.sell_usd().sats_from_cents(cents.clone()).amount())
+                .apply(|p| Ok(*p.sell_usd().sats_from_cents(cents.clone())?.amount()))
                 .await?,
         );
-        Ok(self.fee_calculator.decrease_by_delayed_fee(sats).floor())
+        Ok(self
+            .current_fee_calculator()
+            .await
+            .decrease_by_delayed_fee(time_in_seconds, sats)
+            .floor())
+    }
+
+    #[instrument(name = "price_server.get_quote", skip_all, fields(correlation_id, direction = %direction), ret, err)]
+    pub async fn get_quote(
+        &self,
+        direction: QuoteDirection,
+        amount: QuoteAmount,
+    ) -> Result<Quote, PriceAppError> {
+        let quotes = self
+            .quotes
+            .as_ref()
+            .ok_or(PriceAppError::QuotesUnavailable)?;
+        let fee_calculator = self.current_fee_calculator().await;
+        let (amount_in_satoshis, amount_in_cents, fee_amount_in_cents) = match (direction, amount) {
+            (QuoteDirection::BuyCents, QuoteAmount::Sats(sats)) => {
+                let gross = self
+                    .price_mixer
+                    .apply(|p| Ok(*p.buy_usd().cents_from_sats(sats.clone())?.amount()))
+                    .await?;
+                let cents = fee_calculator
+                    .decrease_by_immediate_fee(UsdCents::from_decimal(gross))
+                    .floor();
+                (*sats.amount(), *cents.amount(), gross - cents.amount())
+            }
+            (QuoteDirection::SellCents, QuoteAmount::Sats(sats)) => {
+                let gross = self
+                    .price_mixer
+                    .apply(|p| Ok(*p.sell_usd().cents_from_sats(sats.clone())?.amount()))
+                    .await?;
+                let cents = fee_calculator
+                    .increase_by_immediate_fee(UsdCents::from_decimal(gross))
+                    .ceil();
+                (*sats.amount(), *cents.amount(), cents.amount() - gross)
+            }
+            (QuoteDirection::BuyCents, QuoteAmount::Cents(cents)) => {
+                let gross = self
+                    .price_mixer
+                    .apply(|p| Ok(*p.buy_usd().sats_from_cents(cents.clone())?.amount()))
+                    .await?;
+                let sats = fee_calculator
+                    .increase_by_immediate_fee(Sats::from_decimal(gross))
+                    .ceil();
+                let fee_in_sats = sats.amount() - gross;
+                (
+                    *sats.amount(),
+                    *cents.amount(),
+                    Self::sats_fee_in_cents(fee_in_sats, gross, *cents.amount()),
+                )
+            }
+            (QuoteDirection::SellCents, QuoteAmount::Cents(cents)) => {
+                let gross = self
+                    .price_mixer
+                    .apply(|p| Ok(*p.sell_usd().sats_from_cents(cents.clone())?.amount()))
+                    .await?;
+                let sats = fee_calculator
+                    .decrease_by_immediate_fee(Sats::from_decimal(gross))
+                    .floor();
+                let fee_in_sats = gross - sats.amount();
+                (
+                    *sats.amount(),
+                    *cents.amount(),
+                    Self::sats_fee_in_cents(fee_in_sats, gross, *cents.amount()),
+                )
+            }
+        };
+
+        quotes
+            .create(NewQuote {
+                direction,
+                amount_in_satoshis,
+                amount_in_cents,
+                fee_rate: fee_calculator.immediate_rate(),
+                fee_amount_in_cents,
+                expires_at: Utc::now() + self.quotes_config.expiration_interval,
+            })
+            .await
+    }
+
+    /// Converts a fee withheld in sats to cents at the rate of the quote
+    /// (`cents` are worth `gross_sats` before fees).
+    fn sats_fee_in_cents(fee_in_sats: Decimal, gross_sats: Decimal, cents: Decimal) -> Decimal {
+        if gross_sats.is_zero() {
+            return Decimal::ZERO;
+        }
+        fee_in_sats * cents / gross_sats
+    }
+
+    #[instrument(name = "price_server.accept_quote", skip(self, signature), ret, err)]
+    pub async fn accept_quote(
+        &self,
+        id: uuid::Uuid,
+        signature: &str,
+    ) -> Result<Quote, PriceAppError> {
+        self.quotes
+            .as_ref()
+            .ok_or(PriceAppError::QuotesUnavailable)?
+            .accept(id, signature)
+            .await
+    }
+
+    #[instrument(name = "price_server.get_price_sources", skip_all)]
+    pub async fn get_price_sources(&self) -> Vec<PriceSource> {
+        self.price_mixer.sources().await
+    }
+
+    /// Streams the mixed prices after every provider update, at most once per `min_interval`.
+    /// Updates arriving in between are coalesced into the next one.
+    pub fn subscribe_prices(
+        &self,
+        min_interval: std::time::Duration,
+    ) -> impl Stream<Item = PriceUpdate> + Send + 'static {
+        let price_mixer = Arc::clone(&self.price_mixer);
+        let updates = self.price_updates.subscribe();
+        futures::stream::unfold(
+            (updates, None::<tokio::time::Instant>),
+            move |(mut updates, last_sent)| {
+                let price_mixer = Arc::clone(&price_mixer);
+                async move {
+                    loop {
+                        if let Err(broadcast::error::RecvError::Closed) = updates.recv().await {
+                            return None;
+                        }
+                        if let Some(last_sent) = last_sent {
+                            tokio::time::sleep_until(last_sent + min_interval).await;
+                        }
+                        if let Ok(update) = Self::current_prices(&price_mixer).await {
+                            return Some((update, (updates, Some(tokio::time::Instant::now()))));
+                        }
+                    }
+                }
+            },
+        )
+    }
+
+    async fn current_prices(price_mixer: &PriceMixer) -> Result<PriceUpdate, PriceAppError> {
+        let snapshot = price_mixer.snapshot().await;
+        let mid = price_mixer
+            .apply_to(&snapshot, |p| Ok(*p.mid_price_of_one_sat()?.amount()))
+            .await?;
+        let bid = price_mixer
+            .apply_to(&snapshot, |p| Ok(*p.bid_price_of_one_sat()?.amount()))
+            .await?;
+        let ask = price_mixer
+            .apply_to(&snapshot, |p| Ok(*p.ask_price_of_one_sat()?.amount()))
+            .await?;
+        Ok(PriceUpdate {
+            mid_price_of_one_sat: UsdCents::from_decimal(mid),
+            bid_price_of_one_sat: UsdCents::from_decimal(bid),
+            ask_price_of_one_sat: UsdCents::from_decimal(ask),
+            timestamp: Utc::now(),
+        })
     }
 
     #[instrument(
@@ -241,7 +538,7 @@ impl PriceApp {
     pub async fn get_cents_per_sat_exchange_mid_rate(&self) -> Result<f64, PriceAppError> {
         let cents_per_sat = self
             .price_mixer
-            .apply(|p| *p.mid_price_of_one_sat().amount())
+            .apply(|p| Ok(*p.mid_price_of_one_sat()?.amount()))
             .await?;
         Ok(f64::try_from(cents_per_sat)?)
     }
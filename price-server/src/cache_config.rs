@@ -10,17 +10,25 @@ pub struct ExchangePriceCacheConfig {
     pub stale_after: Duration,
     #[serde(default)]
     pub dev_mock_price_btc_in_usd: Option<Decimal>,
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    #[serde(default = "default_volatility_window")]
+    pub volatility_window: Duration,
 }
 
 fn default_stale_after_duration() -> Duration {
     Duration::from_std(std::time::Duration::from_secs(30)).unwrap()
 }
 
+fn default_volatility_window() -> Duration {
+    Duration::from_std(std::time::Duration::from_secs(300)).unwrap()
+}
+
 impl Default for ExchangePriceCacheConfig {
     fn default() -> Self {
         ExchangePriceCacheConfig {
             stale_after: default_stale_after_duration(),
             dev_mock_price_btc_in_usd: None,
+            volatility_window: default_volatility_window(),
         }
     }
 }
@@ -5,6 +5,8 @@ use thiserror::Error;
 use rust_decimal::Decimal;
 use rusty_money::Money;
 
+use crate::error::ExchangePriceCacheError;
+
 #[derive(Error, Debug)]
 pub enum CurrencyError {
     #[error("CurrencyError: {0}")]
@@ -108,8 +110,8 @@ currency! { UsdCents, USD_CENT }
 currency! { Sats, SATOSHI }
 
 pub trait VolumePicker {
-    fn cents_from_sats(&self, volume: Sats) -> UsdCents;
-    fn sats_from_cents(&self, volume: UsdCents) -> Sats;
+    fn cents_from_sats(&self, volume: Sats) -> Result<UsdCents, ExchangePriceCacheError>;
+    fn sats_from_cents(&self, volume: UsdCents) -> Result<Sats, ExchangePriceCacheError>;
 }
 
 pub struct CurrencyConverter<'a> {
@@ -117,12 +119,12 @@ pub struct CurrencyConverter<'a> {
 }
 
 impl<'a> VolumePicker for CurrencyConverter<'a> {
-    fn cents_from_sats(&self, volume: Sats) -> UsdCents {
-        UsdCents::from_decimal(volume.amount() * self.price_of_one_sat.amount())
+    fn cents_from_sats(&self, volume: Sats) -> Result<UsdCents, ExchangePriceCacheError> {
+        Ok(CurrencyConverter::cents_from_sats(self, volume))
     }
 
-    fn sats_from_cents(&self, volume: UsdCents) -> Sats {
-        Sats::from_decimal(volume.amount() / self.price_of_one_sat.amount())
+    fn sats_from_cents(&self, volume: UsdCents) -> Result<Sats, ExchangePriceCacheError> {
+        Ok(CurrencyConverter::sats_from_cents(self, volume))
     }
 }
 
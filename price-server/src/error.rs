@@ -14,12 +14,34 @@ pub enum PriceAppError {
     ExchangePriceCacheError(#[from] ExchangePriceCacheError),
     #[error("PriceAppError - DecimalConversion: {0}")]
     DecimalConversion(#[from] rust_decimal::Error),
+    #[error("PriceAppError - Sqlx: {0}")]
+    Sqlx(#[from] sqlx::Error),
+    #[error("PriceAppError - QuoteNotFound: {0}")]
+    QuoteNotFound(uuid::Uuid),
+    #[error("PriceAppError - QuoteExpired: {0}")]
+    QuoteExpired(uuid::Uuid),
+    #[error("PriceAppError - QuoteAlreadyAccepted: {0}")]
+    QuoteAlreadyAccepted(uuid::Uuid),
+    #[error("PriceAppError - UnknownQuoteDirection: {0}")]
+    UnknownQuoteDirection(String),
+    #[error("PriceAppError - InvalidQuoteSignature: {0}")]
+    InvalidQuoteSignature(uuid::Uuid),
+    #[error("PriceAppError - QuotesUnavailable: quotes are not enabled")]
+    QuotesUnavailable,
+    #[error("PriceAppError - QuotesSigningSecretMissing")]
+    QuotesSigningSecretMissing,
 }
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum ExchangePriceCacheError {
     #[error("StalePrice: last update was at {0}")]
     StalePrice(TimeStamp),
     #[error("No price data available")]
     NoPriceAvailable,
+    #[error("Order book is not deep enough to convert the volume")]
+    InsufficientDepth,
+    #[error("Only {0} healthy price providers, {1} required")]
+    NotEnoughHealthyProviders(usize, usize),
+    #[error("Only {0} healthy price providers, {1} required to reject outliers")]
+    NotEnoughProvidersForOutlierRejection(usize, usize),
 }
@@ -1,11 +1,15 @@
 use chrono::Duration;
 use opentelemetry::trace::{SpanContext, TraceContextExt};
+use rust_decimal::Decimal;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::Span;
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
-use crate::{cache_config::ExchangePriceCacheConfig, currency::*, error::*, price_mixer::*};
+use crate::{
+    cache_config::ExchangePriceCacheConfig, currency::*, error::*, price_mixer::*,
+    volatility::MidPriceHistory,
+};
 use shared::{payload::*, pubsub::CorrelationId, time::*};
 
 #[derive(Clone)]
@@ -19,6 +23,7 @@ impl ExchangeTickCache {
         Self {
             inner: Arc::new(RwLock::new(ExchangePriceCacheInner::new(
                 config.stale_after,
+                config.volatility_window,
             ))),
             config,
         }
@@ -34,7 +39,8 @@ impl PriceProvider for ExchangeTickCache {
     async fn latest(&self) -> Result<Box<dyn SidePicker>, ExchangePriceCacheError> {
         if let Some(mock_price) = self.config.dev_mock_price_btc_in_usd {
             let price = PriceRatioRaw::from_one_btc_in_usd_price(mock_price);
-            let cent_price = UsdCents::try_from(price).expect("couldn't create mack UsdCents");
+            let cent_price =
+                UsdCents::try_from(price).map_err(|_| ExchangePriceCacheError::NoPriceAvailable)?;
             return Ok(Box::new(BtcSatTick {
                 timestamp: TimeStamp::now(),
                 correlation_id: CorrelationId::new(),
@@ -54,6 +60,13 @@ impl PriceProvider for ExchangeTickCache {
         );
         Ok(Box::new(tick))
     }
+
+    async fn realized_volatility(&self) -> Option<Decimal> {
+        if self.config.dev_mock_price_btc_in_usd.is_some() {
+            return None;
+        }
+        self.inner.read().await.realized_volatility()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -74,21 +87,35 @@ impl SidePicker for BtcSatTick {
         Box::new(CurrencyConverter::new(&self.ask_price_of_one_sat))
     }
 
-    fn mid_price_of_one_sat(&self) -> UsdCents {
-        (&self.bid_price_of_one_sat + &self.ask_price_of_one_sat) / 2
+    fn mid_price_of_one_sat(&self) -> Result<UsdCents, ExchangePriceCacheError> {
+        Ok((&self.bid_price_of_one_sat + &self.ask_price_of_one_sat) / 2)
+    }
+
+    fn bid_price_of_one_sat(&self) -> Result<UsdCents, ExchangePriceCacheError> {
+        Ok(self.bid_price_of_one_sat.clone())
+    }
+
+    fn ask_price_of_one_sat(&self) -> Result<UsdCents, ExchangePriceCacheError> {
+        Ok(self.ask_price_of_one_sat.clone())
+    }
+
+    fn timestamp(&self) -> TimeStamp {
+        self.timestamp
     }
 }
 
 struct ExchangePriceCacheInner {
     stale_after: Duration,
     tick: Option<BtcSatTick>,
+    mid_prices: MidPriceHistory,
 }
 
 impl ExchangePriceCacheInner {
-    fn new(stale_after: Duration) -> Self {
+    fn new(stale_after: Duration, volatility_window: Duration) -> Self {
         Self {
             stale_after,
             tick: None,
+            mid_prices: MidPriceHistory::new(volatility_window),
         }
     }
 
@@ -102,16 +129,24 @@ impl ExchangePriceCacheInner {
             UsdCents::try_from(payload.ask_price),
             UsdCents::try_from(payload.bid_price),
         ) {
-            self.tick = Some(BtcSatTick {
+            let tick = BtcSatTick {
                 timestamp: payload.timestamp,
                 correlation_id: id,
                 span_context: Span::current().context().span().span_context().clone(),
                 ask_price_of_one_sat,
                 bid_price_of_one_sat,
-            });
+            };
+            if let Ok(mid_price) = tick.mid_price_of_one_sat() {
+                self.mid_prices.record(tick.timestamp, *mid_price.amount());
+            }
+            self.tick = Some(tick);
         }
     }
 
+    fn realized_volatility(&self) -> Option<Decimal> {
+        self.mid_prices.realized_volatility()
+    }
+
     fn latest_tick(&self) -> Result<BtcSatTick, ExchangePriceCacheError> {
         if let Some(ref tick) = self.tick {
             if tick.timestamp.duration_since() > self.stale_after {
@@ -137,6 +172,9 @@ mod tests {
             ask_price_of_one_sat: UsdCents::from_major(10000),
         };
 
-        assert_eq!(UsdCents::from_major(7500), _tick.mid_price_of_one_sat());
+        assert_eq!(
+            UsdCents::from_major(7500),
+            _tick.mid_price_of_one_sat().unwrap()
+        );
     }
 }
@@ -0,0 +1,179 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use shared::time::TimeStamp;
+
+use crate::{currency::*, error::ExchangePriceCacheError, price_mixer::*};
+
+/// Serves the prices of `primary` and switches to `fallback` when it has no price
+/// or can't convert a volume (eg. an order book that is not deep enough).
+pub struct FallbackPriceProvider<P, F> {
+    primary: P,
+    fallback: F,
+}
+
+impl<P, F> FallbackPriceProvider<P, F> {
+    pub fn new(primary: P, fallback: F) -> Self {
+        Self { primary, fallback }
+    }
+}
+
+#[async_trait]
+impl<P, F> PriceProvider for FallbackPriceProvider<P, F>
+where
+    P: PriceProvider + Sync + Send,
+    F: PriceProvider + Sync + Send,
+{
+    async fn latest(&self) -> Result<Box<dyn SidePicker>, ExchangePriceCacheError> {
+        match self.primary.latest().await {
+            Ok(primary) => Ok(Box::new(FallbackSidePicker {
+                primary,
+                fallback: self.fallback.latest().await.ok(),
+            })),
+            Err(_) => self.fallback.latest().await,
+        }
+    }
+
+    async fn realized_volatility(&self) -> Option<Decimal> {
+        match self.primary.realized_volatility().await {
+            Some(volatility) => Some(volatility),
+            None => self.fallback.realized_volatility().await,
+        }
+    }
+}
+
+struct FallbackSidePicker {
+    primary: Box<dyn SidePicker>,
+    fallback: Option<Box<dyn SidePicker>>,
+}
+
+impl SidePicker for FallbackSidePicker {
+    fn buy_usd<'a>(&'a self) -> Box<dyn VolumePicker + 'a> {
+        Box::new(FallbackVolumePicker {
+            primary: self.primary.buy_usd(),
+            fallback: self.fallback.as_ref().map(|fallback| fallback.buy_usd()),
+        })
+    }
+
+    fn sell_usd<'a>(&'a self) -> Box<dyn VolumePicker + 'a> {
+        Box::new(FallbackVolumePicker {
+            primary: self.primary.sell_usd(),
+            fallback: self.fallback.as_ref().map(|fallback| fallback.sell_usd()),
+        })
+    }
+
+    fn mid_price_of_one_sat(&self) -> Result<UsdCents, ExchangePriceCacheError> {
+        self.primary.mid_price_of_one_sat()
+    }
+
+    fn bid_price_of_one_sat(&self) -> Result<UsdCents, ExchangePriceCacheError> {
+        self.primary.bid_price_of_one_sat()
+    }
+
+    fn ask_price_of_one_sat(&self) -> Result<UsdCents, ExchangePriceCacheError> {
+        self.primary.ask_price_of_one_sat()
+    }
+
+    fn timestamp(&self) -> TimeStamp {
+        self.primary.timestamp()
+    }
+}
+
+struct FallbackVolumePicker<'a> {
+    primary: Box<dyn VolumePicker + 'a>,
+    fallback: Option<Box<dyn VolumePicker + 'a>>,
+}
+
+impl<'a> VolumePicker for FallbackVolumePicker<'a> {
+    fn cents_from_sats(&self, volume: Sats) -> Result<UsdCents, ExchangePriceCacheError> {
+        match (self.primary.cents_from_sats(volume.clone()), &self.fallback) {
+            (Err(_), Some(fallback)) => fallback.cents_from_sats(volume),
+            (res, _) => res,
+        }
+    }
+
+    fn sats_from_cents(&self, volume: UsdCents) -> Result<Sats, ExchangePriceCacheError> {
+        match (self.primary.sats_from_cents(volume.clone()), &self.fallback) {
+            (Err(_), Some(fallback)) => fallback.sats_from_cents(volume),
+            (res, _) => res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+    use shared::{
+        payload::{
+            ExchangeIdRaw, InstrumentIdRaw, OrderBookPayload, PriceMessagePayload, PriceRatioRaw,
+            PriceRaw, QuantityRaw,
+        },
+        pubsub::CorrelationId,
+        time::TimeStamp,
+    };
+
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{
+        cache_config::ExchangePriceCacheConfig, exchange_tick_cache::ExchangeTickCache,
+        order_book_cache::OrderBookCache,
+    };
+
+    fn order_book_payload() -> OrderBookPayload {
+        let mut asks = BTreeMap::new();
+        asks.insert(PriceRaw::from(dec!(0.03)), QuantityRaw::from(dec!(100)));
+        let mut bids = BTreeMap::new();
+        bids.insert(PriceRaw::from(dec!(0.02)), QuantityRaw::from(dec!(100)));
+        OrderBookPayload {
+            asks,
+            bids,
+            timestamp: TimeStamp::now(),
+            exchange: ExchangeIdRaw::from("okex"),
+        }
+    }
+
+    fn tick_payload() -> PriceMessagePayload {
+        PriceMessagePayload {
+            exchange: ExchangeIdRaw::from("okex"),
+            instrument_id: InstrumentIdRaw::from("BTC-USD-SWAP"),
+            timestamp: TimeStamp::now(),
+            bid_price: PriceRatioRaw::from_one_btc_in_usd_price(dec!(1000)),
+            ask_price: PriceRatioRaw::from_one_btc_in_usd_price(dec!(1000)),
+        }
+    }
+
+    #[tokio::test]
+    async fn falls_back_beyond_order_book_depth() -> anyhow::Result<()> {
+        let order_book = OrderBookCache::new(ExchangePriceCacheConfig::default());
+        order_book.apply_update(order_book_payload()).await;
+        let ticks = ExchangeTickCache::new(ExchangePriceCacheConfig::default());
+        ticks
+            .apply_update(tick_payload(), CorrelationId::new())
+            .await;
+        let provider = FallbackPriceProvider::new(order_book, ticks);
+
+        let latest = provider.latest().await?;
+        let cents = latest.buy_usd().cents_from_sats(Sats::from_major(10))?;
+        assert_eq!(cents, UsdCents::from_decimal(dec!(0.2)));
+        let cents = latest.buy_usd().cents_from_sats(Sats::from_major(1000))?;
+        assert_eq!(cents, UsdCents::from_decimal(dec!(1)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn falls_back_without_order_book() -> anyhow::Result<()> {
+        let order_book = OrderBookCache::new(ExchangePriceCacheConfig::default());
+        let ticks = ExchangeTickCache::new(ExchangePriceCacheConfig::default());
+        ticks
+            .apply_update(tick_payload(), CorrelationId::new())
+            .await;
+        let provider = FallbackPriceProvider::new(order_book, ticks);
+
+        let latest = provider.latest().await?;
+        let cents = latest.sell_usd().cents_from_sats(Sats::from_major(1000))?;
+        assert_eq!(cents, UsdCents::from_decimal(dec!(1)));
+
+        Ok(())
+    }
+}
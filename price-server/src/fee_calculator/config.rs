@@ -10,6 +10,40 @@ pub struct FeeCalculatorConfig {
     pub immediate_fee_rate: Decimal,
     #[serde(default = "default_delayed_fee_rate")]
     pub delayed_fee_rate: Decimal,
+    #[serde(default)]
+    pub delayed_fee_term_structure: Vec<DelayedFeeTier>,
+    #[serde(default)]
+    pub dynamic_fee: DynamicFeeConfig,
+}
+
+/// Fee rate charged (on top of the base rate) for future quotes expiring within
+/// `max_time_in_seconds`. Expiries beyond the longest tier fall back to `delayed_fee_rate`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DelayedFeeTier {
+    pub max_time_in_seconds: u64,
+    pub fee_rate: Decimal,
+}
+
+/// Widens the spread in fast markets: the realized volatility of recent ticks times
+/// `volatility_multiplier` is added to every fee rate, up to `max_fee_rate`.
+/// The multiplier defaults to 0, ie. dynamic fees are disabled unless configured.
+/// As the volatility is the root mean square of tick returns, a multiplier of 1
+/// adds roughly one average tick move to the spread.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DynamicFeeConfig {
+    #[serde(default)]
+    pub volatility_multiplier: Decimal,
+    #[serde(default = "default_max_dynamic_fee_rate")]
+    pub max_fee_rate: Decimal,
+}
+
+impl Default for DynamicFeeConfig {
+    fn default() -> Self {
+        Self {
+            volatility_multiplier: Decimal::ZERO,
+            max_fee_rate: default_max_dynamic_fee_rate(),
+        }
+    }
 }
 
 fn default_base_fee_rate() -> Decimal {
@@ -24,12 +58,18 @@ fn default_delayed_fee_rate() -> Decimal {
     dec!(0.0007)
 }
 
+fn default_max_dynamic_fee_rate() -> Decimal {
+    dec!(0.005)
+}
+
 impl Default for FeeCalculatorConfig {
     fn default() -> Self {
         Self {
             base_fee_rate: default_base_fee_rate(),
             immediate_fee_rate: default_immediate_fee_rate(),
             delayed_fee_rate: default_delayed_fee_rate(),
+            delayed_fee_term_structure: Vec::new(),
+            dynamic_fee: DynamicFeeConfig::default(),
         }
     }
 }
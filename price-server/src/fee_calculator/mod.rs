@@ -6,9 +6,12 @@ use std::ops::Mul;
 
 pub use config::*;
 
+#[derive(Clone)]
 pub struct FeeCalculator {
     immediate_rate: Decimal,
     delayed_rate: Decimal,
+    delayed_rate_tiers: Vec<(u64, Decimal)>,
+    dynamic_fee: DynamicFeeConfig,
 }
 
 impl FeeCalculator {
@@ -17,14 +20,52 @@ impl FeeCalculator {
             base_fee_rate,
             immediate_fee_rate,
             delayed_fee_rate,
+            delayed_fee_term_structure,
+            dynamic_fee,
         }: FeeCalculatorConfig,
     ) -> Self {
+        let mut delayed_rate_tiers: Vec<_> = delayed_fee_term_structure
+            .into_iter()
+            .map(|tier| (tier.max_time_in_seconds, base_fee_rate + tier.fee_rate))
+            .collect();
+        delayed_rate_tiers.sort_by_key(|(max_time_in_seconds, _)| *max_time_in_seconds);
         Self {
             immediate_rate: base_fee_rate + immediate_fee_rate,
             delayed_rate: base_fee_rate + delayed_fee_rate,
+            delayed_rate_tiers,
+            dynamic_fee,
         }
     }
 
+    /// Returns a calculator with all rates widened according to the current volatility.
+    pub fn with_volatility(&self, volatility: Decimal) -> Self {
+        let dynamic_rate = (volatility * self.dynamic_fee.volatility_multiplier)
+            .max(Decimal::ZERO)
+            .min(self.dynamic_fee.max_fee_rate);
+        Self {
+            immediate_rate: self.immediate_rate + dynamic_rate,
+            delayed_rate: self.delayed_rate + dynamic_rate,
+            delayed_rate_tiers: self
+                .delayed_rate_tiers
+                .iter()
+                .map(|(max_time_in_seconds, rate)| (*max_time_in_seconds, rate + dynamic_rate))
+                .collect(),
+            dynamic_fee: self.dynamic_fee.clone(),
+        }
+    }
+
+    pub fn immediate_rate(&self) -> Decimal {
+        self.immediate_rate
+    }
+
+    fn delayed_rate(&self, time_in_seconds: u64) -> Decimal {
+        self.delayed_rate_tiers
+            .iter()
+            .find(|(max_time_in_seconds, _)| time_in_seconds <= *max_time_in_seconds)
+            .map(|(_, rate)| *rate)
+            .unwrap_or(self.delayed_rate)
+    }
+
     pub fn increase_by_immediate_fee<T: Mul<Decimal>>(
         &self,
         currency: T,
@@ -34,9 +75,10 @@ impl FeeCalculator {
 
     pub fn increase_by_delayed_fee<T: Mul<Decimal>>(
         &self,
+        time_in_seconds: u64,
         currency: T,
     ) -> <T as Mul<Decimal>>::Output {
-        currency * (dec!(1) + self.delayed_rate)
+        currency * (dec!(1) + self.delayed_rate(time_in_seconds))
     }
 
     pub fn decrease_by_immediate_fee<T: Mul<Decimal>>(
@@ -48,9 +90,10 @@ impl FeeCalculator {
 
     pub fn decrease_by_delayed_fee<T: Mul<Decimal>>(
         &self,
+        time_in_seconds: u64,
         currency: T,
     ) -> <T as Mul<Decimal>>::Output {
-        currency * (dec!(1) - self.delayed_rate)
+        currency * (dec!(1) - self.delayed_rate(time_in_seconds))
     }
 }
 
@@ -65,6 +108,8 @@ mod tests {
             base_fee_rate: dec!(0.001),
             immediate_fee_rate: dec!(0.01),
             delayed_fee_rate: dec!(0.1),
+            delayed_fee_term_structure: Vec::new(),
+            dynamic_fee: DynamicFeeConfig::default(),
         });
 
         let usd_in = UsdCents::from_major(10_000);
@@ -73,7 +118,7 @@ mod tests {
             UsdCents::from_major(10_000 - 110)
         );
         assert_eq!(
-            fees.decrease_by_delayed_fee(usd_in.clone()),
+            fees.decrease_by_delayed_fee(60, usd_in.clone()),
             UsdCents::from_major(10_000 - 1010)
         );
         assert_eq!(
@@ -81,8 +126,77 @@ mod tests {
             UsdCents::from_major(10_000 + 110)
         );
         assert_eq!(
-            fees.increase_by_delayed_fee(usd_in),
+            fees.increase_by_delayed_fee(60, usd_in),
             UsdCents::from_major(10_000 + 1010)
         );
     }
+
+    #[test]
+    fn delayed_fee_term_structure() {
+        let fees = FeeCalculator::new(FeeCalculatorConfig {
+            base_fee_rate: dec!(0.001),
+            immediate_fee_rate: dec!(0.01),
+            delayed_fee_rate: dec!(0.1),
+            delayed_fee_term_structure: vec![
+                DelayedFeeTier {
+                    max_time_in_seconds: 86_400,
+                    fee_rate: dec!(0.05),
+                },
+                DelayedFeeTier {
+                    max_time_in_seconds: 600,
+                    fee_rate: dec!(0.02),
+                },
+            ],
+            dynamic_fee: DynamicFeeConfig::default(),
+        });
+
+        let usd_in = UsdCents::from_major(10_000);
+        assert_eq!(
+            fees.decrease_by_delayed_fee(600, usd_in.clone()),
+            UsdCents::from_major(10_000 - 210)
+        );
+        assert_eq!(
+            fees.increase_by_delayed_fee(601, usd_in.clone()),
+            UsdCents::from_major(10_000 + 510)
+        );
+        assert_eq!(
+            fees.increase_by_delayed_fee(86_400, usd_in.clone()),
+            UsdCents::from_major(10_000 + 510)
+        );
+        assert_eq!(
+            fees.decrease_by_delayed_fee(86_401, usd_in),
+            UsdCents::from_major(10_000 - 1010)
+        );
+    }
+
+    #[test]
+    fn dynamic_fee_is_capped() {
+        let fees = FeeCalculator::new(FeeCalculatorConfig {
+            base_fee_rate: dec!(0.001),
+            immediate_fee_rate: dec!(0.01),
+            delayed_fee_rate: dec!(0.1),
+            delayed_fee_term_structure: Vec::new(),
+            dynamic_fee: DynamicFeeConfig {
+                volatility_multiplier: dec!(2),
+                max_fee_rate: dec!(0.05),
+            },
+        });
+
+        let usd_in = UsdCents::from_major(10_000);
+        assert_eq!(
+            fees.with_volatility(dec!(0.01))
+                .increase_by_immediate_fee(usd_in.clone()),
+            UsdCents::from_major(10_000 + 310)
+        );
+        assert_eq!(
+            fees.with_volatility(dec!(0.01))
+                .decrease_by_delayed_fee(60, usd_in.clone()),
+            UsdCents::from_major(10_000 - 1210)
+        );
+        assert_eq!(
+            fees.with_volatility(dec!(1))
+                .increase_by_immediate_fee(usd_in),
+            UsdCents::from_major(10_000 + 610)
+        );
+    }
 }
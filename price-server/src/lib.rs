@@ -6,16 +6,24 @@ mod cache_config;
 pub mod currency;
 mod error;
 mod exchange_tick_cache;
+mod fallback_price_provider;
 mod fee_calculator;
+mod mixer_config;
+mod order_book_cache;
+mod price_converter;
 mod price_mixer;
+mod quotes;
 mod server;
+mod volatility;
 
 use app::PriceApp;
 use shared::{health::HealthCheckTrigger, payload::*, pubsub::memory};
 
 pub use app::{ExchangeWeights, PriceServerHealthCheckConfig};
 pub use cache_config::ExchangePriceCacheConfig;
-pub use fee_calculator::FeeCalculatorConfig;
+pub use fee_calculator::{DelayedFeeTier, DynamicFeeConfig, FeeCalculatorConfig};
+pub use mixer_config::{AggregationStrategy, PriceMixerConfig};
+pub use quotes::QuotesConfig;
 pub use server::*;
 
 pub async fn run(
@@ -26,6 +34,10 @@ pub async fn run(
     subscriber: memory::Subscriber<PriceStreamPayload>,
     price_cache_config: ExchangePriceCacheConfig,
     exchange_weights: ExchangeWeights,
+    mixer_config: PriceMixerConfig,
+    okex_order_book: bool,
+    quotes_config: QuotesConfig,
+    pool: Option<sqlx::PgPool>,
 ) -> Result<(), PriceServerError> {
     let app = PriceApp::run(
         health_check_trigger,
@@ -34,6 +46,10 @@ pub async fn run(
         subscriber,
         price_cache_config,
         exchange_weights,
+        mixer_config,
+        okex_order_book,
+        quotes_config,
+        pool,
     )
     .await?;
 
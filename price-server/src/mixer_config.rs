@@ -0,0 +1,50 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregationStrategy {
+    #[default]
+    WeightedMean,
+    WeightedMedian,
+    /// Weighted mean of the providers that are within `max_deviation` of the weighted median.
+    /// Needs at least 3 healthy providers, otherwise `outlier_rejection_fallback` is used.
+    OutlierRejectingMean,
+}
+
+/// Outliers can only be told apart from the majority with at least this many providers.
+pub const MIN_PROVIDERS_FOR_OUTLIER_REJECTION: usize = 3;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PriceMixerConfig {
+    #[serde(default)]
+    pub strategy: AggregationStrategy,
+    #[serde(default = "default_max_deviation")]
+    pub max_deviation: Decimal,
+    #[serde(default = "default_min_healthy_providers")]
+    pub min_healthy_providers: usize,
+    /// Strategy used instead of `outlier_rejecting_mean` while fewer than 3 providers are
+    /// healthy. Without one no price is served in that case.
+    #[serde(default)]
+    pub outlier_rejection_fallback: Option<AggregationStrategy>,
+}
+
+fn default_max_deviation() -> Decimal {
+    dec!(0.01)
+}
+
+fn default_min_healthy_providers() -> usize {
+    1
+}
+
+impl Default for PriceMixerConfig {
+    fn default() -> Self {
+        Self {
+            strategy: AggregationStrategy::default(),
+            max_deviation: default_max_deviation(),
+            min_healthy_providers: default_min_healthy_providers(),
+            outlier_rejection_fallback: None,
+        }
+    }
+}
@@ -3,20 +3,20 @@ use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 use serde::Deserialize;
 use shared::{
-    payload::{OkexBtcUsdSwapOrderBookPayload, OrderBookPayload, PriceRaw},
-    pubsub::Envelope,
+    payload::{OrderBookPayload, PriceRaw},
     time::TimeStamp,
 };
 use std::{collections::BTreeMap, sync::Arc};
 use thiserror::Error;
 use tokio::sync::RwLock;
 
-use crate::VolumeBasedPriceConverter;
+use crate::{
+    cache_config::ExchangePriceCacheConfig, currency::*, error::ExchangePriceCacheError,
+    price_converter::VolumeBasedPriceConverter, price_mixer::*, volatility::MidPriceHistory,
+};
 
 #[derive(Debug, Error)]
 pub enum OrderBookCacheError {
-    #[error("PayloadConversion: conversion from OrderBookPayload failed")]
-    PayloadConversion,
     #[error("OutdatedSnapshot: last update was at {0}")]
     OutdatedSnapshot(TimeStamp),
     #[error("No snapshot data available")]
@@ -25,19 +25,33 @@ pub enum OrderBookCacheError {
     EmptySide,
 }
 
+impl From<OrderBookCacheError> for ExchangePriceCacheError {
+    fn from(err: OrderBookCacheError) -> Self {
+        match err {
+            OrderBookCacheError::OutdatedSnapshot(timestamp) => {
+                ExchangePriceCacheError::StalePrice(timestamp)
+            }
+            _ => ExchangePriceCacheError::NoPriceAvailable,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct OrderBookCache {
     inner: Arc<RwLock<SnapshotInner>>,
 }
 impl OrderBookCache {
-    pub fn new(stale_after: Duration) -> Self {
+    pub fn new(config: ExchangePriceCacheConfig) -> Self {
         Self {
-            inner: Arc::new(RwLock::new(SnapshotInner::new(stale_after))),
+            inner: Arc::new(RwLock::new(SnapshotInner::new(
+                config.stale_after,
+                config.volatility_window,
+            ))),
         }
     }
 
-    pub async fn apply_update(&self, snapshot: Envelope<OkexBtcUsdSwapOrderBookPayload>) {
-        self.inner.write().await.update_snapshot(snapshot);
+    pub async fn apply_update(&self, payload: OrderBookPayload) {
+        self.inner.write().await.update_snapshot(payload);
     }
 
     pub async fn latest_snapshot(&self) -> Result<OrderBookView, OrderBookCacheError> {
@@ -46,22 +60,35 @@ impl OrderBookCache {
     }
 }
 
+#[async_trait::async_trait]
+impl PriceProvider for OrderBookCache {
+    async fn latest(&self) -> Result<Box<dyn SidePicker>, ExchangePriceCacheError> {
+        let snapshot = self.latest_snapshot().await?;
+        snapshot.mid_price_of_one_sat()?;
+        Ok(Box::new(snapshot))
+    }
+
+    async fn realized_volatility(&self) -> Option<Decimal> {
+        self.inner.read().await.mid_prices.realized_volatility()
+    }
+}
+
 #[derive(Debug, Clone)]
 struct SnapshotInner {
     stale_after: Duration,
     snapshot: Option<OrderBookView>,
+    mid_prices: MidPriceHistory,
 }
 impl SnapshotInner {
-    fn new(stale_after: Duration) -> Self {
+    fn new(stale_after: Duration, volatility_window: Duration) -> Self {
         Self {
             stale_after,
             snapshot: None,
+            mid_prices: MidPriceHistory::new(volatility_window),
         }
     }
 
-    fn update_snapshot(&mut self, snap: Envelope<OkexBtcUsdSwapOrderBookPayload>) {
-        let payload = snap.payload.0;
-
+    fn update_snapshot(&mut self, payload: OrderBookPayload) {
         if let Some(ref snap) = self.snapshot {
             if snap.timestamp > payload.timestamp {
                 return;
@@ -69,6 +96,9 @@ impl SnapshotInner {
         }
 
         let snapshot = OrderBookView::from(payload);
+        if let Ok(mid_price) = snapshot.mid_price_of_one_sat() {
+            self.mid_prices.record(snapshot.timestamp, mid_price);
+        }
         self.snapshot = Some(snapshot);
     }
 
@@ -161,7 +191,7 @@ impl OrderBookView {
     }
 
     fn best_ask_price_of_one_sat(&self) -> Result<Decimal, OrderBookCacheError> {
-        let ask_length = self.bids.iter().next();
+        let ask_length = self.asks.iter().next();
 
         let (best_price, _) = ask_length.ok_or(OrderBookCacheError::EmptySide)?;
 
@@ -169,6 +199,34 @@ impl OrderBookView {
     }
 }
 
+impl SidePicker for OrderBookView {
+    fn buy_usd<'a>(&'a self) -> Box<dyn VolumePicker + 'a> {
+        Box::new(OrderBookView::buy_usd(self))
+    }
+
+    fn sell_usd<'a>(&'a self) -> Box<dyn VolumePicker + 'a> {
+        Box::new(OrderBookView::sell_usd(self))
+    }
+
+    fn mid_price_of_one_sat(&self) -> Result<UsdCents, ExchangePriceCacheError> {
+        Ok(UsdCents::from_decimal(OrderBookView::mid_price_of_one_sat(
+            self,
+        )?))
+    }
+
+    fn bid_price_of_one_sat(&self) -> Result<UsdCents, ExchangePriceCacheError> {
+        Ok(UsdCents::from_decimal(self.best_bid_price_of_one_sat()?))
+    }
+
+    fn ask_price_of_one_sat(&self) -> Result<UsdCents, ExchangePriceCacheError> {
+        Ok(UsdCents::from_decimal(self.best_ask_price_of_one_sat()?))
+    }
+
+    fn timestamp(&self) -> TimeStamp {
+        self.timestamp
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -214,8 +272,27 @@ mod tests {
         };
         let mid_price = snapshot.mid_price_of_one_sat()?;
 
-        assert_eq!(mid_price, dec!(7500));
+        assert_eq!(mid_price, dec!(10000));
 
         Ok(())
     }
+
+    #[test]
+    fn empty_side_is_an_error() {
+        let mut asks = BTreeMap::new();
+        asks.insert(QuotePrice(dec!(10000)), dec!(10));
+
+        let snapshot = OrderBookView {
+            asks,
+            bids: BTreeMap::new(),
+            timestamp: TimeStamp::now(),
+        };
+
+        assert!(SidePicker::ask_price_of_one_sat(&snapshot).is_ok());
+        assert!(matches!(
+            SidePicker::bid_price_of_one_sat(&snapshot),
+            Err(ExchangePriceCacheError::NoPriceAvailable)
+        ));
+        assert!(SidePicker::mid_price_of_one_sat(&snapshot).is_err());
+    }
 }
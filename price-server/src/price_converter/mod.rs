@@ -1,10 +1,14 @@
 use rust_decimal::Decimal;
 
 use crate::{
-    currency::{Sats, UsdCents},
-    QuotePrice,
+    currency::{Sats, UsdCents, VolumePicker},
+    error::ExchangePriceCacheError,
+    order_book_cache::QuotePrice,
 };
 
+/// Converts volumes by walking the levels of one side of the order book.
+/// Volume beyond the available depth can't be priced and is reported as an error,
+/// so that the price mixer falls back to the other providers.
 pub struct VolumeBasedPriceConverter<'a, I: Iterator<Item = (&'a QuotePrice, &'a Decimal)> + Clone>
 {
     pairs: I,
@@ -16,15 +20,13 @@ impl<'a, I: Iterator<Item = (&'a QuotePrice, &'a Decimal)> + Clone>
         Self { pairs }
     }
 
-    pub fn cents_from_sats(&self, sats: Sats) -> UsdCents {
-        UsdCents::from_decimal(sats.amount() * self.weighted_price_of_volume(*sats.amount()))
-    }
-
-    pub fn sats_from_cents(&self, cents: UsdCents) -> Sats {
-        Sats::from_decimal(cents.amount() / self.weighted_price_of_volume(*cents.amount()))
-    }
-
-    fn weighted_price_of_volume(&self, total_volume: Decimal) -> Decimal {
+    fn weighted_price_of_volume(
+        &self,
+        total_volume: Decimal,
+    ) -> Result<Decimal, ExchangePriceCacheError> {
+        if total_volume.is_zero() {
+            return Ok(Decimal::ZERO);
+        }
         let mut price_acc = Decimal::ZERO;
         let mut volume_acc = Decimal::ZERO;
 
@@ -33,15 +35,47 @@ impl<'a, I: Iterator<Item = (&'a QuotePrice, &'a Decimal)> + Clone>
             if (volume_acc + qty) < total_volume {
                 volume_acc += qty;
                 price_acc += price.inner() * qty;
-                continue;
             } else {
                 let remaining_volume = total_volume - volume_acc;
                 price_acc += price.inner() * remaining_volume;
-                break;
+                return Ok(price_acc / total_volume);
+            }
+        }
+
+        Err(ExchangePriceCacheError::InsufficientDepth)
+    }
+}
+
+impl<'a, I: Iterator<Item = (&'a QuotePrice, &'a Decimal)> + Clone> VolumePicker
+    for VolumeBasedPriceConverter<'a, I>
+{
+    fn cents_from_sats(&self, sats: Sats) -> Result<UsdCents, ExchangePriceCacheError> {
+        Ok(UsdCents::from_decimal(
+            sats.amount() * self.weighted_price_of_volume(*sats.amount())?,
+        ))
+    }
+
+    fn sats_from_cents(&self, cents: UsdCents) -> Result<Sats, ExchangePriceCacheError> {
+        let mut sats_acc = Decimal::ZERO;
+        let mut cents_remaining = *cents.amount();
+        if cents_remaining.is_zero() {
+            return Ok(Sats::from_decimal(sats_acc));
+        }
+
+        let pairs = self.pairs.clone();
+        for (price, qty) in pairs {
+            let price = price.inner();
+            let level_cents = price * qty;
+            if level_cents < cents_remaining {
+                sats_acc += qty;
+                cents_remaining -= level_cents;
+            } else {
+                sats_acc += cents_remaining / price;
+                return Ok(Sats::from_decimal(sats_acc));
             }
         }
 
-        price_acc / total_volume
+        Err(ExchangePriceCacheError::InsufficientDepth)
     }
 }
 
@@ -51,7 +85,7 @@ mod tests {
     use serde::Deserialize;
     use std::fs;
 
-    use crate::OrderBookView;
+    use crate::order_book_cache::OrderBookView;
 
     use super::*;
 
@@ -85,7 +119,7 @@ mod tests {
         let expected_prices = vec![dec!(0.1), dec!(0.1), dec!(0.15), dec!(0.19), dec!(0.2)];
 
         for (idx, sats) in volumes.into_iter().enumerate() {
-            let mut price = converter.weighted_price_of_volume(*sats.amount());
+            let mut price = converter.weighted_price_of_volume(*sats.amount())?;
             price.rescale(7);
 
             assert_eq!(price, expected_prices[idx]);
@@ -108,7 +142,7 @@ mod tests {
         let expected_prices = vec![dec!(0.2), dec!(0.2), dec!(0.175), dec!(0.155), dec!(0.1275)];
 
         for (idx, sats) in volumes.into_iter().enumerate() {
-            let mut price = converter.weighted_price_of_volume(*sats.amount());
+            let mut price = converter.weighted_price_of_volume(*sats.amount())?;
             price.rescale(7);
 
             assert_eq!(price, expected_prices[idx]);
@@ -121,11 +155,28 @@ mod tests {
     fn cents_from_sats_volume() -> anyhow::Result<()> {
         let latest_snapshot = load_order_book("real")?.payload;
         let converter = VolumeBasedPriceConverter::new(latest_snapshot.bids.iter().rev());
-        let sats_volume = Sats::from_decimal(dec!(100_000_000));
+        let sats_volume = Sats::from_decimal(dec!(200));
+
+        let cents = converter.cents_from_sats(sats_volume)?;
 
-        let cents = converter.cents_from_sats(sats_volume);
+        assert_eq!(cents.floor(), UsdCents::from_major(25));
+
+        Ok(())
+    }
+
+    #[test]
+    fn volume_beyond_depth() -> anyhow::Result<()> {
+        let latest_snapshot = load_order_book("real")?.payload;
+        let converter = VolumeBasedPriceConverter::new(latest_snapshot.bids.iter().rev());
 
-        assert_eq!(cents.floor(), UsdCents::from_major(65));
+        assert!(matches!(
+            converter.cents_from_sats(Sats::from_decimal(dec!(100_000_000))),
+            Err(ExchangePriceCacheError::InsufficientDepth)
+        ));
+        assert!(matches!(
+            converter.sats_from_cents(UsdCents::from_decimal(dec!(10_000_000))),
+            Err(ExchangePriceCacheError::InsufficientDepth)
+        ));
 
         Ok(())
     }
@@ -136,9 +187,9 @@ mod tests {
         let converter = VolumeBasedPriceConverter::new(latest_snapshot.bids.iter().rev());
         let cents_volume = UsdCents::from_decimal(dec!(10));
 
-        let sats = converter.sats_from_cents(cents_volume);
+        let sats = converter.sats_from_cents(cents_volume)?;
 
-        assert_eq!(sats.floor(), Sats::from_major(50));
+        assert_eq!(sats.floor(), Sats::from_major(63));
 
         Ok(())
     }
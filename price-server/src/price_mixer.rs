@@ -1,30 +1,62 @@
 use async_trait::async_trait;
 use rust_decimal::Decimal;
+use shared::time::TimeStamp;
 
-use crate::{currency::VolumePicker, error::ExchangePriceCacheError};
-use std::collections::HashMap;
+use crate::{currency::VolumePicker, error::ExchangePriceCacheError, mixer_config::*};
+use std::collections::{HashMap, HashSet};
+use tokio::sync::RwLock;
 
 use super::currency::*;
 
-pub trait SidePicker {
+pub trait SidePicker: Send + Sync {
     fn buy_usd<'a>(&'a self) -> Box<dyn VolumePicker + 'a>;
     fn sell_usd<'a>(&'a self) -> Box<dyn VolumePicker + 'a>;
-    fn mid_price_of_one_sat(&self) -> UsdCents;
+    fn mid_price_of_one_sat(&self) -> Result<UsdCents, ExchangePriceCacheError>;
+    fn bid_price_of_one_sat(&self) -> Result<UsdCents, ExchangePriceCacheError>;
+    fn ask_price_of_one_sat(&self) -> Result<UsdCents, ExchangePriceCacheError>;
+    fn timestamp(&self) -> TimeStamp;
 }
 
 #[async_trait]
 pub trait PriceProvider {
     async fn latest(&self) -> Result<Box<dyn SidePicker>, ExchangePriceCacheError>;
+
+    async fn realized_volatility(&self) -> Option<Decimal> {
+        None
+    }
+}
+
+/// Snapshot of what a single provider currently contributes to the mixed price.
+#[derive(Debug, Clone)]
+pub struct PriceSource {
+    pub exchange_id: &'static str,
+    pub weight: Decimal,
+    pub bid_price_of_one_sat: Option<UsdCents>,
+    pub ask_price_of_one_sat: Option<UsdCents>,
+    pub timestamp: Option<TimeStamp>,
+    /// Whether the provider contributed to the last value that was served.
+    pub used: bool,
+    pub error: Option<String>,
+}
+
+/// Latest prices of the healthy providers at one point in time.
+pub struct PriceSnapshot {
+    prices: Vec<(&'static str, Box<dyn SidePicker>, Decimal)>,
+    last_error: Option<ExchangePriceCacheError>,
 }
 
 pub struct PriceMixer {
     providers: HashMap<&'static str, (Box<dyn PriceProvider + Sync + Send>, Decimal)>,
+    config: PriceMixerConfig,
+    last_used: RwLock<HashSet<&'static str>>,
 }
 
 impl PriceMixer {
-    pub fn new() -> Self {
+    pub fn new(config: PriceMixerConfig) -> Self {
         Self {
             providers: HashMap::new(),
+            config,
+            last_used: RwLock::new(HashSet::new()),
         }
     }
 
@@ -38,46 +70,252 @@ impl PriceMixer {
             .insert(exchange_id, (Box::new(provider), weight));
     }
 
+    /// Reads the latest price of every provider once.
+    pub async fn snapshot(&self) -> PriceSnapshot {
+        let mut prices = Vec::new();
+        let mut last_error = None;
+        for (exchange_id, (provider, weight)) in self.providers.iter() {
+            match provider.latest().await {
+                Ok(side_picker) => prices.push((*exchange_id, side_picker, *weight)),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        PriceSnapshot { prices, last_error }
+    }
+
     pub async fn apply(
         &self,
-        f: impl Fn(&Box<dyn SidePicker>) -> Decimal,
+        f: impl Fn(&Box<dyn SidePicker>) -> Result<Decimal, ExchangePriceCacheError>,
     ) -> Result<Decimal, ExchangePriceCacheError> {
+        let snapshot = self.snapshot().await;
+        self.apply_to(&snapshot, f).await
+    }
+
+    /// Aggregates `f` over the providers in `snapshot`, so that several values derived
+    /// from the same snapshot are consistent with each other. Providers for which `f`
+    /// fails are left out of the aggregation.
+    pub async fn apply_to(
+        &self,
+        snapshot: &PriceSnapshot,
+        f: impl Fn(&Box<dyn SidePicker>) -> Result<Decimal, ExchangePriceCacheError>,
+    ) -> Result<Decimal, ExchangePriceCacheError> {
+        let mut exchange_ids = Vec::new();
+        let mut values = Vec::new();
+        let mut prev_error = snapshot.last_error.clone();
+        for (exchange_id, side_picker, weight) in snapshot.prices.iter() {
+            match f(side_picker) {
+                Ok(value) => {
+                    exchange_ids.push(*exchange_id);
+                    values.push((value, *weight));
+                }
+                Err(err) => prev_error = Some(err),
+            }
+        }
+
+        let res = self.aggregate_healthy(values.clone(), prev_error);
+        let mut last_used = self.last_used.write().await;
+        last_used.clear();
+        if res.is_ok() {
+            let used = used_values(&self.config, &values);
+            last_used.extend(
+                exchange_ids
+                    .into_iter()
+                    .zip(used)
+                    .filter_map(|(exchange_id, used)| used.then_some(exchange_id)),
+            );
+        }
+        res
+    }
+
+    fn aggregate_healthy(
+        &self,
+        values: Vec<(Decimal, Decimal)>,
+        prev_error: Option<ExchangePriceCacheError>,
+    ) -> Result<Decimal, ExchangePriceCacheError> {
+        if values.is_empty() {
+            return Err(prev_error.unwrap_or(ExchangePriceCacheError::NoPriceAvailable));
+        }
+        if values.len() < self.config.min_healthy_providers {
+            return Err(ExchangePriceCacheError::NotEnoughHealthyProviders(
+                values.len(),
+                self.config.min_healthy_providers,
+            ));
+        }
+
+        aggregate(&self.config, values)
+    }
+
+    pub async fn sources(&self) -> Vec<PriceSource> {
+        let last_used = self.last_used.read().await;
+        let mut sources = Vec::new();
+        for (exchange_id, (provider, weight)) in self.providers.iter() {
+            let mut source = PriceSource {
+                exchange_id,
+                weight: *weight,
+                bid_price_of_one_sat: None,
+                ask_price_of_one_sat: None,
+                timestamp: None,
+                used: false,
+                error: None,
+            };
+            let prices = provider.latest().await.and_then(|side_picker| {
+                Ok((
+                    side_picker.bid_price_of_one_sat()?,
+                    side_picker.ask_price_of_one_sat()?,
+                    side_picker.timestamp(),
+                ))
+            });
+            match prices {
+                Ok((bid, ask, timestamp)) => {
+                    source.bid_price_of_one_sat = Some(bid);
+                    source.ask_price_of_one_sat = Some(ask);
+                    source.timestamp = Some(timestamp);
+                    source.used = last_used.contains(exchange_id);
+                }
+                Err(err) => {
+                    if let ExchangePriceCacheError::StalePrice(timestamp) = err {
+                        source.timestamp = Some(timestamp);
+                    }
+                    source.error = Some(err.to_string());
+                }
+            }
+            sources.push(source);
+        }
+        sources.sort_by_key(|source| source.exchange_id);
+        sources
+    }
+
+    pub async fn realized_volatility(&self) -> Option<Decimal> {
         let mut total = Decimal::ZERO;
         let mut total_weights = Decimal::ZERO;
-        let mut prev_error: Option<ExchangePriceCacheError> = None;
         for (provider, weight) in self.providers.values() {
-            let side_picker = match provider.latest().await {
-                Ok(side_picker) => side_picker,
-                Err(err) => {
-                    prev_error = Some(err);
-                    continue;
-                }
-            };
-            total_weights += weight;
-            total += f(&side_picker) * weight;
+            if let Some(volatility) = provider.realized_volatility().await {
+                total_weights += weight;
+                total += volatility * weight;
+            }
         }
 
         if total_weights > Decimal::ZERO {
-            Ok(total / total_weights)
+            Some(total / total_weights)
         } else {
-            Err(prev_error.unwrap_or(ExchangePriceCacheError::NoPriceAvailable))
+            None
+        }
+    }
+}
+
+fn aggregate(
+    config: &PriceMixerConfig,
+    mut values: Vec<(Decimal, Decimal)>,
+) -> Result<Decimal, ExchangePriceCacheError> {
+    let aggregated = match aggregation_strategy(config, values.len())? {
+        AggregationStrategy::WeightedMean => weighted_mean(&values),
+        AggregationStrategy::WeightedMedian => weighted_median(&mut values),
+        AggregationStrategy::OutlierRejectingMean => {
+            weighted_median(&mut values).and_then(|median| {
+                values.retain(|(value, _)| within_deviation(config, median, *value));
+                weighted_mean(&values)
+            })
+        }
+    };
+    aggregated.ok_or(ExchangePriceCacheError::NoPriceAvailable)
+}
+
+/// Which of the `values` are part of the aggregated value.
+fn used_values(config: &PriceMixerConfig, values: &[(Decimal, Decimal)]) -> Vec<bool> {
+    match aggregation_strategy(config, values.len()) {
+        Ok(AggregationStrategy::OutlierRejectingMean) => {
+            match weighted_median(&mut values.to_vec()) {
+                Some(median) => values
+                    .iter()
+                    .map(|(value, _)| within_deviation(config, median, *value))
+                    .collect(),
+                None => vec![false; values.len()],
+            }
         }
+        Ok(_) => vec![true; values.len()],
+        Err(_) => vec![false; values.len()],
     }
 }
 
+/// With fewer than 3 providers the median cannot tell which one is the outlier,
+/// so outlier rejection falls back to the configured strategy or fails.
+fn aggregation_strategy(
+    config: &PriceMixerConfig,
+    n_providers: usize,
+) -> Result<AggregationStrategy, ExchangePriceCacheError> {
+    match (config.strategy, config.outlier_rejection_fallback) {
+        (AggregationStrategy::OutlierRejectingMean, fallback)
+            if n_providers < MIN_PROVIDERS_FOR_OUTLIER_REJECTION =>
+        {
+            match fallback {
+                Some(fallback) if fallback != AggregationStrategy::OutlierRejectingMean => {
+                    Ok(fallback)
+                }
+                _ => Err(
+                    ExchangePriceCacheError::NotEnoughProvidersForOutlierRejection(
+                        n_providers,
+                        MIN_PROVIDERS_FOR_OUTLIER_REJECTION,
+                    ),
+                ),
+            }
+        }
+        (strategy, _) => Ok(strategy),
+    }
+}
+
+fn within_deviation(config: &PriceMixerConfig, median: Decimal, value: Decimal) -> bool {
+    median.is_zero() || ((value - median) / median).abs() <= config.max_deviation
+}
+
+fn weighted_mean(values: &[(Decimal, Decimal)]) -> Option<Decimal> {
+    let total_weights: Decimal = values.iter().map(|(_, weight)| weight).sum();
+    if total_weights <= Decimal::ZERO {
+        return None;
+    }
+    let total: Decimal = values.iter().map(|(value, weight)| value * weight).sum();
+    Some(total / total_weights)
+}
+
+fn weighted_median(values: &mut [(Decimal, Decimal)]) -> Option<Decimal> {
+    let total_weights: Decimal = values.iter().map(|(_, weight)| weight).sum();
+    if total_weights <= Decimal::ZERO {
+        return None;
+    }
+    values.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let mut cumulative_weight = Decimal::ZERO;
+    for (i, (value, weight)) in values.iter().enumerate() {
+        cumulative_weight += weight;
+        if cumulative_weight * Decimal::TWO == total_weights {
+            // Exactly half of the weight is on each side, so the median lies in between.
+            return match values[i + 1..].iter().find(|(_, weight)| !weight.is_zero()) {
+                Some((next, _)) => Some((*value + *next) / Decimal::TWO),
+                None => Some(*value),
+            };
+        }
+        if cumulative_weight * Decimal::TWO > total_weights {
+            return Some(*value);
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     pub use std::collections::HashMap;
 
     pub use chrono::Duration;
     pub use rust_decimal::Decimal;
-    use shared::payload::PriceMessagePayload;
+    use shared::payload::{PriceMessagePayload, PriceRatioRaw};
     use shared::pubsub::CorrelationId;
     use shared::time::TimeStamp;
 
+    use rust_decimal_macros::dec;
+
     pub use super::PriceMixer;
     pub use super::PriceProvider;
+    use super::{aggregate, AggregationStrategy, PriceMixerConfig};
     pub use crate::currency::UsdCents;
+    use crate::error::ExchangePriceCacheError;
     pub use crate::{
         cache_config::ExchangePriceCacheConfig,
         currency::{Sats, VolumePicker},
@@ -88,7 +326,7 @@ mod tests {
     #[tokio::test]
     async fn test_price_mixer() -> anyhow::Result<(), Error> {
         let cache = ExchangeTickCache::new(ExchangePriceCacheConfig::default());
-        let mut price_mixer = PriceMixer::new();
+        let mut price_mixer = PriceMixer::new(PriceMixerConfig::default());
         price_mixer.add_provider("okex", cache.clone(), Decimal::from(1));
 
         cache
@@ -97,9 +335,10 @@ mod tests {
 
         let price = price_mixer
             .apply(|p| {
-                *p.sell_usd()
-                    .sats_from_cents(UsdCents::from_decimal(Decimal::ONE))
-                    .amount()
+                Ok(*p
+                    .sell_usd()
+                    .sats_from_cents(UsdCents::from_decimal(Decimal::ONE))?
+                    .amount())
             })
             .await
             .expect("Price should be available");
@@ -107,6 +346,185 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn not_enough_healthy_providers() {
+        let healthy = ExchangeTickCache::new(ExchangePriceCacheConfig::default());
+        let unhealthy = ExchangeTickCache::new(ExchangePriceCacheConfig::default());
+        let mut price_mixer = PriceMixer::new(PriceMixerConfig {
+            min_healthy_providers: 2,
+            ..PriceMixerConfig::default()
+        });
+        price_mixer.add_provider("okex", healthy.clone(), Decimal::ONE);
+        price_mixer.add_provider("bitfinex", unhealthy, Decimal::ONE);
+        healthy
+            .apply_update(get_payload(), CorrelationId::new())
+            .await;
+
+        let res = price_mixer
+            .apply(|p| Ok(*p.mid_price_of_one_sat()?.amount()))
+            .await;
+        assert!(matches!(
+            res,
+            Err(ExchangePriceCacheError::NotEnoughHealthyProviders(1, 2))
+        ));
+    }
+
+    fn config(strategy: AggregationStrategy) -> PriceMixerConfig {
+        PriceMixerConfig {
+            strategy,
+            max_deviation: dec!(0.01),
+            ..PriceMixerConfig::default()
+        }
+    }
+
+    #[test]
+    fn aggregation_strategies() {
+        let values = vec![
+            (dec!(100), dec!(1)),
+            (dec!(101), dec!(1)),
+            (dec!(150), dec!(1)),
+        ];
+        assert_eq!(
+            aggregate(&config(AggregationStrategy::WeightedMean), values.clone()).ok(),
+            Some(dec!(117))
+        );
+        assert_eq!(
+            aggregate(&config(AggregationStrategy::WeightedMedian), values.clone()).ok(),
+            Some(dec!(101))
+        );
+        assert_eq!(
+            aggregate(&config(AggregationStrategy::OutlierRejectingMean), values).ok(),
+            Some(dec!(100.5))
+        );
+    }
+
+    #[test]
+    fn weighted_median_respects_weights() {
+        let values = vec![
+            (dec!(100), dec!(1)),
+            (dec!(101), dec!(1)),
+            (dec!(150), dec!(3)),
+        ];
+        assert_eq!(
+            aggregate(&config(AggregationStrategy::WeightedMedian), values).ok(),
+            Some(dec!(150))
+        );
+    }
+
+    #[test]
+    fn weighted_median_averages_on_half_split() {
+        let values = vec![
+            (dec!(100), dec!(1)),
+            (dec!(102), dec!(2)),
+            (dec!(110), dec!(3)),
+        ];
+        assert_eq!(
+            aggregate(&config(AggregationStrategy::WeightedMedian), values).ok(),
+            Some(dec!(106))
+        );
+    }
+
+    #[tokio::test]
+    async fn outlier_rejection_needs_three_providers() {
+        let mut price_mixer = PriceMixer::new(config(AggregationStrategy::OutlierRejectingMean));
+        let mut fallback_mixer = PriceMixer::new(PriceMixerConfig {
+            outlier_rejection_fallback: Some(AggregationStrategy::WeightedMedian),
+            ..config(AggregationStrategy::OutlierRejectingMean)
+        });
+        for (exchange_id, price) in [("a", dec!(20000)), ("b", dec!(30000))] {
+            let cache = ExchangeTickCache::new(ExchangePriceCacheConfig::default());
+            let mut payload = get_payload();
+            payload.bid_price = PriceRatioRaw::from_one_btc_in_usd_price(price);
+            payload.ask_price = PriceRatioRaw::from_one_btc_in_usd_price(price);
+            cache.apply_update(payload, CorrelationId::new()).await;
+            price_mixer.add_provider(exchange_id, cache.clone(), Decimal::ONE);
+            fallback_mixer.add_provider(exchange_id, cache, Decimal::ONE);
+        }
+
+        let res = price_mixer
+            .apply(|p| Ok(*p.mid_price_of_one_sat()?.amount()))
+            .await;
+        assert!(matches!(
+            res,
+            Err(ExchangePriceCacheError::NotEnoughProvidersForOutlierRejection(2, 3))
+        ));
+        let sources = price_mixer.sources().await;
+        assert!(sources.iter().all(|source| !source.used));
+        assert!(sources.iter().all(|source| source.error.is_none()));
+
+        let mid = fallback_mixer
+            .apply(|p| Ok(*p.mid_price_of_one_sat()?.amount()))
+            .await
+            .expect("fallback strategy should serve a price");
+        assert_eq!(mid, dec!(0.025));
+        let sources = fallback_mixer.sources().await;
+        assert!(sources.iter().all(|source| source.used));
+    }
+
+    #[tokio::test]
+    async fn sources_flag_outliers_as_unused() {
+        let mut price_mixer = PriceMixer::new(config(AggregationStrategy::OutlierRejectingMean));
+        for (exchange_id, price) in [("a", dec!(20000)), ("b", dec!(20010)), ("c", dec!(30000))] {
+            let cache = ExchangeTickCache::new(ExchangePriceCacheConfig::default());
+            let mut payload = get_payload();
+            payload.bid_price = PriceRatioRaw::from_one_btc_in_usd_price(price);
+            payload.ask_price = PriceRatioRaw::from_one_btc_in_usd_price(price);
+            cache.apply_update(payload, CorrelationId::new()).await;
+            price_mixer.add_provider(exchange_id, cache, Decimal::ONE);
+        }
+        let unhealthy = ExchangeTickCache::new(ExchangePriceCacheConfig::default());
+        price_mixer.add_provider("d", unhealthy, Decimal::ONE);
+
+        let sources = price_mixer.sources().await;
+        assert!(sources.iter().all(|source| !source.used));
+
+        price_mixer
+            .apply(|p| {
+                Ok(*p
+                    .buy_usd()
+                    .cents_from_sats(Sats::from_major(1000))?
+                    .amount())
+            })
+            .await
+            .expect("Price should be available");
+        let sources = price_mixer.sources().await;
+        let used: Vec<_> = sources.iter().map(|s| (s.exchange_id, s.used)).collect();
+        assert_eq!(
+            used,
+            vec![("a", true), ("b", true), ("c", false), ("d", false)]
+        );
+        assert!(sources[0].timestamp.is_some());
+        assert!(sources[3].error.is_some());
+    }
+
+    #[tokio::test]
+    async fn values_from_one_snapshot_are_consistent() {
+        let cache = ExchangeTickCache::new(ExchangePriceCacheConfig::default());
+        let mut price_mixer = PriceMixer::new(PriceMixerConfig::default());
+        price_mixer.add_provider("okex", cache.clone(), Decimal::ONE);
+        let mut payload = get_payload();
+        payload.bid_price = PriceRatioRaw::from_one_btc_in_usd_price(dec!(20000));
+        payload.ask_price = PriceRatioRaw::from_one_btc_in_usd_price(dec!(20000));
+        cache.apply_update(payload, CorrelationId::new()).await;
+
+        let snapshot = price_mixer.snapshot().await;
+        let mut payload = get_payload();
+        payload.bid_price = PriceRatioRaw::from_one_btc_in_usd_price(dec!(30000));
+        payload.ask_price = PriceRatioRaw::from_one_btc_in_usd_price(dec!(30000));
+        cache.apply_update(payload, CorrelationId::new()).await;
+
+        let mid = price_mixer
+            .apply_to(&snapshot, |p| Ok(*p.mid_price_of_one_sat()?.amount()))
+            .await
+            .expect("Price should be available");
+        assert_eq!(mid, dec!(0.02));
+        let mid = price_mixer
+            .apply(|p| Ok(*p.mid_price_of_one_sat()?.amount()))
+            .await
+            .expect("Price should be available");
+        assert_eq!(mid, dec!(0.03));
+    }
+
     fn get_payload() -> PriceMessagePayload {
         let raw = r#"{
             "exchange": "okex",
@@ -0,0 +1,47 @@
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+#[serde_with::serde_as]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct QuotesConfig {
+    /// Quotes are persisted, so GetQuote and AcceptQuote are only served when enabled.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Key of the HMAC that binds a quote to its terms, set via QUOTES_SIGNING_SECRET.
+    #[serde(default)]
+    pub signing_secret: String,
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    #[serde(default = "default_expiration_interval")]
+    pub expiration_interval: Duration,
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    #[serde(default = "default_cleanup_interval")]
+    pub cleanup_interval: Duration,
+    /// How long unaccepted quotes are kept after they expired.
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    #[serde(default = "default_expired_retention")]
+    pub expired_retention: Duration,
+}
+
+impl Default for QuotesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            signing_secret: String::new(),
+            expiration_interval: default_expiration_interval(),
+            cleanup_interval: default_cleanup_interval(),
+            expired_retention: default_expired_retention(),
+        }
+    }
+}
+
+fn default_expiration_interval() -> Duration {
+    Duration::seconds(30)
+}
+
+fn default_cleanup_interval() -> Duration {
+    Duration::minutes(10)
+}
+
+fn default_expired_retention() -> Duration {
+    Duration::hours(24)
+}
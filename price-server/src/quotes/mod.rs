@@ -0,0 +1,176 @@
+mod config;
+mod signature;
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{currency::*, error::PriceAppError};
+
+pub use config::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteDirection {
+    BuyCents,
+    SellCents,
+}
+
+impl std::fmt::Display for QuoteDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuoteDirection::BuyCents => write!(f, "buy_cents"),
+            QuoteDirection::SellCents => write!(f, "sell_cents"),
+        }
+    }
+}
+
+impl std::str::FromStr for QuoteDirection {
+    type Err = PriceAppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "buy_cents" => Ok(QuoteDirection::BuyCents),
+            "sell_cents" => Ok(QuoteDirection::SellCents),
+            _ => Err(PriceAppError::UnknownQuoteDirection(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum QuoteAmount {
+    Sats(Sats),
+    Cents(UsdCents),
+}
+
+#[derive(Debug, Clone)]
+pub struct NewQuote {
+    pub direction: QuoteDirection,
+    pub amount_in_satoshis: Decimal,
+    pub amount_in_cents: Decimal,
+    pub fee_rate: Decimal,
+    pub fee_amount_in_cents: Decimal,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Quote {
+    pub id: Uuid,
+    pub direction: QuoteDirection,
+    pub amount_in_satoshis: Decimal,
+    pub amount_in_cents: Decimal,
+    pub fee_rate: Decimal,
+    /// Fee withheld by the quote, always in cents. When the quote computed an amount
+    /// of sats the fee is converted at the rate of the quote.
+    pub fee_amount_in_cents: Decimal,
+    pub expires_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    /// Has to be presented to accept the quote.
+    pub signature: String,
+}
+
+#[derive(Clone)]
+pub struct Quotes {
+    pool: PgPool,
+    signing_secret: String,
+}
+
+impl Quotes {
+    pub fn new(pool: PgPool, config: &QuotesConfig) -> Result<Self, PriceAppError> {
+        if config.signing_secret.is_empty() {
+            return Err(PriceAppError::QuotesSigningSecretMissing);
+        }
+        Ok(Self {
+            pool,
+            signing_secret: config.signing_secret.clone(),
+        })
+    }
+
+    #[instrument(name = "price_server.quotes.create", skip(self), err)]
+    pub async fn create(&self, new_quote: NewQuote) -> Result<Quote, PriceAppError> {
+        let id = Uuid::new_v4();
+        sqlx::query!(
+            r#"INSERT INTO quotes (id, direction, amount_in_satoshis, amount_in_cents, fee_rate, fee_amount_in_cents, expires_at)
+               VALUES ($1, $2, $3, $4, $5, $6, $7)"#,
+            id,
+            new_quote.direction.to_string(),
+            new_quote.amount_in_satoshis,
+            new_quote.amount_in_cents,
+            new_quote.fee_rate,
+            new_quote.fee_amount_in_cents,
+            new_quote.expires_at,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        let mut quote = Quote {
+            id,
+            direction: new_quote.direction,
+            amount_in_satoshis: new_quote.amount_in_satoshis,
+            amount_in_cents: new_quote.amount_in_cents,
+            fee_rate: new_quote.fee_rate,
+            fee_amount_in_cents: new_quote.fee_amount_in_cents,
+            expires_at: new_quote.expires_at,
+            accepted_at: None,
+            signature: String::new(),
+        };
+        quote.signature = signature::sign(&self.signing_secret, &quote);
+        Ok(quote)
+    }
+
+    #[instrument(name = "price_server.quotes.accept", skip(self, signature), err)]
+    pub async fn accept(&self, id: Uuid, signature: &str) -> Result<Quote, PriceAppError> {
+        let mut tx = self.pool.begin().await?;
+        let row = sqlx::query!(
+            r#"SELECT id, direction, amount_in_satoshis, amount_in_cents, fee_rate, fee_amount_in_cents, expires_at, accepted_at
+               FROM quotes WHERE id = $1 FOR UPDATE"#,
+            id
+        )
+        .fetch_optional(&mut tx)
+        .await?
+        .ok_or(PriceAppError::QuoteNotFound(id))?;
+        let mut quote = Quote {
+            id: row.id,
+            direction: row.direction.parse()?,
+            amount_in_satoshis: row.amount_in_satoshis,
+            amount_in_cents: row.amount_in_cents,
+            fee_rate: row.fee_rate,
+            fee_amount_in_cents: row.fee_amount_in_cents,
+            expires_at: row.expires_at,
+            accepted_at: row.accepted_at,
+            signature: signature.to_string(),
+        };
+
+        if !signature::verify(&self.signing_secret, &quote, signature) {
+            return Err(PriceAppError::InvalidQuoteSignature(id));
+        }
+        if quote.accepted_at.is_some() {
+            return Err(PriceAppError::QuoteAlreadyAccepted(id));
+        }
+        let now = Utc::now();
+        if quote.expires_at <= now {
+            return Err(PriceAppError::QuoteExpired(id));
+        }
+
+        sqlx::query!("UPDATE quotes SET accepted_at = $2 WHERE id = $1", id, now)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+
+        quote.accepted_at = Some(now);
+        Ok(quote)
+    }
+
+    /// Deletes quotes that expired more than `retention` ago without being accepted.
+    #[instrument(name = "price_server.quotes.delete_expired", skip(self), err)]
+    pub async fn delete_expired(&self, retention: chrono::Duration) -> Result<u64, PriceAppError> {
+        let res = sqlx::query!(
+            "DELETE FROM quotes WHERE accepted_at IS NULL AND expires_at < $1",
+            Utc::now() - retention
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(res.rows_affected())
+    }
+}
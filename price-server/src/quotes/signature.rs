@@ -0,0 +1,89 @@
+use ring::hmac;
+
+use super::Quote;
+
+/// Hex encoded HMAC-SHA256 over the terms of the quote that the client is accepting.
+pub(super) fn sign(secret: &str, quote: &Quote) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = hmac::sign(&key, signed_payload(quote).as_bytes());
+    data_encoding::HEXLOWER.encode(tag.as_ref())
+}
+
+pub(super) fn verify(secret: &str, quote: &Quote, signature: &str) -> bool {
+    let signature = match data_encoding::HEXLOWER_PERMISSIVE.decode(signature.as_bytes()) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    hmac::verify(&key, signed_payload(quote).as_bytes(), &signature).is_ok()
+}
+
+fn signed_payload(quote: &Quote) -> String {
+    format!(
+        "{}.{}.{}.{}.{}.{}",
+        quote.id,
+        quote.direction,
+        quote.amount_in_satoshis.normalize(),
+        quote.amount_in_cents.normalize(),
+        quote.fee_amount_in_cents.normalize(),
+        quote.expires_at.timestamp()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use rust_decimal_macros::dec;
+
+    use super::*;
+    use crate::quotes::QuoteDirection;
+
+    fn quote() -> Quote {
+        Quote {
+            id: uuid::Uuid::new_v4(),
+            direction: QuoteDirection::BuyCents,
+            amount_in_satoshis: dec!(100_000_000),
+            amount_in_cents: dec!(98900),
+            fee_rate: dec!(0.01),
+            fee_amount_in_cents: dec!(1100),
+            expires_at: Utc::now(),
+            accepted_at: None,
+            signature: String::new(),
+        }
+    }
+
+    #[test]
+    fn accepts_signature_of_same_terms() {
+        let quote = quote();
+        let signature = sign("secret", &quote);
+        let reloaded = Quote {
+            amount_in_cents: dec!(98900.00),
+            ..quote.clone()
+        };
+        assert!(verify("secret", &reloaded, &signature));
+    }
+
+    #[test]
+    fn rejects_changed_terms() {
+        let quote = quote();
+        let signature = sign("secret", &quote);
+        assert!(!verify("other", &quote, &signature));
+        assert!(!verify(
+            "secret",
+            &Quote {
+                amount_in_cents: dec!(99000),
+                ..quote.clone()
+            },
+            &signature
+        ));
+        assert!(!verify(
+            "secret",
+            &Quote {
+                direction: QuoteDirection::SellCents,
+                ..quote.clone()
+            },
+            &signature
+        ));
+        assert!(!verify("secret", &quote, "not-hex"));
+    }
+}
@@ -1,14 +1,20 @@
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
+#[serde_with::serde_as]
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PriceServerConfig {
     #[serde(default = "default_port")]
     pub listen_port: u16,
+    #[serde_as(as = "serde_with::DurationMilliSeconds<u64>")]
+    #[serde(default = "default_price_subscription_min_interval")]
+    pub price_subscription_min_interval: Duration,
 }
 impl Default for PriceServerConfig {
     fn default() -> Self {
         Self {
             listen_port: default_port(),
+            price_subscription_min_interval: default_price_subscription_min_interval(),
         }
     }
 }
@@ -16,3 +22,7 @@ impl Default for PriceServerConfig {
 fn default_port() -> u16 {
     3325
 }
+
+fn default_price_subscription_min_interval() -> Duration {
+    Duration::from_millis(500)
+}
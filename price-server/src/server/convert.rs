@@ -1,4 +1,5 @@
-use crate::app::PriceAppError;
+use super::proto;
+use crate::app::{PriceAppError, PriceSource, PriceUpdate, Quote, QuoteDirection, UsdCents};
 
 impl From<PriceAppError> for tonic::Status {
     fn from(err: PriceAppError) -> Self {
@@ -10,6 +11,101 @@ impl From<PriceAppError> for tonic::Status {
                 tonic::Status::new(tonic::Code::Unknown, format!("{err}"))
             }
             DecimalConversion(err) => tonic::Status::new(tonic::Code::Unknown, format!("{err}")),
+            Sqlx(err) => tonic::Status::new(tonic::Code::Unknown, format!("{err}")),
+            QuoteNotFound(_) => tonic::Status::new(tonic::Code::NotFound, format!("{err}")),
+            QuoteExpired(_) | QuoteAlreadyAccepted(_) => {
+                tonic::Status::new(tonic::Code::FailedPrecondition, format!("{err}"))
+            }
+            UnknownQuoteDirection(_) => {
+                tonic::Status::new(tonic::Code::InvalidArgument, format!("{err}"))
+            }
+            InvalidQuoteSignature(_) => {
+                tonic::Status::new(tonic::Code::PermissionDenied, format!("{err}"))
+            }
+            QuotesUnavailable => tonic::Status::new(tonic::Code::Unavailable, format!("{err}")),
+            QuotesSigningSecretMissing => {
+                tonic::Status::new(tonic::Code::Internal, format!("{err}"))
+            }
+        }
+    }
+}
+
+impl TryFrom<proto::QuoteDirection> for QuoteDirection {
+    type Error = PriceAppError;
+
+    fn try_from(direction: proto::QuoteDirection) -> Result<Self, Self::Error> {
+        match direction {
+            proto::QuoteDirection::Unspecified => Err(PriceAppError::UnknownQuoteDirection(
+                "QUOTE_DIRECTION_UNSPECIFIED".to_string(),
+            )),
+            proto::QuoteDirection::BuyCents => Ok(QuoteDirection::BuyCents),
+            proto::QuoteDirection::SellCents => Ok(QuoteDirection::SellCents),
+        }
+    }
+}
+
+impl From<QuoteDirection> for proto::QuoteDirection {
+    fn from(direction: QuoteDirection) -> Self {
+        match direction {
+            QuoteDirection::BuyCents => proto::QuoteDirection::BuyCents,
+            QuoteDirection::SellCents => proto::QuoteDirection::SellCents,
         }
     }
 }
+
+impl TryFrom<Quote> for proto::PriceQuote {
+    type Error = PriceAppError;
+
+    fn try_from(quote: Quote) -> Result<Self, Self::Error> {
+        Ok(Self {
+            quote_id: quote.id.to_string(),
+            direction: proto::QuoteDirection::from(quote.direction) as i32,
+            amount_in_satoshis: u64::try_from(quote.amount_in_satoshis)?,
+            amount_in_cents: u64::try_from(quote.amount_in_cents)?,
+            fee_rate: f64::try_from(quote.fee_rate)?,
+            fee_amount_in_cents: f64::try_from(quote.fee_amount_in_cents)?,
+            expires_at: quote.expires_at.timestamp(),
+            accepted: quote.accepted_at.is_some(),
+            signature: quote.signature,
+        })
+    }
+}
+
+impl TryFrom<PriceSource> for proto::PriceSource {
+    type Error = PriceAppError;
+
+    fn try_from(source: PriceSource) -> Result<Self, Self::Error> {
+        let price = |price: Option<UsdCents>| -> Result<f64, PriceAppError> {
+            Ok(price
+                .map(|price| f64::try_from(*price.amount()))
+                .transpose()?
+                .unwrap_or_default())
+        };
+        Ok(Self {
+            exchange_id: source.exchange_id.to_string(),
+            weight: f64::try_from(source.weight)?,
+            bid_price_of_one_sat_in_cents: price(source.bid_price_of_one_sat)?,
+            ask_price_of_one_sat_in_cents: price(source.ask_price_of_one_sat)?,
+            has_data: source.timestamp.is_some(),
+            age_in_milliseconds: source
+                .timestamp
+                .map(|timestamp| timestamp.duration_since().num_milliseconds().max(0) as u64)
+                .unwrap_or_default(),
+            used: source.used,
+            error: source.error.unwrap_or_default(),
+        })
+    }
+}
+
+impl TryFrom<PriceUpdate> for proto::SubscribePricesResponse {
+    type Error = PriceAppError;
+
+    fn try_from(update: PriceUpdate) -> Result<Self, Self::Error> {
+        Ok(Self {
+            mid_price_of_one_sat_in_cents: f64::try_from(*update.mid_price_of_one_sat.amount())?,
+            bid_price_of_one_sat_in_cents: f64::try_from(*update.bid_price_of_one_sat.amount())?,
+            ask_price_of_one_sat_in_cents: f64::try_from(*update.ask_price_of_one_sat.amount())?,
+            timestamp: update.timestamp.timestamp(),
+        })
+    }
+}
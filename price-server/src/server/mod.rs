@@ -7,6 +7,7 @@ pub mod proto {
     tonic::include_proto!("services.price.v1");
 }
 
+use futures::stream::{Stream, StreamExt};
 use opentelemetry::{
     propagation::{Extractor, TextMapPropagator},
     sdk::propagation::TraceContextPropagator,
@@ -16,6 +17,8 @@ use tonic::{transport::Server, Request, Response, Status};
 use tracing::instrument;
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
+use std::pin::Pin;
+
 use crate::app::*;
 
 pub use config::*;
@@ -23,10 +26,14 @@ pub use error::*;
 
 pub struct Price {
     app: PriceApp,
+    price_subscription_min_interval: std::time::Duration,
 }
 
 #[tonic::async_trait]
 impl PriceService for Price {
+    type SubscribePricesStream =
+        Pin<Box<dyn Stream<Item = Result<SubscribePricesResponse, Status>> + Send + 'static>>;
+
     #[instrument(name = "price_server.get_cents_from_sats_for_immediate_buy", skip_all,
         fields(amount_in_satoshis = request.get_ref().amount_in_satoshis,
                error, error.level, error.message),
@@ -91,7 +98,10 @@ impl PriceService for Price {
             let req = request.into_inner();
             let amount_in_cents = self
                 .app
-                .get_cents_from_sats_for_future_buy(Sats::from_major(req.amount_in_satoshis))
+                .get_cents_from_sats_for_future_buy(
+                    Sats::from_major(req.amount_in_satoshis),
+                    req.time_in_seconds,
+                )
                 .await?;
             Ok(Response::new(GetCentsFromSatsForFutureBuyResponse {
                 amount_in_cents: u64::try_from(amount_in_cents).map_err(PriceAppError::from)?,
@@ -116,7 +126,10 @@ impl PriceService for Price {
             let req = request.into_inner();
             let amount_in_cents = self
                 .app
-                .get_cents_from_sats_for_future_sell(Sats::from_major(req.amount_in_satoshis))
+                .get_cents_from_sats_for_future_sell(
+                    Sats::from_major(req.amount_in_satoshis),
+                    req.time_in_seconds,
+                )
                 .await?;
             Ok(Response::new(GetCentsFromSatsForFutureSellResponse {
                 amount_in_cents: u64::try_from(amount_in_cents).map_err(PriceAppError::from)?,
@@ -191,7 +204,10 @@ impl PriceService for Price {
             let req = request.into_inner();
             let amount_in_satoshis = self
                 .app
-                .get_sats_from_cents_for_future_buy(UsdCents::from_major(req.amount_in_cents))
+                .get_sats_from_cents_for_future_buy(
+                    UsdCents::from_major(req.amount_in_cents),
+                    req.time_in_seconds,
+                )
                 .await?;
             Ok(Response::new(GetSatsFromCentsForFutureBuyResponse {
                 amount_in_satoshis: u64::try_from(amount_in_satoshis)
@@ -217,7 +233,10 @@ impl PriceService for Price {
             let req = request.into_inner();
             let amount_in_satoshis = self
                 .app
-                .get_sats_from_cents_for_future_sell(UsdCents::from_major(req.amount_in_cents))
+                .get_sats_from_cents_for_future_sell(
+                    UsdCents::from_major(req.amount_in_cents),
+                    req.time_in_seconds,
+                )
                 .await?;
             Ok(Response::new(GetSatsFromCentsForFutureSellResponse {
                 amount_in_satoshis: u64::try_from(amount_in_satoshis)
@@ -243,13 +262,105 @@ impl PriceService for Price {
         })
         .await
     }
+
+    #[instrument(name = "price_server.get_quote", skip_all,
+        fields(direction = request.get_ref().direction,
+            error, error.level, error.message),
+        err
+    )]
+    async fn get_quote(
+        &self,
+        request: Request<GetQuoteRequest>,
+    ) -> Result<Response<GetQuoteResponse>, Status> {
+        shared::tracing::record_error(tracing::Level::ERROR, || async move {
+            extract_tracing(&request);
+
+            let req = request.into_inner();
+            let direction = proto::QuoteDirection::from_i32(req.direction)
+                .ok_or_else(|| Status::invalid_argument("unknown quote direction"))?;
+            let amount = match req.amount {
+                Some(get_quote_request::Amount::AmountInSatoshis(sats)) => {
+                    QuoteAmount::Sats(Sats::from_major(sats))
+                }
+                Some(get_quote_request::Amount::AmountInCents(cents)) => {
+                    QuoteAmount::Cents(UsdCents::from_major(cents))
+                }
+                None => return Err(Status::invalid_argument("missing quote amount")),
+            };
+            let quote = self.app.get_quote(direction.try_into()?, amount).await?;
+            Ok(Response::new(GetQuoteResponse {
+                quote: Some(PriceQuote::try_from(quote)?),
+            }))
+        })
+        .await
+    }
+
+    #[instrument(name = "price_server.accept_quote", skip_all,
+        fields(quote_id = %request.get_ref().quote_id,
+            error, error.level, error.message),
+        err
+    )]
+    async fn accept_quote(
+        &self,
+        request: Request<AcceptQuoteRequest>,
+    ) -> Result<Response<AcceptQuoteResponse>, Status> {
+        shared::tracing::record_error(tracing::Level::ERROR, || async move {
+            extract_tracing(&request);
+
+            let req = request.into_inner();
+            let id = uuid::Uuid::parse_str(&req.quote_id)
+                .map_err(|_| Status::invalid_argument("invalid quote id"))?;
+            let quote = self.app.accept_quote(id, &req.signature).await?;
+            Ok(Response::new(AcceptQuoteResponse {
+                quote: Some(PriceQuote::try_from(quote)?),
+            }))
+        })
+        .await
+    }
+
+    #[instrument(name = "price_server.get_price_sources", skip_all, fields(error, error.level, error.message), err)]
+    async fn get_price_sources(
+        &self,
+        request: Request<GetPriceSourcesRequest>,
+    ) -> Result<Response<GetPriceSourcesResponse>, Status> {
+        shared::tracing::record_error(tracing::Level::ERROR, || async move {
+            extract_tracing(&request);
+
+            let sources = self
+                .app
+                .get_price_sources()
+                .await
+                .into_iter()
+                .map(proto::PriceSource::try_from)
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Response::new(GetPriceSourcesResponse { sources }))
+        })
+        .await
+    }
+
+    #[instrument(name = "price_server.subscribe_prices", skip_all, fields(error, error.level, error.message), err)]
+    async fn subscribe_prices(
+        &self,
+        request: Request<SubscribePricesRequest>,
+    ) -> Result<Response<Self::SubscribePricesStream>, Status> {
+        extract_tracing(&request);
+
+        let stream = self
+            .app
+            .subscribe_prices(self.price_subscription_min_interval)
+            .map(|update| SubscribePricesResponse::try_from(update).map_err(Status::from));
+        Ok(Response::new(Box::pin(stream)))
+    }
 }
 
 pub(crate) async fn start(
     server_config: PriceServerConfig,
     app: PriceApp,
 ) -> Result<(), PriceServerError> {
-    let price_service = Price { app };
+    let price_service = Price {
+        app,
+        price_subscription_min_interval: server_config.price_subscription_min_interval,
+    };
     Server::builder()
         .add_service(proto::price_service_server::PriceServiceServer::new(
             price_service,
@@ -0,0 +1,98 @@
+use chrono::Duration;
+use rust_decimal::prelude::*;
+use std::collections::VecDeque;
+
+use shared::time::TimeStamp;
+
+/// Rolling window of mid prices used to estimate how fast the market is moving.
+#[derive(Debug, Clone)]
+pub struct MidPriceHistory {
+    window: Duration,
+    mid_prices: VecDeque<(TimeStamp, Decimal)>,
+}
+
+impl MidPriceHistory {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            mid_prices: VecDeque::new(),
+        }
+    }
+
+    pub fn record(&mut self, timestamp: TimeStamp, mid_price: Decimal) {
+        self.mid_prices.push_back((timestamp, mid_price));
+        while let Some((oldest, _)) = self.mid_prices.front() {
+            if &timestamp - oldest > self.window {
+                self.mid_prices.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Realized volatility of the mid price over the window, ie. the root mean square of
+    /// the tick-to-tick returns. Averaging over the number of returns keeps the value
+    /// independent of how many ticks the providers happen to publish in the window.
+    /// `None` if there are not enough recent ticks.
+    pub fn realized_volatility(&self) -> Option<Decimal> {
+        let mut prices = self
+            .mid_prices
+            .iter()
+            .filter(|(timestamp, _)| timestamp.duration_since() <= self.window)
+            .map(|(_, price)| *price);
+        let mut prev = prices.next()?;
+        let mut variance = Decimal::ZERO;
+        let mut n_returns = 0;
+        for price in prices {
+            if !prev.is_zero() {
+                let ret = price / prev - Decimal::ONE;
+                variance += ret * ret;
+                n_returns += 1;
+            }
+            prev = price;
+        }
+        if n_returns == 0 {
+            return None;
+        }
+        Decimal::from_f64((variance / Decimal::from(n_returns)).to_f64()?.sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn realized_volatility() {
+        let mut history = MidPriceHistory::new(Duration::seconds(300));
+        let now = TimeStamp::now();
+        history.record(now, Decimal::from(100));
+        assert_eq!(history.realized_volatility(), None);
+
+        history.record(now, Decimal::from(103));
+        history.record(now, Decimal::from(103));
+        let volatility = history.realized_volatility().unwrap();
+        assert_eq!(volatility.round_dp(6), Decimal::new(21_2132, 6));
+
+        history.record(now, Decimal::new(98_88, 2));
+        let volatility = history.realized_volatility().unwrap();
+        assert_eq!(volatility.round_dp(6), Decimal::new(28_8675, 6));
+    }
+
+    #[test]
+    fn realized_volatility_does_not_grow_with_tick_count() {
+        let now = TimeStamp::now();
+        let mut sparse = MidPriceHistory::new(Duration::seconds(300));
+        let mut dense = MidPriceHistory::new(Duration::seconds(300));
+        for price in [100, 101, 100] {
+            sparse.record(now, Decimal::from(price));
+        }
+        for price in [100, 101, 100, 101, 100, 101, 100] {
+            dense.record(now, Decimal::from(price));
+        }
+        assert_eq!(
+            sparse.realized_volatility().unwrap().round_dp(4),
+            dense.realized_volatility().unwrap().round_dp(4)
+        );
+    }
+}
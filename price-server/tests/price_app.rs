@@ -1,7 +1,8 @@
+use futures::StreamExt;
 use rust_decimal_macros::dec;
 use std::fs;
 
-use price_server::{app::*, ExchangePriceCacheConfig};
+use price_server::{app::*, ExchangePriceCacheConfig, PriceMixerConfig, QuotesConfig};
 use shared::{payload::*, pubsub::*, time::*};
 
 #[derive(serde::Deserialize)]
@@ -24,6 +25,10 @@ async fn price_app() -> anyhow::Result<()> {
 
     let (_, recv) = futures::channel::mpsc::unbounded();
 
+    let pg_host = std::env::var("PG_HOST").unwrap_or("localhost".to_string());
+    let pg_con = format!("postgres://user:password@{pg_host}:5432/pg",);
+    let pool = sqlx::PgPool::connect(&pg_con).await?;
+
     let ex_cfgs = ExchangeWeights {
         okex: Some(dec!(1.0)),
         bitfinex: None,
@@ -36,10 +41,20 @@ async fn price_app() -> anyhow::Result<()> {
             base_fee_rate: dec!(0.001),
             immediate_fee_rate: dec!(0.01),
             delayed_fee_rate: dec!(0.1),
+            delayed_fee_term_structure: Vec::new(),
+            dynamic_fee: DynamicFeeConfig::default(),
         },
         tick_recv,
         ExchangePriceCacheConfig::default(),
         ex_cfgs,
+        PriceMixerConfig::default(),
+        false,
+        QuotesConfig {
+            enabled: true,
+            signing_secret: "secret".to_string(),
+            ..QuotesConfig::default()
+        },
+        Some(pool),
     )
     .await?;
 
@@ -72,6 +87,7 @@ async fn price_app() -> anyhow::Result<()> {
         assert!(false)
     }
 
+    let mut prices = Box::pin(app.subscribe_prices(std::time::Duration::ZERO));
     payload.timestamp = TimeStamp::now();
     publisher
         .publish(PriceStreamPayload::OkexBtcSwapPricePayload(payload))
@@ -98,20 +114,20 @@ async fn price_app() -> anyhow::Result<()> {
     assert_eq!(cents, UsdCents::from_major(1));
 
     let cents = app
-        .get_cents_from_sats_for_future_buy(Sats::from_major(100_000_000))
+        .get_cents_from_sats_for_future_buy(Sats::from_major(100_000_000), 600)
         .await?;
     assert_eq!(cents, UsdCents::from_major(89900));
     let cents = app
-        .get_cents_from_sats_for_future_buy(Sats::from_major(1))
+        .get_cents_from_sats_for_future_buy(Sats::from_major(1), 600)
         .await?;
     assert_eq!(cents, UsdCents::from_major(0));
 
     let future_buy = app
-        .get_cents_from_sats_for_future_sell(Sats::from_major(100_000_000))
+        .get_cents_from_sats_for_future_sell(Sats::from_major(100_000_000), 600)
         .await?;
     assert_eq!(future_buy, UsdCents::from_major(1101000));
     let future_buy = app
-        .get_cents_from_sats_for_future_sell(Sats::from_major(1))
+        .get_cents_from_sats_for_future_sell(Sats::from_major(1), 600)
         .await?;
     assert_eq!(future_buy, UsdCents::from_major(1));
 
@@ -130,25 +146,100 @@ async fn price_app() -> anyhow::Result<()> {
     assert_eq!(sats, Sats::from_major(98));
 
     let sats = app
-        .get_sats_from_cents_for_future_buy(UsdCents::from_major(1000000))
+        .get_sats_from_cents_for_future_buy(UsdCents::from_major(1000000), 600)
         .await?;
     assert_eq!(sats, Sats::from_major(1101000000));
     let sats = app
-        .get_sats_from_cents_for_future_buy(UsdCents::from_major(1))
+        .get_sats_from_cents_for_future_buy(UsdCents::from_major(1), 600)
         .await?;
     assert_eq!(sats, Sats::from_major(1101));
 
     let sats = app
-        .get_sats_from_cents_for_future_sell(UsdCents::from_major(1000000))
+        .get_sats_from_cents_for_future_sell(UsdCents::from_major(1000000), 600)
         .await?;
     assert_eq!(sats, Sats::from_major(89900000));
     let sats = app
-        .get_sats_from_cents_for_future_sell(UsdCents::from_major(1))
+        .get_sats_from_cents_for_future_sell(UsdCents::from_major(1), 600)
         .await?;
     assert_eq!(sats, Sats::from_major(89));
 
     let ratio = app.get_cents_per_sat_exchange_mid_rate().await?;
     assert_eq!(ratio, 0.0055);
 
+    let update = prices.next().await.expect("price update");
+    assert_eq!(*update.mid_price_of_one_sat.amount(), dec!(0.0055));
+
+    let quote = app
+        .get_quote(
+            QuoteDirection::BuyCents,
+            QuoteAmount::Sats(Sats::from_major(100_000_000)),
+        )
+        .await?;
+    assert_eq!(quote.amount_in_satoshis, dec!(100_000_000));
+    assert_eq!(quote.amount_in_cents, dec!(98900));
+    assert_eq!(quote.fee_amount_in_cents, dec!(1100));
+    assert!(quote.accepted_at.is_none());
+
+    assert!(matches!(
+        app.accept_quote(quote.id, "00").await,
+        Err(PriceAppError::InvalidQuoteSignature(_))
+    ));
+    let accepted = app.accept_quote(quote.id, &quote.signature).await?;
+    assert!(accepted.accepted_at.is_some());
+    assert_eq!(accepted.amount_in_cents, quote.amount_in_cents);
+    assert!(matches!(
+        app.accept_quote(quote.id, &quote.signature).await,
+        Err(PriceAppError::QuoteAlreadyAccepted(_))
+    ));
+
+    let quote = app
+        .get_quote(
+            QuoteDirection::BuyCents,
+            QuoteAmount::Cents(UsdCents::from_major(98900)),
+        )
+        .await?;
+    assert_eq!(quote.amount_in_cents, dec!(98900));
+    assert!(quote.fee_amount_in_cents > dec!(0));
+    assert!(quote.fee_amount_in_cents < quote.amount_in_cents);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn quotes_unavailable_without_pool() -> anyhow::Result<()> {
+    let (_tick_send, tick_recv) =
+        memory::channel(chrono::Duration::from_std(std::time::Duration::from_secs(2)).unwrap());
+    let (_, recv) = futures::channel::mpsc::unbounded();
+
+    let app = PriceApp::run(
+        recv,
+        PriceServerHealthCheckConfig::default(),
+        FeeCalculatorConfig::default(),
+        tick_recv,
+        ExchangePriceCacheConfig::default(),
+        ExchangeWeights {
+            okex: Some(dec!(1.0)),
+            bitfinex: None,
+        },
+        PriceMixerConfig::default(),
+        false,
+        QuotesConfig::default(),
+        None,
+    )
+    .await?;
+
+    assert!(matches!(
+        app.get_quote(
+            QuoteDirection::BuyCents,
+            QuoteAmount::Sats(Sats::from_major(100_000_000)),
+        )
+        .await,
+        Err(PriceAppError::QuotesUnavailable)
+    ));
+    assert!(matches!(
+        app.accept_quote(uuid::Uuid::new_v4(), "").await,
+        Err(PriceAppError::QuotesUnavailable)
+    ));
+
     Ok(())
 }
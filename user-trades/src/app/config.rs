@@ -1,18 +1,30 @@
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
+use crate::{reconciliation::ReconciliationConfig, webhook::UserTradesWebhookConfig};
+
 #[serde_with::serde_as]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserTradesConfig {
     #[serde_as(as = "serde_with::DurationSeconds<u64>")]
     #[serde(default = "default_galoy_poll_frequency")]
     pub galoy_poll_frequency: Duration,
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    #[serde(default = "default_quarantine_unpaired_after")]
+    pub quarantine_unpaired_after: chrono::Duration,
+    #[serde(default)]
+    pub webhook: UserTradesWebhookConfig,
+    #[serde(default)]
+    pub reconciliation: ReconciliationConfig,
 }
 
 impl Default for UserTradesConfig {
     fn default() -> Self {
         Self {
             galoy_poll_frequency: default_galoy_poll_frequency(),
+            quarantine_unpaired_after: default_quarantine_unpaired_after(),
+            webhook: UserTradesWebhookConfig::default(),
+            reconciliation: ReconciliationConfig::default(),
         }
     }
 }
@@ -20,3 +32,7 @@ impl Default for UserTradesConfig {
 fn default_galoy_poll_frequency() -> Duration {
     Duration::from_secs(10)
 }
+
+fn default_quarantine_unpaired_after() -> chrono::Duration {
+    chrono::Duration::hours(1)
+}
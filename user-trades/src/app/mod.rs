@@ -1,10 +1,12 @@
 mod config;
 
+use futures::stream::StreamExt;
 use sqlxmq::OwnedHandle;
 
 use galoy_client::{GaloyClient, GaloyClientConfig};
+use shared::health::HealthCheckTrigger;
 
-use crate::{error::*, job, user_trades::*};
+use crate::{error::*, job, reconciliation::*, user_trades::*, webhook};
 pub use config::*;
 
 pub struct UserTradesApp {
@@ -14,8 +16,12 @@ pub struct UserTradesApp {
 impl UserTradesApp {
     pub async fn run(
         pool: sqlx::PgPool,
+        health_check_trigger: HealthCheckTrigger,
         UserTradesConfig {
             galoy_poll_frequency,
+            quarantine_unpaired_after,
+            webhook: webhook_config,
+            reconciliation,
         }: UserTradesConfig,
         galoy_client_cfg: GaloyClientConfig,
     ) -> Result<Self, UserTradesError> {
@@ -27,14 +33,47 @@ impl UserTradesApp {
             user_trades,
             GaloyClient::connect(galoy_client_cfg).await?,
             galoy_poll_frequency,
+            quarantine_unpaired_after,
+            reconciliation,
+        )
+        .await?;
+        job::spawn_reconcile_balances(&pool, std::time::Duration::from_secs(0)).await?;
+        Self::spawn_health_checker(
+            health_check_trigger,
+            ReconciliationReports::new(pool.clone()),
+        );
+        let webhook_pool = pool.clone();
+        let webhook = async move {
+            if webhook_config.enabled {
+                tracing::info!(
+                    listen_port = webhook_config.listen_port,
+                    "starting galoy transactions webhook"
+                );
+                webhook::run(webhook_config, webhook_pool).await?;
+            }
+            Ok::<_, UserTradesError>(())
+        };
+        futures::future::try_join(
+            webhook,
+            Self::spawn_poll_galoy_transactions(pool, galoy_poll_frequency),
         )
         .await?;
-        Self::spawn_poll_galoy_transactions(pool, galoy_poll_frequency).await?;
         Ok(Self {
             _runner: job_runner,
         })
     }
 
+    fn spawn_health_checker(
+        mut health_check_trigger: HealthCheckTrigger,
+        reconciliation_reports: ReconciliationReports,
+    ) {
+        tokio::spawn(async move {
+            while let Some(check) = health_check_trigger.next().await {
+                let _ = check.send(reconciliation_reports.healthy().await);
+            }
+        });
+    }
+
     async fn spawn_poll_galoy_transactions(
         pool: sqlx::PgPool,
         delay: std::time::Duration,
@@ -15,6 +15,16 @@ pub enum UserTradesError {
     PubSub(#[from] PublisherError),
     #[error("UserTradesError - GaloyClient: {0}")]
     GaloyClient(#[from] galoy_client::GaloyClientError),
+    #[error("UserTradesError - WebhookServer: {0}")]
+    WebhookServer(#[from] axum::Error),
+    #[error("UserTradesError - InvalidWebhookSignature")]
+    InvalidWebhookSignature,
+    #[error("UserTradesError - InvalidWebhookPayload: {0}")]
+    InvalidWebhookPayload(String),
+    #[error("UserTradesError - GaloyTransactionResolution: {0}")]
+    GaloyTransactionResolution(String),
+    #[error("UserTradesError - WatermarkBehind: user trades are not synced up to {0}")]
+    WatermarkBehind(chrono::DateTime<chrono::Utc>),
     #[error("UserTradesError - Leger: {0}")]
     Ledger(#[from] ledger::LedgerError),
 }
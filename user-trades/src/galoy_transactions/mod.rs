@@ -1,11 +1,19 @@
 use rust_decimal::Decimal;
 use sqlx::{PgPool, Postgres, QueryBuilder, Transaction};
 
-use crate::error::UserTradesError;
+use crate::{error::UserTradesError, user_trades::*};
 use galoy_client::{GaloyTransaction, SettlementCurrency};
 
 pub struct LatestCursor(pub String);
 
+/// Transactions pushed by the webhook don't carry a reliable cursor so they are
+/// ignored when picking the cursor to poll from until polling has seen them too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionSource {
+    Polling,
+    Webhook,
+}
+
 #[derive(Debug, Clone)]
 pub struct UnpairedTransaction {
     pub id: String,
@@ -18,6 +26,42 @@ pub struct UnpairedTransaction {
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
+impl UnpairedTransaction {
+    /// Builds the user trade represented by the two legs of a conversion
+    pub fn pair_with(self, other: UnpairedTransaction) -> NewUserTrade {
+        let external_ref = if self.settlement_currency == SettlementCurrency::BTC {
+            ExternalRef {
+                timestamp: self.created_at,
+                btc_tx_id: self.id,
+                usd_tx_id: other.id,
+            }
+        } else {
+            ExternalRef {
+                timestamp: self.created_at,
+                btc_tx_id: other.id,
+                usd_tx_id: self.id,
+            }
+        };
+        if self.settlement_amount < Decimal::ZERO {
+            NewUserTrade {
+                buy_unit: self.settlement_currency.into(),
+                buy_amount: self.settlement_amount.abs(),
+                sell_unit: other.settlement_currency.into(),
+                sell_amount: other.settlement_amount.abs(),
+                external_ref,
+            }
+        } else {
+            NewUserTrade {
+                buy_unit: other.settlement_currency.into(),
+                buy_amount: other.settlement_amount.abs(),
+                sell_unit: self.settlement_currency.into(),
+                sell_amount: self.settlement_amount.abs(),
+                external_ref,
+            }
+        }
+    }
+}
+
 pub struct UnpairedTransactions<'a> {
     pub list: Vec<UnpairedTransaction>,
     pub tx: Transaction<'a, Postgres>,
@@ -35,12 +79,13 @@ impl GaloyTransactions {
     pub async fn persist_all(
         &self,
         transactions: Vec<GaloyTransaction>,
+        source: TransactionSource,
     ) -> Result<(), UserTradesError> {
         if transactions.is_empty() {
             return Ok(());
         }
         let mut query_builder: QueryBuilder<Postgres> = QueryBuilder::new(
-            "INSERT INTO galoy_transactions (id, cursor, is_paired, settlement_amount, settlement_currency, settlement_method, cents_per_unit, amount_in_usd_cents, created_at, memo, direction)"
+            "INSERT INTO galoy_transactions (id, cursor, is_paired, settlement_amount, settlement_currency, settlement_method, cents_per_unit, amount_in_usd_cents, created_at, memo, direction, from_webhook)"
         );
         query_builder.push_values(
             transactions,
@@ -50,6 +95,7 @@ impl GaloyTransactions {
                  id,
                  cursor,
                  settlement_amount,
+                 settlement_fee: _,
                  settlement_method,
                  settlement_currency,
                  cents_per_unit,
@@ -69,19 +115,24 @@ impl GaloyTransactions {
                 builder.push_bind(created_at);
                 builder.push_bind(memo);
                 builder.push_bind(direction.to_string());
+                builder.push_bind(source == TransactionSource::Webhook);
             },
         );
-        query_builder.push("ON CONFLICT DO NOTHING");
+        query_builder.push(
+            " ON CONFLICT (id) DO UPDATE SET cursor = EXCLUDED.cursor, from_webhook = FALSE
+              WHERE galoy_transactions.from_webhook AND NOT EXCLUDED.from_webhook",
+        );
         let query = query_builder.build();
         query.execute(&self.pool).await?;
         Ok(())
     }
 
     pub async fn get_latest_cursor(&self) -> Result<Option<LatestCursor>, UserTradesError> {
-        let res =
-            sqlx::query!("SELECT cursor FROM galoy_transactions ORDER BY created_at DESC LIMIT 1")
-                .fetch_optional(&self.pool)
-                .await?;
+        let res = sqlx::query!(
+            "SELECT cursor FROM galoy_transactions WHERE from_webhook = FALSE ORDER BY created_at DESC LIMIT 1"
+        )
+        .fetch_optional(&self.pool)
+        .await?;
 
         if let Some(res) = res {
             Ok(Some(LatestCursor(res.cursor)))
@@ -90,6 +141,18 @@ impl GaloyTransactions {
         }
     }
 
+    /// Creation time of the newest transaction, whether it was polled or pushed by the webhook
+    pub async fn latest_created_at(
+        &self,
+    ) -> Result<Option<chrono::DateTime<chrono::Utc>>, UserTradesError> {
+        let res = sqlx::query!("SELECT MAX(created_at) AS created_at FROM galoy_transactions")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(res.created_at)
+    }
+
+    /// Quarantined transactions are included when a transaction with the same
+    /// `created_at` arrived since, so that a late counterpart still pairs them.
     pub async fn list_unpaired_transactions(
         &self,
     ) -> Result<UnpairedTransactions, UserTradesError> {
@@ -97,8 +160,13 @@ impl GaloyTransactions {
         let res = sqlx::query!(
             "
             SELECT id, direction, amount_in_usd_cents, memo, settlement_method, settlement_amount, settlement_currency, created_at
-            FROM galoy_transactions
-            WHERE is_paired = false AND amount_in_usd_cents != 0 ORDER BY created_at FOR UPDATE
+            FROM galoy_transactions t
+            WHERE is_paired = false AND ignored_at IS NULL AND amount_in_usd_cents != 0
+              AND (quarantined_at IS NULL OR EXISTS (
+                SELECT 1 FROM galoy_transactions o
+                WHERE o.is_paired = false AND o.quarantined_at IS NULL AND o.created_at = t.created_at AND o.id != t.id
+              ))
+            ORDER BY created_at FOR UPDATE
          "
         )
         .fetch_all(&mut tx)
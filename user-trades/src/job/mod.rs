@@ -1,4 +1,6 @@
 mod poll_galoy_transactions;
+mod process_galoy_transactions;
+mod reconcile_balances;
 
 use sqlxmq::{job, CurrentJob, JobBuilder, JobRegistry, OwnedHandle};
 use tracing::instrument;
@@ -9,16 +11,22 @@ use shared::sqlxmq::JobExecutor;
 use std::time::Duration;
 
 use crate::{
-    error::UserTradesError, galoy_transactions::GaloyTransactions, user_trades::UserTrades,
+    error::UserTradesError, galoy_transactions::GaloyTransactions,
+    quarantine::GaloyTransactionsQuarantine, reconciliation::*, user_trades::UserTrades,
+    watermark::UserTradesWatermark,
 };
 
 pub const PUBLISH_LIABILITY_ID: Uuid = uuid!("00000000-0000-0000-0000-000000000001");
 pub const POLL_GALOY_TRANSACTIONS_ID: Uuid = uuid!("00000000-0000-0000-0000-000000000002");
+pub const PROCESS_GALOY_TRANSACTIONS_ID: Uuid = uuid!("00000000-0000-0000-0000-000000000003");
+pub const RECONCILE_BALANCES_ID: Uuid = uuid!("00000000-0000-0000-0000-000000000004");
 
 #[derive(Debug, Clone)]
 struct LiabilityPublishDelay(Duration);
 #[derive(Debug, Clone)]
 struct PollGaloyTransactionsDelay(Duration);
+#[derive(Debug, Clone)]
+struct QuarantineUnpairedAfter(chrono::Duration);
 
 #[allow(clippy::too_many_arguments)]
 pub async fn start_job_runner(
@@ -27,12 +35,21 @@ pub async fn start_job_runner(
     user_trades: UserTrades,
     galoy_client: GaloyClient,
     galoy_poll_delay: Duration,
+    quarantine_unpaired_after: chrono::Duration,
+    reconciliation: ReconciliationConfig,
 ) -> Result<OwnedHandle, UserTradesError> {
-    let mut registry = JobRegistry::new(&[poll_galoy_transactions]);
+    let mut registry = JobRegistry::new(&[
+        poll_galoy_transactions,
+        process_galoy_transactions,
+        reconcile_balances,
+    ]);
     registry.set_context(ledger);
     registry.set_context(user_trades);
     registry.set_context(galoy_client);
     registry.set_context(PollGaloyTransactionsDelay(galoy_poll_delay));
+    registry.set_context(QuarantineUnpairedAfter(quarantine_unpaired_after));
+    registry.set_context(ReconciliationReports::new(pool.clone()));
+    registry.set_context(reconciliation);
 
     Ok(registry
         .runner(&pool)
@@ -62,12 +79,51 @@ pub async fn spawn_poll_galoy_transactions(
     }
 }
 
+#[instrument(name = "user_trades.job.spawn_process_galoy_transactions", skip_all,fields(error, error.level, error.message), err)]
+pub async fn spawn_process_galoy_transactions(pool: &sqlx::PgPool) -> Result<(), UserTradesError> {
+    match JobBuilder::new_with_id(PROCESS_GALOY_TRANSACTIONS_ID, "process_galoy_transactions")
+        .set_channel_name("user_trades")
+        .set_channel_args("process_galoy_transactions")
+        .spawn(pool)
+        .await
+    {
+        Err(sqlx::Error::Database(err)) if err.message().contains("duplicate key") => Ok(()),
+        Err(e) => {
+            shared::tracing::insert_error_fields(tracing::Level::ERROR, &e);
+            Err(e.into())
+        }
+        Ok(_) => Ok(()),
+    }
+}
+
+#[instrument(name = "user_trades.job.spawn_reconcile_balances", skip_all,fields(error, error.level, error.message), err)]
+pub async fn spawn_reconcile_balances(
+    pool: &sqlx::PgPool,
+    duration: Duration,
+) -> Result<(), UserTradesError> {
+    match JobBuilder::new_with_id(RECONCILE_BALANCES_ID, "reconcile_balances")
+        .set_channel_name("user_trades")
+        .set_channel_args("reconcile_balances")
+        .set_delay(duration)
+        .spawn(pool)
+        .await
+    {
+        Err(sqlx::Error::Database(err)) if err.message().contains("duplicate key") => Ok(()),
+        Err(e) => {
+            shared::tracing::insert_error_fields(tracing::Level::ERROR, &e);
+            Err(e.into())
+        }
+        Ok(_) => Ok(()),
+    }
+}
+
 #[job(name = "poll_galoy_transactions")]
 async fn poll_galoy_transactions(
     mut current_job: CurrentJob,
     user_trades: UserTrades,
     galoy: GaloyClient,
     PollGaloyTransactionsDelay(delay): PollGaloyTransactionsDelay,
+    QuarantineUnpairedAfter(quarantine_unpaired_after): QuarantineUnpairedAfter,
     ledger: ledger::Ledger,
 ) -> Result<(), UserTradesError> {
     let pool = current_job.pool().clone();
@@ -77,12 +133,17 @@ async fn poll_galoy_transactions(
         .expect("couldn't build JobExecutor")
         .execute(|_| async move {
             let galoy_transactions = GaloyTransactions::new(pool.clone());
+            let watermark = UserTradesWatermark::new(pool.clone());
+            let quarantine = GaloyTransactionsQuarantine::new(pool.clone());
             poll_galoy_transactions::execute(
                 &pool,
                 &user_trades,
                 &galoy_transactions,
                 &galoy,
                 &ledger,
+                &watermark,
+                &quarantine,
+                quarantine_unpaired_after,
             )
             .await
         })
@@ -94,3 +155,53 @@ async fn poll_galoy_transactions(
     }
     Ok(())
 }
+
+#[job(name = "process_galoy_transactions")]
+async fn process_galoy_transactions(
+    mut current_job: CurrentJob,
+    user_trades: UserTrades,
+    ledger: ledger::Ledger,
+) -> Result<(), UserTradesError> {
+    let pool = current_job.pool().clone();
+    JobExecutor::builder(&mut current_job)
+        .initial_retry_delay(Duration::from_secs(1))
+        .build()
+        .expect("couldn't build JobExecutor")
+        .execute(|_| async move {
+            let galoy_transactions = GaloyTransactions::new(pool.clone());
+            let watermark = UserTradesWatermark::new(pool.clone());
+            process_galoy_transactions::execute(
+                &pool,
+                &user_trades,
+                &galoy_transactions,
+                &ledger,
+                &watermark,
+            )
+            .await
+        })
+        .await?;
+    Ok(())
+}
+
+#[job(name = "reconcile_balances")]
+async fn reconcile_balances(
+    mut current_job: CurrentJob,
+    galoy: GaloyClient,
+    ledger: ledger::Ledger,
+    reports: ReconciliationReports,
+    config: ReconciliationConfig,
+) -> Result<(), UserTradesError> {
+    let pool = current_job.pool().clone();
+    let frequency = config.frequency;
+    JobExecutor::builder(&mut current_job)
+        .initial_retry_delay(Duration::from_secs(60))
+        .build()
+        .expect("couldn't build JobExecutor")
+        .execute(|_| async move {
+            let watermark = UserTradesWatermark::new(pool);
+            reconcile_balances::execute(&galoy, &ledger, &watermark, &reports, &config).await
+        })
+        .await?;
+    spawn_reconcile_balances(current_job.pool(), frequency).await?;
+    Ok(())
+}
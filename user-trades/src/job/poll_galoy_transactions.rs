@@ -3,27 +3,57 @@ use tracing::{instrument, warn};
 
 use std::collections::BTreeMap;
 
-use galoy_client::{GaloyClient, SettlementCurrency, TxCursor};
+use galoy_client::{GaloyClient, TxCursor};
 
-use crate::{error::UserTradesError, galoy_transactions::*, user_trades::*};
+use crate::{
+    error::UserTradesError, galoy_transactions::*, quarantine::*, user_trades::*, watermark::*,
+};
 
 #[instrument(
     name = "user_trades.job.poll_galoy_transactions",
     skip_all,
     err,
-    fields(n_galoy_txs, n_unpaired_txs, n_user_trades, has_more, n_bad_trades)
+    fields(
+        n_galoy_txs,
+        n_unpaired_txs,
+        n_user_trades,
+        has_more,
+        n_bad_trades,
+        n_quarantined_txs,
+        watermark
+    )
 )]
+#[allow(clippy::too_many_arguments)]
 pub(super) async fn execute(
     pool: &sqlx::PgPool,
     user_trades: &UserTrades,
     galoy_transactions: &GaloyTransactions,
     galoy: &GaloyClient,
     ledger: &ledger::Ledger,
+    watermark: &UserTradesWatermark,
+    quarantine: &GaloyTransactionsQuarantine,
+    quarantine_unpaired_after: chrono::Duration,
 ) -> Result<bool, UserTradesError> {
+    let started_at = chrono::Utc::now();
     let has_more = import_galoy_transactions(galoy_transactions, galoy.clone()).await?;
     update_user_trades(galoy_transactions, user_trades).await?;
+    if !has_more {
+        let n_quarantined = quarantine
+            .quarantine_stale(quarantine_unpaired_after)
+            .await?;
+        tracing::Span::current()
+            .record("n_quarantined_txs", &tracing::field::display(n_quarantined));
+    }
     update_ledger(pool, user_trades, ledger).await?;
 
+    if !has_more {
+        let latest_cursor = galoy_transactions.get_latest_cursor().await?;
+        let synced_at = watermark
+            .advance(started_at, latest_cursor.map(|cursor| cursor.0))
+            .await?;
+        tracing::Span::current().record("watermark", &tracing::field::display(synced_at));
+    }
+
     Ok(has_more)
 }
 
@@ -41,12 +71,14 @@ async fn import_galoy_transactions(
     );
     tracing::Span::current().record("has_more", &tracing::field::display(transactions.has_more));
     if !transactions.list.is_empty() {
-        galoy_transactions.persist_all(transactions.list).await?;
+        galoy_transactions
+            .persist_all(transactions.list, TransactionSource::Polling)
+            .await?;
     }
     Ok(transactions.has_more)
 }
 
-async fn update_user_trades(
+pub(super) async fn update_user_trades(
     galoy_transactions: &GaloyTransactions,
     user_trades: &UserTrades,
 ) -> Result<(), UserTradesError> {
@@ -101,7 +133,7 @@ fn find_trades_needing_correction(
     (filtered_trades, bad_trades)
 }
 
-async fn update_ledger(
+pub(super) async fn update_ledger(
     pool: &sqlx::PgPool,
     user_trades: &UserTrades,
     ledger: &ledger::Ledger,
@@ -226,38 +258,10 @@ fn unify(unpaired_transactions: Vec<UnpairedTransaction>) -> (Vec<NewUserTrade>,
                 continue;
             };
             let other = txs.remove(&idx).unwrap();
-            let external_ref = if tx.settlement_currency == SettlementCurrency::BTC {
-                ExternalRef {
-                    timestamp: tx.created_at,
-                    btc_tx_id: tx.id,
-                    usd_tx_id: other.id,
-                }
-            } else {
-                ExternalRef {
-                    timestamp: tx.created_at,
-                    btc_tx_id: other.id,
-                    usd_tx_id: tx.id,
-                }
-            };
-            paired_ids.push(external_ref.btc_tx_id.clone());
-            paired_ids.push(external_ref.usd_tx_id.clone());
-            if tx.settlement_amount < Decimal::ZERO {
-                user_trades.push(NewUserTrade {
-                    buy_unit: tx.settlement_currency.into(),
-                    buy_amount: tx.settlement_amount.abs(),
-                    sell_unit: other.settlement_currency.into(),
-                    sell_amount: other.settlement_amount.abs(),
-                    external_ref,
-                });
-            } else {
-                user_trades.push(NewUserTrade {
-                    buy_unit: other.settlement_currency.into(),
-                    buy_amount: other.settlement_amount.abs(),
-                    sell_unit: tx.settlement_currency.into(),
-                    sell_amount: tx.settlement_amount.abs(),
-                    external_ref,
-                });
-            }
+            let trade = tx.pair_with(other);
+            paired_ids.push(trade.external_ref.btc_tx_id.clone());
+            paired_ids.push(trade.external_ref.usd_tx_id.clone());
+            user_trades.push(trade);
         }
     }
     tracing::Span::current().record("n_unpaired_txs", &tracing::field::display(unpaired));
@@ -283,16 +287,6 @@ fn is_pair(tx1: &UnpairedTransaction, tx2: &UnpairedTransaction) -> bool {
     false
 }
 
-impl From<SettlementCurrency> for UserTradeUnit {
-    fn from(currency: SettlementCurrency) -> Self {
-        match currency {
-            SettlementCurrency::BTC => Self::Satoshi,
-            SettlementCurrency::USD => Self::UsdCent,
-            _ => unimplemented!(),
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use galoy_client::SettlementCurrency;
@@ -0,0 +1,30 @@
+use tracing::instrument;
+
+use super::poll_galoy_transactions::{update_ledger, update_user_trades};
+use crate::{error::UserTradesError, galoy_transactions::*, user_trades::*, watermark::*};
+
+/// Pairs the galoy transactions that are already stored and posts the resulting
+/// user trades to the ledger without waiting for the next poll.
+/// Galoy pushes transactions as they settle, so the watermark moves up to the newest one
+/// (held back by any trade that is not in the ledger yet) while the cursor is left to polling.
+#[instrument(
+    name = "user_trades.job.process_galoy_transactions",
+    skip_all,
+    err,
+    fields(n_unpaired_txs, n_user_trades, n_bad_trades, watermark)
+)]
+pub(super) async fn execute(
+    pool: &sqlx::PgPool,
+    user_trades: &UserTrades,
+    galoy_transactions: &GaloyTransactions,
+    ledger: &ledger::Ledger,
+    watermark: &UserTradesWatermark,
+) -> Result<(), UserTradesError> {
+    update_user_trades(galoy_transactions, user_trades).await?;
+    update_ledger(pool, user_trades, ledger).await?;
+    if let Some(latest) = galoy_transactions.latest_created_at().await? {
+        let synced_at = watermark.advance(latest, None).await?;
+        tracing::Span::current().record("watermark", &tracing::field::display(synced_at));
+    }
+    Ok(())
+}
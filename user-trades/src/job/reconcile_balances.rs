@@ -0,0 +1,130 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use tracing::instrument;
+
+use galoy_client::GaloyClient;
+
+use std::time::Duration;
+
+use crate::{error::UserTradesError, reconciliation::*, watermark::UserTradesWatermark};
+
+const WATERMARK_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[instrument(
+    name = "user_trades.job.reconcile_balances",
+    skip_all,
+    err,
+    fields(
+        ledger_usd_liability_in_cents,
+        galoy_usd_balance_in_cents,
+        usd_difference_in_cents,
+        ledger_btc_balance_in_sats,
+        galoy_btc_balance_in_sats,
+        btc_difference_in_sats,
+        is_baseline,
+        usd_drift_in_cents,
+        btc_drift_in_sats,
+        btc_checked,
+        within_tolerance,
+        error,
+        error.level,
+        error.message
+    )
+)]
+pub(super) async fn execute(
+    galoy: &GaloyClient,
+    ledger: &ledger::Ledger,
+    watermark: &UserTradesWatermark,
+    reports: &ReconciliationReports,
+    config: &ReconciliationConfig,
+) -> Result<(), UserTradesError> {
+    let sampled_at = Utc::now();
+    let wallet_balances = galoy.wallet_balances().await?;
+    // The ledger has to include every galoy transaction the wallets already reflect
+    wait_for_watermark(watermark, sampled_at, config.max_watermark_wait).await?;
+    let balances = ledger.balances();
+    let usd_liability = balances
+        .stablesats_liability()
+        .await?
+        .map(|balance| balance.settled())
+        .unwrap_or(Decimal::ZERO);
+    let btc_balance = balances
+        .stablesats_btc_wallet()
+        .await?
+        .map(|balance| balance.settled())
+        .unwrap_or(Decimal::ZERO);
+    let report = ReconciliationReport::new(
+        config,
+        reports.baseline().await?,
+        usd_liability,
+        btc_balance,
+        wallet_balances.usd,
+        wallet_balances.btc,
+    );
+
+    let span = tracing::Span::current();
+    span.record(
+        "ledger_usd_liability_in_cents",
+        &tracing::field::display(report.ledger_usd_liability_in_cents),
+    );
+    span.record(
+        "galoy_usd_balance_in_cents",
+        &tracing::field::display(report.galoy_usd_balance_in_cents),
+    );
+    span.record(
+        "usd_difference_in_cents",
+        &tracing::field::display(report.usd_difference_in_cents),
+    );
+    span.record(
+        "ledger_btc_balance_in_sats",
+        &tracing::field::display(report.ledger_btc_balance_in_sats),
+    );
+    span.record(
+        "galoy_btc_balance_in_sats",
+        &tracing::field::display(report.galoy_btc_balance_in_sats),
+    );
+    span.record(
+        "btc_difference_in_sats",
+        &tracing::field::display(report.btc_difference_in_sats),
+    );
+    span.record("is_baseline", &tracing::field::display(report.is_baseline));
+    span.record(
+        "usd_drift_in_cents",
+        &tracing::field::display(report.usd_drift_in_cents),
+    );
+    span.record(
+        "btc_drift_in_sats",
+        &tracing::field::display(report.btc_drift_in_sats),
+    );
+    span.record("btc_checked", &tracing::field::display(report.btc_checked));
+    span.record(
+        "within_tolerance",
+        &tracing::field::display(report.within_tolerance),
+    );
+
+    reports.persist(&report).await?;
+    if !report.within_tolerance {
+        shared::tracing::insert_error_fields(
+            tracing::Level::ERROR,
+            format!("Ledger and galoy balances drifted apart: {report}"),
+        );
+    }
+    Ok(())
+}
+
+async fn wait_for_watermark(
+    watermark: &UserTradesWatermark,
+    sampled_at: DateTime<Utc>,
+    max_wait: Duration,
+) -> Result<(), UserTradesError> {
+    let deadline = tokio::time::Instant::now() + max_wait;
+    loop {
+        if matches!(watermark.synced_at().await?, Some(synced_at) if synced_at >= sampled_at) {
+            return Ok(());
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return Err(UserTradesError::WatermarkBehind(sampled_at));
+        }
+        tokio::time::sleep(WATERMARK_POLL_INTERVAL).await;
+    }
+}
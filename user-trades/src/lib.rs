@@ -5,18 +5,28 @@ mod app;
 mod error;
 mod galoy_transactions;
 pub mod job;
+mod quarantine;
+mod reconciliation;
 pub mod user_trades;
+mod watermark;
+pub mod webhook;
 
 use galoy_client::GaloyClientConfig;
+use shared::health::HealthCheckTrigger;
 
 pub use app::*;
 pub use error::*;
+pub use quarantine::*;
+pub use reconciliation::*;
+pub use watermark::*;
+pub use webhook::UserTradesWebhookConfig;
 
 pub async fn run(
     pool: sqlx::PgPool,
+    health_check_trigger: HealthCheckTrigger,
     config: UserTradesConfig,
     galoy_client_cfg: GaloyClientConfig,
 ) -> Result<(), UserTradesError> {
-    UserTradesApp::run(pool, config, galoy_client_cfg).await?;
+    UserTradesApp::run(pool, health_check_trigger, config, galoy_client_cfg).await?;
     Ok(())
 }
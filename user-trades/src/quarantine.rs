@@ -0,0 +1,285 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{error::UserTradesError, galoy_transactions::*, job, user_trades::UserTrades};
+
+const PAIR_ACTION: &str = "pair";
+const IGNORE_ACTION: &str = "ignore";
+/// Both legs of a conversion are created together, manual pairs may only be a bit apart
+const MAX_PAIR_CREATED_AT_DIFFERENCE_SECS: i64 = 600;
+
+#[derive(Debug, Clone)]
+pub struct QuarantinedTransaction {
+    pub id: String,
+    pub settlement_amount: Decimal,
+    pub settlement_currency: String,
+    pub settlement_method: String,
+    pub direction: String,
+    pub memo: Option<String>,
+    pub amount_in_usd_cents: Decimal,
+    pub created_at: DateTime<Utc>,
+    pub quarantined_at: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
+/// Galoy transactions that could not be paired automatically.
+/// They stay in quarantine until a late counterpart is imported and pairs them
+/// automatically, or an operator pairs or ignores them.
+/// Every manual resolution is kept in `galoy_transaction_resolutions`.
+#[derive(Clone)]
+pub struct GaloyTransactionsQuarantine {
+    pool: PgPool,
+    galoy_transactions: GaloyTransactions,
+    user_trades: UserTrades,
+}
+
+impl GaloyTransactionsQuarantine {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            galoy_transactions: GaloyTransactions::new(pool.clone()),
+            user_trades: UserTrades::new(pool.clone()),
+            pool,
+        }
+    }
+
+    #[instrument(name = "user_trades.quarantine.quarantine_stale", skip(self))]
+    pub async fn quarantine_stale(
+        &self,
+        older_than: chrono::Duration,
+    ) -> Result<u64, UserTradesError> {
+        let reason = format!("No pair found within {}s", older_than.num_seconds());
+        let res = sqlx::query!(
+            r#"UPDATE galoy_transactions SET quarantined_at = NOW(), quarantine_reason = $2
+               WHERE is_paired = false AND quarantined_at IS NULL AND amount_in_usd_cents != 0 AND created_at < $1"#,
+            Utc::now() - older_than,
+            reason
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(res.rows_affected())
+    }
+
+    pub async fn list(&self) -> Result<Vec<QuarantinedTransaction>, UserTradesError> {
+        let rows = sqlx::query!(
+            r#"SELECT id, settlement_amount, settlement_currency, settlement_method, direction, memo, amount_in_usd_cents, created_at,
+                      quarantined_at as "quarantined_at!", quarantine_reason
+               FROM galoy_transactions
+               WHERE quarantined_at IS NOT NULL AND is_paired = false AND ignored_at IS NULL
+               ORDER BY created_at"#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| QuarantinedTransaction {
+                id: row.id,
+                settlement_amount: row.settlement_amount,
+                settlement_currency: row.settlement_currency,
+                settlement_method: row.settlement_method,
+                direction: row.direction,
+                memo: row.memo,
+                amount_in_usd_cents: row.amount_in_usd_cents,
+                created_at: row.created_at,
+                quarantined_at: row.quarantined_at,
+                reason: row.quarantine_reason,
+            })
+            .collect())
+    }
+
+    /// Records the user trade formed by two galoy transactions the automatic pairing missed
+    #[instrument(name = "user_trades.quarantine.pair", skip(self))]
+    pub async fn pair(
+        &self,
+        first_id: String,
+        second_id: String,
+        resolved_by: String,
+        note: Option<String>,
+    ) -> Result<(), UserTradesError> {
+        let mut tx = self.pool.begin().await?;
+        let first = self.load_unresolved(&mut tx, &first_id).await?;
+        let second = self.load_unresolved(&mut tx, &second_id).await?;
+        check_pair(&first, &second)?;
+        let ids = vec![first_id, second_id];
+        self.galoy_transactions
+            .update_paired_ids(&mut tx, &ids)
+            .await?;
+        self.user_trades
+            .persist_all(&mut tx, vec![first.pair_with(second)])
+            .await?;
+        record_resolution(&mut tx, PAIR_ACTION, ids, resolved_by, note).await?;
+        tx.commit().await?;
+        job::spawn_process_galoy_transactions(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Marks a galoy transaction as not being part of any user trade
+    #[instrument(name = "user_trades.quarantine.ignore", skip(self))]
+    pub async fn ignore(
+        &self,
+        id: String,
+        resolved_by: String,
+        note: Option<String>,
+    ) -> Result<(), UserTradesError> {
+        let mut tx = self.pool.begin().await?;
+        self.load_unresolved(&mut tx, &id).await?;
+        sqlx::query!(
+            "UPDATE galoy_transactions SET ignored_at = NOW() WHERE id = $1",
+            id
+        )
+        .execute(&mut tx)
+        .await?;
+        record_resolution(&mut tx, IGNORE_ACTION, vec![id], resolved_by, note).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn load_unresolved(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        id: &str,
+    ) -> Result<UnpairedTransaction, UserTradesError> {
+        let row = sqlx::query!(
+            r#"SELECT id, direction, amount_in_usd_cents, memo, settlement_method, settlement_amount, settlement_currency, created_at, is_paired, ignored_at
+               FROM galoy_transactions WHERE id = $1 FOR UPDATE"#,
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| {
+            UserTradesError::GaloyTransactionResolution(format!("{id} does not exist"))
+        })?;
+        if row.is_paired == Some(true) {
+            return Err(UserTradesError::GaloyTransactionResolution(format!(
+                "{id} is already paired"
+            )));
+        }
+        if row.ignored_at.is_some() {
+            return Err(UserTradesError::GaloyTransactionResolution(format!(
+                "{id} is already ignored"
+            )));
+        }
+        Ok(UnpairedTransaction {
+            id: row.id,
+            settlement_amount: row.settlement_amount,
+            settlement_currency: row
+                .settlement_currency
+                .parse()
+                .expect("Couldn't parse settlement currency"),
+            direction: row.direction,
+            memo: row.memo,
+            amount_in_usd_cents: row.amount_in_usd_cents,
+            settlement_method: row.settlement_method,
+            created_at: row.created_at,
+        })
+    }
+}
+
+fn check_pair(
+    first: &UnpairedTransaction,
+    second: &UnpairedTransaction,
+) -> Result<(), UserTradesError> {
+    let (first_id, second_id) = (&first.id, &second.id);
+    if first.settlement_currency == second.settlement_currency {
+        return Err(UserTradesError::GaloyTransactionResolution(format!(
+            "{first_id} and {second_id} are both settled in {}",
+            first.settlement_currency
+        )));
+    }
+    if first.direction == second.direction {
+        return Err(UserTradesError::GaloyTransactionResolution(format!(
+            "{first_id} and {second_id} are both {}",
+            first.direction
+        )));
+    }
+    if first.settlement_amount.is_sign_negative() == second.settlement_amount.is_sign_negative()
+        || first.settlement_amount.is_zero()
+        || second.settlement_amount.is_zero()
+    {
+        return Err(UserTradesError::GaloyTransactionResolution(format!(
+            "{first_id} and {second_id} do not move funds in opposite directions"
+        )));
+    }
+    if (first.created_at - second.created_at).num_seconds().abs()
+        > MAX_PAIR_CREATED_AT_DIFFERENCE_SECS
+    {
+        return Err(UserTradesError::GaloyTransactionResolution(format!(
+            "{first_id} and {second_id} were created more than {MAX_PAIR_CREATED_AT_DIFFERENCE_SECS}s apart"
+        )));
+    }
+    Ok(())
+}
+
+async fn record_resolution(
+    tx: &mut Transaction<'_, Postgres>,
+    action: &str,
+    ids: Vec<String>,
+    resolved_by: String,
+    note: Option<String>,
+) -> Result<(), UserTradesError> {
+    sqlx::query!(
+        r#"INSERT INTO galoy_transaction_resolutions (id, action, galoy_transaction_ids, resolved_by, note)
+           VALUES ($1, $2, $3, $4, $5)"#,
+        Uuid::new_v4(),
+        action,
+        &ids[..],
+        resolved_by,
+        note
+    )
+    .execute(&mut *tx)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use galoy_client::SettlementCurrency;
+    use rust_decimal_macros::dec;
+
+    use super::*;
+
+    fn tx(
+        id: &str,
+        settlement_currency: SettlementCurrency,
+        direction: &str,
+        settlement_amount: Decimal,
+        created_at: DateTime<Utc>,
+    ) -> UnpairedTransaction {
+        UnpairedTransaction {
+            id: id.to_string(),
+            settlement_amount,
+            settlement_currency,
+            direction: direction.to_string(),
+            settlement_method: "SettlementViaIntraLedger".to_string(),
+            memo: None,
+            amount_in_usd_cents: dec!(10),
+            created_at,
+        }
+    }
+
+    #[test]
+    fn pair_requires_opposite_legs_close_in_time() {
+        let now = Utc::now();
+        let btc = tx("btc", SettlementCurrency::BTC, "SEND", dec!(-1000), now);
+        let usd = tx("usd", SettlementCurrency::USD, "RECEIVE", dec!(10), now);
+        assert!(check_pair(&btc, &usd).is_ok());
+        assert!(check_pair(&usd, &btc).is_ok());
+
+        let same_currency = tx("btc2", SettlementCurrency::BTC, "RECEIVE", dec!(1000), now);
+        assert!(check_pair(&btc, &same_currency).is_err());
+        let same_direction = tx("usd2", SettlementCurrency::USD, "SEND", dec!(10), now);
+        assert!(check_pair(&btc, &same_direction).is_err());
+        let same_sign = tx("usd3", SettlementCurrency::USD, "RECEIVE", dec!(-10), now);
+        assert!(check_pair(&btc, &same_sign).is_err());
+        let too_late = tx(
+            "usd4",
+            SettlementCurrency::USD,
+            "RECEIVE",
+            dec!(10),
+            now + chrono::Duration::hours(1),
+        );
+        assert!(check_pair(&btc, &too_late).is_err());
+    }
+}
@@ -0,0 +1,332 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::instrument;
+use uuid::Uuid;
+
+use std::time::Duration;
+
+use crate::error::UserTradesError;
+
+const CENTS_PER_USD: Decimal = dec!(100);
+const SATS_PER_BTC: Decimal = dec!(100_000_000);
+
+#[serde_with::serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciliationConfig {
+    #[serde_as(as = "serde_with::DurationSeconds<u64>")]
+    #[serde(default = "default_frequency")]
+    pub frequency: Duration,
+    #[serde(default = "default_usd_tolerance_in_cents")]
+    pub usd_tolerance_in_cents: Decimal,
+    #[serde(default = "default_btc_tolerance_in_sats")]
+    pub btc_tolerance_in_sats: Decimal,
+    /// The btc side only balances when okex transfers are in the ledger, set to false to make it report-only
+    #[serde(default = "default_check_btc")]
+    pub check_btc: bool,
+    #[serde_as(as = "serde_with::DurationSeconds<u64>")]
+    #[serde(default = "default_max_watermark_wait")]
+    pub max_watermark_wait: Duration,
+}
+
+impl Default for ReconciliationConfig {
+    fn default() -> Self {
+        Self {
+            frequency: default_frequency(),
+            usd_tolerance_in_cents: default_usd_tolerance_in_cents(),
+            btc_tolerance_in_sats: default_btc_tolerance_in_sats(),
+            check_btc: default_check_btc(),
+            max_watermark_wait: default_max_watermark_wait(),
+        }
+    }
+}
+
+fn default_frequency() -> Duration {
+    Duration::from_secs(60 * 60 * 24)
+}
+
+fn default_usd_tolerance_in_cents() -> Decimal {
+    dec!(100)
+}
+
+fn default_btc_tolerance_in_sats() -> Decimal {
+    dec!(10_000)
+}
+
+fn default_check_btc() -> bool {
+    true
+}
+
+fn default_max_watermark_wait() -> Duration {
+    Duration::from_secs(60)
+}
+
+/// Differences found by the first reconciliation, later reports only measure drift from it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconciliationBaseline {
+    pub usd_difference_in_cents: Decimal,
+    pub btc_difference_in_sats: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReconciliationReport {
+    pub ledger_usd_liability_in_cents: Decimal,
+    pub galoy_usd_balance_in_cents: Decimal,
+    pub usd_difference_in_cents: Decimal,
+    pub ledger_btc_balance_in_sats: Decimal,
+    pub galoy_btc_balance_in_sats: Decimal,
+    pub btc_difference_in_sats: Decimal,
+    pub is_baseline: bool,
+    pub usd_drift_in_cents: Decimal,
+    pub btc_drift_in_sats: Decimal,
+    pub btc_checked: bool,
+    pub within_tolerance: bool,
+}
+
+impl ReconciliationReport {
+    /// Compares the ledger (in USD / BTC) with the galoy wallets (in cents / sats).
+    /// Without a baseline the report becomes the baseline, its drift is the whole difference
+    /// so that a mismatch that already exists is still checked against the tolerance once.
+    pub fn new(
+        config: &ReconciliationConfig,
+        baseline: Option<ReconciliationBaseline>,
+        ledger_usd_liability: Decimal,
+        ledger_btc_balance: Decimal,
+        galoy_usd_balance_in_cents: Decimal,
+        galoy_btc_balance_in_sats: Decimal,
+    ) -> Self {
+        let ledger_usd_liability_in_cents = ledger_usd_liability * CENTS_PER_USD;
+        let ledger_btc_balance_in_sats = ledger_btc_balance * SATS_PER_BTC;
+        let usd_difference_in_cents = galoy_usd_balance_in_cents - ledger_usd_liability_in_cents;
+        let btc_difference_in_sats = galoy_btc_balance_in_sats - ledger_btc_balance_in_sats;
+        let (usd_drift_in_cents, btc_drift_in_sats) = baseline
+            .map(|baseline| {
+                (
+                    usd_difference_in_cents - baseline.usd_difference_in_cents,
+                    btc_difference_in_sats - baseline.btc_difference_in_sats,
+                )
+            })
+            .unwrap_or((usd_difference_in_cents, btc_difference_in_sats));
+        Self {
+            ledger_usd_liability_in_cents,
+            galoy_usd_balance_in_cents,
+            usd_difference_in_cents,
+            ledger_btc_balance_in_sats,
+            galoy_btc_balance_in_sats,
+            btc_difference_in_sats,
+            is_baseline: baseline.is_none(),
+            usd_drift_in_cents,
+            btc_drift_in_sats,
+            btc_checked: config.check_btc,
+            within_tolerance: usd_drift_in_cents.abs() <= config.usd_tolerance_in_cents
+                && (!config.check_btc || btc_drift_in_sats.abs() <= config.btc_tolerance_in_sats),
+        }
+    }
+}
+
+impl std::fmt::Display for ReconciliationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "usd_drift_in_cents={}, btc_drift_in_sats={}, btc_checked={}",
+            self.usd_drift_in_cents, self.btc_drift_in_sats, self.btc_checked
+        )
+    }
+}
+
+#[derive(Clone)]
+pub struct ReconciliationReports {
+    pool: PgPool,
+}
+
+impl ReconciliationReports {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    #[instrument(name = "user_trades.reconciliation_reports.persist", skip(self))]
+    pub async fn persist(&self, report: &ReconciliationReport) -> Result<(), UserTradesError> {
+        sqlx::query!(
+            r#"INSERT INTO reconciliation_reports (id, ledger_usd_liability_in_cents, galoy_usd_balance_in_cents, usd_difference_in_cents,
+                 ledger_btc_balance_in_sats, galoy_btc_balance_in_sats, btc_difference_in_sats, is_baseline,
+                 usd_drift_in_cents, btc_drift_in_sats, btc_checked, within_tolerance)
+               VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)"#,
+            Uuid::new_v4(),
+            report.ledger_usd_liability_in_cents,
+            report.galoy_usd_balance_in_cents,
+            report.usd_difference_in_cents,
+            report.ledger_btc_balance_in_sats,
+            report.galoy_btc_balance_in_sats,
+            report.btc_difference_in_sats,
+            report.is_baseline,
+            report.usd_drift_in_cents,
+            report.btc_drift_in_sats,
+            report.btc_checked,
+            report.within_tolerance
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn baseline(&self) -> Result<Option<ReconciliationBaseline>, UserTradesError> {
+        let row = sqlx::query!(
+            r#"SELECT usd_difference_in_cents, btc_difference_in_sats
+               FROM reconciliation_reports WHERE is_baseline"#
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|row| ReconciliationBaseline {
+            usd_difference_in_cents: row.usd_difference_in_cents,
+            btc_difference_in_sats: row.btc_difference_in_sats,
+        }))
+    }
+
+    pub async fn latest(
+        &self,
+    ) -> Result<Option<(ReconciliationReport, DateTime<Utc>)>, UserTradesError> {
+        let row = sqlx::query!(
+            r#"SELECT ledger_usd_liability_in_cents, galoy_usd_balance_in_cents, usd_difference_in_cents,
+                 ledger_btc_balance_in_sats, galoy_btc_balance_in_sats, btc_difference_in_sats, is_baseline,
+                 usd_drift_in_cents, btc_drift_in_sats, btc_checked, within_tolerance, created_at
+               FROM reconciliation_reports ORDER BY created_at DESC LIMIT 1"#
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|row| {
+            (
+                ReconciliationReport {
+                    ledger_usd_liability_in_cents: row.ledger_usd_liability_in_cents,
+                    galoy_usd_balance_in_cents: row.galoy_usd_balance_in_cents,
+                    usd_difference_in_cents: row.usd_difference_in_cents,
+                    ledger_btc_balance_in_sats: row.ledger_btc_balance_in_sats,
+                    galoy_btc_balance_in_sats: row.galoy_btc_balance_in_sats,
+                    btc_difference_in_sats: row.btc_difference_in_sats,
+                    is_baseline: row.is_baseline,
+                    usd_drift_in_cents: row.usd_drift_in_cents,
+                    btc_drift_in_sats: row.btc_drift_in_sats,
+                    btc_checked: row.btc_checked,
+                    within_tolerance: row.within_tolerance,
+                },
+                row.created_at,
+            )
+        }))
+    }
+
+    /// Unhealthy while the latest report is outside of the tolerance
+    pub async fn healthy(&self) -> Result<(), String> {
+        match self.latest().await {
+            Ok(Some((report, created_at))) if !report.within_tolerance => Err(format!(
+                "Ledger and galoy balances drifted apart ({report}) at {created_at}"
+            )),
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn baseline() -> Option<ReconciliationBaseline> {
+        Some(ReconciliationBaseline {
+            usd_difference_in_cents: dec!(1_000),
+            btc_difference_in_sats: dec!(-50_000),
+        })
+    }
+
+    #[test]
+    fn first_report_is_the_baseline() {
+        let report = ReconciliationReport::new(
+            &ReconciliationConfig::default(),
+            None,
+            dec!(1000.50),
+            dec!(0.5),
+            dec!(100_100),
+            dec!(50_005_000),
+        );
+        assert!(report.is_baseline);
+        assert_eq!(report.usd_difference_in_cents, dec!(50));
+        assert_eq!(report.usd_drift_in_cents, dec!(50));
+        assert_eq!(report.btc_drift_in_sats, dec!(5_000));
+        assert!(report.within_tolerance);
+    }
+
+    #[test]
+    fn initial_difference_is_checked_against_tolerance() {
+        let report = ReconciliationReport::new(
+            &ReconciliationConfig::default(),
+            None,
+            dec!(1000.50),
+            dec!(0.5),
+            dec!(110_050),
+            dec!(50_000_000),
+        );
+        assert!(report.is_baseline);
+        assert_eq!(report.usd_drift_in_cents, dec!(10_000));
+        assert!(!report.within_tolerance);
+    }
+
+    #[test]
+    fn reconciles_drift_in_minor_units() {
+        let report = ReconciliationReport::new(
+            &ReconciliationConfig::default(),
+            baseline(),
+            dec!(1000.50),
+            dec!(0.5),
+            dec!(101_100),
+            dec!(49_955_000),
+        );
+        assert!(!report.is_baseline);
+        assert_eq!(report.ledger_usd_liability_in_cents, dec!(100_050));
+        assert_eq!(report.usd_difference_in_cents, dec!(1_050));
+        assert_eq!(report.usd_drift_in_cents, dec!(50));
+        assert_eq!(report.ledger_btc_balance_in_sats, dec!(50_000_000));
+        assert_eq!(report.btc_difference_in_sats, dec!(-45_000));
+        assert_eq!(report.btc_drift_in_sats, dec!(5_000));
+        assert!(report.within_tolerance);
+    }
+
+    #[test]
+    fn btc_drift_is_only_checked_when_enabled() {
+        let mut config = ReconciliationConfig {
+            check_btc: false,
+            ..Default::default()
+        };
+        let usd_drift = ReconciliationReport::new(
+            &config,
+            baseline(),
+            dec!(1000),
+            dec!(1),
+            dec!(100_000),
+            dec!(99_950_000),
+        );
+        assert!(!usd_drift.within_tolerance);
+        let btc_drift = ReconciliationReport::new(
+            &config,
+            baseline(),
+            dec!(1000),
+            dec!(1),
+            dec!(101_000),
+            dec!(98_950_000),
+        );
+        assert_eq!(btc_drift.btc_drift_in_sats, dec!(-1_000_000));
+        assert!(!btc_drift.btc_checked);
+        assert!(btc_drift.within_tolerance);
+
+        config.check_btc = true;
+        let btc_drift = ReconciliationReport::new(
+            &config,
+            baseline(),
+            dec!(1000),
+            dec!(1),
+            dec!(101_000),
+            dec!(98_950_000),
+        );
+        assert!(btc_drift.btc_checked);
+        assert!(!btc_drift.within_tolerance);
+    }
+}
@@ -1,6 +1,18 @@
+use galoy_client::SettlementCurrency;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
 #[sqlx(type_name = "UserTradeUnit", rename_all = "snake_case")]
 pub enum UserTradeUnit {
     UsdCent,
     Satoshi,
 }
+
+impl From<SettlementCurrency> for UserTradeUnit {
+    fn from(currency: SettlementCurrency) -> Self {
+        match currency {
+            SettlementCurrency::BTC => Self::Satoshi,
+            SettlementCurrency::USD => Self::UsdCent,
+            _ => unimplemented!(),
+        }
+    }
+}
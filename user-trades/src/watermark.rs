@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tracing::instrument;
+
+use crate::{error::UserTradesError, user_trades::BAD_TRADE_MARKER};
+
+/// Point in time up to which every galoy transaction has been imported and,
+/// if it is part of a user trade, posted to the ledger.
+/// Hedging waits for it to catch up before acting on ledger balances.
+#[derive(Clone)]
+pub struct UserTradesWatermark {
+    pool: PgPool,
+}
+
+impl UserTradesWatermark {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Moves the watermark to `synced_at`, or to just before the oldest user trade
+    /// that still has to be posted to (or reverted in) the ledger, so that a stuck
+    /// trade holds the watermark back without freezing it. The galoy cursor is only
+    /// moved once every trade is in the ledger.
+    /// Returns the published watermark.
+    #[instrument(name = "user_trades.watermark.advance", skip(self))]
+    pub async fn advance(
+        &self,
+        synced_at: DateTime<Utc>,
+        galoy_cursor: Option<String>,
+    ) -> Result<DateTime<Utc>, UserTradesError> {
+        let res = sqlx::query!(
+            r#"WITH pending AS (
+                 SELECT MIN(to_timestamp((external_ref->>'timestamp')::BIGINT)) AS oldest
+                 FROM user_trades WHERE ledger_tx_id IS NULL OR correction_ledger_tx_id = $3
+               )
+               INSERT INTO user_trades_watermark (id, synced_at, galoy_cursor)
+               SELECT TRUE,
+                      CASE WHEN oldest IS NULL THEN $1 ELSE LEAST($1, oldest - INTERVAL '1 second') END,
+                      CASE WHEN oldest IS NULL THEN $2 END
+               FROM pending
+               ON CONFLICT (id) DO UPDATE
+               SET synced_at = GREATEST(user_trades_watermark.synced_at, EXCLUDED.synced_at),
+                   galoy_cursor = COALESCE(EXCLUDED.galoy_cursor, user_trades_watermark.galoy_cursor),
+                   updated_at = NOW()
+               RETURNING synced_at"#,
+            synced_at,
+            galoy_cursor,
+            BAD_TRADE_MARKER
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(res.synced_at)
+    }
+
+    pub async fn synced_at(&self) -> Result<Option<DateTime<Utc>>, UserTradesError> {
+        let row = sqlx::query!("SELECT synced_at FROM user_trades_watermark")
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|row| row.synced_at))
+    }
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+#[serde_with::serde_as]
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UserTradesWebhookConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_port")]
+    pub listen_port: u16,
+    #[serde(default)]
+    pub secret: String,
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    #[serde(default = "default_max_age")]
+    pub max_age: chrono::Duration,
+}
+
+impl Default for UserTradesWebhookConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen_port: default_port(),
+            secret: String::new(),
+            max_age: default_max_age(),
+        }
+    }
+}
+
+fn default_port() -> u16 {
+    3327
+}
+
+fn default_max_age() -> chrono::Duration {
+    chrono::Duration::seconds(300)
+}
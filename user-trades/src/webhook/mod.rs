@@ -0,0 +1,123 @@
+mod config;
+mod payload;
+mod signature;
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Router,
+};
+use tracing::instrument;
+
+use std::{net::SocketAddr, sync::Arc};
+
+use galoy_client::GaloyTransaction;
+
+use crate::{error::UserTradesError, galoy_transactions::*, job};
+
+pub use config::*;
+pub use payload::*;
+
+const TIMESTAMP_HEADER: &str = "x-galoy-timestamp";
+const SIGNATURE_HEADER: &str = "x-galoy-signature";
+
+#[derive(Clone)]
+struct WebhookState {
+    pool: sqlx::PgPool,
+    galoy_transactions: GaloyTransactions,
+    config: Arc<UserTradesWebhookConfig>,
+}
+
+pub(crate) async fn run(
+    config: UserTradesWebhookConfig,
+    pool: sqlx::PgPool,
+) -> Result<(), UserTradesError> {
+    let addr = SocketAddr::from(([0, 0, 0, 0], config.listen_port));
+    axum::Server::bind(&addr)
+        .serve(router(config, pool).into_make_service())
+        .await
+        .map_err(axum::Error::new)?;
+    Ok(())
+}
+
+pub fn router(config: UserTradesWebhookConfig, pool: sqlx::PgPool) -> Router {
+    let state = WebhookState {
+        galoy_transactions: GaloyTransactions::new(pool.clone()),
+        pool,
+        config: Arc::new(config),
+    };
+    Router::new()
+        .route("/webhooks/galoy/transactions", post(transactions_settled))
+        .with_state(state)
+}
+
+async fn transactions_settled(
+    State(state): State<WebhookState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> StatusCode {
+    match handle_transactions_settled(&state, &headers, &body).await {
+        Ok(()) => StatusCode::OK,
+        Err(UserTradesError::InvalidWebhookSignature) => StatusCode::UNAUTHORIZED,
+        Err(UserTradesError::InvalidWebhookPayload(_) | UserTradesError::SerdeJson(_)) => {
+            StatusCode::BAD_REQUEST
+        }
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[instrument(name = "user_trades.webhook.transactions_settled", skip_all,
+    fields(n_galoy_txs, error, error.level, error.message),
+    err
+)]
+async fn handle_transactions_settled(
+    state: &WebhookState,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<(), UserTradesError> {
+    shared::tracing::record_error(tracing::Level::WARN, || async move {
+        verify_request(&state.config, headers, body)?;
+        let transactions = serde_json::from_slice::<TransactionsSettled>(body)?
+            .transactions
+            .into_iter()
+            .map(GaloyTransaction::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        tracing::Span::current()
+            .record("n_galoy_txs", &tracing::field::display(transactions.len()));
+        state
+            .galoy_transactions
+            .persist_all(transactions, TransactionSource::Webhook)
+            .await?;
+        job::spawn_process_galoy_transactions(&state.pool).await?;
+        Ok(())
+    })
+    .await
+}
+
+fn verify_request(
+    config: &UserTradesWebhookConfig,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<(), UserTradesError> {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .ok_or(UserTradesError::InvalidWebhookSignature)
+    };
+    let timestamp = header(TIMESTAMP_HEADER)?;
+    let signature = header(SIGNATURE_HEADER)?;
+    let sent_at = timestamp
+        .parse::<i64>()
+        .map_err(|_| UserTradesError::InvalidWebhookSignature)?;
+    let age = chrono::Utc::now().timestamp() - sent_at;
+    if config.secret.is_empty()
+        || age.abs() > config.max_age.num_seconds()
+        || !signature::verify(&config.secret, timestamp, body, signature)
+    {
+        return Err(UserTradesError::InvalidWebhookSignature);
+    }
+    Ok(())
+}
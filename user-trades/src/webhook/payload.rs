@@ -0,0 +1,57 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use galoy_client::{GaloyTransaction, TxCursor, TxStatus};
+
+use crate::error::UserTradesError;
+
+#[derive(Debug, Deserialize)]
+pub struct TransactionsSettled {
+    pub transactions: Vec<SettledTransaction>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SettledTransaction {
+    pub id: String,
+    pub settlement_amount: Decimal,
+    #[serde(default)]
+    pub settlement_fee: Decimal,
+    pub settlement_currency: String,
+    pub settlement_method: String,
+    pub cents_per_unit: Decimal,
+    pub memo: Option<String>,
+    pub direction: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TryFrom<SettledTransaction> for GaloyTransaction {
+    type Error = UserTradesError;
+
+    fn try_from(tx: SettledTransaction) -> Result<Self, Self::Error> {
+        Ok(Self {
+            // Webhook transactions are never used as a polling cursor
+            cursor: TxCursor::from(tx.id.clone()),
+            id: tx.id,
+            amount_in_usd_cents: (tx.settlement_amount * tx.cents_per_unit).round(),
+            settlement_amount: tx.settlement_amount,
+            settlement_fee: tx.settlement_fee,
+            settlement_currency: tx
+                .settlement_currency
+                .parse()
+                .map_err(UserTradesError::InvalidWebhookPayload)?,
+            settlement_method: tx
+                .settlement_method
+                .parse()
+                .map_err(UserTradesError::InvalidWebhookPayload)?,
+            cents_per_unit: tx.cents_per_unit,
+            memo: tx.memo,
+            direction: tx
+                .direction
+                .parse()
+                .map_err(UserTradesError::InvalidWebhookPayload)?,
+            status: TxStatus::SUCCESS,
+            created_at: tx.created_at,
+        })
+    }
+}
@@ -0,0 +1,51 @@
+use ring::hmac;
+
+/// Checks the hex encoded HMAC-SHA256 of `{timestamp}.{body}` sent by galoy.
+pub(super) fn verify(secret: &str, timestamp: &str, body: &[u8], signature: &str) -> bool {
+    let signature = match data_encoding::HEXLOWER_PERMISSIVE.decode(signature.as_bytes()) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    hmac::verify(&key, &signed_payload(timestamp, body), &signature).is_ok()
+}
+
+fn signed_payload(timestamp: &str, body: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(timestamp.len() + 1 + body.len());
+    payload.extend_from_slice(timestamp.as_bytes());
+    payload.push(b'.');
+    payload.extend_from_slice(body);
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(secret: &str, timestamp: &str, body: &[u8]) -> String {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        let tag = hmac::sign(&key, &signed_payload(timestamp, body));
+        data_encoding::HEXLOWER.encode(tag.as_ref())
+    }
+
+    #[test]
+    fn accepts_valid_signature() {
+        let signature = sign("secret", "1681200000", b"{}");
+        assert!(verify("secret", "1681200000", b"{}", &signature));
+        assert!(verify(
+            "secret",
+            "1681200000",
+            b"{}",
+            &signature.to_uppercase()
+        ));
+    }
+
+    #[test]
+    fn rejects_tampered_requests() {
+        let signature = sign("secret", "1681200000", b"{}");
+        assert!(!verify("other", "1681200000", b"{}", &signature));
+        assert!(!verify("secret", "1681200001", b"{}", &signature));
+        assert!(!verify("secret", "1681200000", b"[]", &signature));
+        assert!(!verify("secret", "1681200000", b"{}", "not-hex"));
+    }
+}
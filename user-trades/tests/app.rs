@@ -31,10 +31,13 @@ async fn publishes_liability() -> anyhow::Result<()> {
         .await?
         .usd_liability_balance_events()
         .await;
+    let (_, recv) = futures::channel::mpsc::unbounded();
     let _ = tokio::spawn(UserTradesApp::run(
         pool,
+        recv,
         UserTradesConfig {
             galoy_poll_frequency: std::time::Duration::from_secs(1),
+            ..Default::default()
         },
         galoy_client_configuration(),
     ));
@@ -0,0 +1,185 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serial_test::serial;
+
+use ::user_trades::*;
+
+async fn init_pool() -> anyhow::Result<sqlx::PgPool> {
+    let pg_host = std::env::var("PG_HOST").unwrap_or("localhost".to_string());
+    let pg_con = format!("postgres://user:password@{pg_host}:5432/pg");
+    Ok(sqlx::PgPool::connect(&pg_con).await?)
+}
+
+fn unique_id(prefix: &str) -> String {
+    format!(
+        "quarantine-test-{prefix}-{}",
+        chrono::Utc::now().timestamp_nanos()
+    )
+}
+
+async fn insert_tx(
+    pool: &sqlx::PgPool,
+    id: &str,
+    currency: &str,
+    direction: &str,
+    settlement_amount: Decimal,
+    created_at: chrono::DateTime<chrono::Utc>,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO galoy_transactions (id, cursor, is_paired, settlement_amount, settlement_currency, settlement_method, cents_per_unit, amount_in_usd_cents, created_at, direction)
+         VALUES ($1, $1, false, $2, $3, 'SettlementViaIntraLedger', 1, 10, $4, $5)",
+    )
+    .bind(id)
+    .bind(settlement_amount)
+    .bind(currency)
+    .bind(created_at)
+    .bind(direction)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+fn is_listed(list: &[QuarantinedTransaction], id: &str) -> bool {
+    list.iter().any(|tx| tx.id == id)
+}
+
+#[tokio::test]
+#[serial]
+async fn quarantines_stale_transactions() -> anyhow::Result<()> {
+    let pool = init_pool().await?;
+    let quarantine = GaloyTransactionsQuarantine::new(pool.clone());
+    let stale = unique_id("stale");
+    let fresh = unique_id("fresh");
+    let now = chrono::Utc::now();
+    insert_tx(
+        &pool,
+        &stale,
+        "BTC",
+        "SEND",
+        dec!(-1000),
+        now - chrono::Duration::hours(2),
+    )
+    .await?;
+    insert_tx(&pool, &fresh, "BTC", "SEND", dec!(-1000), now).await?;
+
+    assert!(
+        quarantine
+            .quarantine_stale(chrono::Duration::hours(1))
+            .await?
+            >= 1
+    );
+    let list = quarantine.list().await?;
+    assert!(is_listed(&list, &stale));
+    assert!(!is_listed(&list, &fresh));
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn pairs_quarantined_transactions() -> anyhow::Result<()> {
+    let pool = init_pool().await?;
+    let quarantine = GaloyTransactionsQuarantine::new(pool.clone());
+    let btc = unique_id("btc");
+    let usd = unique_id("usd");
+    let same_direction = unique_id("same-direction");
+    let created_at = chrono::Utc::now() - chrono::Duration::hours(2);
+    insert_tx(&pool, &btc, "BTC", "SEND", dec!(-1000), created_at).await?;
+    insert_tx(
+        &pool,
+        &usd,
+        "USD",
+        "RECEIVE",
+        dec!(10),
+        created_at + chrono::Duration::seconds(1),
+    )
+    .await?;
+    insert_tx(&pool, &same_direction, "USD", "SEND", dec!(-10), created_at).await?;
+    quarantine
+        .quarantine_stale(chrono::Duration::hours(1))
+        .await?;
+
+    assert!(quarantine
+        .pair(
+            btc.clone(),
+            same_direction.clone(),
+            "test".to_string(),
+            None
+        )
+        .await
+        .is_err());
+    quarantine
+        .pair(usd.clone(), btc.clone(), "test".to_string(), None)
+        .await?;
+
+    let list = quarantine.list().await?;
+    assert!(!is_listed(&list, &btc));
+    assert!(!is_listed(&list, &usd));
+    assert!(is_listed(&list, &same_direction));
+    assert!(quarantine
+        .pair(btc.clone(), usd.clone(), "test".to_string(), None)
+        .await
+        .is_err());
+
+    // The user sent sats and received cents, whichever leg is passed first
+    let (buy_unit, buy_amount, sell_unit, sell_amount): (String, Decimal, String, Decimal) =
+        sqlx::query_as(
+            "SELECT buy_unit::TEXT, buy_amount, sell_unit::TEXT, sell_amount FROM user_trades
+             WHERE external_ref->>'btc_tx_id' = $1 AND external_ref->>'usd_tx_id' = $2",
+        )
+        .bind(&btc)
+        .bind(&usd)
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(buy_unit, "satoshi");
+    assert_eq!(buy_amount, dec!(1000));
+    assert_eq!(sell_unit, "usd_cent");
+    assert_eq!(sell_amount, dec!(10));
+
+    let (action,): (String,) = sqlx::query_as(
+        "SELECT action FROM galoy_transaction_resolutions WHERE $1 = ANY(galoy_transaction_ids)",
+    )
+    .bind(&btc)
+    .fetch_one(&pool)
+    .await?;
+    assert_eq!(action, "pair");
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn ignores_quarantined_transaction() -> anyhow::Result<()> {
+    let pool = init_pool().await?;
+    let quarantine = GaloyTransactionsQuarantine::new(pool.clone());
+    let id = unique_id("ignored");
+    insert_tx(
+        &pool,
+        &id,
+        "BTC",
+        "RECEIVE",
+        dec!(1000),
+        chrono::Utc::now() - chrono::Duration::hours(2),
+    )
+    .await?;
+    quarantine
+        .quarantine_stale(chrono::Duration::hours(1))
+        .await?;
+    assert!(is_listed(&quarantine.list().await?, &id));
+
+    quarantine
+        .ignore(
+            id.clone(),
+            "test".to_string(),
+            Some("not a trade".to_string()),
+        )
+        .await?;
+    assert!(!is_listed(&quarantine.list().await?, &id));
+    assert!(quarantine
+        .ignore(id.clone(), "test".to_string(), None)
+        .await
+        .is_err());
+    assert!(quarantine
+        .ignore(unique_id("missing"), "test".to_string(), None)
+        .await
+        .is_err());
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use rust_decimal_macros::dec;
+use serial_test::serial;
+
+use ::user_trades::*;
+
+async fn init_pool() -> anyhow::Result<sqlx::PgPool> {
+    let pg_host = std::env::var("PG_HOST").unwrap_or("localhost".to_string());
+    let pg_con = format!("postgres://user:password@{pg_host}:5432/pg");
+    Ok(sqlx::PgPool::connect(&pg_con).await?)
+}
+
+fn report(galoy_usd_balance_in_cents: rust_decimal::Decimal) -> ReconciliationReport {
+    ReconciliationReport::new(
+        &ReconciliationConfig::default(),
+        Some(ReconciliationBaseline {
+            usd_difference_in_cents: dec!(0),
+            btc_difference_in_sats: dec!(0),
+        }),
+        dec!(1000),
+        dec!(1),
+        galoy_usd_balance_in_cents,
+        dec!(100_000_000),
+    )
+}
+
+#[tokio::test]
+#[serial]
+async fn health_follows_latest_report() -> anyhow::Result<()> {
+    let pool = init_pool().await?;
+    let reports = ReconciliationReports::new(pool);
+
+    let drifted = report(dec!(100_500));
+    assert!(!drifted.within_tolerance);
+    reports.persist(&drifted).await?;
+    assert!(reports.healthy().await.is_err());
+
+    let reconciled = report(dec!(100_050));
+    assert!(reconciled.within_tolerance);
+    reports.persist(&reconciled).await?;
+    assert!(reports.healthy().await.is_ok());
+
+    Ok(())
+}
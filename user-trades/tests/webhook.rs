@@ -0,0 +1,99 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use ring::hmac;
+use serial_test::serial;
+use tower::ServiceExt;
+
+use ::user_trades::{job::PROCESS_GALOY_TRANSACTIONS_ID, webhook, UserTradesWebhookConfig};
+
+const SECRET: &str = "webhook-secret";
+
+async fn init_pool() -> anyhow::Result<sqlx::PgPool> {
+    let pg_host = std::env::var("PG_HOST").unwrap_or("localhost".to_string());
+    let pg_con = format!("postgres://user:password@{pg_host}:5432/pg");
+    Ok(sqlx::PgPool::connect(&pg_con).await?)
+}
+
+fn config() -> UserTradesWebhookConfig {
+    UserTradesWebhookConfig {
+        enabled: true,
+        secret: SECRET.to_string(),
+        ..Default::default()
+    }
+}
+
+fn body() -> String {
+    let now = chrono::Utc::now();
+    serde_json::json!({
+        "transactions": [{
+            "id": format!("webhook-test-{}", now.timestamp_nanos()),
+            "settlement_amount": "-1000",
+            "settlement_currency": "BTC",
+            "settlement_method": "SettlementViaIntraLedger",
+            "cents_per_unit": "0.02",
+            "memo": null,
+            "direction": "SEND",
+            "created_at": now.to_rfc3339(),
+        }]
+    })
+    .to_string()
+}
+
+fn request(body: String, secret: &str) -> Request<Body> {
+    let timestamp = chrono::Utc::now().timestamp().to_string();
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let mut payload = format!("{timestamp}.").into_bytes();
+    payload.extend_from_slice(body.as_bytes());
+    let signature = data_encoding::HEXLOWER.encode(hmac::sign(&key, &payload).as_ref());
+    Request::builder()
+        .method("POST")
+        .uri("/webhooks/galoy/transactions")
+        .header("x-galoy-timestamp", timestamp)
+        .header("x-galoy-signature", signature)
+        .body(Body::from(body))
+        .expect("valid request")
+}
+
+async fn process_job_spawned(pool: &sqlx::PgPool) -> anyhow::Result<bool> {
+    let row: Option<(uuid::Uuid,)> = sqlx::query_as("SELECT id FROM mq_msgs WHERE id = $1")
+        .bind(PROCESS_GALOY_TRANSACTIONS_ID)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.is_some())
+}
+
+#[tokio::test]
+#[serial]
+async fn rejects_bad_signature() -> anyhow::Result<()> {
+    let pool = init_pool().await?;
+    sqlx::query("DELETE FROM mq_msgs WHERE id = $1")
+        .bind(PROCESS_GALOY_TRANSACTIONS_ID)
+        .execute(&pool)
+        .await?;
+
+    let response = webhook::router(config(), pool.clone())
+        .oneshot(request(body(), "wrong-secret"))
+        .await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert!(!process_job_spawned(&pool).await?);
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn spawns_processing_for_valid_payload() -> anyhow::Result<()> {
+    let pool = init_pool().await?;
+    sqlx::query("DELETE FROM mq_msgs WHERE id = $1")
+        .bind(PROCESS_GALOY_TRANSACTIONS_ID)
+        .execute(&pool)
+        .await?;
+
+    let response = webhook::router(config(), pool.clone())
+        .oneshot(request(body(), SECRET))
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(process_job_spawned(&pool).await?);
+    Ok(())
+}